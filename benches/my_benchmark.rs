@@ -110,6 +110,70 @@ pub fn iterative_segment_tree_queries_benchmark(c: &mut Criterion) {
     group.finish();
 }
 
+pub fn padded_iterative_segment_tree_queries_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("padded_iterative_segment_tree_queries_benchmark");
+    let mut rng = rand::thread_rng();
+    let node_distr = Uniform::from(-N..=N);
+    for i in 1..=6 {
+        for j in 1..10 {
+            let n = j * 10_usize.pow(i);
+            let nodes: Vec<_> = (&mut rng)
+                .sample_iter(node_distr)
+                .map(|x| Min::initialize(&x))
+                .take(n)
+                .collect();
+            let segment_tree = PaddedIterative::build(&nodes);
+            let index_distr = Uniform::from(0..n);
+            group.throughput(Throughput::Elements(n as u64));
+            group.warm_up_time(Duration::from_secs(1));
+            group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, &n| {
+                b.iter_batched(
+                    || {
+                        Some((index_distr.sample(&mut rng), index_distr.sample(&mut rng)))
+                            .map(|(i, j)| (i.min(j), i.max(j)))
+                            .unwrap()
+                    },
+                    |(i, j)| segment_tree.query(i, j),
+                    BatchSize::SmallInput,
+                );
+            });
+        }
+    }
+    group.finish();
+}
+
+pub fn flat_segment_tree_queries_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("flat_segment_tree_queries_benchmark");
+    let mut rng = rand::thread_rng();
+    let node_distr = Uniform::from(-N..=N);
+    for i in 1..=6 {
+        for j in 1..10 {
+            let n = j * 10_usize.pow(i);
+            let nodes: Vec<_> = (&mut rng)
+                .sample_iter(node_distr)
+                .map(|x| Min::initialize(&x))
+                .take(n)
+                .collect();
+            let segment_tree = Flat::build(&nodes);
+            let index_distr = Uniform::from(0..n);
+            group.throughput(Throughput::Elements(n as u64));
+            group.warm_up_time(Duration::from_secs(1));
+            group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, &n| {
+                b.iter_batched(
+                    || {
+                        Some((index_distr.sample(&mut rng), index_distr.sample(&mut rng)))
+                            .map(|(i, j)| (i.min(j), i.max(j)))
+                            .unwrap()
+                    },
+                    |(i, j)| segment_tree.query(i, j),
+                    BatchSize::SmallInput,
+                );
+            });
+        }
+    }
+    group.finish();
+}
+
 pub fn iterative_segment_tree_updates_benchmark(c: &mut Criterion) {
     let mut group = c.benchmark_group("iterative_segment_tree_updates_benchmark");
     let mut rng = rand::thread_rng();
@@ -204,6 +268,8 @@ criterion_group!(
     benches,
     recursive_segment_tree_queries_benchmark,
     iterative_segment_tree_queries_benchmark,
+    padded_iterative_segment_tree_queries_benchmark,
+    flat_segment_tree_queries_benchmark,
     lazy_recursive_segment_tree_queries_benchmark,
     recursive_segment_tree_updates_benchmark,
     iterative_segment_tree_updates_benchmark
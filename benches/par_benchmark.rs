@@ -0,0 +1,97 @@
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rand::{distributions::Uniform, prelude::Distribution, Rng};
+use seg_tree::{nodes::Node, utils::Sum, Iterative, Persistent};
+
+const N: i64 = 1_000_000;
+const QUERIES: usize = 1_000_000;
+
+fn random_queries(n: usize, q: usize) -> Vec<(usize, usize)> {
+    let mut rng = rand::thread_rng();
+    let index_distr = Uniform::from(0..n);
+    (0..q)
+        .map(|_| {
+            let (i, j) = (index_distr.sample(&mut rng), index_distr.sample(&mut rng));
+            (i.min(j), i.max(j))
+        })
+        .collect()
+}
+
+pub fn iterative_par_query_batch_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("iterative_par_query_batch_benchmark");
+    let rng = rand::thread_rng();
+    let n = 1_000_000;
+    let nodes: Vec<_> = rng
+        .sample_iter(Uniform::from(-N..=N))
+        .map(|x| Sum::initialize(&x))
+        .take(n)
+        .collect();
+    let segment_tree = Iterative::build(&nodes);
+    let queries = random_queries(n, QUERIES);
+    group.throughput(Throughput::Elements(QUERIES as u64));
+    group.warm_up_time(Duration::from_secs(1));
+    group.sample_size(10);
+    let max_threads = std::thread::available_parallelism().map_or(1, usize::from);
+    let mut threads = 1;
+    while threads <= max_threads {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(threads), &threads, |b, _| {
+            b.iter(|| pool.install(|| segment_tree.par_query_batch(&queries)));
+        });
+        threads *= 2;
+    }
+    group.finish();
+}
+
+pub fn persistent_par_query_batch_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("persistent_par_query_batch_benchmark");
+    let mut rng = rand::thread_rng();
+    let n = 1_000_000;
+    let node_distr = Uniform::from(-N..=N);
+    let nodes: Vec<_> = (&mut rng)
+        .sample_iter(node_distr)
+        .map(|x| Sum::initialize(&x))
+        .take(n)
+        .collect();
+    let mut segment_tree = Persistent::build(&nodes);
+    let index_distr = Uniform::from(0..n);
+    for version in 0..1_000 {
+        segment_tree.update(
+            version,
+            index_distr.sample(&mut rng),
+            &node_distr.sample(&mut rng),
+        );
+    }
+    let version_distr = Uniform::from(0..segment_tree.versions());
+    let queries: Vec<_> = random_queries(n, QUERIES)
+        .into_iter()
+        .map(|(i, j)| (version_distr.sample(&mut rng), i, j))
+        .collect();
+    group.throughput(Throughput::Elements(QUERIES as u64));
+    group.warm_up_time(Duration::from_secs(1));
+    group.sample_size(10);
+    let max_threads = std::thread::available_parallelism().map_or(1, usize::from);
+    let mut threads = 1;
+    while threads <= max_threads {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(threads), &threads, |b, _| {
+            b.iter(|| pool.install(|| segment_tree.par_query_batch(&queries)));
+        });
+        threads *= 2;
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    iterative_par_query_batch_benchmark,
+    persistent_par_query_batch_benchmark
+);
+criterion_main!(benches);
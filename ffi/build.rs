@@ -0,0 +1,12 @@
+use std::{env, fs, path::PathBuf};
+
+/// Writes the header declaring the functions of the library to `include/seg_tree.h`, only if it changed so it isn't rebuilt every time.
+fn main() {
+    let path = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap()).join("include/seg_tree.h");
+    let header = seg_tree::ffi::header();
+    if fs::read_to_string(&path).ok().as_deref() != Some(header.as_str()) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, header).unwrap();
+    }
+    println!("cargo:rerun-if-changed=build.rs");
+}
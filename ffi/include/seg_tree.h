@@ -0,0 +1,37 @@
+/* Generated by seg_tree::ffi::header, don't edit it by hand. */
+#ifndef SEG_TREE_H
+#define SEG_TREE_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+typedef struct SegSumTree SegSumTree;
+SegSumTree *seg_sum_tree_new(const int64_t *values, size_t len);
+bool seg_sum_tree_update(SegSumTree *tree, size_t i, int64_t value);
+bool seg_sum_tree_query(const SegSumTree *tree, size_t left, size_t right, int64_t *out);
+size_t seg_sum_tree_len(const SegSumTree *tree);
+void seg_sum_tree_free(SegSumTree *tree);
+
+typedef struct SegMinTree SegMinTree;
+SegMinTree *seg_min_tree_new(const int64_t *values, size_t len);
+bool seg_min_tree_update(SegMinTree *tree, size_t i, int64_t value);
+bool seg_min_tree_query(const SegMinTree *tree, size_t left, size_t right, int64_t *out);
+size_t seg_min_tree_len(const SegMinTree *tree);
+void seg_min_tree_free(SegMinTree *tree);
+
+typedef struct SegMaxTree SegMaxTree;
+SegMaxTree *seg_max_tree_new(const int64_t *values, size_t len);
+bool seg_max_tree_update(SegMaxTree *tree, size_t i, int64_t value);
+bool seg_max_tree_query(const SegMaxTree *tree, size_t left, size_t right, int64_t *out);
+size_t seg_max_tree_len(const SegMaxTree *tree);
+void seg_max_tree_free(SegMaxTree *tree);
+
+typedef struct SegAssignMinTree SegAssignMinTree;
+SegAssignMinTree *seg_assign_min_tree_new(const int64_t *values, size_t len);
+bool seg_assign_min_tree_update(SegAssignMinTree *tree, size_t left, size_t right, int64_t value);
+bool seg_assign_min_tree_query(SegAssignMinTree *tree, size_t left, size_t right, int64_t *out);
+size_t seg_assign_min_tree_len(const SegAssignMinTree *tree);
+void seg_assign_min_tree_free(SegAssignMinTree *tree);
+
+#endif /* SEG_TREE_H */
@@ -0,0 +1,3 @@
+//! C library of the segment trees of [`seg_tree::ffi`], built as a `cdylib` and a `staticlib`, whose functions are declared in the generated `include/seg_tree.h` header.
+
+pub use seg_tree::ffi::*;
@@ -0,0 +1,19 @@
+mod block_sparse_table;
+mod distinct_count;
+mod kth_query;
+/// Algorithms which answer a batch of queries at once, given upfront.
+pub mod offline;
+mod prefix_agg;
+mod range_mode;
+mod row_aggregated;
+mod sliding_window;
+mod static_rect_count;
+mod static_rmq;
+mod versioned_aggregate;
+
+pub use self::{
+    block_sparse_table::BlockSparseTable, distinct_count::DistinctCount, kth_query::KthQuery,
+    prefix_agg::PrefixAgg, range_mode::RangeMode, row_aggregated::RowAggregated,
+    sliding_window::SlidingWindow, static_rect_count::StaticRectCount, static_rmq::StaticRmq,
+    versioned_aggregate::VersionedAggregate,
+};
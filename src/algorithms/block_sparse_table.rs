@@ -0,0 +1,158 @@
+use alloc::{vec, vec::Vec};
+
+use crate::nodes::{IdempotentNode, Node};
+
+/// Amount of elements of each block, the queries inside a block are answered by a sparse table of at most [`MAX_LEVEL`] levels.
+const BLOCK: usize = 32;
+const MAX_LEVEL: usize = 4;
+
+/// Read only range queries in `O(1)` for any idempotent node, such as gcd, see [`IdempotentNode`].
+///
+/// The elements are split in blocks of `32`, each block has a sparse table of the segments of length up to `16` inside it, and there's a sparse table over the results of the blocks, so any range is covered by at most four overlapping segments.
+/// It's the generic fallback of [`BlockSparseTable`](crate::algorithms::BlockSparseTable), whose in-block lookup encodes the Cartesian tree of each block in a bitmask, which needs [`combine`](Node::combine) to return one of its arguments, see [`SelectionNode`](crate::nodes::SelectionNode).
+/// So building it takes `O(n+(n/32)*log(n))` instead of `O(n)`, although the sparse table over the blocks has fewer than `n` nodes for `n` up to `2^32`.
+/// It uses `O(n+(n/32)*log(n))` space, assuming that each node uses `O(1)` space.
+/// ```
+/// # use seg_tree::{algorithms::BlockSparseTable,utils::Min,nodes::Node};
+/// # let nodes: Vec<Min<usize>> = (0..100).map(|x| Min::initialize(&(x % 7))).collect();
+/// let table = BlockSparseTable::build(&nodes); // [0,1,2,3,4,5,6,0,1,...] with Min<usize> nodes
+/// assert_eq!(table.query(1, 6).unwrap().value(), &1);
+/// assert_eq!(table.query(1, 99).unwrap().value(), &0);
+/// ```
+pub struct BlockSparseTable<T> {
+    inner: Vec<Vec<T>>,
+    blocks: Vec<Vec<T>>,
+}
+
+impl<T> BlockSparseTable<T>
+where
+    T: IdempotentNode + Clone,
+{
+    /// Builds the tables from slice.
+    /// It has time complexity of `O(n+(n/32)*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn build(values: &[T]) -> Self {
+        let inner = sparse_table(values.to_vec(), MAX_LEVEL);
+        let results = (0..values.len())
+            .step_by(BLOCK)
+            .map(|start| table_query(&inner, start, (start + BLOCK).min(values.len()) - 1))
+            .collect();
+        let blocks = sparse_table(results, usize::MAX);
+        Self { inner, blocks }
+    }
+
+    /// Returns the result from the range `[left,right]`.
+    /// It returns None if and only if range is empty.
+    /// It will **panic** if left or right are not in `[0,n)`.
+    /// It has time complexity of `O(1)`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn query(&self, left: usize, right: usize) -> Option<T> {
+        if left > right {
+            return None;
+        }
+        let (first, last) = (left / BLOCK, right / BLOCK);
+        if first == last {
+            return Some(table_query(&self.inner, left, right));
+        }
+        let prefix = table_query(&self.inner, left, first * BLOCK + BLOCK - 1);
+        let suffix = table_query(&self.inner, last * BLOCK, right);
+        let prefix = if first + 1 < last {
+            T::combine(&prefix, &table_query(&self.blocks, first + 1, last - 1))
+        } else {
+            prefix
+        };
+        Some(T::combine(&prefix, &suffix))
+    }
+
+    /// Returns the amount of elements.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.inner.first().map_or(0, Vec::len)
+    }
+
+    /// Returns `true` if there are no elements.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Returns the levels of the sparse table of values, where the `k`-th level has the results of the segments of length `2^k`, up to `max_level`.
+pub(super) fn sparse_table<T>(values: Vec<T>, max_level: usize) -> Vec<Vec<T>>
+where
+    T: Node,
+{
+    let mut levels = vec![values];
+    for level in 1..=max_level {
+        let half = 1 << (level - 1);
+        let previous = &levels[level - 1];
+        if previous.len() <= half {
+            break;
+        }
+        let next = (0..previous.len() - half)
+            .map(|i| T::combine(&previous[i], &previous[i + half]))
+            .collect();
+        levels.push(next);
+    }
+    levels
+}
+
+/// Returns the result from the range `[left,right]` combining two overlapping segments from the highest level which fits, so `right-left+1` must be at most twice the length of the segments of the last level.
+pub(super) fn table_query<T>(levels: &[Vec<T>], left: usize, right: usize) -> T
+where
+    T: Node,
+{
+    let level = ((right - left + 1).ilog2() as usize).min(levels.len() - 1);
+    let length = 1 << level;
+    T::combine(&levels[level][left], &levels[level][right + 1 - length])
+}
+
+impl<T> core::fmt::Debug for BlockSparseTable<T>
+where
+    T: Node,
+    T::Value: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list()
+            .entries(self.inner.iter().take(1).flatten().map(Node::value))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{distributions::Uniform, prelude::Distribution, thread_rng};
+
+    use crate::{
+        nodes::Node,
+        utils::{Max, Min},
+    };
+
+    use super::BlockSparseTable;
+
+    #[test]
+    fn query_works() {
+        const N: usize = 150;
+        let random = Uniform::from(-1000..1000);
+        let values: Vec<i32> = random.sample_iter(thread_rng()).take(N).collect();
+        let nodes: Vec<Min<i32>> = values.iter().map(Min::initialize).collect();
+        let table = BlockSparseTable::build(&nodes);
+        assert_eq!(table.len(), N);
+        for left in 0..N {
+            for right in left..N {
+                let expected = values[left..=right].iter().min().unwrap();
+                assert_eq!(table.query(left, right).unwrap().value(), expected);
+            }
+        }
+        assert!(table.query(5, 4).is_none());
+    }
+
+    #[test]
+    fn small_and_empty_work() {
+        let nodes: Vec<Max<usize>> = (0..3).map(|x| Max::initialize(&x)).collect();
+        let table = BlockSparseTable::build(&nodes);
+        assert_eq!(table.query(0, 2).unwrap().value(), &2);
+        assert_eq!(table.query(0, 0).unwrap().value(), &0);
+        assert!(BlockSparseTable::<Max<usize>>::build(&[]).is_empty());
+    }
+}
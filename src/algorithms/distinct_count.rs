@@ -0,0 +1,103 @@
+use alloc::vec;
+
+use crate::{
+    nodes::Node,
+    segment_tree::Persistent,
+    utils::{Compressor, Sum},
+};
+
+/// Amount of distinct values in subarrays of a fixed array, built on a [`Persistent`] counting tree.
+///
+/// A value is counted in `[l,r]` at its first occurrence in the range, which is the only one whose previous occurrence in the array is before `l`.
+/// So version `i` of the tree counts the previous occurrences of the first `i` elements, shifted by one so elements without a previous occurrence are counted at `0`, and any subarray is the difference between versions `r+1` and `l`.
+/// For a batch of ranges known upfront, [`count_distinct_in_ranges`](super::offline::count_distinct_in_ranges) uses `O(n)` space instead.
+/// It uses `O(n*log(n))` space.
+/// ```
+/// # use seg_tree::algorithms::DistinctCount;
+/// let distinct = DistinctCount::build(&[1, 2, 1, 3, 2]);
+/// assert_eq!(distinct.query(0, 2), 2); // 1 and 2.
+/// assert_eq!(distinct.query(1, 4), 3);
+/// assert_eq!(distinct.query(3, 2), 0);
+/// ```
+pub struct DistinctCount {
+    tree: Persistent<Sum<usize>>,
+}
+
+impl DistinctCount {
+    /// Builds the counting tree from slice.
+    /// It has time complexity of `O(n*log(n))`.
+    #[must_use]
+    pub fn build<T>(values: &[T]) -> Self
+    where
+        T: Ord + Clone,
+    {
+        let compressor: Compressor<T> = values.iter().cloned().collect();
+        let mut last = vec![0; compressor.len()];
+        let nodes = vec![Sum::initialize(&0); values.len() + 1];
+        let mut tree = Persistent::build(&nodes);
+        for (version, value) in values.iter().enumerate() {
+            let rank = compressor.lower_bound(value);
+            tree.update_with(version, last[rank], |count| count + 1);
+            last[rank] = version + 1;
+        }
+        Self { tree }
+    }
+
+    /// Returns the amount of distinct values from the range `[left,right]`.
+    /// It will **panic** if left or right are not in `[0,n)`.
+    /// It has time complexity of `O(log(n))`.
+    #[must_use]
+    pub fn query(&self, left: usize, right: usize) -> usize {
+        if left > right {
+            return 0;
+        }
+        let count = |version| {
+            self.tree
+                .query(version, 0, left)
+                .map_or(0, |node| *node.value())
+        };
+        count(right + 1) - count(left)
+    }
+
+    /// Returns the amount of elements.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.tree.versions() - 1
+    }
+
+    /// Returns `true` if there are no elements.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl core::fmt::Debug for DistinctCount {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("DistinctCount")
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::DistinctCount;
+
+    #[test]
+    fn distinct_count_works() {
+        let values: Vec<usize> = (0..40).map(|x| (x * x * 7) % 9).collect();
+        let distinct = DistinctCount::build(&values);
+        assert_eq!(distinct.len(), values.len());
+        for left in 0..values.len() {
+            for right in left..values.len() {
+                let expected = values[left..=right].iter().collect::<BTreeSet<_>>().len();
+                assert_eq!(distinct.query(left, right), expected);
+            }
+        }
+        assert_eq!(distinct.query(3, 2), 0);
+        assert!(DistinctCount::build::<usize>(&[]).is_empty());
+    }
+}
@@ -0,0 +1,143 @@
+use alloc::vec;
+
+use crate::{
+    nodes::Node,
+    segment_tree::Persistent,
+    utils::{Compressor, Sum},
+};
+
+/// Order statistics over subarrays of a fixed array, built on a [`Persistent`] counting tree.
+///
+/// The values are compressed to their rank among the distinct values of the array, and version `i` of the tree counts how many times each rank appears in the first `i` elements, so any subarray `[l,r]` is the difference between versions `r+1` and `l`.
+/// It uses `O(n*log(n))` space.
+/// ```
+/// # use seg_tree::algorithms::KthQuery;
+/// let kth = KthQuery::build(&[5, 1, 4, 2, 3]);
+/// assert_eq!(kth.kth_smallest(1, 3, 0), Some(&1)); // The smallest of [1,4,2].
+/// assert_eq!(kth.kth_smallest(1, 3, 2), Some(&4));
+/// assert_eq!(kth.count_in_range(0, 4, &2, &4), 3); // 4, 2 and 3 are in [2,4].
+/// ```
+pub struct KthQuery<T> {
+    compressor: Compressor<T>,
+    tree: Persistent<Sum<usize>>,
+}
+
+impl<T> KthQuery<T>
+where
+    T: Ord + Clone,
+{
+    /// Builds the counting tree from slice.
+    /// It has time complexity of `O(n*log(n))`.
+    #[must_use]
+    pub fn build(values: &[T]) -> Self {
+        let compressor: Compressor<T> = values.iter().cloned().collect();
+        let nodes = vec![Sum::initialize(&0); compressor.len()];
+        let mut tree = Persistent::build(&nodes);
+        if !compressor.is_empty() {
+            for (version, value) in values.iter().enumerate() {
+                let rank = compressor.lower_bound(value);
+                let count = tree
+                    .query(version, rank, rank)
+                    .map_or(0, |node| *node.value());
+                tree.update(version, rank, &(count + 1));
+            }
+        }
+        Self { compressor, tree }
+    }
+
+    /// Returns the k-th smallest value (counting from `0`) from the range `[left,right]`, or None if the range has at most `k` values.
+    /// It will **panic** if left or right are not in `[0,n)`.
+    /// It has time complexity of `O(log(n))`.
+    #[must_use]
+    pub fn kth_smallest(&self, left: usize, right: usize, k: usize) -> Option<&T> {
+        if left > right {
+            return None;
+        }
+        let rank = self.tree.lower_bound_diff(right + 1, left, k)?;
+        Some(self.compressor.key(rank))
+    }
+
+    /// Returns how many values from the range `[left,right]` are in `[lo,hi]`.
+    /// It will **panic** if left or right are not in `[0,n)`.
+    /// It has time complexity of `O(log(n))`.
+    #[must_use]
+    pub fn count_in_range(&self, left: usize, right: usize, lo: &T, hi: &T) -> usize {
+        if left > right {
+            return 0;
+        }
+        let first = self.compressor.lower_bound(lo);
+        let last = self.compressor.upper_bound(hi);
+        if first >= last {
+            return 0;
+        }
+        self.count_ranks(left, right + 1, first, last - 1)
+    }
+
+    /// Returns how many values with rank in `[first,last]` there are between versions `lo` and `hi`.
+    fn count_ranks(&self, lo: usize, hi: usize, first: usize, last: usize) -> usize {
+        let count = |version| *self.tree.query(version, first, last).unwrap().value();
+        count(hi) - count(lo)
+    }
+}
+
+impl<T> core::fmt::Debug for KthQuery<T>
+where
+    T: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("KthQuery")
+            .field("compressor", &self.compressor)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{distributions::Uniform, prelude::Distribution, thread_rng};
+
+    use super::KthQuery;
+
+    const N: usize = 100;
+
+    #[test]
+    fn kth_smallest_works() {
+        let random = Uniform::from(0..20);
+        let values: Vec<i32> = random.sample_iter(thread_rng()).take(N).collect();
+        let kth = KthQuery::build(&values);
+        for i in 0..N {
+            for j in i..N {
+                let mut range = values[i..=j].to_vec();
+                range.sort_unstable();
+                for (k, value) in range.iter().enumerate() {
+                    assert_eq!(kth.kth_smallest(i, j, k), Some(value));
+                }
+                assert_eq!(kth.kth_smallest(i, j, range.len()), None);
+            }
+        }
+    }
+
+    #[test]
+    fn count_in_range_works() {
+        let random = Uniform::from(0..20);
+        let values: Vec<i32> = random.sample_iter(thread_rng()).take(N).collect();
+        let kth = KthQuery::build(&values);
+        for i in (0..N).step_by(7) {
+            for j in i..N {
+                for (lo, hi) in [(0, 19), (5, 10), (-3, 2), (10, 5), (21, 30)] {
+                    let expected = values[i..=j]
+                        .iter()
+                        .filter(|&&x| lo <= x && x <= hi)
+                        .count();
+                    assert_eq!(kth.count_in_range(i, j, &lo, &hi), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn empty_works() {
+        let kth = KthQuery::<i32>::build(&[]);
+        assert_eq!(kth.count_in_range(1, 0, &0, &10), 0);
+        assert_eq!(kth.kth_smallest(1, 0, 0), None);
+    }
+}
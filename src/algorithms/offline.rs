@@ -0,0 +1,369 @@
+use alloc::{vec, vec::Vec};
+use core::cmp::Ordering;
+
+use crate::{
+    nodes::{LazyNode, Node},
+    segment_tree::{Iterative, LazyRecursive},
+    utils::{Compressor, Sum},
+};
+
+/// Returns the amount of inversions of values, that is, the amount of pairs `i<j` such that `values[i]>values[j]`.
+/// It has time complexity of `O(n*log(n))`.
+/// ```
+/// # use seg_tree::algorithms::offline::count_inversions;
+/// assert_eq!(count_inversions(&[3, 1, 2]), 2); // (3,1) and (3,2).
+/// assert_eq!(count_inversions(&[1, 1, 2]), 0);
+/// ```
+#[must_use]
+pub fn count_inversions<T>(values: &[T]) -> usize
+where
+    T: Ord + Clone,
+{
+    let (ranks, distinct) = compress(values);
+    let mut counter = RankCounter::new(distinct);
+    let mut inversions = 0;
+    for &rank in &ranks {
+        inversions += counter.greater(rank);
+        counter.insert(rank);
+    }
+    inversions
+}
+
+/// Returns the amount of inversions of each range `[left,right]` of values, in the same order as ranges.
+///
+/// The ranges are answered together with Mo's algorithm, moving the ends of a window over a counting tree.
+/// It doesn't use a merge sort tree, since it only counts the elements of a range smaller than a given value, so it would take `O(len*log(n)^2)` for each range of length `len`, i.e. `O(q*n*log(n)^2)` for long ranges, while Mo's algorithm shares the moves of the window between ranges.
+/// It will **panic** if any left or right are not in `[0,n)`.
+/// It has time complexity of `O((n+q)*sqrt(n)*log(n))`, where `q` is the amount of ranges.
+/// ```
+/// # use seg_tree::algorithms::offline::count_inversions_in_ranges;
+/// let values = [4, 1, 3, 2];
+/// assert_eq!(count_inversions_in_ranges(&values, &[(0, 3), (1, 3), (2, 2), (2, 1)]), [4, 1, 0, 0]);
+/// ```
+#[must_use]
+pub fn count_inversions_in_ranges<T>(values: &[T], ranges: &[(usize, usize)]) -> Vec<usize>
+where
+    T: Ord + Clone,
+{
+    let (ranks, distinct) = compress(values);
+    let block = values.len().isqrt().max(1);
+    let mut order: Vec<usize> = (0..ranges.len())
+        .filter(|&k| ranges[k].0 <= ranges[k].1)
+        .collect();
+    order.sort_unstable_by_key(|&k| {
+        let (left, right) = ranges[k];
+        (left / block, right)
+    });
+    let mut counter = RankCounter::new(distinct);
+    let mut answers = vec![0; ranges.len()];
+    // The window is [left,right), and inversions are the ones inside it.
+    let (mut left, mut right, mut inversions) = (0, 0, 0);
+    for k in order {
+        let (query_left, query_right) = ranges[k];
+        while right <= query_right {
+            inversions += counter.greater(ranks[right]);
+            counter.insert(ranks[right]);
+            right += 1;
+        }
+        while left > query_left {
+            left -= 1;
+            inversions += counter.less(ranks[left]);
+            counter.insert(ranks[left]);
+        }
+        while right > query_right + 1 {
+            right -= 1;
+            counter.remove(ranks[right]);
+            inversions -= counter.greater(ranks[right]);
+        }
+        while left < query_left {
+            counter.remove(ranks[left]);
+            inversions -= counter.less(ranks[left]);
+            left += 1;
+        }
+        answers[k] = inversions;
+    }
+    answers
+}
+
+/// Returns the amount of distinct values of each range `[left,right]` of values, in the same order as ranges.
+///
+/// The ranges are sorted by their right end, and the elements are added in order to an [`Iterative`] tree which only marks the last occurrence of each value so far, so the distinct values of a range ending at the last added element are the marks inside it.
+/// For ranges which aren't known upfront, see [`DistinctCount`](super::DistinctCount).
+/// It will **panic** if any left or right are not in `[0,n)`.
+/// It has time complexity of `O((n+q)*log(n))`, where `q` is the amount of ranges.
+/// ```
+/// # use seg_tree::algorithms::offline::count_distinct_in_ranges;
+/// let values = [1, 2, 1, 3, 2];
+/// assert_eq!(count_distinct_in_ranges(&values, &[(0, 2), (1, 4), (3, 3), (2, 1)]), [2, 3, 1, 0]);
+/// ```
+#[must_use]
+pub fn count_distinct_in_ranges<T>(values: &[T], ranges: &[(usize, usize)]) -> Vec<usize>
+where
+    T: Ord + Clone,
+{
+    let (ranks, distinct) = compress(values);
+    let mut order: Vec<usize> = (0..ranges.len())
+        .filter(|&k| ranges[k].0 <= ranges[k].1)
+        .collect();
+    order.sort_unstable_by_key(|&k| ranges[k].1);
+    let nodes = vec![Sum::initialize(&0); values.len()];
+    let mut tree = Iterative::build(&nodes);
+    let mut last = vec![None; distinct];
+    let mut answers = vec![0; ranges.len()];
+    let mut added = 0;
+    for k in order {
+        let (left, right) = ranges[k];
+        while added <= right {
+            if let Some(previous) = last[ranks[added]].replace(added) {
+                tree.update(previous, &0);
+            }
+            tree.update(added, &1);
+            added += 1;
+        }
+        answers[k] = tree.query(left, right).map_or(0, |node| *node.value());
+    }
+    answers
+}
+
+/// Returns the area of the union of rectangles, each given as `(x1, y1, x2, y2)` with `(x1, y1)` as its lower left corner and `(x2, y2)` as its upper right one.
+///
+/// It sweeps the rectangles from bottom to top over a [`LazyRecursive`] tree of the compressed `x` coordinates, where each node keeps the minimum amount of rectangles covering its segment and the width covered by that minimum, so the uncovered width is the one covered by `0` rectangles.
+/// Rectangles with `x1 >= x2` or `y1 >= y2` are empty, so they don't add any area.
+/// It has time complexity of `O(n*log(n))`.
+/// ```
+/// # use seg_tree::algorithms::offline::union_area;
+/// assert_eq!(union_area(&[(0, 0, 2, 2), (1, 1, 3, 3)]), 7);
+/// assert_eq!(union_area(&[(0, 0, 4, 4), (1, 1, 2, 2), (5, 5, 5, 9)]), 16);
+/// ```
+#[must_use]
+pub fn union_area(rectangles: &[(i64, i64, i64, i64)]) -> u128 {
+    let rectangles: Vec<_> = rectangles
+        .iter()
+        .filter(|&&(x1, y1, x2, y2)| x1 < x2 && y1 < y2)
+        .collect();
+    let xs: Compressor<i64> = rectangles
+        .iter()
+        .flat_map(|&&(x1, _, x2, _)| [x1, x2])
+        .collect();
+    if xs.len() < 2 {
+        return 0;
+    }
+    let nodes: Vec<MinCount> = xs
+        .keys()
+        .windows(2)
+        .map(|x| MinCount::initialize(&(0, x[1].abs_diff(x[0]))))
+        .collect();
+    let width = xs.key(xs.len() - 1).abs_diff(*xs.key(0));
+    let mut tree = LazyRecursive::build(&nodes);
+    let mut events: Vec<(i64, i64, usize, usize)> = Vec::with_capacity(2 * rectangles.len());
+    for &&(x1, y1, x2, y2) in &rectangles {
+        let (left, right) = (xs.lower_bound(&x1), xs.lower_bound(&x2) - 1);
+        events.push((y1, 1, left, right));
+        events.push((y2, -1, left, right));
+    }
+    events.sort_unstable();
+    let mut area = 0;
+    let mut last_y = events[0].0;
+    for (y, delta, left, right) in events {
+        let uncovered = tree
+            .query(0, nodes.len() - 1)
+            .map_or(0, |node| match *node.value() {
+                (0, count) => count,
+                _ => 0,
+            });
+        area += u128::from(width - uncovered) * u128::from(y.abs_diff(last_y));
+        tree.update(left, right, &(delta, 0));
+        last_y = y;
+    }
+    area
+}
+
+/// Returns the rank of each value among the distinct values, and the amount of distinct values.
+fn compress<T>(values: &[T]) -> (Vec<usize>, usize)
+where
+    T: Ord + Clone,
+{
+    let compressor: Compressor<T> = values.iter().cloned().collect();
+    let ranks = values
+        .iter()
+        .map(|value| compressor.lower_bound(value))
+        .collect();
+    (ranks, compressor.len())
+}
+
+/// Multiset of ranks in `[0,m)`, counting how many of them are smaller or greater than a given one.
+struct RankCounter {
+    counts: Vec<usize>,
+    tree: Iterative<Sum<usize>>,
+}
+
+impl RankCounter {
+    fn new(m: usize) -> Self {
+        let counts = vec![0; m];
+        let nodes = vec![Sum::initialize(&0); m];
+        Self {
+            counts,
+            tree: Iterative::build(&nodes),
+        }
+    }
+
+    fn insert(&mut self, rank: usize) {
+        self.counts[rank] += 1;
+        self.tree.update(rank, &self.counts[rank]);
+    }
+
+    fn remove(&mut self, rank: usize) {
+        self.counts[rank] -= 1;
+        self.tree.update(rank, &self.counts[rank]);
+    }
+
+    fn less(&self, rank: usize) -> usize {
+        rank.checked_sub(1).map_or(0, |right| self.count(0, right))
+    }
+
+    fn greater(&self, rank: usize) -> usize {
+        self.count(rank + 1, self.counts.len() - 1)
+    }
+
+    fn count(&self, left: usize, right: usize) -> usize {
+        if left > right {
+            return 0;
+        }
+        self.tree.query(left, right).map_or(0, |node| *node.value())
+    }
+}
+
+/// Node of the minimum of a segment and the total width of the elements equal to it, the lazy value adds its minimum to every element, ignoring its width.
+#[derive(Clone, Debug)]
+struct MinCount {
+    value: (i64, u64),
+    lazy_value: Option<(i64, u64)>,
+}
+
+impl Node for MinCount {
+    type Value = (i64, u64);
+    fn initialize(value: &Self::Value) -> Self {
+        Self {
+            value: *value,
+            lazy_value: None,
+        }
+    }
+    fn combine(a: &Self, b: &Self) -> Self {
+        let ((min_a, count_a), (min_b, count_b)) = (a.value, b.value);
+        let value = match min_a.cmp(&min_b) {
+            Ordering::Less => (min_a, count_a),
+            Ordering::Equal => (min_a, count_a + count_b),
+            Ordering::Greater => (min_b, count_b),
+        };
+        Self {
+            value,
+            lazy_value: None,
+        }
+    }
+    fn value(&self) -> &Self::Value {
+        &self.value
+    }
+}
+
+impl LazyNode for MinCount {
+    fn lazy_update(&mut self, _i: usize, _j: usize) {
+        if let Some((delta, _)) = self.lazy_value.take() {
+            self.value.0 += delta;
+        }
+    }
+    fn update_lazy_value(&mut self, new_value: &Self::Value) {
+        let delta = self.lazy_value.map_or(0, |(delta, _)| delta) + new_value.0;
+        self.lazy_value = Some((delta, 0));
+    }
+    fn lazy_value(&self) -> Option<&Self::Value> {
+        self.lazy_value.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{distributions::Uniform, prelude::Distribution, thread_rng, Rng};
+
+    use super::{
+        count_distinct_in_ranges, count_inversions, count_inversions_in_ranges, union_area,
+    };
+
+    const N: usize = 100;
+
+    fn brute_force(values: &[i32]) -> usize {
+        (0..values.len())
+            .map(|j| (0..j).filter(|&i| values[i] > values[j]).count())
+            .sum()
+    }
+
+    #[test]
+    fn count_inversions_works() {
+        let random = Uniform::from(-10..10);
+        let values: Vec<i32> = random.sample_iter(thread_rng()).take(N).collect();
+        assert_eq!(count_inversions(&values), brute_force(&values));
+        assert_eq!(count_inversions::<i32>(&[]), 0);
+    }
+
+    #[test]
+    fn count_inversions_in_ranges_works() {
+        let random = Uniform::from(-10..10);
+        let values: Vec<i32> = random.sample_iter(thread_rng()).take(N).collect();
+        let mut rng = thread_rng();
+        let ranges: Vec<(usize, usize)> = (0..N)
+            .map(|_| (rng.gen_range(0..N), rng.gen_range(0..N)))
+            .collect();
+        let answers = count_inversions_in_ranges(&values, &ranges);
+        for (&(left, right), answer) in ranges.iter().zip(answers) {
+            let expected = if left <= right {
+                brute_force(&values[left..=right])
+            } else {
+                0
+            };
+            assert_eq!(answer, expected);
+        }
+    }
+
+    #[test]
+    fn count_distinct_in_ranges_works() {
+        let random = Uniform::from(-10..10);
+        let values: Vec<i32> = random.sample_iter(thread_rng()).take(N).collect();
+        let mut rng = thread_rng();
+        let ranges: Vec<(usize, usize)> = (0..N)
+            .map(|_| (rng.gen_range(0..N), rng.gen_range(0..N)))
+            .collect();
+        let answers = count_distinct_in_ranges(&values, &ranges);
+        for (&(left, right), answer) in ranges.iter().zip(answers) {
+            let expected = if left <= right {
+                (-10..10)
+                    .filter(|x| values[left..=right].contains(x))
+                    .count()
+            } else {
+                0
+            };
+            assert_eq!(answer, expected);
+        }
+    }
+
+    #[test]
+    fn union_area_works() {
+        let random = Uniform::from(-10..10);
+        let mut rng = thread_rng();
+        let rectangles: Vec<(i64, i64, i64, i64)> = (0..N)
+            .map(|_| {
+                let (x1, y1) = (random.sample(&mut rng), random.sample(&mut rng));
+                (x1, y1, x1 + rng.gen_range(0..5), y1 + rng.gen_range(0..5))
+            })
+            .collect();
+        let expected = (-10..15)
+            .flat_map(|x| (-10..15).map(move |y| (x, y)))
+            .filter(|&(x, y)| {
+                rectangles
+                    .iter()
+                    .any(|&(x1, y1, x2, y2)| x1 <= x && x < x2 && y1 <= y && y < y2)
+            })
+            .count();
+        assert_eq!(union_area(&rectangles), u128::try_from(expected).unwrap());
+        assert_eq!(union_area(&[]), 0);
+        assert_eq!(union_area(&[(0, 0, 0, 5)]), 0);
+    }
+}
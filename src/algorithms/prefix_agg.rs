@@ -0,0 +1,149 @@
+use alloc::vec::Vec;
+
+use crate::nodes::{InvertibleNode, Node};
+
+/// Results of every prefix of a fixed array, for query only workloads where a segment tree is overkill.
+///
+/// [`Adaptive`](crate::Adaptive) uses it until the first update, and only then builds a segment tree.
+///
+/// It answers [`prefix`](PrefixAgg::prefix) in `O(1)`, and any range in `O(1)` if the node implements [`InvertibleNode`].
+/// It uses `O(n)` space, assuming that each node uses `O(1)` space.
+/// ```
+/// # use seg_tree::{algorithms::PrefixAgg,utils::Sum,nodes::Node};
+/// # let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+/// let prefixes = PrefixAgg::build(&nodes); // [0,1,2,3,4,5,6,7,8,9] with Sum<usize> nodes
+/// assert_eq!(prefixes.prefix(3).value(), &6);
+/// assert_eq!(prefixes.query(4, 6).unwrap().value(), &15);
+/// ```
+pub struct PrefixAgg<T> {
+    prefixes: Vec<T>,
+}
+
+impl<T> PrefixAgg<T>
+where
+    T: Node + Clone,
+{
+    /// Builds the results of every prefix of slice.
+    /// It has time complexity of `O(n)`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn build(values: &[T]) -> Self {
+        let mut prefixes: Vec<T> = Vec::with_capacity(values.len());
+        for value in values {
+            let prefix = prefixes
+                .last()
+                .map_or_else(|| value.clone(), |prefix| T::combine(prefix, value));
+            prefixes.push(prefix);
+        }
+        Self { prefixes }
+    }
+
+    /// Returns the result from the range `[0,i]`.
+    /// It will **panic** if i is not in `[0,n)`.
+    /// It has time complexity of `O(1)`.
+    #[must_use]
+    pub fn prefix(&self, i: usize) -> &T {
+        &self.prefixes[i]
+    }
+
+    /// Returns the amount of elements.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.prefixes.len()
+    }
+
+    /// Returns `true` if there are no elements.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.prefixes.is_empty()
+    }
+}
+
+impl<T> PrefixAgg<T>
+where
+    T: InvertibleNode + Clone,
+{
+    /// Returns the result from the range `[left,right]`.
+    /// It returns None if and only if range is empty.
+    /// It will **panic** if left or right are not in `[0,n)`.
+    /// It has time complexity of `O(1)`, assuming that [`uncombine`](InvertibleNode::uncombine) has constant time complexity.
+    #[must_use]
+    pub fn query(&self, left: usize, right: usize) -> Option<T> {
+        if left > right {
+            return None;
+        }
+        let whole = &self.prefixes[right];
+        Some(left.checked_sub(1).map_or_else(
+            || whole.clone(),
+            |before| T::uncombine(whole, &self.prefixes[before]),
+        ))
+    }
+
+    /// Returns the original nodes, recovered by undoing the combines of consecutive prefixes.
+    /// It has time complexity of `O(n)`, assuming that [`uncombine`](InvertibleNode::uncombine) has constant time complexity.
+    pub(crate) fn into_values(self) -> Vec<T> {
+        let first = self.prefixes.first().cloned();
+        first
+            .into_iter()
+            .chain(
+                self.prefixes
+                    .windows(2)
+                    .map(|pair| T::uncombine(&pair[1], &pair[0])),
+            )
+            .collect()
+    }
+}
+
+impl<T> Default for PrefixAgg<T> {
+    fn default() -> Self {
+        Self {
+            prefixes: Vec::new(),
+        }
+    }
+}
+
+impl<T> core::fmt::Debug for PrefixAgg<T>
+where
+    T: Node,
+    T::Value: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list()
+            .entries(self.prefixes.iter().map(Node::value))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        nodes::Node,
+        utils::{Min, Sum},
+    };
+
+    use super::PrefixAgg;
+
+    #[test]
+    fn prefix_works() {
+        let nodes: Vec<Min<usize>> = (0..=10).rev().map(|x| Min::initialize(&x)).collect();
+        let prefixes = PrefixAgg::build(&nodes);
+        for i in 0..=10 {
+            assert_eq!(prefixes.prefix(i).value(), &(10 - i));
+        }
+        assert_eq!(prefixes.len(), 11);
+        assert!(PrefixAgg::<Min<usize>>::build(&[]).is_empty());
+    }
+
+    #[test]
+    fn query_works() {
+        let values: Vec<i64> = (0..=10).map(|x| x * x - 20).collect();
+        let nodes: Vec<Sum<i64>> = values.iter().map(Sum::initialize).collect();
+        let prefixes = PrefixAgg::build(&nodes);
+        for left in 0..=10 {
+            for right in left..=10 {
+                let expected: i64 = values[left..=right].iter().sum();
+                assert_eq!(prefixes.query(left, right).unwrap().value(), &expected);
+            }
+        }
+        assert!(prefixes.query(5, 4).is_none());
+    }
+}
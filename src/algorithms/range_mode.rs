@@ -0,0 +1,168 @@
+use alloc::{vec, vec::Vec};
+use core::iter::repeat_n;
+
+use crate::utils::Compressor;
+
+/// Most frequent value of subarrays of a fixed array, which isn't expressible as a node as the modes of two halves don't determine the mode of the whole.
+///
+/// The array is split in blocks of about `sqrt(n)` elements, and the mode of every range of whole blocks is precomputed, so a query only has to check whether the values of the partial blocks at its ends beat the mode of the whole blocks between them.
+/// Whether a value appears at least `f` times from a position is checked in `O(1)` with the sorted positions of each value, so a query checks `O(sqrt(n))` values in total.
+/// It uses `O(n)` space.
+/// ```
+/// # use seg_tree::algorithms::RangeMode;
+/// let modes = RangeMode::build(&[3, 1, 3, 2, 2, 2, 1]);
+/// assert_eq!(modes.query(0, 2), Some((&3, 2)));
+/// assert_eq!(modes.query(1, 6), Some((&2, 3)));
+/// assert_eq!(modes.query(4, 3), None);
+/// ```
+pub struct RangeMode<T> {
+    compressor: Compressor<T>,
+    ranks: Vec<usize>,
+    // `positions[v]` are the positions of rank `v` in increasing order, and `occurrence[i]` is the index of position `i` in them.
+    positions: Vec<Vec<usize>>,
+    occurrence: Vec<usize>,
+    block: usize,
+    // The rank and frequency of the mode of blocks `[a,b]`, at `a*blocks+b`.
+    modes: Vec<(usize, usize)>,
+}
+
+impl<T> RangeMode<T>
+where
+    T: Ord + Clone,
+{
+    /// Builds the tables from slice.
+    /// It has time complexity of `O(n*sqrt(n))`.
+    #[must_use]
+    pub fn build(values: &[T]) -> Self {
+        let compressor: Compressor<T> = values.iter().cloned().collect();
+        let ranks: Vec<usize> = values
+            .iter()
+            .map(|value| compressor.lower_bound(value))
+            .collect();
+        let mut positions = vec![Vec::new(); compressor.len()];
+        let occurrence = ranks
+            .iter()
+            .enumerate()
+            .map(|(i, &rank)| {
+                positions[rank].push(i);
+                positions[rank].len() - 1
+            })
+            .collect();
+        let block = values.len().isqrt().max(1);
+        let blocks = values.len().div_ceil(block);
+        let mut modes = Vec::with_capacity(blocks * blocks);
+        let mut counts = vec![0; compressor.len()];
+        for first in 0..blocks {
+            // Blocks before the first one are never read, they keep the table square.
+            modes.extend(repeat_n((0, 0), first));
+            counts.fill(0);
+            let mut mode = (0, 0);
+            for (i, &rank) in ranks.iter().enumerate().skip(first * block) {
+                counts[rank] += 1;
+                if counts[rank] > mode.1 {
+                    mode = (rank, counts[rank]);
+                }
+                if (i + 1) % block == 0 || i + 1 == ranks.len() {
+                    modes.push(mode);
+                }
+            }
+        }
+        Self {
+            compressor,
+            ranks,
+            positions,
+            occurrence,
+            block,
+            modes,
+        }
+    }
+
+    /// Returns the most frequent value from the range `[left,right]` and how many times it appears in it, if there are ties any of the tied values may be returned.
+    /// It returns None if and only if range is empty.
+    /// It will **panic** if left or right are not in `[0,n)`.
+    /// It has time complexity of `O(sqrt(n))`.
+    #[must_use]
+    pub fn query(&self, left: usize, right: usize) -> Option<(&T, usize)> {
+        if left > right {
+            return None;
+        }
+        let blocks = self.ranks.len().div_ceil(self.block);
+        let (first, last) = (left / self.block, right / self.block);
+        let (mut mode, prefix_end, suffix_start) = if first + 1 < last {
+            let mode = self.modes[(first + 1) * blocks + last - 1];
+            (mode, (first + 1) * self.block, last * self.block)
+        } else {
+            ((0, 0), right + 1, right + 1)
+        };
+        for i in left..prefix_end {
+            let (rank, occurrence) = (self.ranks[i], self.occurrence[i]);
+            let positions = &self.positions[rank];
+            while positions
+                .get(occurrence + mode.1)
+                .is_some_and(|&p| p <= right)
+            {
+                mode = (rank, mode.1 + 1);
+            }
+        }
+        for i in suffix_start..=right {
+            let (rank, occurrence) = (self.ranks[i], self.occurrence[i]);
+            let positions = &self.positions[rank];
+            while occurrence >= mode.1 && positions[occurrence - mode.1] >= left {
+                mode = (rank, mode.1 + 1);
+            }
+        }
+        Some((self.compressor.key(mode.0), mode.1))
+    }
+
+    /// Returns the amount of elements.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.ranks.len()
+    }
+
+    /// Returns `true` if there are no elements.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.ranks.is_empty()
+    }
+}
+
+impl<T> core::fmt::Debug for RangeMode<T>
+where
+    T: Ord + core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list()
+            .entries(self.ranks.iter().map(|&rank| self.compressor.key(rank)))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RangeMode;
+
+    #[test]
+    fn range_mode_works() {
+        let values: Vec<usize> = (0..40).map(|x| (x * x * 7) % 9).collect();
+        let modes = RangeMode::build(&values);
+        for left in 0..values.len() {
+            for right in left..values.len() {
+                let count = |v: usize| values[left..=right].iter().filter(|&&x| x == v).count();
+                let expected = (0..9).map(count).max().unwrap();
+                let (mode, frequency) = modes.query(left, right).unwrap();
+                assert_eq!(frequency, expected);
+                assert_eq!(count(*mode), expected);
+            }
+        }
+        assert_eq!(modes.query(3, 2), None);
+    }
+
+    #[test]
+    fn small_range_mode_works() {
+        let modes = RangeMode::build(&['a']);
+        assert_eq!(modes.query(0, 0), Some((&'a', 1)));
+        let modes = RangeMode::<char>::build(&[]);
+        assert!(modes.is_empty());
+    }
+}
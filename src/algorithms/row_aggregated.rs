@@ -0,0 +1,194 @@
+use alloc::vec::Vec;
+
+use crate::{
+    nodes::{LazyNode, Node},
+    segment_tree::{Iterative, LazyRecursive},
+};
+
+/// Matrix made of a lazy segment tree per row, plus a segment tree over the rows whose i-th element is the result of the whole i-th row.
+///
+/// Ranges of a row are updated lazily, while rectangles are queried by combining the result of each row, or by querying the tree over the rows when they span every column.
+/// It's a simpler alternative to a two-dimensional lazy segment tree, which is worth it when updates touch few rows or queries span whole rows.
+/// It uses `O(n*m)` space, where `n` is the amount of rows and `m` the amount of columns.
+/// ```
+/// # use seg_tree::{algorithms::RowAggregated,utils::Sum,nodes::Node};
+/// let rows: Vec<Vec<Sum<usize>>> = (0..3)
+///     .map(|i| (0..4).map(|j| Sum::initialize(&(4 * i + j))).collect())
+///     .collect(); // [[0,1,2,3],[4,5,6,7],[8,9,10,11]] with Sum<usize> nodes
+/// let mut matrix = RowAggregated::build(&rows);
+/// matrix.update_row_range(1, 0, 2, &10); // [[0,1,2,3],[14,15,16,7],[8,9,10,11]]
+/// assert_eq!(matrix.query_rows_range(0, 1).unwrap().value(), &58);
+/// assert_eq!(matrix.query(1, 2, 1, 2).unwrap().value(), &50);
+/// ```
+pub struct RowAggregated<T>
+where
+    T: LazyNode,
+{
+    rows: Vec<LazyRecursive<T>>,
+    summaries: Iterative<T>,
+    width: usize,
+}
+
+impl<T> RowAggregated<T>
+where
+    T: LazyNode + Clone,
+{
+    /// Builds the matrix whose i-th row has the elements of `rows[i]`.
+    /// It will **panic** if the rows don't have the same amount of elements.
+    /// It has time complexity of `O(n*m*log(m))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn build(rows: &[Vec<T>]) -> Self {
+        let width = rows.first().map_or(0, Vec::len);
+        assert_same_width(rows, width);
+        let mut rows: Vec<LazyRecursive<T>> =
+            rows.iter().map(|row| LazyRecursive::build(row)).collect();
+        let summaries: Vec<T> = rows
+            .iter_mut()
+            .filter_map(|row| row.query(0, width.saturating_sub(1)))
+            .collect();
+        Self {
+            rows,
+            summaries: Iterative::build(&summaries),
+            width,
+        }
+    }
+
+    /// Updates the range `[left,right]` of the row with value, and the result of the row in the tree over the rows.
+    /// It will **panic** if `row` is not in `[0,n)`, or if `left` or `right` are not in `[0,m)`.
+    /// It has time complexity of `O(log(n)+log(m))`, assuming that [`combine`](Node::combine), [`update_lazy_value`](LazyNode::update_lazy_value) and [`lazy_update`](LazyNode::lazy_update) have constant time complexity.
+    pub fn update_row_range(
+        &mut self,
+        row: usize,
+        left: usize,
+        right: usize,
+        value: &<T as Node>::Value,
+    ) {
+        let tree = &mut self.rows[row];
+        tree.update(left, right, value);
+        if let Some(summary) = tree.query(0, self.width - 1) {
+            self.summaries.update(row, summary.value());
+        }
+    }
+
+    /// Returns the result of the whole rows in `[first_row,last_row]`.
+    /// It returns None if and only if the range is empty or the matrix has no elements.
+    /// It will **panic** if `first_row` or `last_row` are not in `[0,n)`.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn query_rows_range(&self, first_row: usize, last_row: usize) -> Option<T> {
+        self.summaries.query(first_row, last_row)
+    }
+
+    /// Returns the result of the rectangle with rows in `[first_row,last_row]` and columns in `[left,right]`, combining it row by row.
+    /// It returns None if and only if either range is empty or the matrix has no elements.
+    /// It will **panic** if `first_row` or `last_row` are not in `[0,n)`, or if `left` or `right` are not in `[0,m)`.
+    /// It has time complexity of `O(log(n))` if the columns are `[0,m)` and `O(k*log(m))` otherwise, where `k` is the amount of rows in the rectangle, assuming that [`combine`](Node::combine), [`update_lazy_value`](LazyNode::update_lazy_value) and [`lazy_update`](LazyNode::lazy_update) have constant time complexity.
+    pub fn query(
+        &mut self,
+        first_row: usize,
+        last_row: usize,
+        left: usize,
+        right: usize,
+    ) -> Option<T> {
+        if first_row > last_row {
+            return None;
+        }
+        if left == 0 && right + 1 == self.width {
+            return self.query_rows_range(first_row, last_row);
+        }
+        self.rows[first_row..=last_row]
+            .iter_mut()
+            .filter_map(|row| row.query(left, right))
+            .reduce(|result, node| Node::combine(&result, &node))
+    }
+
+    /// Returns the amount of rows of the matrix.
+    #[must_use]
+    pub const fn rows(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns the amount of columns of the matrix.
+    #[must_use]
+    pub const fn width(&self) -> usize {
+        self.width
+    }
+}
+
+/// It will **panic** if some row doesn't have width elements.
+fn assert_same_width<T>(rows: &[Vec<T>], width: usize) {
+    for (i, row) in rows.iter().enumerate() {
+        assert!(
+            row.len() == width,
+            "row {i} has {} elements, but row 0 has {width}",
+            row.len()
+        );
+    }
+}
+
+impl<T> core::fmt::Debug for RowAggregated<T>
+where
+    T: LazyNode + core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("RowAggregated")
+            .field("rows", &self.rows)
+            .field("summaries", &self.summaries)
+            .field("width", &self.width)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{nodes::Node, utils::Sum};
+
+    use super::RowAggregated;
+
+    #[test]
+    fn row_aggregated_works() {
+        let mut values: Vec<Vec<usize>> = (0..5)
+            .map(|i| (0..7).map(|j| (i * 7 + j * 3) % 5).collect())
+            .collect();
+        let rows: Vec<Vec<Sum<usize>>> = values
+            .iter()
+            .map(|row| row.iter().map(Sum::initialize).collect())
+            .collect();
+        let mut matrix = RowAggregated::build(&rows);
+        for step in 0..10 {
+            let (row, left, right) = (step % 5, step % 3, 3 + step % 4);
+            matrix.update_row_range(row, left, right, &step);
+            for value in &mut values[row][left..=right] {
+                *value += step;
+            }
+            for first_row in 0..5 {
+                for last_row in first_row..5 {
+                    let rows = &values[first_row..=last_row];
+                    let expected: usize = rows.iter().flatten().sum();
+                    let result = matrix.query_rows_range(first_row, last_row).unwrap();
+                    assert_eq!(result.value(), &expected);
+                    for i in 0..7 {
+                        for j in i..7 {
+                            let expected: usize = rows.iter().flat_map(|row| &row[i..=j]).sum();
+                            let result = matrix.query(first_row, last_row, i, j).unwrap();
+                            assert_eq!(result.value(), &expected);
+                        }
+                    }
+                }
+            }
+        }
+        assert!(matrix.query(3, 2, 0, 6).is_none());
+        assert!(matrix.query(0, 4, 4, 3).is_none());
+        assert_eq!((matrix.rows(), matrix.width()), (5, 7));
+    }
+
+    #[test]
+    #[should_panic = "row 1 has 2 elements, but row 0 has 3"]
+    fn row_aggregated_checks_widths() {
+        let rows: Vec<Vec<Sum<usize>>> = [3, 2]
+            .iter()
+            .map(|&len| (0..len).map(|x| Sum::initialize(&x)).collect())
+            .collect();
+        let _ = RowAggregated::build(&rows);
+    }
+}
@@ -0,0 +1,160 @@
+use alloc::vec::Vec;
+
+use crate::nodes::Node;
+
+/// Aggregate over a sliding window of values, i.e. a queue which answers the [`combine`](Node::combine) of all of its values.
+///
+/// It keeps the values in two stacks, the newest ones with the result of all of them, and the oldest ones with the result of each suffix, so every operation takes amortized `O(1)` combines, unlike a segment tree over the whole array.
+/// It uses `O(n)` space, where `n` is the amount of values in the window.
+/// ```
+/// # use seg_tree::{algorithms::SlidingWindow,utils::Min,nodes::Node};
+/// let mut window = SlidingWindow::<Min<usize>>::new();
+/// for value in [5, 3, 4] {
+///     window.push_back(value);
+/// }
+/// assert_eq!(window.query().unwrap().value(), &3);
+/// window.pop_front();
+/// window.pop_front(); // The window is [4].
+/// assert_eq!(window.query().unwrap().value(), &4);
+/// ```
+pub struct SlidingWindow<T>
+where
+    T: Node,
+{
+    front: Vec<(T::Value, T)>,
+    back: Vec<T::Value>,
+    back_result: Option<T>,
+}
+
+impl<T> SlidingWindow<T>
+where
+    T: Node + Clone,
+{
+    /// Creates an empty window.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            front: Vec::new(),
+            back: Vec::new(),
+            back_result: None,
+        }
+    }
+
+    /// Adds value at the end of the window.
+    /// It has time complexity of `O(1)`, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn push_back(&mut self, value: T::Value) {
+        let node = T::initialize(&value);
+        self.back_result = Some(match self.back_result.take() {
+            Some(result) => T::combine(&result, &node),
+            None => node,
+        });
+        self.back.push(value);
+    }
+
+    /// Removes the value at the start of the window and returns it, or None if the window is empty.
+    /// It has amortized time complexity of `O(1)`, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn pop_front(&mut self) -> Option<T::Value> {
+        if self.front.is_empty() {
+            self.back_result = None;
+            while let Some(value) = self.back.pop() {
+                let node = T::initialize(&value);
+                let result = match self.front.last() {
+                    Some((_, result)) => T::combine(&node, result),
+                    None => node,
+                };
+                self.front.push((value, result));
+            }
+        }
+        self.front.pop().map(|(value, _)| value)
+    }
+
+    /// Returns the result of the values in the window, or None if the window is empty.
+    /// It has time complexity of `O(1)`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn query(&self) -> Option<T> {
+        match (self.front.last(), &self.back_result) {
+            (Some((_, front)), Some(back)) => Some(T::combine(front, back)),
+            (Some((_, result)), None) | (None, Some(result)) => Some(result.clone()),
+            (None, None) => None,
+        }
+    }
+
+    /// Returns the amount of values in the window.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.front.len() + self.back.len()
+    }
+
+    /// Returns `true` if the window has no values.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.front.is_empty() && self.back.is_empty()
+    }
+}
+
+impl<T> Default for SlidingWindow<T>
+where
+    T: Node + Clone,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> core::fmt::Debug for SlidingWindow<T>
+where
+    T: Node,
+    T::Value: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let values = self.front.iter().rev().map(|(value, _)| value);
+        f.debug_list().entries(values.chain(&self.back)).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::collections::VecDeque;
+
+    use rand::{thread_rng, Rng};
+
+    use crate::{
+        nodes::Node,
+        utils::{MaxSubArraySum, Min},
+    };
+
+    use super::SlidingWindow;
+
+    #[test]
+    fn sliding_window_works() {
+        let mut rng = thread_rng();
+        let mut window = SlidingWindow::<MaxSubArraySum>::new();
+        let mut expected = VecDeque::new();
+        for _ in 0..1_000 {
+            if rng.gen_bool(0.6) {
+                let value = rng.gen_range(-10..=10);
+                window.push_back(value);
+                expected.push_back(value);
+            } else {
+                assert_eq!(window.pop_front(), expected.pop_front());
+            }
+            assert_eq!(window.len(), expected.len());
+            let result = expected
+                .iter()
+                .map(MaxSubArraySum::initialize)
+                .reduce(|a, b| MaxSubArraySum::combine(&a, &b));
+            assert_eq!(window.query(), result);
+        }
+    }
+
+    #[test]
+    fn empty_window_works() {
+        let mut window = SlidingWindow::<Min<usize>>::default();
+        assert!(window.is_empty());
+        assert!(window.query().is_none());
+        assert!(window.pop_front().is_none());
+        window.push_back(1);
+        window.push_back(2);
+        assert_eq!(format!("{window:?}"), "[1, 2]");
+    }
+}
@@ -0,0 +1,123 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{
+    nodes::Node,
+    segment_tree::Persistent,
+    utils::{Compressor, Sum},
+};
+
+/// Amount of points of a fixed set inside axis-aligned rectangles, built on a [`Persistent`] counting tree.
+///
+/// The points are sorted by `x` and their `y` coordinates are compressed, so version `i` of the tree counts how many of the first `i` points have each `y`.
+/// A rectangle is then the difference between the versions of the points with `x` up to its right side and before its left side, restricted to the ranks of its `y` range.
+/// It uses `O(n*log(n))` space.
+/// ```
+/// # use seg_tree::algorithms::StaticRectCount;
+/// let points = StaticRectCount::build(&[(1, 5), (2, 2), (4, 3), (4, 7), (6, 1)]);
+/// assert_eq!(points.count(&2, &4, &2, &5), 2); // (2,2) and (4,3).
+/// assert_eq!(points.count(&0, &10, &4, &10), 2); // (1,5) and (4,7).
+/// assert_eq!(points.count(&5, &3, &0, &10), 0);
+/// ```
+pub struct StaticRectCount<X, Y> {
+    xs: Vec<X>,
+    ys: Compressor<Y>,
+    tree: Persistent<Sum<usize>>,
+}
+
+impl<X, Y> StaticRectCount<X, Y>
+where
+    X: Ord + Clone,
+    Y: Ord + Clone,
+{
+    /// Builds the counting tree from the points, given as `(x, y)` pairs, repeated points are counted as many times as they appear.
+    /// It has time complexity of `O(n*log(n))`.
+    #[must_use]
+    pub fn build(points: &[(X, Y)]) -> Self {
+        let mut points = points.to_vec();
+        points.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        let ys: Compressor<Y> = points.iter().map(|(_, y)| y.clone()).collect();
+        let nodes = vec![Sum::initialize(&0); ys.len()];
+        let mut tree = Persistent::build(&nodes);
+        for (version, (_, y)) in points.iter().enumerate() {
+            tree.update_with(version, ys.lower_bound(y), |count| count + 1);
+        }
+        let xs = points.into_iter().map(|(x, _)| x).collect();
+        Self { xs, ys, tree }
+    }
+
+    /// Returns how many points have `x` in `[x1,x2]` and `y` in `[y1,y2]`.
+    /// It has time complexity of `O(log(n))`.
+    #[must_use]
+    pub fn count(&self, x1: &X, x2: &X, y1: &Y, y2: &Y) -> usize {
+        let lo = self.xs.partition_point(|x| x < x1);
+        let hi = self.xs.partition_point(|x| x <= x2);
+        let first = self.ys.lower_bound(y1);
+        let last = self.ys.upper_bound(y2);
+        if lo >= hi || first >= last {
+            return 0;
+        }
+        let count = |version| {
+            self.tree
+                .query(version, first, last - 1)
+                .map_or(0, |node| *node.value())
+        };
+        count(hi) - count(lo)
+    }
+
+    /// Returns the amount of points.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.xs.len()
+    }
+
+    /// Returns `true` if there are no points.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.xs.is_empty()
+    }
+}
+
+impl<X, Y> core::fmt::Debug for StaticRectCount<X, Y>
+where
+    X: core::fmt::Debug,
+    Y: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("StaticRectCount")
+            .field("xs", &self.xs)
+            .field("ys", &self.ys)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StaticRectCount;
+
+    #[test]
+    fn static_rect_count_works() {
+        let points: Vec<(usize, usize)> = (0..13).map(|i| ((i * 7) % 5, (i * 3) % 7)).collect();
+        let rects = StaticRectCount::build(&points);
+        assert_eq!(rects.len(), 13);
+        for x1 in 0..6 {
+            for x2 in 0..6 {
+                for y1 in 0..8 {
+                    for y2 in 0..8 {
+                        let expected = points
+                            .iter()
+                            .filter(|&&(x, y)| x1 <= x && x <= x2 && y1 <= y && y <= y2)
+                            .count();
+                        assert_eq!(rects.count(&x1, &x2, &y1, &y2), expected);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn empty_static_rect_count_returns_zero() {
+        let rects = StaticRectCount::<i32, i32>::build(&[]);
+        assert!(rects.is_empty());
+        assert_eq!(rects.count(&0, &10, &0, &10), 0);
+    }
+}
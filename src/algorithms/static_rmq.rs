@@ -0,0 +1,157 @@
+use alloc::vec::Vec;
+
+use super::block_sparse_table::{sparse_table, table_query};
+use crate::nodes::{Node, SelectionNode};
+
+/// Amount of elements of each block, the Cartesian tree of a block is encoded in the bits of a [`u64`].
+const BLOCK: usize = 64;
+
+/// Read only range queries in `O(1)` with `O(n)` preprocessing for selection nodes such as [`Min`](crate::utils::Min) and [`Max`](crate::utils::Max), see [`SelectionNode`].
+///
+/// It's the Fischer–Heun structure, the elements are split in blocks of `64` and there's a sparse table over the results of the blocks.
+/// Inside a block, each element keeps a bitmask of the positions of the right spine of the Cartesian tree of the block up to it, i.e. the elements selected over every later element up to it, so the result of a range inside a block is the first position of the mask of its right end which isn't before its left end.
+/// For idempotent nodes whose [`combine`](Node::combine) doesn't return one of its arguments, such as gcd, use [`BlockSparseTable`](crate::algorithms::BlockSparseTable).
+/// It uses `O(n)` space, assuming that each node uses `O(1)` space.
+/// ```
+/// # use seg_tree::{algorithms::StaticRmq,utils::Min,nodes::Node};
+/// # let nodes: Vec<Min<usize>> = (0..100).map(|x| Min::initialize(&(x % 7))).collect();
+/// let rmq = StaticRmq::build(&nodes); // [0,1,2,3,4,5,6,0,1,...] with Min<usize> nodes
+/// assert_eq!(rmq.query(1, 6).unwrap().value(), &1);
+/// assert_eq!(rmq.query(1, 99).unwrap().value(), &0);
+/// ```
+pub struct StaticRmq<T> {
+    values: Vec<T>,
+    masks: Vec<u64>,
+    blocks: Vec<Vec<T>>,
+}
+
+impl<T> StaticRmq<T>
+where
+    T: SelectionNode + Clone,
+{
+    /// Builds the bitmasks and the sparse table over the blocks from slice.
+    /// It has time complexity of `O(n)`, assuming that [`combine`](Node::combine) and [`selects_left`](SelectionNode::selects_left) have constant time complexity.
+    #[must_use]
+    pub fn build(values: &[T]) -> Self {
+        let mut masks = Vec::with_capacity(values.len());
+        for block in values.chunks(BLOCK) {
+            let mut mask = 0_u64;
+            for (i, value) in block.iter().enumerate() {
+                while mask != 0 {
+                    let last = mask.ilog2() as usize;
+                    if T::selects_left(&block[last], value) {
+                        break;
+                    }
+                    mask ^= 1 << last;
+                }
+                mask |= 1 << i;
+                masks.push(mask);
+            }
+        }
+        let values = values.to_vec();
+        let results = masks
+            .chunks(BLOCK)
+            .enumerate()
+            .map(|(block, block_masks)| {
+                let last = block_masks[block_masks.len() - 1];
+                values[block * BLOCK + last.trailing_zeros() as usize].clone()
+            })
+            .collect();
+        let blocks = sparse_table(results, usize::MAX);
+        Self {
+            values,
+            masks,
+            blocks,
+        }
+    }
+
+    /// Returns the result from the range `[left,right]`.
+    /// It returns None if and only if range is empty.
+    /// It will **panic** if left or right are not in `[0,n)`.
+    /// It has time complexity of `O(1)`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn query(&self, left: usize, right: usize) -> Option<T> {
+        if left > right {
+            return None;
+        }
+        let (first, last) = (left / BLOCK, right / BLOCK);
+        if first == last {
+            return Some(self.block_query(left, right).clone());
+        }
+        let prefix = self.block_query(left, first * BLOCK + BLOCK - 1);
+        let suffix = self.block_query(last * BLOCK, right);
+        if first + 1 < last {
+            let middle = table_query(&self.blocks, first + 1, last - 1);
+            return Some(T::combine(&T::combine(prefix, &middle), suffix));
+        }
+        Some(T::combine(prefix, suffix))
+    }
+
+    /// Returns the amount of elements.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns `true` if there are no elements.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Returns the selected element of the range `[left,right]`, which must be inside a single block.
+    fn block_query(&self, left: usize, right: usize) -> &T {
+        let mask = self.masks[right] >> (left % BLOCK);
+        &self.values[left + mask.trailing_zeros() as usize]
+    }
+}
+
+impl<T> core::fmt::Debug for StaticRmq<T>
+where
+    T: Node,
+    T::Value: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list()
+            .entries(self.values.iter().map(Node::value))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{distributions::Uniform, prelude::Distribution, thread_rng};
+
+    use crate::{
+        nodes::Node,
+        utils::{Max, Min},
+    };
+
+    use super::StaticRmq;
+
+    #[test]
+    fn query_works() {
+        const N: usize = 300;
+        let random = Uniform::from(-50..50);
+        let values: Vec<i32> = random.sample_iter(thread_rng()).take(N).collect();
+        let nodes: Vec<Min<i32>> = values.iter().map(Min::initialize).collect();
+        let rmq = StaticRmq::build(&nodes);
+        assert_eq!(rmq.len(), N);
+        for left in 0..N {
+            for right in left..N {
+                let expected = values[left..=right].iter().min().unwrap();
+                assert_eq!(rmq.query(left, right).unwrap().value(), expected);
+            }
+        }
+        assert!(rmq.query(5, 4).is_none());
+    }
+
+    #[test]
+    fn small_and_empty_work() {
+        let nodes: Vec<Max<usize>> = (0..3).map(|x| Max::initialize(&x)).collect();
+        let rmq = StaticRmq::build(&nodes);
+        assert_eq!(rmq.query(0, 2).unwrap().value(), &2);
+        assert_eq!(rmq.query(0, 0).unwrap().value(), &0);
+        assert!(StaticRmq::<Max<usize>>::build(&[]).is_empty());
+    }
+}
@@ -0,0 +1,251 @@
+use alloc::vec::Vec;
+use core::iter::repeat;
+
+use crate::{
+    internal_utils::persistent_utils::{NodeIndex, PersistentWrapper},
+    nodes::{LazyNode, Node},
+    segment_tree::{Iterative, LazyPersistent, Persistent, VersionId},
+    storage::Arena,
+};
+
+/// Segment tree over the versions of a persistent segment tree, which aggregates the result of a fixed range `[left,right]` across versions.
+///
+/// Version `i` contributes the result of `[left,right]` in version `i` of the persistent segment tree, combined with the node `U`, e.g. the maximum over versions `[a,b]` of the sum of `[left,right]`, or with `U` a [`Sum`](crate::utils::Sum) and `left == right`, the total of an element over versions `[a,b]`.
+/// It's kept up to date with [`sync`](VersionedAggregate::sync), which only queries the versions created since the last call.
+/// Any history rewrite of the persistent segment tree, i.e. `rollback_to` or `retain_versions`, invalidates the results already synced, so the aggregate must be [`clear`](VersionedAggregate::clear)ed and synced again after it.
+/// It uses `O(v)` space, where `v` is the amount of versions synced.
+/// ```
+/// # use seg_tree::{Persistent,algorithms::VersionedAggregate,utils::{Max,Sum},nodes::Node};
+/// # let nodes: Vec<Sum<usize>> = (0..5).map(|x| Sum::initialize(&x)).collect();
+/// let mut seg_tree = Persistent::build(&nodes); // [0,1,2,3,4] with Sum<usize> nodes
+/// let mut max_sums = VersionedAggregate::<Max<usize>>::new(1, 3);
+/// seg_tree.update(0, 2, &10); // [0,1,10,3,4]
+/// seg_tree.update(1, 2, &0); // [0,1,0,3,4]
+/// max_sums.sync(&seg_tree);
+/// assert_eq!(max_sums.query(0, 2).unwrap().value(), &14);
+/// assert_eq!(max_sums.query(2, 2).unwrap().value(), &4);
+/// ```
+pub struct VersionedAggregate<U>
+where
+    U: Node,
+{
+    left: usize,
+    right: usize,
+    results: Vec<U::Value>,
+    tree: Option<Iterative<U>>,
+}
+
+impl<U> VersionedAggregate<U>
+where
+    U: Node + Clone,
+{
+    /// Creates the aggregate of the range `[left,right]` with no versions synced.
+    #[must_use]
+    pub const fn new(left: usize, right: usize) -> Self {
+        Self {
+            left,
+            right,
+            results: Vec::new(),
+            tree: None,
+        }
+    }
+
+    /// Adds the versions of tree created since the last call.
+    /// It will **panic** if `left` or `right` are not in `[0,n)`, if the range is empty, or if tree has fewer versions than the ones already synced, since its history was rewritten.
+    /// A rewrite which keeps at least as many versions isn't detected, so it must be followed by [`clear`](VersionedAggregate::clear).
+    /// It has amortized time complexity of `O(k*(log(n)+log(v)))`, where `k` is the amount of versions added, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn sync<T, I, A>(&mut self, tree: &Persistent<T, I, A>)
+    where
+        T: Node<Value = U::Value> + Clone,
+        I: NodeIndex,
+        A: Arena<PersistentWrapper<T, I>>,
+    {
+        self.assert_not_rewritten(tree.versions());
+        for version in self.results.len()..tree.versions() {
+            let result = tree.query(version, self.left, self.right);
+            self.push(result.map(Node::into_value));
+        }
+    }
+
+    /// Same as [`sync`](VersionedAggregate::sync), but for a [`LazyPersistent`], it will **panic** in the same cases.
+    /// It has amortized time complexity of `O(k*(log(n)+log(v)))`, where `k` is the amount of versions added, assuming that [`combine`](Node::combine), [`update_lazy_value`](LazyNode::update_lazy_value) and [`lazy_update`](LazyNode::lazy_update) have constant time complexity.
+    pub fn sync_lazy<T, I, A>(&mut self, tree: &LazyPersistent<T, I, A>)
+    where
+        T: LazyNode<Value = U::Value> + Clone,
+        I: NodeIndex,
+        A: Arena<PersistentWrapper<T, I>>,
+    {
+        self.assert_not_rewritten(tree.versions());
+        for version in self.results.len()..tree.versions() {
+            let result = tree.query(version, self.left, self.right);
+            self.push(result.map(Node::into_value));
+        }
+    }
+
+    /// Returns the combination of the results of the versions in `[first_version,last_version]`.
+    /// It returns None if and only if the range is empty or some of its versions aren't synced yet.
+    /// It has time complexity of `O(log(v))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn query(&self, first_version: VersionId, last_version: VersionId) -> Option<U> {
+        if last_version >= self.results.len() {
+            return None;
+        }
+        self.tree.as_ref()?.query(first_version, last_version)
+    }
+
+    /// Discards every synced version, so that the next sync queries every version again, e.g. after a history rewrite.
+    pub fn clear(&mut self) {
+        self.results.clear();
+        self.tree = None;
+    }
+
+    /// Returns the amount of versions synced.
+    #[must_use]
+    pub const fn versions(&self) -> usize {
+        self.results.len()
+    }
+
+    /// Checks that the synced versions still exist in a tree with the given amount of versions.
+    fn assert_not_rewritten(&self, versions: usize) {
+        assert!(
+            versions >= self.results.len(),
+            "the tree has {versions} versions but {} were synced, its history was rewritten",
+            self.results.len()
+        );
+    }
+
+    /// Adds the result of the next version, the tree over the versions doubles its capacity when it's full, padding it with copies of result which are never queried.
+    fn push(&mut self, result: Option<U::Value>) {
+        let Some(result) = result else {
+            panic!("range [{}, {}] is empty", self.left, self.right);
+        };
+        let version = self.results.len();
+        match &mut self.tree {
+            Some(tree) if version < tree.len() => tree.update(version, &result),
+            _ => {
+                let capacity = 2 * version + 1;
+                let nodes: Vec<U> = self
+                    .results
+                    .iter()
+                    .chain(repeat(&result))
+                    .take(capacity)
+                    .map(U::initialize)
+                    .collect();
+                self.tree = Some(Iterative::build(&nodes));
+            }
+        }
+        self.results.push(result);
+    }
+}
+
+impl<U> core::fmt::Debug for VersionedAggregate<U>
+where
+    U: Node,
+    U::Value: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("VersionedAggregate")
+            .field("left", &self.left)
+            .field("right", &self.right)
+            .field("results", &self.results)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        nodes::Node,
+        segment_tree::{LazyPersistent, Persistent},
+        testing::small_values,
+        utils::{Max, Min, Sum},
+    };
+
+    use super::VersionedAggregate;
+
+    #[test]
+    fn versioned_aggregate_works() {
+        let mut values: Vec<usize> = small_values(13);
+        let nodes: Vec<Sum<usize>> = values.iter().map(Sum::initialize).collect();
+        let mut segment_tree = Persistent::build(&nodes);
+        let mut max_sums = VersionedAggregate::<Max<usize>>::new(2, 8);
+        let mut sums = vec![values[2..=8].iter().sum::<usize>()];
+        for version in 0..40 {
+            if version % 7 == 0 {
+                max_sums.sync(&segment_tree);
+            }
+            let p = (version * 5) % values.len();
+            values[p] = (version * 3) % 11;
+            segment_tree.update(version, p, &values[p]);
+            sums.push(values[2..=8].iter().sum());
+        }
+        assert_eq!(max_sums.query(0, 40), None);
+        max_sums.sync(&segment_tree);
+        assert_eq!(max_sums.versions(), 41);
+        for first in 0..=40 {
+            for last in first..=40 {
+                let expected = sums[first..=last].iter().max();
+                assert_eq!(
+                    max_sums.query(first, last).unwrap().value(),
+                    expected.unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn versioned_aggregate_syncs_lazy_trees() {
+        let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = LazyPersistent::build(&nodes);
+        let mut min_sums = VersionedAggregate::<Min<usize>>::new(0, 4);
+        for version in 0..5 {
+            segment_tree.update(version, version, 9, &1);
+        }
+        min_sums.sync_lazy(&segment_tree);
+        assert_eq!(min_sums.query(0, 5).unwrap().value(), &10);
+        assert_eq!(min_sums.query(5, 5).unwrap().value(), &25);
+    }
+
+    #[test]
+    #[should_panic = "the tree has 2 versions but 4 were synced, its history was rewritten"]
+    fn sync_checks_for_rewritten_history() {
+        let nodes: Vec<Sum<usize>> = (0..5).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = Persistent::build(&nodes);
+        let mut max_sums = VersionedAggregate::<Max<usize>>::new(0, 4);
+        for version in 0..3 {
+            segment_tree.update(version, version, &10);
+        }
+        max_sums.sync(&segment_tree);
+        segment_tree.rollback_to(1);
+        max_sums.sync(&segment_tree);
+    }
+
+    #[test]
+    fn clear_resyncs_every_version() {
+        let nodes: Vec<Sum<usize>> = (0..5).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = Persistent::build(&nodes);
+        let mut max_sums = VersionedAggregate::<Max<usize>>::new(0, 4);
+        segment_tree.update(0, 0, &20);
+        max_sums.sync(&segment_tree);
+        segment_tree.rollback_to(0);
+        segment_tree.update(0, 0, &5);
+        max_sums.clear();
+        max_sums.sync(&segment_tree);
+        assert_eq!(max_sums.versions(), 2);
+        assert_eq!(max_sums.query(0, 1).unwrap().value(), &15);
+    }
+
+    #[test]
+    fn versioned_aggregate_totals_an_element() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = Persistent::build(&nodes);
+        for i in 0..10 {
+            segment_tree.update(i, 3, &i);
+        }
+        let mut totals = VersionedAggregate::<Sum<usize>>::new(3, 3);
+        totals.sync(&segment_tree);
+        assert_eq!(totals.query(2, 5).unwrap().value(), &10);
+        assert_eq!(totals.query(0, 1).unwrap().value(), &3);
+        assert!(totals.query(5, 2).is_none());
+    }
+}
@@ -0,0 +1,158 @@
+use alloc::vec::Vec;
+
+/// Compact binary encoding of values, used to persist and restore segment trees without serde.
+///
+/// See e.g. [`Iterative::encode_into`](crate::Iterative::encode_into) and [`Iterative::decode_from`](crate::Iterative::decode_from).
+///
+/// Numbers are encoded in little endian, `usize` and `isize` as 64-bit integers so the encoding doesn't depend on the platform.
+/// ```
+/// # use seg_tree::codec::Codec;
+/// let mut bytes = Vec::new();
+/// (7u32, -1i64).encode(&mut bytes);
+/// assert_eq!(bytes.len(), 12);
+/// assert_eq!(<(u32, i64)>::decode(&mut &bytes[..]), Some((7, -1)));
+/// ```
+pub trait Codec: Sized {
+    /// Appends the encoding of `self` to bytes.
+    fn encode(&self, bytes: &mut Vec<u8>);
+    /// Decodes a value from the start of bytes, and advances bytes past it.
+    /// It returns None if and only if bytes don't start with a valid encoding, in which case bytes may have been advanced anyway.
+    fn decode(bytes: &mut &[u8]) -> Option<Self>;
+}
+
+/// Removes the first `N` bytes of bytes and returns them.
+fn take<const N: usize>(bytes: &mut &[u8]) -> Option<[u8; N]> {
+    let (first, rest) = bytes.split_first_chunk::<N>()?;
+    *bytes = rest;
+    Some(*first)
+}
+
+macro_rules! impl_codec_le {
+    ($($t:ty),*) => {
+        $(
+            impl Codec for $t {
+                #[inline]
+                fn encode(&self, bytes: &mut Vec<u8>) {
+                    bytes.extend_from_slice(&self.to_le_bytes());
+                }
+
+                #[inline]
+                fn decode(bytes: &mut &[u8]) -> Option<Self> {
+                    take(bytes).map(Self::from_le_bytes)
+                }
+            }
+        )*
+    };
+}
+
+impl_codec_le!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+impl Codec for usize {
+    #[inline]
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        (*self as u64).encode(bytes);
+    }
+
+    #[inline]
+    fn decode(bytes: &mut &[u8]) -> Option<Self> {
+        Self::try_from(u64::decode(bytes)?).ok()
+    }
+}
+
+impl Codec for isize {
+    #[inline]
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        (*self as i64).encode(bytes);
+    }
+
+    #[inline]
+    fn decode(bytes: &mut &[u8]) -> Option<Self> {
+        Self::try_from(i64::decode(bytes)?).ok()
+    }
+}
+
+impl Codec for bool {
+    #[inline]
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        bytes.push(u8::from(*self));
+    }
+
+    #[inline]
+    fn decode(bytes: &mut &[u8]) -> Option<Self> {
+        match u8::decode(bytes)? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+}
+
+/// `None` is encoded as a `0` byte, and `Some(value)` as a `1` byte followed by value.
+impl<T> Codec for Option<T>
+where
+    T: Codec,
+{
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        self.is_some().encode(bytes);
+        if let Some(value) = self {
+            value.encode(bytes);
+        }
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Option<Self> {
+        if bool::decode(bytes)? {
+            T::decode(bytes).map(Some)
+        } else {
+            Some(None)
+        }
+    }
+}
+
+impl<A, B> Codec for (A, B)
+where
+    A: Codec,
+    B: Codec,
+{
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        self.0.encode(bytes);
+        self.1.encode(bytes);
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Option<Self> {
+        Some((A::decode(bytes)?, B::decode(bytes)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Codec;
+
+    fn round_trip<T>(value: &T) -> Option<T>
+    where
+        T: Codec,
+    {
+        let mut bytes = Vec::new();
+        value.encode(&mut bytes);
+        let mut slice = &bytes[..];
+        let decoded = T::decode(&mut slice);
+        assert!(slice.is_empty());
+        decoded
+    }
+
+    #[test]
+    fn round_trip_works() {
+        assert_eq!(round_trip(&-5i8), Some(-5));
+        assert_eq!(round_trip(&u128::MAX), Some(u128::MAX));
+        assert_eq!(round_trip(&usize::MAX), Some(usize::MAX));
+        assert_eq!(round_trip(&-1.5f64), Some(-1.5));
+        assert_eq!(round_trip(&Some((true, 3u16))), Some(Some((true, 3))));
+        assert_eq!(round_trip(&None::<u8>), Some(None));
+    }
+
+    #[test]
+    fn invalid_bytes_return_none() {
+        assert_eq!(u32::decode(&mut &[1, 2, 3][..]), None);
+        assert_eq!(bool::decode(&mut &[2][..]), None);
+        assert_eq!(<Option<u8>>::decode(&mut &[1][..]), None);
+    }
+}
@@ -0,0 +1,379 @@
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::num::Wrapping;
+
+use crate::{
+    nodes::Node,
+    segment_tree::{Iterative, LazyRecursive},
+    utils::{LazySetWrapper, Max, Min, Sum},
+};
+
+/// Values of the nodes of the trees, which are `i64` on the C side.
+trait FfiValue {
+    fn from_i64(value: i64) -> Self;
+    fn into_i64(self) -> i64;
+}
+
+impl FfiValue for i64 {
+    fn from_i64(value: i64) -> Self {
+        value
+    }
+    fn into_i64(self) -> i64 {
+        self
+    }
+}
+
+impl FfiValue for Wrapping<i64> {
+    fn from_i64(value: i64) -> Self {
+        Self(value)
+    }
+    fn into_i64(self) -> i64 {
+        self.0
+    }
+}
+
+/// Returns the nodes initialized with the `len` values pointed by values.
+///
+/// # Safety
+/// If `len` isn't `0`, values must point to `len` initialized values.
+unsafe fn nodes_from_raw<T>(values: *const i64, len: usize) -> Vec<T>
+where
+    T: Node,
+    T::Value: FfiValue,
+{
+    if len == 0 {
+        return Vec::new();
+    }
+    // SAFETY: The caller guarantees values points to len initialized values.
+    let values = unsafe { core::slice::from_raw_parts(values, len) };
+    values
+        .iter()
+        .map(|&value| T::initialize(&FfiValue::from_i64(value)))
+        .collect()
+}
+
+/// Returns `true` if `[left,right]` is a non empty range inside `[0,n)`.
+const fn is_valid_range(left: usize, right: usize, n: usize) -> bool {
+    left <= right && right < n
+}
+
+macro_rules! ffi_point_tree {
+    (
+        $(#[$doc:meta])* $name:ident, $node:ty,
+        $new:ident, $update:ident, $query:ident, $len:ident, $free:ident
+    ) => {
+        $(#[$doc])*
+        pub struct $name(Iterative<$node>);
+
+        impl $name {
+            /// Declarations of the type and its functions in the C header.
+            const DECLARATIONS: &'static str = concat!(
+                "typedef struct ", stringify!($name), " ", stringify!($name), ";\n",
+                stringify!($name), " *", stringify!($new), "(const int64_t *values, size_t len);\n",
+                "bool ", stringify!($update), "(", stringify!($name), " *tree, size_t i, int64_t value);\n",
+                "bool ", stringify!($query), "(const ", stringify!($name), " *tree, size_t left, size_t right, int64_t *out);\n",
+                "size_t ", stringify!($len), "(const ", stringify!($name), " *tree);\n",
+                "void ", stringify!($free), "(", stringify!($name), " *tree);\n",
+            );
+        }
+
+        /// Builds the segment tree from the `len` values pointed by values, the returned tree must be freed with
+        #[doc = concat!("[`", stringify!($free), "`].")]
+        /// It has time complexity of `O(n)`.
+        ///
+        /// # Safety
+        /// If `len` isn't `0`, values must point to `len` initialized values.
+        #[no_mangle]
+        pub unsafe extern "C" fn $new(values: *const i64, len: usize) -> *mut $name {
+            // SAFETY: Guaranteed by the caller.
+            let nodes: Vec<$node> = unsafe { nodes_from_raw(values, len) };
+            Box::into_raw(Box::new($name(Iterative::build(&nodes))))
+        }
+
+        /// Sets the i-th element of tree to value, it returns `false` and does nothing if i is not in `[0,n)`.
+        /// It has time complexity of `O(log(n))`.
+        ///
+        /// # Safety
+        #[doc = concat!("tree must have been returned by [`", stringify!($new), "`] and not freed.")]
+        #[no_mangle]
+        pub unsafe extern "C" fn $update(tree: *mut $name, i: usize, value: i64) -> bool {
+            // SAFETY: Guaranteed by the caller.
+            let tree = unsafe { &mut (*tree).0 };
+            if i >= tree.len() {
+                return false;
+            }
+            tree.update(i, &FfiValue::from_i64(value));
+            true
+        }
+
+        /// Writes the result from the range `[left,right]` of tree to out, it returns `false` and doesn't write anything if the range is empty or not inside `[0,n)`.
+        /// It has time complexity of `O(log(n))`.
+        ///
+        /// # Safety
+        #[doc = concat!("tree must have been returned by [`", stringify!($new), "`] and not freed, and out must be valid for writes.")]
+        #[no_mangle]
+        pub unsafe extern "C" fn $query(
+            tree: *const $name,
+            left: usize,
+            right: usize,
+            out: *mut i64,
+        ) -> bool {
+            // SAFETY: Guaranteed by the caller.
+            let tree = unsafe { &(*tree).0 };
+            if !is_valid_range(left, right, tree.len()) {
+                return false;
+            }
+            tree.query(left, right).is_some_and(|node| {
+                // SAFETY: Guaranteed by the caller.
+                unsafe { out.write(node.into_value().into_i64()) };
+                true
+            })
+        }
+
+        /// Returns the amount of elements of tree.
+        ///
+        /// # Safety
+        #[doc = concat!("tree must have been returned by [`", stringify!($new), "`] and not freed.")]
+        #[no_mangle]
+        pub const unsafe extern "C" fn $len(tree: *const $name) -> usize {
+            // SAFETY: Guaranteed by the caller.
+            unsafe { (*tree).0.len() }
+        }
+
+        /// Frees tree, it does nothing if tree is null.
+        ///
+        /// # Safety
+        #[doc = concat!("tree must be null or have been returned by [`", stringify!($new), "`] and not freed.")]
+        #[no_mangle]
+        pub unsafe extern "C" fn $free(tree: *mut $name) {
+            if !tree.is_null() {
+                // SAFETY: Guaranteed by the caller.
+                drop(unsafe { Box::from_raw(tree) });
+            }
+        }
+    };
+}
+
+ffi_point_tree!(
+    /// Opaque range sum tree of `i64` with point updates, an [`Iterative`] segment tree with [`Sum<Wrapping<i64>>`] nodes.
+    ///
+    /// The sums wrap around on overflow, as an overflow can't panic through an `extern "C"` function.
+    SegSumTree,
+    Sum<Wrapping<i64>>,
+    seg_sum_tree_new,
+    seg_sum_tree_update,
+    seg_sum_tree_query,
+    seg_sum_tree_len,
+    seg_sum_tree_free
+);
+ffi_point_tree!(
+    /// Opaque range min tree of `i64` with point updates, an [`Iterative`] segment tree with [`Min<i64>`] nodes.
+    SegMinTree,
+    Min<i64>,
+    seg_min_tree_new,
+    seg_min_tree_update,
+    seg_min_tree_query,
+    seg_min_tree_len,
+    seg_min_tree_free
+);
+ffi_point_tree!(
+    /// Opaque range max tree of `i64` with point updates, an [`Iterative`] segment tree with [`Max<i64>`] nodes.
+    SegMaxTree,
+    Max<i64>,
+    seg_max_tree_new,
+    seg_max_tree_update,
+    seg_max_tree_query,
+    seg_max_tree_len,
+    seg_max_tree_free
+);
+
+/// Opaque range min tree of `i64` with range assignments, a [`LazyRecursive`] segment tree with [`LazySetWrapper<Min<i64>>`] nodes.
+pub struct SegAssignMinTree(LazyRecursive<LazySetWrapper<Min<i64>>>);
+
+impl SegAssignMinTree {
+    /// Declarations of the type and its functions in the C header.
+    const DECLARATIONS: &'static str = concat!(
+        "typedef struct SegAssignMinTree SegAssignMinTree;\n",
+        "SegAssignMinTree *seg_assign_min_tree_new(const int64_t *values, size_t len);\n",
+        "bool seg_assign_min_tree_update(SegAssignMinTree *tree, size_t left, size_t right, int64_t value);\n",
+        "bool seg_assign_min_tree_query(SegAssignMinTree *tree, size_t left, size_t right, int64_t *out);\n",
+        "size_t seg_assign_min_tree_len(const SegAssignMinTree *tree);\n",
+        "void seg_assign_min_tree_free(SegAssignMinTree *tree);\n",
+    );
+}
+
+/// Builds the segment tree from the `len` values pointed by values, the returned tree must be freed with [`seg_assign_min_tree_free`].
+/// It has time complexity of `O(n*log(n))`.
+///
+/// # Safety
+/// If `len` isn't `0`, values must point to `len` initialized values.
+#[no_mangle]
+pub unsafe extern "C" fn seg_assign_min_tree_new(
+    values: *const i64,
+    len: usize,
+) -> *mut SegAssignMinTree {
+    // SAFETY: Guaranteed by the caller.
+    let nodes: Vec<LazySetWrapper<Min<i64>>> = unsafe { nodes_from_raw(values, len) };
+    Box::into_raw(Box::new(SegAssignMinTree(LazyRecursive::build(&nodes))))
+}
+
+/// Sets every element of the range `[left,right]` of tree to value, it returns `false` and does nothing if the range is empty or not inside `[0,n)`.
+/// It has time complexity of `O(log(n))`.
+///
+/// # Safety
+/// tree must have been returned by [`seg_assign_min_tree_new`] and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn seg_assign_min_tree_update(
+    tree: *mut SegAssignMinTree,
+    left: usize,
+    right: usize,
+    value: i64,
+) -> bool {
+    // SAFETY: Guaranteed by the caller.
+    let tree = unsafe { &mut (*tree).0 };
+    if !is_valid_range(left, right, tree.len()) {
+        return false;
+    }
+    tree.update(left, right, &value);
+    true
+}
+
+/// Writes the result from the range `[left,right]` of tree to out, it returns `false` and doesn't write anything if the range is empty or not inside `[0,n)`.
+/// It has time complexity of `O(log(n))`.
+///
+/// # Safety
+/// tree must have been returned by [`seg_assign_min_tree_new`] and not freed, and out must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn seg_assign_min_tree_query(
+    tree: *mut SegAssignMinTree,
+    left: usize,
+    right: usize,
+    out: *mut i64,
+) -> bool {
+    // SAFETY: Guaranteed by the caller.
+    let tree = unsafe { &mut (*tree).0 };
+    if !is_valid_range(left, right, tree.len()) {
+        return false;
+    }
+    tree.query(left, right).is_some_and(|node| {
+        // SAFETY: Guaranteed by the caller.
+        unsafe { out.write(node.into_value()) };
+        true
+    })
+}
+
+/// Returns the amount of elements of tree.
+///
+/// # Safety
+/// tree must have been returned by [`seg_assign_min_tree_new`] and not freed.
+#[no_mangle]
+pub const unsafe extern "C" fn seg_assign_min_tree_len(tree: *const SegAssignMinTree) -> usize {
+    // SAFETY: Guaranteed by the caller.
+    unsafe { (*tree).0.len() }
+}
+
+/// Frees tree, it does nothing if tree is null.
+///
+/// # Safety
+/// tree must be null or have been returned by [`seg_assign_min_tree_new`] and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn seg_assign_min_tree_free(tree: *mut SegAssignMinTree) {
+    if !tree.is_null() {
+        // SAFETY: Guaranteed by the caller.
+        drop(unsafe { Box::from_raw(tree) });
+    }
+}
+
+/// Returns the C header which declares the types and functions of this module, the `seg-tree-ffi` crate writes it to `ffi/include/seg_tree.h` when it's built.
+#[must_use]
+pub fn header() -> String {
+    let mut header = String::from(
+        "/* Generated by seg_tree::ffi::header, don't edit it by hand. */\n\
+         #ifndef SEG_TREE_H\n\
+         #define SEG_TREE_H\n\
+         \n\
+         #include <stdbool.h>\n\
+         #include <stddef.h>\n\
+         #include <stdint.h>\n",
+    );
+    for declarations in [
+        SegSumTree::DECLARATIONS,
+        SegMinTree::DECLARATIONS,
+        SegMaxTree::DECLARATIONS,
+        SegAssignMinTree::DECLARATIONS,
+    ] {
+        header.push('\n');
+        header.push_str(declarations);
+    }
+    header.push_str("\n#endif /* SEG_TREE_H */\n");
+    header
+}
+
+#[cfg(test)]
+mod tests {
+    use core::ptr;
+
+    use super::{
+        header, seg_assign_min_tree_free, seg_assign_min_tree_new, seg_assign_min_tree_query,
+        seg_assign_min_tree_update, seg_sum_tree_free, seg_sum_tree_len, seg_sum_tree_new,
+        seg_sum_tree_query, seg_sum_tree_update,
+    };
+
+    #[test]
+    fn point_tree_works() {
+        let values = [1, 2, 3, 4];
+        let mut out = 0;
+        unsafe {
+            let tree = seg_sum_tree_new(values.as_ptr(), values.len());
+            assert_eq!(seg_sum_tree_len(tree), 4);
+            assert!(seg_sum_tree_update(tree, 1, 10));
+            assert!(!seg_sum_tree_update(tree, 4, 10));
+            assert!(seg_sum_tree_query(tree, 0, 3, &raw mut out));
+            assert_eq!(out, 18);
+            assert!(!seg_sum_tree_query(tree, 3, 4, &raw mut out));
+            assert!(!seg_sum_tree_query(tree, 2, 1, &raw mut out));
+            seg_sum_tree_free(tree);
+            let empty = seg_sum_tree_new(ptr::null(), 0);
+            assert!(!seg_sum_tree_query(empty, 0, 0, &raw mut out));
+            seg_sum_tree_free(empty);
+            seg_sum_tree_free(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn lazy_tree_works() {
+        let values = [5, 3, 8, 6];
+        let mut out = 0;
+        unsafe {
+            let tree = seg_assign_min_tree_new(values.as_ptr(), values.len());
+            assert!(seg_assign_min_tree_update(tree, 1, 2, 7));
+            assert!(seg_assign_min_tree_query(tree, 1, 3, &raw mut out));
+            assert_eq!(out, 6);
+            assert!(!seg_assign_min_tree_update(tree, 0, 4, 7));
+            seg_assign_min_tree_free(tree);
+        }
+    }
+
+    #[test]
+    fn sum_wraps_around() {
+        let values = [i64::MAX, 1, i64::MIN];
+        let mut out = 0;
+        unsafe {
+            let tree = seg_sum_tree_new(values.as_ptr(), values.len());
+            assert!(seg_sum_tree_query(tree, 0, 1, &raw mut out));
+            assert_eq!(out, i64::MIN);
+            assert!(seg_sum_tree_query(tree, 0, 2, &raw mut out));
+            assert_eq!(out, 0);
+            seg_sum_tree_free(tree);
+        }
+    }
+
+    #[test]
+    fn header_declares_every_function() {
+        let header = header();
+        for name in ["sum", "min", "max", "assign_min"] {
+            for function in ["new", "update", "query", "len", "free"] {
+                assert!(header.contains(&format!("seg_{name}_tree_{function}(")));
+            }
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use core::cell::Cell;
+
+/// Counters of the work done by an operation of a segment tree, see the `stats` method of the trees, such as [`Recursive::stats`](crate::segment_tree::Recursive::stats).
+///
+/// Each tree keeps the counters of its own last build, query or update, so operations on other trees don't change them.
+/// ```
+/// # use seg_tree::{Recursive,utils::Sum,nodes::Node};
+/// # let nodes: Vec<Sum<usize>> = (0..16).map(|x| Sum::initialize(&x)).collect();
+/// let seg_tree = Recursive::build(&nodes);
+/// seg_tree.query(1, 14);
+/// let stats = seg_tree.stats();
+/// assert!(stats.combines <= 2 * 4);
+/// assert!(stats.max_depth <= 5);
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct OpStats {
+    /// Amount of calls to [`combine`](crate::nodes::Node::combine).
+    pub combines: usize,
+    /// Amount of lazy values pushed to the children of a node.
+    pub pushes: usize,
+    /// Amount of nodes allocated, by builds and by the updates of persistent trees.
+    pub allocations: usize,
+    /// Maximum recursion depth reached, it's `0` for the trees which don't recurse, such as [`Iterative`](crate::segment_tree::Iterative).
+    pub max_depth: usize,
+}
+
+// The hooks don't know which tree they are called from, so they count on the current thread and each operation takes its share when it ends.
+thread_local! {
+    static STATS: Cell<OpStats> = const {
+        Cell::new(OpStats {
+            combines: 0,
+            pushes: 0,
+            allocations: 0,
+            max_depth: 0,
+        })
+    };
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Starts counting an operation from zero, returns the counters and the depth of the operations it's nested in.
+pub(crate) fn start() -> (OpStats, usize) {
+    (STATS.with(Cell::take), DEPTH.with(|depth| depth.replace(0)))
+}
+
+/// Ends the operation started when [`start`] returned `outer` and `depth`, returns its counters and adds them to the ones of the operations it's nested in.
+pub(crate) fn finish(outer: OpStats, depth: usize) -> OpStats {
+    DEPTH.with(|current| current.set(depth));
+    let stats = STATS.with(Cell::get);
+    STATS.with(|current| {
+        current.set(OpStats {
+            combines: outer.combines + stats.combines,
+            pushes: outer.pushes + stats.pushes,
+            allocations: outer.allocations + stats.allocations,
+            max_depth: outer.max_depth.max(depth + stats.max_depth),
+        });
+    });
+    stats
+}
+
+pub(crate) fn record(f: impl FnOnce(&mut OpStats)) {
+    STATS.with(|stats| {
+        let mut current = stats.get();
+        f(&mut current);
+        stats.set(current);
+    });
+}
+
+pub(crate) fn enter() {
+    let depth = DEPTH.with(|depth| {
+        depth.set(depth.get() + 1);
+        depth.get()
+    });
+    record(|stats| stats.max_depth = stats.max_depth.max(depth));
+}
+
+pub(crate) fn leave() {
+    DEPTH.with(|depth| depth.set(depth.get() - 1));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        nodes::Node,
+        segment_tree::{LazyRecursive, Persistent, Recursive},
+        utils::{LazySetWrapper, Sum},
+    };
+
+    use super::OpStats;
+
+    #[test]
+    fn lazy_query_counts_pushes() {
+        let nodes: Vec<LazySetWrapper<Sum<usize>>> =
+            (0..16).map(|x| LazySetWrapper::initialize(&x)).collect();
+        let mut segment_tree = LazyRecursive::build(&nodes);
+        segment_tree.update(0, 15, &1);
+        segment_tree.query(3, 3);
+        let stats = segment_tree.stats();
+        assert_eq!(stats.pushes, 3);
+        assert_eq!(stats.combines, 0);
+        assert_eq!(stats.max_depth, 5);
+    }
+
+    #[test]
+    fn persistent_update_counts_allocations() {
+        let nodes: Vec<Sum<usize>> = (0..16).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = Persistent::build(&nodes);
+        assert_eq!(segment_tree.stats().allocations, 31);
+        segment_tree.update(0, 5, &1);
+        let stats = segment_tree.stats();
+        assert_eq!(stats.allocations, 5);
+        assert_eq!(stats.combines, 4);
+        assert_eq!(stats.max_depth, 5);
+    }
+
+    #[test]
+    fn stats_are_kept_per_tree() {
+        let nodes: Vec<Sum<usize>> = (0..16).map(|x| Sum::initialize(&x)).collect();
+        let mut a = Recursive::build(&nodes);
+        let b = Recursive::build(&nodes);
+        a.update(3, &1);
+        b.query(0, 15);
+        assert_eq!(a.stats().combines, 4);
+        assert_eq!(b.stats().combines, 0);
+        a.query(1, 14);
+        assert!(a.stats().combines > 0);
+        assert_eq!(b.stats().combines, 0);
+        assert_eq!(b.stats().max_depth, 1);
+        assert_ne!(a.stats(), OpStats::default());
+    }
+}
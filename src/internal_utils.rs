@@ -1,2 +1,13 @@
+pub mod append_utils;
+pub mod codec_utils;
 pub mod dbg_utils;
+pub mod empty_utils;
+pub mod instrument_utils;
+pub mod leaf_utils;
+pub mod observer_utils;
+pub mod partial_utils;
 pub mod persistent_utils;
+pub mod range_utils;
+pub mod tracing_utils;
+pub mod validate_utils;
+pub mod version_utils;
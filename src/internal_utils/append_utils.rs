@@ -0,0 +1,174 @@
+use alloc::boxed::Box;
+use core::{
+    cell::UnsafeCell,
+    marker::PhantomData,
+    mem::MaybeUninit,
+    ops::Index,
+    ptr::null_mut,
+    sync::atomic::{AtomicPtr, AtomicUsize, Ordering},
+};
+
+type Slot<T> = UnsafeCell<MaybeUninit<T>>;
+
+/// Append only list, which can be read from other threads while a single writer appends to it.
+///
+/// The values are stored in chunks which are never moved, chunk `k` holding `2^k` values, and each value is published by increasing `len` after it's written.
+/// The values before `len` are only modified or dropped through `&mut self`, so never while they're being read.
+pub struct AppendList<T> {
+    chunks: [AtomicPtr<Slot<T>>; usize::BITS as usize],
+    len: AtomicUsize,
+    _values: PhantomData<T>,
+}
+
+// SAFETY: the values are only moved in and out by the single writer, and shared with readers as `&T`.
+unsafe impl<T: Send + Sync> Send for AppendList<T> {}
+// SAFETY: same as `Send`, a shared list only hands out `&T`.
+unsafe impl<T: Send + Sync> Sync for AppendList<T> {}
+
+impl<T> AppendList<T> {
+    pub const fn new() -> Self {
+        Self {
+            chunks: [const { AtomicPtr::new(null_mut()) }; usize::BITS as usize],
+            len: AtomicUsize::new(0),
+            _values: PhantomData,
+        }
+    }
+
+    /// Returns the chunk and the position inside of it of the i-th value.
+    const fn position(i: usize) -> (usize, usize) {
+        let chunk = (i + 1).ilog2() as usize;
+        (chunk, i + 1 - (1 << chunk))
+    }
+
+    /// Returns the amount of published values.
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Acquire)
+    }
+
+    /// Returns the amount of values which fit in the allocated chunks.
+    pub fn capacity(&self) -> usize {
+        self.chunks
+            .iter()
+            .take_while(|chunk| !chunk.load(Ordering::Relaxed).is_null())
+            .fold(0, |capacity, _| 2 * capacity + 1)
+    }
+
+    /// Returns the slot of the i-th value, its chunk must be allocated.
+    fn slot(&self, i: usize) -> *mut MaybeUninit<T> {
+        let (chunk, offset) = Self::position(i);
+        let slots = self.chunks[chunk].load(Ordering::Acquire);
+        // SAFETY: the chunk was allocated with `2^chunk` slots, and `offset < 2^chunk`.
+        UnsafeCell::raw_get(unsafe { slots.add(offset) })
+    }
+
+    /// Returns the i-th value, or None if it isn't published yet.
+    pub fn get(&self, i: usize) -> Option<&T> {
+        // SAFETY: a published value is initialized, and it's only modified through `&mut self`.
+        (i < self.len()).then(|| unsafe { (*self.slot(i)).assume_init_ref() })
+    }
+
+    /// Returns the i-th value, or None if it isn't published yet.
+    pub fn get_mut(&mut self, i: usize) -> Option<&mut T> {
+        // SAFETY: a published value is initialized, and `&mut self` means nobody else reads it.
+        (i < *self.len.get_mut()).then(|| unsafe { (*self.slot(i)).assume_init_mut() })
+    }
+
+    /// Appends value and publishes it.
+    ///
+    /// # Safety
+    /// It must not be called concurrently with another `push`, i.e. the list must have a single writer.
+    pub unsafe fn push(&self, value: T) {
+        let len = self.len.load(Ordering::Relaxed);
+        let (chunk, offset) = Self::position(len);
+        if offset == 0 && self.chunks[chunk].load(Ordering::Relaxed).is_null() {
+            let slots: Box<[Slot<T>]> = (0..1_usize << chunk)
+                .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+                .collect();
+            self.chunks[chunk].store(Box::into_raw(slots).cast(), Ordering::Release);
+        }
+        // SAFETY: the slot isn't published yet, so no reader can see it, and there's a single writer.
+        unsafe { (*self.slot(len)).write(value) };
+        self.len.store(len + 1, Ordering::Release);
+    }
+
+    /// Keeps only the first `len` values, dropping the rest, the chunks are kept for the values appended later.
+    pub fn truncate(&mut self, len: usize) {
+        while *self.len.get_mut() > len {
+            let last = *self.len.get_mut() - 1;
+            *self.len.get_mut() = last;
+            // SAFETY: the value was published, so it's initialized, and it isn't anymore, so it's never read again.
+            unsafe { (*self.slot(last)).assume_init_drop() };
+        }
+    }
+}
+
+impl<T> Index<usize> for AppendList<T> {
+    type Output = T;
+
+    /// It will **panic** if the i-th value isn't published yet.
+    #[inline]
+    fn index(&self, i: usize) -> &T {
+        let len = self.len();
+        self.get(i)
+            .unwrap_or_else(|| panic!("index {i} is out of bounds, there are {len} values"))
+    }
+}
+
+impl<T> Default for AppendList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for AppendList<T> {
+    fn drop(&mut self) {
+        self.truncate(0);
+        for (chunk, slots) in self.chunks.iter_mut().enumerate() {
+            let slots = *slots.get_mut();
+            if slots.is_null() {
+                break;
+            }
+            // SAFETY: the chunk was allocated by `push` as a boxed slice of `2^chunk` slots, whose values were dropped.
+            drop(unsafe { Box::from_raw(core::ptr::slice_from_raw_parts_mut(slots, 1 << chunk)) });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::AppendList;
+
+    #[test]
+    fn push_and_truncate_work() {
+        let mut list = AppendList::new();
+        for i in 0..20 {
+            unsafe { list.push(i) };
+        }
+        assert_eq!(list.len(), 20);
+        assert_eq!(list.capacity(), 31);
+        assert!((0..20).all(|i| list.get(i) == Some(&i)));
+        assert_eq!(list.get(20), None);
+        *list.get_mut(3).unwrap() = 30;
+        list.truncate(5);
+        assert_eq!(list.get(3), Some(&30));
+        assert_eq!(list.get(5), None);
+        unsafe { list.push(7) };
+        assert_eq!(list.get(5), Some(&7));
+        assert_eq!(list.capacity(), 31);
+    }
+
+    #[test]
+    fn values_are_dropped() {
+        let value = Rc::new(0);
+        let mut list = AppendList::new();
+        for _ in 0..10 {
+            unsafe { list.push(Rc::clone(&value)) };
+        }
+        list.truncate(4);
+        assert_eq!(Rc::strong_count(&value), 5);
+        drop(list);
+        assert_eq!(Rc::strong_count(&value), 1);
+    }
+}
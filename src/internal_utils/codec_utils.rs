@@ -0,0 +1,31 @@
+use alloc::vec::Vec;
+
+use crate::codec::Codec;
+
+/// Appends the amount of values followed by each of them to bytes.
+pub fn encode_values<'a, V, I>(values: I, bytes: &mut Vec<u8>)
+where
+    V: Codec + 'a,
+    I: IntoIterator<Item = &'a V>,
+    I::IntoIter: ExactSizeIterator,
+{
+    let values = values.into_iter();
+    values.len().encode(bytes);
+    for value in values {
+        value.encode(bytes);
+    }
+}
+
+/// Decodes values encoded by [`encode_values`] from the start of bytes, advancing bytes past them.
+pub fn decode_values<V>(bytes: &mut &[u8]) -> Option<Vec<V>>
+where
+    V: Codec,
+{
+    let n = usize::decode(bytes)?;
+    // Every value takes at least a byte, so a corrupted length can't allocate more than bytes.
+    let mut values = Vec::with_capacity(n.min(bytes.len()));
+    for _ in 0..n {
+        values.push(V::decode(bytes)?);
+    }
+    Some(values)
+}
@@ -1,8 +1,8 @@
-use std::marker::PhantomData;
+use core::{marker::PhantomData, ops::Index};
 
 use bit_vec::BitVec;
 
-use super::persistent_utils::PersistentWrapper;
+use super::persistent_utils::{NodeIndex, PersistentWrapper};
 
 pub struct NodeKey {
     pub i: usize,
@@ -10,25 +10,27 @@ pub struct NodeKey {
 }
 
 impl core::fmt::Debug for NodeKey {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.write_fmt(format_args!("[{}, {}]", self.i, self.j))
     }
 }
 
-pub struct DbgTree<'a, A, B, F>
+pub struct DbgTree<'a, A, N, F>
 where
     A: core::fmt::Debug + 'a,
-    F: Fn(&'a [B], &mut dyn FnMut(usize, usize, &'a A)),
+    N: ?Sized,
+    F: Fn(&'a N, &mut dyn FnMut(usize, usize, &'a A)),
 {
-    nodes: &'a [B],
+    nodes: &'a N,
     visitor: F,
     _phantom_data: PhantomData<A>,
 }
 #[inline]
-pub const fn as_dbg_tree<'a, A, B, F>(nodes: &'a [B], visitor: F) -> DbgTree<'a, A, B, F>
+pub const fn as_dbg_tree<'a, A, N, F>(nodes: &'a N, visitor: F) -> DbgTree<'a, A, N, F>
 where
     A: core::fmt::Debug + 'a,
-    F: Fn(&'a [B], &mut dyn FnMut(usize, usize, &'a A)),
+    N: ?Sized,
+    F: Fn(&'a N, &mut dyn FnMut(usize, usize, &'a A)),
 {
     DbgTree {
         nodes,
@@ -37,12 +39,13 @@ where
     }
 }
 
-impl<'a, A, B, F> core::fmt::Debug for DbgTree<'a, A, B, F>
+impl<'a, A, N, F> core::fmt::Debug for DbgTree<'a, A, N, F>
 where
     A: core::fmt::Debug + 'a,
-    F: Fn(&'a [B], &mut dyn FnMut(usize, usize, &'a A)),
+    N: ?Sized,
+    F: Fn(&'a N, &mut dyn FnMut(usize, usize, &'a A)),
 {
-    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         let mut formatter = f.debug_map();
         let mut f = |i, j, value: &A| {
             formatter.entry(&NodeKey { i, j }, value);
@@ -51,6 +54,26 @@ where
         formatter.finish()
     }
 }
+/// Writes values like a slice, e.g. `[1, 2, 3]`, if the formatter has a precision only that many values are written, followed by `...` if there were more.
+pub fn display_values<I>(f: &mut core::fmt::Formatter<'_>, values: I) -> core::fmt::Result
+where
+    I: IntoIterator,
+    I::Item: core::fmt::Display,
+{
+    let limit = f.precision().unwrap_or(usize::MAX);
+    f.write_str("[")?;
+    for (k, value) in values.into_iter().enumerate() {
+        if k > 0 {
+            f.write_str(", ")?;
+        }
+        if k == limit {
+            return f.write_str("...]");
+        }
+        write!(f, "{value}")?;
+    }
+    f.write_str("]")
+}
+
 #[inline]
 pub fn recursive_visitor<'a, T>(
     curr_node: usize,
@@ -65,29 +88,31 @@ pub fn recursive_visitor<'a, T>(
     if i == j {
         return;
     }
-    let mid = (i + j) / 2;
+    let mid = usize::midpoint(i, j);
     recursive_visitor(2 * curr_node + 1, i, mid, f, nodes);
     recursive_visitor(2 * curr_node + 2, mid + 1, j, f, nodes);
 }
 
-pub fn persistent_visitor<'a, 'b, T>(
+pub fn persistent_visitor<'a, 'b, T, I, N>(
     curr_node: usize,
     i: usize,
     j: usize,
     f: &mut dyn FnMut(usize, usize, &'a T),
-    nodes: &'a [PersistentWrapper<T>],
+    nodes: &'a N,
     visited: &'b mut BitVec,
 ) where
     T: core::fmt::Debug,
+    I: NodeIndex + 'a,
+    N: Index<usize, Output = PersistentWrapper<T, I>> + ?Sized,
 {
     f(i, j, nodes[curr_node].get_inner());
     visited.set(curr_node, true);
     if i == j {
         return;
     }
-    let mid = (i + j) / 2;
-    let left_node = nodes[curr_node].left_child().unwrap().get();
-    let right_node = nodes[curr_node].right_child().unwrap().get();
+    let mid = usize::midpoint(i, j);
+    let left_node = nodes[curr_node].left_child().unwrap();
+    let right_node = nodes[curr_node].right_child().unwrap();
     if !visited[left_node] {
         persistent_visitor(left_node, i, mid, f, nodes, visited);
     }
@@ -96,30 +121,30 @@ pub fn persistent_visitor<'a, 'b, T>(
     }
 }
 
-pub fn lazy_persistent_visitor<'a, 'b, T>(
+pub fn lazy_persistent_visitor<'a, 'b, T, I, N>(
     curr_node: usize,
     i: usize,
     j: usize,
     f: &mut dyn FnMut(usize, usize, &'a T),
-    nodes: &'a [PersistentWrapper<T>],
+    nodes: &'a N,
     visited: &'b mut BitVec,
 ) where
     T: core::fmt::Debug,
+    I: NodeIndex + 'a,
+    N: Index<usize, Output = PersistentWrapper<T, I>> + ?Sized,
 {
     f(i, j, nodes[curr_node].get_inner());
     visited.set(curr_node, true);
     if i == j {
         return;
     }
-    let mid = (i + j) / 2;
+    let mid = usize::midpoint(i, j);
     if let Some(left_node) = nodes[curr_node].left_child() {
-        let left_node = left_node.get();
         if !visited[left_node] {
             lazy_persistent_visitor(left_node, i, mid, f, nodes, visited);
         }
     }
     if let Some(right_node) = nodes[curr_node].right_child() {
-        let right_node = right_node.get();
         if !visited[right_node] {
             lazy_persistent_visitor(right_node, mid + 1, j, f, nodes, visited);
         }
@@ -0,0 +1,12 @@
+/// It will **panic** if the segment tree has no elements, `operation` names what was attempted in the message.
+pub fn assert_not_empty(n: usize, operation: &str) {
+    assert!(n != 0, "can't {operation} an empty segment tree");
+}
+
+/// It will **panic** if the segment trees don't have the same amount of elements, `operation` names what was attempted in the message.
+pub fn assert_same_len(n: usize, other_n: usize, operation: &str) {
+    assert!(
+        n == other_n,
+        "can't {operation} segment trees of different lengths, {n} != {other_n}"
+    );
+}
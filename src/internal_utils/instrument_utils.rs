@@ -0,0 +1,100 @@
+//! Hooks used by the trees to record their work, they compile to nothing unless the `instrument` feature is enabled.
+// Without the feature the hooks could be `const`, but not with it.
+#![allow(clippy::missing_const_for_fn)]
+
+#[cfg(feature = "instrument")]
+use alloc::sync::Arc;
+#[cfg(feature = "instrument")]
+use std::sync::{Mutex, PoisonError};
+
+#[cfg(feature = "instrument")]
+use crate::instrument::OpStats;
+use crate::nodes::Node;
+
+/// Same as [`Node::combine`], but it's counted in [`OpStats::combines`](crate::instrument::OpStats::combines).
+#[inline]
+pub fn combine<T>(a: &T, b: &T) -> T
+where
+    T: Node,
+{
+    #[cfg(feature = "instrument")]
+    crate::instrument::record(|stats| stats.combines += 1);
+    T::combine(a, b)
+}
+
+#[inline]
+pub fn record_push() {
+    #[cfg(feature = "instrument")]
+    crate::instrument::record(|stats| stats.pushes += 1);
+}
+
+#[inline]
+pub fn record_allocations(count: usize) {
+    #[cfg(feature = "instrument")]
+    crate::instrument::record(|stats| stats.allocations += count);
+    #[cfg(not(feature = "instrument"))]
+    let _ = count;
+}
+
+/// Marks a level of recursion until it's dropped.
+pub struct DepthGuard(());
+
+#[inline]
+pub fn enter() -> DepthGuard {
+    #[cfg(feature = "instrument")]
+    crate::instrument::enter();
+    DepthGuard(())
+}
+
+#[cfg(feature = "instrument")]
+impl Drop for DepthGuard {
+    #[inline]
+    fn drop(&mut self) {
+        crate::instrument::leave();
+    }
+}
+
+/// Counters of the last operation measured on a tree, they're only kept with the `instrument` feature.
+#[derive(Default)]
+pub struct LastOp {
+    // Shared with the guards, so measuring doesn't borrow the tree.
+    #[cfg(feature = "instrument")]
+    stats: Arc<Mutex<OpStats>>,
+}
+
+impl LastOp {
+    /// Starts measuring an operation, its counters replace the last ones when the returned guard is dropped.
+    #[inline]
+    #[cfg_attr(not(feature = "instrument"), allow(clippy::unused_self))]
+    pub fn measure(&self) -> OpGuard {
+        OpGuard {
+            #[cfg(feature = "instrument")]
+            stats: Arc::clone(&self.stats),
+            #[cfg(feature = "instrument")]
+            outer: crate::instrument::start(),
+        }
+    }
+
+    #[cfg(feature = "instrument")]
+    pub fn get(&self) -> OpStats {
+        *self.stats.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Measures an operation until it's dropped.
+pub struct OpGuard {
+    #[cfg(feature = "instrument")]
+    stats: Arc<Mutex<OpStats>>,
+    #[cfg(feature = "instrument")]
+    outer: (OpStats, usize),
+}
+
+#[cfg(feature = "instrument")]
+impl Drop for OpGuard {
+    #[inline]
+    fn drop(&mut self) {
+        let (outer, depth) = self.outer;
+        *self.stats.lock().unwrap_or_else(PoisonError::into_inner) =
+            crate::instrument::finish(outer, depth);
+    }
+}
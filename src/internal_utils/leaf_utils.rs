@@ -0,0 +1,207 @@
+use alloc::vec::Vec;
+use bit_vec::BitVec;
+use core::mem::MaybeUninit;
+
+use crate::nodes::{LazyNode, Node};
+
+/// Moves the values of the leaves out of the nodes of a tree with n leaves where the node of `curr_node` has its children at `2*curr_node+1` and `2*curr_node+2`, in order.
+/// The internal nodes and the padding of the buffer are dropped.
+pub fn into_leaf_values<T>(nodes: Vec<T>, n: usize) -> Vec<T::Value>
+where
+    T: Node,
+{
+    into_leaves(nodes, n, T::into_value)
+}
+
+/// Same as [`into_leaf_values`], but the leaves themselves are moved out.
+pub fn into_leaf_nodes<T>(nodes: Vec<T>, n: usize) -> Vec<T>
+where
+    T: Node,
+{
+    into_leaves(nodes, n, core::convert::identity)
+}
+
+fn into_leaves<T, U, F>(nodes: Vec<T>, n: usize, f: F) -> Vec<U>
+where
+    F: Fn(T) -> U,
+{
+    let mut nodes: Vec<Option<T>> = nodes.into_iter().map(Some).collect();
+    leaf_indices(n)
+        .into_iter()
+        .map(|k| f(nodes[k].take().unwrap()))
+        .collect()
+}
+
+/// Returns the positions in the buffer of the leaves of a tree with n leaves, laid out as in [`into_leaf_values`], in order.
+fn leaf_indices(n: usize) -> Vec<usize> {
+    let mut indices = Vec::with_capacity(n);
+    if n > 0 {
+        leaf_indices_helper(0, 0, n - 1, &mut indices);
+    }
+    indices
+}
+
+fn leaf_indices_helper(curr_node: usize, i: usize, j: usize, indices: &mut Vec<usize>) {
+    if i == j {
+        indices.push(curr_node);
+        return;
+    }
+    let mid = usize::midpoint(i, j);
+    leaf_indices_helper(2 * curr_node + 1, i, mid, indices);
+    leaf_indices_helper(2 * curr_node + 2, mid + 1, j, indices);
+}
+
+/// Initializes the nodes of the buffer of a tree with n leaves, laid out as in [`into_leaf_values`], which aren't part of the tree, with clones of its first leaf.
+/// The nodes of the tree must already be initialized, so afterwards every node of the buffer is, as [`Storage::assume_init`](crate::storage::Storage::assume_init) requires.
+pub fn fill_padding<T>(nodes: &mut [MaybeUninit<T>], n: usize)
+where
+    T: Clone,
+{
+    if n == 0 {
+        return;
+    }
+    let mut used = BitVec::from_elem(nodes.len(), false);
+    mark_used(&mut used, 0, 0, n - 1);
+    // The first leaf is reached by always descending to the left child, whose range is `[0,j/2]`.
+    let (mut first_leaf, mut j) = (0, n - 1);
+    while j > 0 {
+        j /= 2;
+        first_leaf = 2 * first_leaf + 1;
+    }
+    // SAFETY: The leaves of the tree are initialized.
+    let leaf = unsafe { nodes[first_leaf].assume_init_ref() }.clone();
+    for (node, _) in nodes.iter_mut().zip(&used).filter(|(_, used)| !used) {
+        node.write(leaf.clone());
+    }
+}
+
+fn mark_used(used: &mut BitVec, curr_node: usize, i: usize, j: usize) {
+    used.set(curr_node, true);
+    if i != j {
+        let mid = usize::midpoint(i, j);
+        mark_used(used, 2 * curr_node + 1, i, mid);
+        mark_used(used, 2 * curr_node + 2, mid + 1, j);
+    }
+}
+
+/// Returns references to the leaves of a tree with n leaves, laid out as in [`into_leaf_values`], in order.
+pub fn leaves<T>(nodes: &[T], n: usize) -> Vec<&T> {
+    leaf_indices(n).into_iter().map(|k| &nodes[k]).collect()
+}
+
+/// Calls `f` with the corresponding leaves of two trees with n leaves and the same layout as in [`into_leaf_values`], in order, returning the results.
+pub fn zip_leaves<T, U, F>(first: &[T], second: &[T], n: usize, f: F) -> Vec<U>
+where
+    F: Fn(&T, &T) -> U,
+{
+    let mut results = Vec::with_capacity(n);
+    if n > 0 {
+        zip_leaves_helper(first, second, 0, 0, n - 1, &f, &mut results);
+    }
+    results
+}
+
+fn zip_leaves_helper<T, U, F>(
+    first: &[T],
+    second: &[T],
+    curr_node: usize,
+    i: usize,
+    j: usize,
+    f: &F,
+    results: &mut Vec<U>,
+) where
+    F: Fn(&T, &T) -> U,
+{
+    if i == j {
+        results.push(f(&first[curr_node], &second[curr_node]));
+        return;
+    }
+    let mid = usize::midpoint(i, j);
+    zip_leaves_helper(first, second, 2 * curr_node + 1, i, mid, f, results);
+    zip_leaves_helper(first, second, 2 * curr_node + 2, mid + 1, j, f, results);
+}
+
+/// Returns `true` if the leaves of the subtree of `[i,j]` rooted at `curr_node` have the same values in both trees, which must have the same layout as in [`into_leaf_values`].
+pub fn leaves_eq<T>(a: &[T], b: &[T], curr_node: usize, i: usize, j: usize) -> bool
+where
+    T: Node,
+    T::Value: PartialEq,
+{
+    if i == j {
+        return a[curr_node].value() == b[curr_node].value();
+    }
+    let mid = usize::midpoint(i, j);
+    leaves_eq(a, b, 2 * curr_node + 1, i, mid) && leaves_eq(a, b, 2 * curr_node + 2, mid + 1, j)
+}
+
+/// Same as [`leaves_eq`], but for lazy nodes, `pending` are the lazy values pending from the ancestors of `curr_node` in each tree.
+/// The lazy values are applied to copies of the nodes, so the trees aren't modified.
+pub fn lazy_leaves_eq<T>(
+    (a, b): (&[T], &[T]),
+    curr_node: usize,
+    pending: (Option<&T::Value>, Option<&T::Value>),
+    i: usize,
+    j: usize,
+) -> bool
+where
+    T: LazyNode + Clone,
+    T::Value: PartialEq,
+{
+    let mut node_a = with_pending(&a[curr_node], pending.0);
+    let mut node_b = with_pending(&b[curr_node], pending.1);
+    if i == j {
+        node_a.lazy_update(i, j);
+        node_b.lazy_update(i, j);
+        return node_a.value() == node_b.value();
+    }
+    let mid = usize::midpoint(i, j);
+    let pending = (node_a.lazy_value(), node_b.lazy_value());
+    lazy_leaves_eq((a, b), 2 * curr_node + 1, pending, i, mid)
+        && lazy_leaves_eq((a, b), 2 * curr_node + 2, pending, mid + 1, j)
+}
+
+/// Returns the values of the leaves of a lazy tree with n leaves, laid out as in [`into_leaf_values`], in order.
+/// The pending lazy values are applied to copies of the nodes, as in [`lazy_leaves_eq`].
+pub fn lazy_leaf_values<T>(nodes: &[T], n: usize) -> Vec<T::Value>
+where
+    T: LazyNode + Clone,
+{
+    let mut values = Vec::with_capacity(n);
+    if n > 0 {
+        lazy_leaf_values_helper(nodes, 0, None, 0, n - 1, &mut values);
+    }
+    values
+}
+
+fn lazy_leaf_values_helper<T>(
+    nodes: &[T],
+    curr_node: usize,
+    pending: Option<&T::Value>,
+    i: usize,
+    j: usize,
+    values: &mut Vec<T::Value>,
+) where
+    T: LazyNode + Clone,
+{
+    let mut node = with_pending(&nodes[curr_node], pending);
+    if i == j {
+        node.lazy_update(i, j);
+        values.push(node.into_value());
+        return;
+    }
+    let mid = usize::midpoint(i, j);
+    let pending = node.lazy_value();
+    lazy_leaf_values_helper(nodes, 2 * curr_node + 1, pending, i, mid, values);
+    lazy_leaf_values_helper(nodes, 2 * curr_node + 2, pending, mid + 1, j, values);
+}
+
+fn with_pending<T>(node: &T, pending: Option<&T::Value>) -> T
+where
+    T: LazyNode + Clone,
+{
+    let mut node = node.clone();
+    if let Some(value) = pending {
+        node.update_lazy_value(value);
+    }
+    node
+}
@@ -0,0 +1,62 @@
+use alloc::{boxed::Box, vec::Vec};
+use core::ops::RangeInclusive;
+
+use crate::nodes::Node;
+
+/// Callback notified of the updates of a tree with nodes `T`.
+///
+/// It only exists so [`Observers`] can be stored in trees without requiring `T: Node` on their definition.
+pub trait Observer<T> {
+    fn notify(&mut self, range: RangeInclusive<usize>, value: &T::Value)
+    where
+        T: Node;
+}
+
+impl<T, F> Observer<T> for F
+where
+    T: Node,
+    F: FnMut(RangeInclusive<usize>, &T::Value),
+{
+    #[inline]
+    fn notify(&mut self, range: RangeInclusive<usize>, value: &T::Value) {
+        self(range, value);
+    }
+}
+
+/// Callbacks subscribed to the updates of a tree with nodes `T`.
+pub struct Observers<T> {
+    callbacks: Vec<Box<dyn Observer<T> + Send + Sync>>,
+}
+
+impl<T> Observers<T> {
+    pub fn push<F>(&mut self, callback: F)
+    where
+        T: Node,
+        F: FnMut(RangeInclusive<usize>, &T::Value) + Send + Sync + 'static,
+    {
+        self.callbacks.push(Box::new(callback));
+    }
+
+    /// Calls every callback, in subscription order.
+    #[inline]
+    pub fn notify(&mut self, range: RangeInclusive<usize>, value: &T::Value)
+    where
+        T: Node,
+    {
+        for callback in &mut self.callbacks {
+            callback.notify(range.clone(), value);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.callbacks.clear();
+    }
+}
+
+impl<T> Default for Observers<T> {
+    fn default() -> Self {
+        Self {
+            callbacks: Vec::new(),
+        }
+    }
+}
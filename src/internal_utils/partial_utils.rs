@@ -0,0 +1,49 @@
+use crate::nodes::Node;
+
+use super::instrument_utils::combine;
+
+/// Result of one side of a query, the first node is borrowed so a side with a single node is never cloned unless it's the whole result.
+pub enum Partial<'a, T> {
+    Empty,
+    Borrowed(&'a T),
+    Owned(T),
+}
+
+impl<'a, T> Partial<'a, T>
+where
+    T: Node + Clone,
+{
+    /// Returns the result of this side followed by node.
+    pub fn push_back(self, node: &'a T) -> Self {
+        match self {
+            Self::Empty => Self::Borrowed(node),
+            Self::Borrowed(ans) => Self::Owned(combine(ans, node)),
+            Self::Owned(ans) => Self::Owned(combine(&ans, node)),
+        }
+    }
+
+    /// Returns node followed by the result of this side.
+    pub fn push_front(self, node: &'a T) -> Self {
+        match self {
+            Self::Empty => Self::Borrowed(node),
+            Self::Borrowed(ans) => Self::Owned(combine(node, ans)),
+            Self::Owned(ans) => Self::Owned(combine(node, &ans)),
+        }
+    }
+
+    pub const fn as_ref(&self) -> Option<&T> {
+        match self {
+            Self::Empty => None,
+            Self::Borrowed(ans) => Some(ans),
+            Self::Owned(ans) => Some(ans),
+        }
+    }
+
+    pub fn into_owned(self) -> Option<T> {
+        match self {
+            Self::Empty => None,
+            Self::Borrowed(ans) => Some(ans.clone()),
+            Self::Owned(ans) => Some(ans),
+        }
+    }
+}
@@ -1,37 +1,78 @@
-use core::num::NonZeroUsize;
+use alloc::vec;
+use core::hash::{BuildHasher, Hash};
+#[cfg(feature = "std")]
+use std::{collections::HashMap, hash::RandomState};
 
-use crate::nodes::{LazyNode, Node};
+use bit_vec::BitVec;
 
-#[derive(Clone, Copy)]
-pub struct NonNUsize<const N: usize>(NonZeroUsize);
+use crate::{
+    nodes::{LazyNode, Node},
+    segment_tree::VersionStats,
+    storage::Arena,
+};
 
-impl<const N: usize> NonNUsize<N> {
-    pub fn new(n: usize) -> Option<Self> {
-        NonZeroUsize::new(n ^ N).map(NonNUsize)
-    }
+mod private {
+    pub trait Sealed {}
+}
 
-    pub const fn get(self) -> usize {
-        self.0.get() ^ N
-    }
+/// Unsigned integer types which can be used to store the indices of the children of the nodes of persistent segment trees.
+///
+/// Smaller types use less memory per node, but limit how many nodes the tree can allocate, using a node index past that limit will **panic**.
+/// The default, `u32`, roughly halves the memory used by the indices on 64-bit targets compared to `usize` and allows for up to `u32::MAX - 1` nodes.
+pub trait NodeIndex: Copy + Eq + Hash + core::fmt::Debug + private::Sealed {
+    /// Value used to mark a missing child, it's never a valid index.
+    #[doc(hidden)]
+    const NONE: Self;
+
+    /// Converts `index` to `Self`, it returns `None` if `index` doesn't fit.
+    #[doc(hidden)]
+    fn from_usize(index: usize) -> Option<Self>;
+
+    /// Converts `self` back to an `usize`.
+    #[doc(hidden)]
+    fn to_usize(self) -> usize;
 }
 
-impl<const N: usize> std::fmt::Debug for NonNUsize<N> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!("{}", self.0.get() ^ N))
-    }
+macro_rules! impl_node_index {
+    ($($t:ty),*) => {
+        $(
+            impl private::Sealed for $t {}
+            impl NodeIndex for $t {
+                const NONE: Self = <$t>::MAX;
+
+                #[inline]
+                fn from_usize(index: usize) -> Option<Self> {
+                    <$t>::try_from(index).ok().filter(|&index| index != Self::NONE)
+                }
+
+                #[inline]
+                #[allow(clippy::cast_possible_truncation)]
+                fn to_usize(self) -> usize {
+                    self as usize
+                }
+            }
+        )*
+    };
 }
-type IUsize = NonNUsize<{ usize::MAX }>;
 
-#[derive(Clone, Debug)]
-pub struct PersistentWrapper<T> {
+impl_node_index!(u16, u32, u64, usize);
+
+/// Node of the persistent segment trees, it's the node `T` with the indices of its children in the [`Arena`], stored as `I`, see [`NodeIndex`].
+///
+/// It's `#[repr(C)]`, so its layout is stable and an arena of them can be kept in a memory mapped file and reopened later, see [`Persistent::reopen_in`](crate::Persistent::reopen_in), as long as `T` has a stable layout and no pointers.
+/// A missing child is stored as the largest value of `I`.
+#[repr(C)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct PersistentWrapper<T, I = u32> {
     node: T,
-    left: Option<IUsize>,
-    right: Option<IUsize>,
+    left: I,
+    right: I,
 }
 
-impl<T> Node for PersistentWrapper<T>
+impl<T, I> Node for PersistentWrapper<T, I>
 where
     T: Node,
+    I: NodeIndex,
 {
     type Value = T::Value;
 
@@ -39,8 +80,8 @@ where
     fn initialize(value: &Self::Value) -> Self {
         Self {
             node: T::initialize(value),
-            left: None,
-            right: None,
+            left: I::NONE,
+            right: I::NONE,
         }
     }
 
@@ -48,8 +89,8 @@ where
     fn combine(a: &Self, b: &Self) -> Self {
         Self {
             node: T::combine(&a.node, &b.node),
-            left: None,
-            right: None,
+            left: I::NONE,
+            right: I::NONE,
         }
     }
     #[inline]
@@ -57,9 +98,10 @@ where
         self.node.value()
     }
 }
-impl<T> LazyNode for PersistentWrapper<T>
+impl<T, I> LazyNode for PersistentWrapper<T, I>
 where
     T: LazyNode,
+    I: NodeIndex,
 {
     #[inline]
     fn lazy_update(&mut self, i: usize, j: usize) {
@@ -77,61 +119,369 @@ where
     }
 }
 
-impl<T> From<T> for PersistentWrapper<T>
+impl<T, I> From<T> for PersistentWrapper<T, I>
 where
     T: Node,
+    I: NodeIndex,
 {
     #[inline]
     fn from(node: T) -> Self {
         Self {
             node,
-            left: None,
-            right: None,
+            left: I::NONE,
+            right: I::NONE,
         }
     }
 }
 
-impl<T> PersistentWrapper<T> {
+impl<T, I> PersistentWrapper<T, I>
+where
+    I: NodeIndex,
+{
+    /// Returns the wrapped node.
     #[inline]
     #[allow(clippy::missing_const_for_fn)]
     pub fn into_inner(self) -> T {
         self.node
     }
 
+    /// Returns the index of the left child, or None if the node is a leaf.
+    #[inline]
+    pub fn left_child(&self) -> Option<usize> {
+        Self::child(self.left)
+    }
+    /// Returns the index of the right child, or None if the node is a leaf.
     #[inline]
-    pub const fn left_child(&self) -> Option<IUsize> {
-        self.left
+    pub fn right_child(&self) -> Option<usize> {
+        Self::child(self.right)
     }
+
     #[inline]
-    pub const fn right_child(&self) -> Option<IUsize> {
-        self.right
+    fn child(index: I) -> Option<usize> {
+        (index != I::NONE).then(|| index.to_usize())
     }
 
+    /// Sets the children of the node.
+    /// It will **panic** if either index doesn't fit in `I`.
     #[inline]
-    pub fn set_children(&mut self, left: usize, right: usize) {
-        self.right = IUsize::new(right);
-        self.left = IUsize::new(left);
+    pub(crate) fn set_children(&mut self, left: usize, right: usize) {
+        let to_index = |index| {
+            I::from_usize(index).unwrap_or_else(|| {
+                panic!(
+                    "node index {index} doesn't fit in {}, use a wider index type",
+                    core::any::type_name::<I>()
+                )
+            })
+        };
+        self.right = to_index(right);
+        self.left = to_index(left);
     }
 
+    /// Returns a reference to the wrapped node.
     #[inline]
     pub const fn get_inner(&self) -> &T {
         &self.node
     }
 }
 
+#[cfg(feature = "std")]
+type Table = HashMap<u64, usize>;
+#[cfg(not(feature = "std"))]
+type Table = alloc::collections::BTreeMap<u64, usize>;
+
+#[cfg(feature = "std")]
+type HashState = RandomState;
+#[cfg(not(feature = "std"))]
+type HashState = core::hash::BuildHasherDefault<FnvHasher>;
+
+/// FNV-1a hasher, used to hash the nodes when there's no randomly seeded hasher available.
+#[cfg(not(feature = "std"))]
+pub struct FnvHasher(u64);
+
+#[cfg(not(feature = "std"))]
+impl Default for FnvHasher {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl core::hash::Hasher for FnvHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+/// Hash table of the nodes of a persistent segment tree, used to deduplicate identical subtrees.
+///
+/// Two nodes are identical if they have the same value and the same children, so deduplicating nodes bottom-up deduplicates whole subtrees.
+/// The hash and equality functions are captured when it's created, so the trees only need `T: Hash + Eq` to enable it.
+pub struct Interner<T, I> {
+    table: Table,
+    state: HashState,
+    hash: fn(&HashState, &PersistentWrapper<T, I>) -> u64,
+    eq: fn(&PersistentWrapper<T, I>, &PersistentWrapper<T, I>) -> bool,
+}
+
+impl<T, I> Interner<T, I>
+where
+    I: NodeIndex,
+{
+    pub fn new() -> Self
+    where
+        T: Hash + Eq,
+    {
+        Self {
+            table: Table::new(),
+            state: HashState::default(),
+            hash: |state, node| state.hash_one(node),
+            eq: PartialEq::eq,
+        }
+    }
+
+    /// Returns the index of a node identical to `node`, pushing it to `nodes` if there is none.
+    /// On a hash collision between different nodes the new node is pushed without being deduplicated.
+    pub fn insert<A>(&mut self, nodes: &mut A, node: PersistentWrapper<T, I>) -> usize
+    where
+        A: Arena<PersistentWrapper<T, I>>,
+    {
+        let at = nodes.len();
+        self.find(nodes, &node, at).unwrap_or_else(|| {
+            nodes.push(node);
+            at
+        })
+    }
+
+    /// Same as [`insert`](Self::insert), but if there is no identical node `node` is written at index `at` of nodes instead of being pushed.
+    /// Every node before `at` must already be known by the interner, and the ones from `at` onwards must not.
+    pub fn insert_at<A>(&mut self, nodes: &mut A, node: PersistentWrapper<T, I>, at: usize) -> usize
+    where
+        A: Arena<PersistentWrapper<T, I>>,
+    {
+        self.find(nodes, &node, at).unwrap_or_else(|| {
+            nodes[at] = node;
+            at
+        })
+    }
+
+    /// Returns the index of a node identical to `node`, otherwise `node` is registered as stored at `at`.
+    fn find<A>(&mut self, nodes: &A, node: &PersistentWrapper<T, I>, at: usize) -> Option<usize>
+    where
+        A: Arena<PersistentWrapper<T, I>>,
+    {
+        let hash = (self.hash)(&self.state, node);
+        if let Some(&index) = self.table.get(&hash) {
+            if (self.eq)(&nodes[index], node) {
+                return Some(index);
+            }
+        } else {
+            self.table.insert(hash, at);
+        }
+        None
+    }
+
+    pub fn clear(&mut self) {
+        self.table.clear();
+    }
+
+    /// Forgets every node whose index isn't in `[0,len)`.
+    pub fn truncate(&mut self, len: usize) {
+        self.table.retain(|_, index| *index < len);
+    }
+
+    /// Returns the memory reserved by the hash table, in bytes.
+    pub fn reserved_bytes(&self) -> usize {
+        #[cfg(feature = "std")]
+        let slots = self.table.capacity();
+        #[cfg(not(feature = "std"))]
+        let slots = self.table.len();
+        slots * core::mem::size_of::<(u64, usize)>()
+    }
+}
+
+impl<T, I> core::fmt::Debug for Interner<T, I> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Interner")
+            .field("len", &self.table.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Checks that an arena given to build a tree is empty.
+/// It will **panic** if `nodes` isn't empty.
+pub fn assert_empty<T, A>(nodes: &A)
+where
+    A: Arena<T>,
+{
+    assert!(
+        nodes.is_empty(),
+        "the arena already has {} nodes",
+        nodes.len()
+    );
+}
+
+/// Checks that the roots given to reopen a tree of n elements are in the arena.
+/// It will **panic** if there are no roots, or if some root isn't in `nodes`.
+pub fn assert_roots<T, A>(nodes: &A, n: usize, roots: &[usize])
+where
+    A: Arena<T>,
+{
+    assert!(!roots.is_empty(), "there must be at least one version");
+    // The roots of an empty tree are never read.
+    if let Some(&root) = roots.iter().find(|&&root| n > 0 && root >= nodes.len()) {
+        panic!("root {root} isn't in the arena of {} nodes", nodes.len());
+    }
+}
+
+/// Pushes `node` to `nodes`, deduplicating it with `interner` if there is one, and returns its index.
+#[inline]
+pub fn push_node<T, I, A>(
+    nodes: &mut A,
+    interner: Option<&mut Interner<T, I>>,
+    node: PersistentWrapper<T, I>,
+) -> usize
+where
+    I: NodeIndex,
+    A: Arena<PersistentWrapper<T, I>>,
+{
+    if let Some(interner) = interner {
+        interner.insert(nodes, node)
+    } else {
+        nodes.push(node);
+        nodes.len() - 1
+    }
+}
+
+/// Marks every node reachable from `root` in `visited`, and returns how many of them weren't already marked.
+pub fn mark_reachable<T, I, A>(nodes: &A, root: usize, visited: &mut BitVec) -> usize
+where
+    I: NodeIndex,
+    A: Arena<PersistentWrapper<T, I>>,
+{
+    let mut stack = vec![root];
+    let mut count = 0;
+    while let Some(curr_node) = stack.pop() {
+        if visited[curr_node] {
+            continue;
+        }
+        visited.set(curr_node, true);
+        count += 1;
+        stack.extend(nodes[curr_node].left_child());
+        stack.extend(nodes[curr_node].right_child());
+    }
+    count
+}
+
+/// Returns the stats of the version with root `root` and parent with root `parent`, see [`VersionStats`].
+pub fn version_stats<T, I, A>(nodes: &A, root: usize, parent: Option<usize>) -> VersionStats
+where
+    I: NodeIndex,
+    A: Arena<PersistentWrapper<T, I>>,
+{
+    if nodes.is_empty() {
+        // Only the versions of an empty tree have no nodes.
+        return VersionStats::default();
+    }
+    let reachable = mark_reachable(nodes, root, &mut BitVec::from_elem(nodes.len(), false));
+    let introduced = parent.map_or(reachable, |parent| {
+        let mut visited = BitVec::from_elem(nodes.len(), false);
+        mark_reachable(nodes, parent, &mut visited);
+        mark_reachable(nodes, root, &mut visited)
+    });
+    VersionStats {
+        nodes: reachable,
+        introduced,
+        shared: reachable - introduced,
+    }
+}
+
+/// Compacts `nodes` in place keeping only the nodes reachable from `roots`, and updates `roots` to the new indices of the nodes.
+/// The kept nodes keep their relative order, so each of them is moved to an index not greater than its own, and nodes shared between roots are kept only once.
+/// If there is an `interner` it's rebuilt, so identical subtrees are also kept only once, which needs every child to be stored before its parent.
+/// Only the new index of each node is allocated besides the arena, the nodes are moved one at a time.
+pub fn compact<T, I, A>(nodes: &mut A, roots: &mut [usize], mut interner: Option<&mut Interner<T, I>>)
+where
+    T: Clone,
+    I: NodeIndex,
+    A: Arena<PersistentWrapper<T, I>>,
+{
+    if nodes.is_empty() {
+        // Only the versions of an empty tree have no nodes, and their roots are never read.
+        return;
+    }
+    let mut reachable = BitVec::from_elem(nodes.len(), false);
+    for &root in roots.iter() {
+        mark_reachable(&*nodes, root, &mut reachable);
+    }
+    let mut new_index = vec![usize::MAX; nodes.len()];
+    let mut len = 0;
+    for (old_index, _) in reachable.iter().enumerate().filter(|&(_, kept)| kept) {
+        new_index[old_index] = len;
+        len += 1;
+    }
+    if let Some(interner) = interner.as_deref_mut() {
+        interner.clear();
+    }
+    len = 0;
+    for (old_index, _) in reachable.iter().enumerate().filter(|&(_, kept)| kept) {
+        let mut node = nodes[old_index].clone();
+        if let (Some(left_node), Some(right_node)) = (node.left_child(), node.right_child()) {
+            node.set_children(new_index[left_node], new_index[right_node]);
+        }
+        // Every node before `len` is already compacted, so the node at `len` is either this one or unreachable.
+        new_index[old_index] = if let Some(interner) = interner.as_deref_mut() {
+            interner.insert_at(nodes, node, len)
+        } else {
+            nodes[len] = node;
+            len
+        };
+        if new_index[old_index] == len {
+            len += 1;
+        }
+    }
+    nodes.truncate(len);
+    for root in roots.iter_mut() {
+        *root = new_index[*root];
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::NonNUsize;
+    use crate::{nodes::Node, utils::Sum};
+
+    use super::{NodeIndex, PersistentWrapper};
+
+    #[test]
+    fn node_index_works() {
+        assert_eq!(u32::from_usize(0).unwrap().to_usize(), 0);
+        assert_eq!(u32::from_usize(1 << 20).unwrap().to_usize(), 1 << 20);
+    }
+
+    #[test]
+    fn node_index_rejects_sentinel() {
+        assert!(u16::from_usize(u16::MAX as usize).is_none());
+        assert!(u16::from_usize(1 << 16).is_none());
+    }
 
     #[test]
-    fn non_n_works() {
-        let test = NonNUsize::<1>::new(0);
-        assert_eq!(test.unwrap().get(), 0);
+    fn u32_index_is_smaller() {
+        use core::mem::size_of;
+        assert!(
+            size_of::<PersistentWrapper<usize, u32>>()
+                < size_of::<PersistentWrapper<usize, usize>>()
+        );
     }
 
     #[test]
-    fn non_n_works2() {
-        let test = NonNUsize::<1>::new(1);
-        assert!(test.is_none());
+    #[should_panic(expected = "doesn't fit in u16")]
+    fn set_children_panics_on_overflow() {
+        let mut node: PersistentWrapper<Sum<usize>, u16> = Sum::initialize(&0).into();
+        node.set_children(0, 1 << 16);
     }
 }
@@ -0,0 +1,33 @@
+use core::ops::Range;
+
+/// Clamps the range `[left,right]` to `[0,n)`, returning it as a half-open range.
+/// It returns None if the clamped range is empty.
+pub fn clamp(left: usize, right: usize, n: usize) -> Option<Range<usize>> {
+    let right = right.min(n.checked_sub(1)?);
+    (left <= right).then(|| left..right + 1)
+}
+
+/// It will **panic** if `at` isn't a valid index to split a segment tree of n elements at, i.e. it's not in `[0,n]`.
+pub fn assert_split_index(at: usize, n: usize) {
+    assert!(at <= n, "split index {at} is out of bounds, it must be at most {n}");
+}
+
+/// It will **panic** if `i` isn't the index of an element of a segment tree of n elements, i.e. it's not in `[0,n)`.
+pub fn assert_in_bounds(i: usize, n: usize) {
+    assert!(i < n, "index {i} is out of bounds, the segment tree has {n} elements");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::clamp;
+
+    #[test]
+    fn clamp_works() {
+        assert_eq!(clamp(0, 9, 10), Some(0..10));
+        assert_eq!(clamp(3, 20, 10), Some(3..10));
+        assert_eq!(clamp(9, usize::MAX, 10), Some(9..10));
+        assert_eq!(clamp(10, 20, 10), None);
+        assert_eq!(clamp(5, 4, 10), None);
+        assert_eq!(clamp(0, 0, 0), None);
+    }
+}
@@ -0,0 +1,69 @@
+//! Spans emitted by the trees, they compile to nothing unless the `tracing` feature is enabled.
+
+/// Enters a span with the given level, name and fields until the end of the current block.
+#[cfg(feature = "tracing")]
+macro_rules! span {
+    ($level:ident, $name:literal, $($fields:tt)*) => {
+        let _span = tracing::span!(tracing::Level::$level, $name, $($fields)*).entered();
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! span {
+    ($($tokens:tt)*) => {};
+}
+
+pub(crate) use span;
+
+#[cfg(all(test, feature = "tracing", feature = "std"))]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tracing::{
+        span::{Attributes, Id, Record},
+        Event, Metadata, Subscriber,
+    };
+
+    use crate::{
+        nodes::Node,
+        segment_tree::{Persistent, Recursive},
+        utils::Sum,
+    };
+
+    struct SpanNames(Arc<Mutex<Vec<&'static str>>>);
+
+    impl Subscriber for SpanNames {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut names = self.0.lock().unwrap();
+            names.push(span.metadata().name());
+            Id::from_u64(names.len() as u64)
+        }
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, _: &Event<'_>) {}
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn operations_emit_spans() {
+        let names = Arc::new(Mutex::new(Vec::new()));
+        let subscriber = SpanNames(Arc::clone(&names));
+        tracing::subscriber::with_default(subscriber, || {
+            let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+            let mut segment_tree = Recursive::build(&nodes);
+            segment_tree.update(0, &1);
+            let _ = segment_tree.query(0, 10);
+            let mut segment_tree = Persistent::build(&nodes);
+            segment_tree.update(0, 0, &1);
+            segment_tree.gc();
+        });
+        assert_eq!(
+            *names.lock().unwrap(),
+            ["build", "update", "query", "build", "update", "gc"]
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use core::{fmt::Display, ops::Index};
+
+use bit_vec::BitVec;
+
+use crate::nodes::{LazyNode, Node};
+
+/// It will **panic** if node isn't the combination of left and right, `at` names the node in the message.
+pub fn assert_combines<T>(node: &T, left: &T, right: &T, at: impl Display)
+where
+    T: Node,
+    T::Value: PartialEq,
+{
+    assert_matches(
+        T::combine(left, right).value() == node.value(),
+        format_args!("node of {at}"),
+        "its children",
+    );
+}
+
+/// It will **panic** if `matches` is `false`, `at` names what doesn't match the combination of `of` in the message.
+pub fn assert_matches(matches: bool, at: impl Display, of: &str) {
+    assert!(matches, "{at} doesn't match the combination of {of}");
+}
+
+fn expect_children<F>(children: &F, curr_node: usize, i: usize, j: usize) -> (usize, usize)
+where
+    F: Fn(usize) -> Option<(usize, usize)>,
+{
+    children(curr_node).unwrap_or_else(|| panic!("node of [{i}, {j}] is missing its children"))
+}
+
+/// Checks that every internal node of the subtree of `[i,j]` rooted at `curr_node` is the combination of its children.
+/// Nodes marked in `visited` are skipped, and every checked node is marked, so shared subtrees are checked once.
+pub fn validate<T, N, F>(
+    nodes: &N,
+    children: &F,
+    visited: &mut BitVec,
+    curr_node: usize,
+    i: usize,
+    j: usize,
+) where
+    T: Node,
+    T::Value: PartialEq,
+    N: Index<usize, Output = T> + ?Sized,
+    F: Fn(usize) -> Option<(usize, usize)>,
+{
+    if i == j || visited.get(curr_node) == Some(true) {
+        return;
+    }
+    visited.set(curr_node, true);
+    let (left_node, right_node) = expect_children(children, curr_node, i, j);
+    let mid = usize::midpoint(i, j);
+    validate(nodes, children, visited, left_node, i, mid);
+    validate(nodes, children, visited, right_node, mid + 1, j);
+    assert_combines(
+        &nodes[curr_node],
+        &nodes[left_node],
+        &nodes[right_node],
+        format_args!("[{i}, {j}]"),
+    );
+}
+
+/// Same as [`validate`], but for lazy nodes, `pending` is the lazy value pending from the ancestors of `curr_node`.
+/// It returns a copy of the node with every pending lazy value applied, and checks that it's the combination of its children with the lazy values applied, which holds only if [`lazy_update`](LazyNode::lazy_update) distributes over [`combine`](Node::combine).
+pub fn validate_lazy<T, N, F>(
+    nodes: &N,
+    children: &F,
+    curr_node: usize,
+    pending: Option<&T::Value>,
+    i: usize,
+    j: usize,
+) -> T
+where
+    T: LazyNode + Clone,
+    T::Value: PartialEq,
+    N: Index<usize, Output = T> + ?Sized,
+    F: Fn(usize) -> Option<(usize, usize)>,
+{
+    let mut node = nodes[curr_node].clone();
+    if let Some(value) = pending {
+        node.update_lazy_value(value);
+        assert!(
+            node.lazy_value().is_some(),
+            "update_lazy_value didn't set the lazy value of the node of [{i}, {j}]"
+        );
+    }
+    let lazy_value = node.lazy_value().cloned();
+    node.lazy_update(i, j);
+    assert!(
+        node.lazy_value().is_none(),
+        "lazy_update didn't clear the lazy value of the node of [{i}, {j}]"
+    );
+    if i != j {
+        let (left_node, right_node) = expect_children(children, curr_node, i, j);
+        let mid = usize::midpoint(i, j);
+        let left = validate_lazy(nodes, children, left_node, lazy_value.as_ref(), i, mid);
+        let right = validate_lazy(nodes, children, right_node, lazy_value.as_ref(), mid + 1, j);
+        assert_combines(&node, &left, &right, format_args!("[{i}, {j}]"));
+    }
+    node
+}
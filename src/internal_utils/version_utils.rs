@@ -0,0 +1,107 @@
+use alloc::{collections::BTreeMap, string::String, vec, vec::Vec};
+
+use crate::segment_tree::VersionId;
+
+/// Parent and label metadata of the versions of a persistent segment tree, kept parallel to its roots.
+#[derive(Clone, Debug, Default)]
+pub struct VersionHistory {
+    parents: Vec<Option<VersionId>>,
+    labels: BTreeMap<String, VersionId>,
+}
+
+impl VersionHistory {
+    /// Creates the metadata of `len` versions with no parents nor labels.
+    pub fn without_parents(len: usize) -> Self {
+        Self {
+            parents: vec![None; len],
+            labels: BTreeMap::new(),
+        }
+    }
+
+    /// Registers a new version created from `parent`, `None` if it was created by a build.
+    #[inline]
+    pub fn push(&mut self, parent: Option<VersionId>) {
+        self.parents.push(parent);
+    }
+
+    #[inline]
+    pub fn parent(&self, version: VersionId) -> Option<VersionId> {
+        self.parents[version]
+    }
+
+    /// It will **panic** if `version` doesn't exist.
+    pub fn assert_exists(&self, version: VersionId) {
+        assert!(
+            version < self.parents.len(),
+            "version {version} doesn't exist, there are {} versions",
+            self.parents.len()
+        );
+    }
+
+    /// Sets `label` to refer to `version`, returning the version it previously referred to.
+    /// It will **panic** if `version` doesn't exist.
+    pub fn set_label(&mut self, label: String, version: VersionId) -> Option<VersionId> {
+        self.assert_exists(version);
+        self.labels.insert(label, version)
+    }
+
+    pub fn find(&self, label: &str) -> Option<VersionId> {
+        self.labels.get(label).copied()
+    }
+
+    /// Keeps only the given versions, version `i` becomes `versions[i]`.
+    /// Parents and labels of discarded versions are dropped.
+    pub fn retain(&mut self, versions: &[VersionId]) {
+        let mut new_version = vec![None; self.parents.len()];
+        for (i, &version) in versions.iter().enumerate() {
+            new_version[version].get_or_insert(i);
+        }
+        self.parents = versions
+            .iter()
+            .map(|&version| self.parents[version].and_then(|parent| new_version[parent]))
+            .collect();
+        self.labels.retain(|_, version| {
+            new_version[*version].is_some_and(|new| {
+                *version = new;
+                true
+            })
+        });
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        self.parents.reserve(additional);
+    }
+
+    /// Keeps only the first `len` versions.
+    pub fn truncate(&mut self, len: usize) {
+        self.parents.truncate(len);
+        self.labels.retain(|_, version| *version < len);
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.parents.shrink_to_fit();
+    }
+
+    /// Returns the memory reserved by the metadata, without accounting for the contents of the labels, in bytes.
+    pub fn reserved_bytes(&self) -> usize {
+        self.parents.capacity() * core::mem::size_of::<Option<VersionId>>()
+            + self.labels.len() * core::mem::size_of::<(String, VersionId)>()
+    }
+}
+
+/// Returns the first version in `[0,versions)` for which `predicate` is `false`, or `versions` if there is none, assuming `predicate` is `true` for a prefix of the versions.
+pub fn partition_versions<P>(versions: usize, mut predicate: P) -> VersionId
+where
+    P: FnMut(VersionId) -> bool,
+{
+    let (mut lo, mut hi) = (0, versions);
+    while lo < hi {
+        let mid = usize::midpoint(lo, hi);
+        if predicate(mid) {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    lo
+}
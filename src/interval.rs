@@ -0,0 +1,106 @@
+/// Convention of the ranges given to a [`Ranged`](crate::Ranged) segment tree.
+///
+/// The segment trees take inclusive ranges `[left,right]`, a convention converts its ranges to them once, so the call sites don't have to.
+/// It's implemented for [`Inclusive`] and [`HalfOpen`].
+pub trait Interval {
+    /// Converts the range from `left` to `right` in this convention to the inclusive range `[left,right]`.
+    /// It returns None if the range is empty.
+    fn to_inclusive(left: usize, right: usize) -> Option<(usize, usize)>;
+}
+
+/// Inclusive ranges `[left,right]`, the convention used by the segment trees themselves.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Inclusive;
+
+impl Interval for Inclusive {
+    #[inline]
+    fn to_inclusive(left: usize, right: usize) -> Option<(usize, usize)> {
+        (left <= right).then_some((left, right))
+    }
+}
+
+/// Half-open ranges `[left,right)`, as the ranges of slices, so the whole segment tree is `[0,n)` and `[p,p)` is empty.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct HalfOpen;
+
+impl Interval for HalfOpen {
+    #[inline]
+    fn to_inclusive(left: usize, right: usize) -> Option<(usize, usize)> {
+        (left < right).then(|| (left, right - 1))
+    }
+}
+
+/// Type of the positions given to a [`Ranged`](crate::Ranged) segment tree, which are converted to the `usize` indices of the tree once, before reaching it.
+///
+/// It's implemented for the unsigned integers which fit in `usize` on the target, so `u64` positions only compile on 64-bit targets and a conversion never truncates a position.
+/// It only changes the type of the positions at the API boundary of [`Ranged`](crate::Ranged), every segment tree still indexes its elements with `usize`, and the memory used by the indices stored inside the persistent trees is chosen with [`NodeIndex`](crate::segment_tree::NodeIndex) instead.
+pub trait Position: Copy {
+    /// Converts the position to the index of its element in the tree.
+    fn to_index(self) -> usize;
+}
+
+impl Position for usize {
+    #[inline]
+    fn to_index(self) -> usize {
+        self
+    }
+}
+
+impl Position for u8 {
+    #[inline]
+    fn to_index(self) -> usize {
+        usize::from(self)
+    }
+}
+
+impl Position for u16 {
+    #[inline]
+    fn to_index(self) -> usize {
+        usize::from(self)
+    }
+}
+
+#[cfg(any(target_pointer_width = "32", target_pointer_width = "64"))]
+impl Position for u32 {
+    #[inline]
+    #[allow(clippy::cast_possible_truncation)]
+    fn to_index(self) -> usize {
+        // `usize` has at least 32 bits on this target.
+        self as usize
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+impl Position for u64 {
+    #[inline]
+    #[allow(clippy::cast_possible_truncation)]
+    fn to_index(self) -> usize {
+        // `usize` has 64 bits on this target.
+        self as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HalfOpen, Inclusive, Interval, Position};
+
+    #[test]
+    fn to_inclusive_works() {
+        assert_eq!(Inclusive::to_inclusive(2, 5), Some((2, 5)));
+        assert_eq!(Inclusive::to_inclusive(3, 3), Some((3, 3)));
+        assert_eq!(Inclusive::to_inclusive(4, 3), None);
+        assert_eq!(HalfOpen::to_inclusive(2, 5), Some((2, 4)));
+        assert_eq!(HalfOpen::to_inclusive(3, 3), None);
+        assert_eq!(HalfOpen::to_inclusive(0, 0), None);
+    }
+
+    #[test]
+    fn to_index_works() {
+        assert_eq!(7_u8.to_index(), 7);
+        assert_eq!(300_u16.to_index(), 300);
+        assert_eq!(u32::MAX.to_index(), 4_294_967_295);
+        assert_eq!(5_usize.to_index(), 5);
+        #[cfg(target_pointer_width = "64")]
+        assert_eq!((1_u64 << 40).to_index(), 1 << 40);
+    }
+}
@@ -14,12 +14,38 @@
 #![warn(clippy::cargo)]
 #![warn(clippy::nursery)]
 #![warn(missing_docs)]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
+extern crate alloc;
+
+/// C bindings of some concrete segment trees, enabled by the `ffi` feature.
+#[cfg(feature = "ffi")]
+pub mod ffi;
+/// Higher level utilities built on the segment trees.
+pub mod algorithms;
+/// Compact binary encoding of the segment trees, without serde.
+pub mod codec;
+/// Counters of the work done by the segment trees, enabled by the `instrument` feature.
+#[cfg(feature = "instrument")]
+pub mod instrument;
+/// Conventions of the ranges given to the segment trees, and the types of the positions taken by [`Ranged`].
+pub mod interval;
 /// Node traits.
 pub mod nodes;
 /// Segment trees.
 mod segment_tree;
 pub use segment_tree::*;
+/// [`pyo3`](https://docs.rs/pyo3) classes wrapping some concrete segment trees, enabled by the `pyo3` feature.
+#[cfg(feature = "pyo3")]
+pub mod python;
+/// Backing storage for the nodes of the segment trees.
+pub mod storage;
+/// Brute force reference implementations to test the segment trees against, enabled by the `test-utils` feature.
+#[cfg(any(test, feature = "test-utils"))]
+pub mod testing;
 /// Provided node implementations.
 pub mod utils;
+/// [`wasm-bindgen`](https://docs.rs/wasm-bindgen) wrappers of some concrete segment trees, enabled by the `wasm-bindgen` feature.
+#[cfg(feature = "wasm-bindgen")]
+pub mod wasm;
 mod internal_utils;
\ No newline at end of file
@@ -0,0 +1,18 @@
+use super::Node;
+
+/// Trait for nodes which are completely described by their [`Value`](Node::Value), which in turn is a cheap [`Copy`] type (usually a primitive).
+///
+/// Nodes implementing it can be stored as a flat array of values, which lets build and wide queries use plain loops over slices the compiler can auto-vectorize, instead of per-node [`combine`](Node::combine) calls.
+/// See [`Flat`](crate::segment_tree::Flat) and [Implementors](FlatNode#implementors) for some example implementations.
+pub trait FlatNode: Node<Value: Copy> {
+    /// Combines two values, it must be equivalent to `*Node::combine(&Node::initialize(&a), &Node::initialize(&b)).value()`.
+    fn combine_values(a: Self::Value, b: Self::Value) -> Self::Value;
+    /// Folds a **non-empty** slice of values from left to right using [`combine_values`](FlatNode::combine_values).
+    /// The default implementation is a simple loop, which is vectorized for most primitive operations, it can be overridden if a faster (explicitly vectorized) implementation exists.
+    #[inline]
+    fn fold_values(values: &[Self::Value]) -> Self::Value {
+        values[1..]
+            .iter()
+            .fold(values[0], |acc, &value| Self::combine_values(acc, value))
+    }
+}
@@ -0,0 +1,6 @@
+use super::Node;
+
+/// Marker trait for nodes whose [`combine`](Node::combine) is idempotent, i.e. `combine(a, a)` is `a`, such as min, max or gcd.
+///
+/// It means overlapping segments can be combined without counting any element twice, which lets [`BlockSparseTable`](crate::algorithms::BlockSparseTable) answer queries in `O(1)`.
+pub trait IdempotentNode: Node {}
@@ -0,0 +1,9 @@
+use super::Node;
+
+/// Trait for nodes whose [`combine`](Node::combine) can be undone, i.e. nodes over a group such as sums or xors.
+///
+/// It lets the results of prefixes answer any range, see [`PrefixAgg`](crate::algorithms::PrefixAgg).
+pub trait InvertibleNode: Node {
+    /// Given `whole` and `prefix`, which correspond to segments `[i,k]` and `[i,j]` with `j<k`, it returns the node which corresponds to the segment `[j+1,k]`, i.e. the node `b` such that `combine(prefix, b)` is `whole`.
+    fn uncombine(whole: &Self, prefix: &Self) -> Self;
+}
@@ -1,4 +1,14 @@
+mod flat_node;
+mod idempotent_node;
+mod invertible_node;
 mod lazy_node;
+mod monoid_node;
 mod node;
+mod selection_node;
+mod set_apply;
 
-pub use self::{lazy_node::LazyNode, node::Node};
+pub use self::{
+    flat_node::FlatNode, idempotent_node::IdempotentNode, invertible_node::InvertibleNode,
+    lazy_node::LazyNode, monoid_node::MonoidNode, node::Node, selection_node::SelectionNode,
+    set_apply::SetApply,
+};
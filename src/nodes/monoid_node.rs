@@ -0,0 +1,9 @@
+use super::Node;
+
+/// Trait for nodes which have an identity for [`combine`](Node::combine), i.e. a node `e` such that `combine(e, a)` and `combine(a, e)` are `a`, such as `0` for sums.
+///
+/// It lets segment trees pad their leaves without changing any result, see [`PaddedIterative`](crate::PaddedIterative).
+pub trait MonoidNode: Node {
+    /// Returns the identity of [`combine`](Node::combine).
+    fn identity() -> Self;
+}
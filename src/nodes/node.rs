@@ -9,4 +9,12 @@ pub trait Node {
     fn combine(a: &Self, b: &Self) -> Self;
     /// Method which returns a reference to the current saved value.
     fn value(&self) -> &Self::Value;
+    /// Consumes the node and returns its value, it's used by the segment trees to hand their values back, e.g. [`into_values`](crate::Iterative::into_values).
+    /// The default implementation clones [`value`](Node::value), nodes which own their value should override it to move the value out instead.
+    fn into_value(self) -> Self::Value
+    where
+        Self: Sized,
+    {
+        self.value().clone()
+    }
 }
@@ -0,0 +1,10 @@
+use super::IdempotentNode;
+
+/// Trait for nodes whose [`combine`](super::Node::combine) returns one of its arguments, such as min or max.
+///
+/// It lets [`StaticRmq`](crate::algorithms::StaticRmq) answer queries inside a block from the Cartesian tree of the block, which is built in `O(n)`.
+pub trait SelectionNode: IdempotentNode {
+    /// Returns `true` if `combine(left, right)` is left, and `false` if it's right.
+    /// It must be consistent with [`combine`](super::Node::combine), so for any `a`, `b` and `c`, if `a` selects over `b` and `b` selects over `c`, then `a` selects over `c`.
+    fn selects_left(left: &Self, right: &Self) -> bool;
+}
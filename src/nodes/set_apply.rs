@@ -0,0 +1,21 @@
+use super::{IdempotentNode, Node};
+
+/// Trait for nodes which can be built directly for a segment whose elements are all equal.
+///
+/// It lets setting a range to a value be applied to each segment at once, see [`LazySetWrapper`](crate::utils::LazySetWrapper).
+/// It's implemented for every [`IdempotentNode`], whose segment of equal elements is a single element, and for additive nodes like [`Sum`](crate::utils::Sum), whose segment depends on its length.
+pub trait SetApply: Node {
+    /// Returns the node which corresponds to a segment of `len` elements, each of them equal to value, i.e. the [`combine`](Node::combine) of `len` nodes initialized with value.
+    fn repeated(value: &Self::Value, len: usize) -> Self;
+}
+
+/// Combining equal elements gives back the element, so the length is ignored.
+impl<T> SetApply for T
+where
+    T: IdempotentNode,
+{
+    #[inline]
+    fn repeated(value: &Self::Value, _len: usize) -> Self {
+        Self::initialize(value)
+    }
+}
@@ -0,0 +1,179 @@
+use alloc::{format, vec::Vec};
+use core::ops::{Add, Mul};
+
+use pyo3::{exceptions::PyIndexError, prelude::*};
+
+use crate::{
+    nodes::Node,
+    segment_tree::{Iterative, LazyRecursive},
+    utils::{Min, Sum},
+};
+
+/// Returns an [`IndexError`](PyIndexError) if the range `[left,right]` is non empty and not inside `[0,n)`.
+fn check_range(left: usize, right: usize, n: usize) -> PyResult<()> {
+    if left <= right && right >= n {
+        return Err(PyIndexError::new_err(format!(
+            "range [{left}, {right}] out of range for length {n}"
+        )));
+    }
+    Ok(())
+}
+
+macro_rules! py_point_tree {
+    ($(#[$doc:meta])* $name:ident, $py_name:literal, $node:ident, $value:ty) => {
+        $(#[$doc])*
+        #[pyclass(name = $py_name, module = "seg_tree")]
+        pub struct $name {
+            tree: Iterative<$node<$value>>,
+        }
+
+        #[pymethods]
+        impl $name {
+            /// Builds the segment tree from the values, each of them will correspond to a leaf of the segment tree.
+            /// It has time complexity of `O(n)`.
+            #[new]
+            fn new(values: Vec<$value>) -> Self {
+                let nodes: Vec<$node<$value>> = values.iter().map($node::initialize).collect();
+                Self {
+                    tree: Iterative::build(&nodes),
+                }
+            }
+
+            /// Sets the i-th element to value, it raises an `IndexError` if i is not in `[0,n)`.
+            /// It has time complexity of `O(log(n))`.
+            fn update(&mut self, i: usize, value: $value) -> PyResult<()> {
+                check_range(i, i, self.tree.len())?;
+                self.tree.update(i, &value);
+                Ok(())
+            }
+
+            /// Returns the result from the range `[left,right]`, or `None` if the range is empty, it raises an `IndexError` if the range is not inside `[0,n)`.
+            /// It has time complexity of `O(log(n))`.
+            fn query(&self, left: usize, right: usize) -> PyResult<Option<$value>> {
+                check_range(left, right, self.tree.len())?;
+                Ok(self.tree.query(left, right).map($node::into_value))
+            }
+
+            const fn __len__(&self) -> usize {
+                self.tree.len()
+            }
+        }
+    };
+}
+
+py_point_tree!(
+    /// Range sum of floats with point updates, an [`Iterative`] segment tree with [`Sum<f64>`] nodes, exposed to Python as `SumTree`.
+    PySumTree,
+    "SumTree",
+    Sum,
+    f64
+);
+py_point_tree!(
+    /// Range min of integers with point updates, an [`Iterative`] segment tree with [`Min<i64>`] nodes, exposed to Python as `MinTree`.
+    PyMinTree,
+    "MinTree",
+    Min,
+    i64
+);
+
+/// Integer which implements `Mul<usize>`, which [`Sum`] requires to be a [`LazyNode`](crate::nodes::LazyNode).
+#[derive(Clone, Copy)]
+struct Addend(i64);
+
+impl Add for Addend {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl Mul<usize> for Addend {
+    type Output = Self;
+    fn mul(self, rhs: usize) -> Self {
+        Self(self.0 * i64::try_from(rhs).expect("the length of a segment fits in an i64"))
+    }
+}
+
+/// Range sum of integers with range additions, a [`LazyRecursive`] segment tree with [`Sum`] nodes, exposed to Python as `LazyAddTree`.
+#[pyclass(name = "LazyAddTree", module = "seg_tree")]
+pub struct PyLazyAddTree {
+    tree: LazyRecursive<Sum<Addend>>,
+}
+
+#[pymethods]
+impl PyLazyAddTree {
+    /// Builds the segment tree from the values, each of them will correspond to a leaf of the segment tree.
+    /// It has time complexity of `O(n*log(n))`.
+    #[new]
+    fn new(values: Vec<i64>) -> Self {
+        let nodes: Vec<Sum<Addend>> = values
+            .into_iter()
+            .map(|value| Sum::initialize(&Addend(value)))
+            .collect();
+        Self {
+            tree: LazyRecursive::build(&nodes),
+        }
+    }
+
+    /// Adds value to every element of the range `[left,right]`, it does nothing if the range is empty, and raises an `IndexError` if the range is not inside `[0,n)`.
+    /// It has time complexity of `O(log(n))`.
+    fn update(&mut self, left: usize, right: usize, value: i64) -> PyResult<()> {
+        check_range(left, right, self.tree.len())?;
+        if left <= right {
+            self.tree.update(left, right, &Addend(value));
+        }
+        Ok(())
+    }
+
+    /// Returns the sum of the range `[left,right]`, or `None` if the range is empty, it raises an `IndexError` if the range is not inside `[0,n)`.
+    /// It has time complexity of `O(log(n))`.
+    fn query(&mut self, left: usize, right: usize) -> PyResult<Option<i64>> {
+        check_range(left, right, self.tree.len())?;
+        Ok(self.tree.query(left, right).map(|node| node.into_value().0))
+    }
+
+    const fn __len__(&self) -> usize {
+        self.tree.len()
+    }
+}
+
+/// Python module `seg_tree` with the classes of this module, it's the entry point of the extension module when the crate is built as a `cdylib`.
+///
+/// # Errors
+/// It returns an error if a class can't be added to the module.
+#[pymodule]
+pub fn seg_tree(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PySumTree>()?;
+    module.add_class::<PyMinTree>()?;
+    module.add_class::<PyLazyAddTree>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pyo3::{exceptions::PyIndexError, Python};
+
+    use super::{PyLazyAddTree, PyMinTree, PySumTree};
+
+    #[test]
+    fn classes_work() {
+        Python::initialize();
+        Python::attach(|py| {
+            let mut sums = PySumTree::new(vec![1.5, 2.0, 3.25]);
+            sums.update(1, 4.0).unwrap();
+            assert_eq!(sums.query(0, 2).unwrap(), Some(8.75));
+            assert_eq!(sums.query(2, 1).unwrap(), None);
+            assert!(sums
+                .query(1, 3)
+                .unwrap_err()
+                .is_instance_of::<PyIndexError>(py));
+            assert_eq!(sums.__len__(), 3);
+            let mins = PyMinTree::new(vec![3, -1, 2]);
+            assert_eq!(mins.query(0, 2).unwrap(), Some(-1));
+            let mut lazy = PyLazyAddTree::new(vec![1, 2, 3, 4]);
+            lazy.update(1, 3, -5).unwrap();
+            assert_eq!(lazy.query(0, 3).unwrap(), Some(-5));
+            assert_eq!(lazy.query(2, 2).unwrap(), Some(-2));
+        });
+    }
+}
@@ -0,0 +1,150 @@
+use alloc::vec::Vec;
+
+use crate::{
+    algorithms::PrefixAgg,
+    internal_utils::tracing_utils::span,
+    nodes::{InvertibleNode, Node},
+    segment_tree::Iterative,
+};
+
+/// Segment tree which picks its layout from the workload, answering queries from a [`PrefixAgg`] until the first update, and from an [`Iterative`] tree afterwards.
+///
+/// While it's query only every query has time complexity of `O(1)`, the first update turns the prefixes back into leaves and builds the tree, after which it behaves as an [`Iterative`] tree.
+/// It uses `O(n)` space, assuming that each node uses `O(1)` space.
+/// ```
+/// # use seg_tree::{Adaptive,utils::Sum,nodes::Node};
+/// # let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+/// let mut seg_tree = Adaptive::build(&nodes); // [0,1,2,3,4,5,6,7,8,9] with Sum<usize> nodes
+/// assert!(seg_tree.is_query_only());
+/// assert_eq!(seg_tree.query(2, 4).unwrap().value(), &9);
+/// seg_tree.update(3, &10);
+/// assert!(!seg_tree.is_query_only());
+/// assert_eq!(seg_tree.query(2, 4).unwrap().value(), &16);
+/// ```
+pub struct Adaptive<T> {
+    layout: Layout<T>,
+}
+
+enum Layout<T> {
+    Prefixes(PrefixAgg<T>),
+    Tree(Iterative<T>),
+}
+
+impl<T> Adaptive<T>
+where
+    T: InvertibleNode + Clone,
+{
+    /// Builds the prefixes from slice, each element of the slice will correspond to a leaf of the segment tree once it's updated.
+    /// It has time complexity of `O(n)`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn build(values: &[T]) -> Self {
+        span!(DEBUG, "build", tree = "Adaptive", n = values.len());
+        Self {
+            layout: Layout::Prefixes(PrefixAgg::build(values)),
+        }
+    }
+
+    /// Returns the amount of elements.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        match &self.layout {
+            Layout::Prefixes(prefixes) => prefixes.len(),
+            Layout::Tree(tree) => tree.len(),
+        }
+    }
+
+    /// Returns `true` if there are no elements.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if it wasn't updated yet, i.e. if it still answers queries from its prefixes.
+    #[must_use]
+    pub const fn is_query_only(&self) -> bool {
+        matches!(self.layout, Layout::Prefixes(_))
+    }
+
+    /// Sets the i-th element to value T and updates the segment tree correspondingly.
+    /// It will panic if i is not in `[0,n)`
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) has constant time complexity, except for the first update which also builds the tree in `O(n*log(n))`.
+    pub fn update(&mut self, i: usize, value: &<T as Node>::Value) {
+        self.tree().update(i, value);
+    }
+
+    /// Returns the result from the range `[left,right]`.
+    /// It returns None if and only if range is empty or there are no elements.
+    /// It will **panic** if left or right are not in `[0,n)`.
+    /// It has time complexity of `O(1)` while it's [query only](Self::is_query_only), assuming that [`uncombine`](InvertibleNode::uncombine) has constant time complexity, and `O(log(n))` afterwards.
+    #[must_use]
+    pub fn query(&self, left: usize, right: usize) -> Option<T> {
+        match &self.layout {
+            Layout::Prefixes(prefixes) if prefixes.is_empty() => None,
+            Layout::Prefixes(prefixes) => prefixes.query(left, right),
+            Layout::Tree(tree) => tree.query(left, right),
+        }
+    }
+
+    /// Returns the tree, building it from the prefixes the first time.
+    fn tree(&mut self) -> &mut Iterative<T> {
+        if let Layout::Prefixes(prefixes) = &mut self.layout {
+            span!(
+                DEBUG,
+                "switch_to_tree",
+                tree = "Adaptive",
+                n = prefixes.len()
+            );
+            let values: Vec<T> = core::mem::take(prefixes).into_values();
+            self.layout = Layout::Tree(Iterative::build(&values));
+        }
+        match &mut self.layout {
+            Layout::Tree(tree) => tree,
+            Layout::Prefixes(_) => unreachable!("the tree was just built"),
+        }
+    }
+}
+
+impl<T> core::fmt::Debug for Adaptive<T>
+where
+    T: Node + core::fmt::Debug,
+    T::Value: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match &self.layout {
+            Layout::Prefixes(prefixes) => f.debug_tuple("Adaptive").field(prefixes).finish(),
+            Layout::Tree(tree) => f.debug_tuple("Adaptive").field(tree).finish(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        nodes::Node,
+        testing::{NaiveRangeQuery, Op},
+        utils::Sum,
+    };
+
+    use super::Adaptive;
+
+    #[test]
+    fn switches_to_tree_on_update() {
+        let nodes: Vec<Sum<usize>> = (0..13).map(|x| Sum::initialize(&x)).collect();
+        let mut ops: Vec<Op<usize>> = (0..13).map(|i| Op::Query(i / 2, i)).collect();
+        ops.extend([Op::Update(4, 20), Op::Query(0, 12), Op::Update(12, 0)]);
+        ops.extend((0..13).map(|i| Op::Query(i / 3, 12 - i / 3)));
+        let mut seg_tree = Adaptive::build(&nodes);
+        NaiveRangeQuery::build(&nodes).check(&mut seg_tree, &ops[..13]);
+        assert!(seg_tree.is_query_only());
+        NaiveRangeQuery::build(&nodes).check(&mut seg_tree, &ops[13..]);
+        assert!(!seg_tree.is_query_only());
+        assert_eq!(seg_tree.len(), 13);
+    }
+
+    #[test]
+    fn empty_works() {
+        let seg_tree = Adaptive::<Sum<usize>>::build(&[]);
+        assert!(seg_tree.is_empty());
+        assert!(seg_tree.query(0, 0).is_none());
+    }
+}
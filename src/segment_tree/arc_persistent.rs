@@ -0,0 +1,648 @@
+use alloc::{collections::BTreeSet, sync::Arc};
+use core::ops::Range;
+
+use crate::{
+    internal_utils::{
+        append_utils::AppendList,
+        empty_utils::assert_not_empty,
+        instrument_utils::{combine, enter, record_allocations, LastOp},
+        range_utils::clamp,
+        tracing_utils::span,
+        validate_utils::assert_combines,
+    },
+    nodes::Node,
+    segment_tree::VersionId,
+};
+
+type Link<T> = Arc<ArcNode<T>>;
+
+struct ArcNode<T> {
+    node: T,
+    children: Option<(Link<T>, Link<T>)>,
+}
+
+/// Persistent segment tree whose nodes are reference counted, it saves every version of itself, it has range queries and point updates.
+///
+/// Unlike [`Persistent`](crate::segment_tree::Persistent), whose versions all live in a single arena owned by the tree, each version is an [`ArcVersion`] which can be cheaply cloned and sent to another thread for read-only querying, while the tree keeps accepting updates.
+/// Nodes are freed once no version references them.
+/// It uses `O(n+q*log(n))` space, where `q` is the amount of updates, and assuming that each node uses `O(1)` space.
+///
+/// Readers on other threads can also follow the versions as they are created through an [`ArcReader`], which never blocks nor is blocked by the writer.
+pub struct ArcPersistent<T> {
+    roots: Arc<Roots<T>>,
+    n: usize,
+    last_op: LastOp,
+}
+
+/// Handle to every version of an [`ArcPersistent`] segment tree, including the versions created after the handle.
+///
+/// The versions are published atomically, so a reader sees the version once [`update`](ArcPersistent::update) returns, without any locking between the reader and the writer.
+/// ```
+/// # use seg_tree::{ArcPersistent,utils::Sum,nodes::Node};
+/// # let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+/// let mut seg_tree = ArcPersistent::build(&nodes); // [0,1,2,3,4,5,6,7,8,9] with Sum<usize> nodes
+/// let reader = seg_tree.reader();
+/// let handle = std::thread::spawn(move || {
+///     while reader.versions() < 2 {
+///         std::hint::spin_loop();
+///     }
+///     *reader.query(1, 0, 9).unwrap().value()
+/// });
+/// seg_tree.update(0, 0, &10);
+/// assert_eq!(handle.join().unwrap(), 55);
+/// ```
+pub struct ArcReader<T> {
+    roots: Arc<Roots<T>>,
+    n: usize,
+}
+
+/// Roots of the versions, which can be read while the owning [`ArcPersistent`] appends to it, as it's the single writer.
+///
+/// The roots of an empty tree are `None`, as it has no nodes.
+struct Roots<T>(AppendList<Option<Link<T>>>);
+
+impl<T> Roots<T> {
+    const fn new() -> Self {
+        Self(AppendList::new())
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// It will **panic** if version is not in `[0,len)`.
+    fn get(&self, version: VersionId) -> Option<&ArcNode<T>> {
+        self.link_ref(version).map(|root| &**root)
+    }
+
+    /// It will **panic** if version is not in `[0,len)`.
+    fn link(&self, version: VersionId) -> Option<Link<T>> {
+        self.link_ref(version).cloned()
+    }
+
+    /// It will **panic** if version is not in `[0,len)`.
+    fn link_ref(&self, version: VersionId) -> Option<&Link<T>> {
+        let len = self.len();
+        self.0
+            .get(version)
+            .unwrap_or_else(|| panic!("version {version} doesn't exist, there are {len} versions"))
+            .as_ref()
+    }
+
+    /// Appends a root, it must only be called by the single writer.
+    fn push(&self, root: Option<Link<T>>) {
+        // SAFETY: only the owning `ArcPersistent` calls it, through `&mut self`.
+        unsafe { self.0.push(root) };
+    }
+}
+
+/// A single version of an [`ArcPersistent`] segment tree.
+///
+/// Cloning it has time complexity of `O(1)`, and it's [`Send`] and [`Sync`] whenever `T` is.
+pub struct ArcVersion<T> {
+    root: Option<Link<T>>,
+    n: usize,
+}
+
+impl<T> ArcPersistent<T>
+where
+    T: Clone + Node,
+{
+    /// Builds persistent segment tree from slice, each element of the slice will correspond to a leaf of the segment tree.
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn build(values: &[T]) -> Self {
+        span!(DEBUG, "build", tree = "ArcPersistent", n = values.len());
+        let last_op = LastOp::default();
+        let _op = last_op.measure();
+        let n = values.len();
+        let roots = Roots::new();
+        roots.push((n != 0).then(|| Self::build_helper(values, 0, n - 1)));
+        Self {
+            roots: Arc::new(roots),
+            n,
+            last_op,
+        }
+    }
+
+    fn build_helper(values: &[T], i: usize, j: usize) -> Link<T> {
+        let _depth = enter();
+        if i == j {
+            record_allocations(1);
+            return Arc::new(ArcNode {
+                node: values[i].clone(),
+                children: None,
+            });
+        }
+        let mid = usize::midpoint(i, j);
+        let left_node = Self::build_helper(values, i, mid);
+        let right_node = Self::build_helper(values, mid + 1, j);
+        Self::combine(left_node, right_node)
+    }
+
+    fn combine(left_node: Link<T>, right_node: Link<T>) -> Link<T> {
+        record_allocations(1);
+        Arc::new(ArcNode {
+            node: combine(&left_node.node, &right_node.node),
+            children: Some((left_node, right_node)),
+        })
+    }
+
+    /// Returns the result from the range `[left,right]` from the version of the segment tree.
+    /// It returns None if and only if range is empty or the segment tree has no elements.
+    /// It will **panic** if left or right are not in `[0,n)`, or if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn query(&self, version: VersionId, left: usize, right: usize) -> Option<T> {
+        span!(
+            TRACE,
+            "query",
+            tree = "ArcPersistent",
+            n = self.n,
+            version,
+            left,
+            right
+        );
+        let _op = self.last_op.measure();
+        self.roots
+            .get(version)
+            .and_then(|root| query_helper(root, left, right, 0, self.n - 1))
+    }
+
+    /// Same as [`query`](Self::query), but the range is clamped to `[0,n)` instead of **panicking** when it's out of bounds, and the range that was actually aggregated is returned with the result, as a half-open range.
+    /// It returns None if and only if the clamped range is empty.
+    /// It will **panic** if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn query_with_range(
+        &self,
+        version: VersionId,
+        left: usize,
+        right: usize,
+    ) -> Option<(Range<usize>, T)> {
+        let range = clamp(left, right, self.n);
+        let (left, right) = range
+            .as_ref()
+            .map_or((1, 0), |range| (range.start, range.end - 1));
+        range.zip(self.query(version, left, right))
+    }
+
+    /// Same as [`query_with_range`](Self::query_with_range), but only the result is returned.
+    /// It returns None if and only if the range clamped to `[0,n)` is empty.
+    /// It will **panic** if version is not in <code>[0,[versions](Self::versions))</code>.
+    #[must_use]
+    pub fn query_clamped(&self, version: VersionId, left: usize, right: usize) -> Option<T> {
+        self.query_with_range(version, left, right)
+            .map(|(_, node)| node)
+    }
+
+    /// Creates a new segment tree version from version were the p-th element of the segment tree to value T and update the segment tree correspondingly.
+    /// It will panic if p is not in `[0,n)`, or if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It returns the new version, i.e. <code>[versions](Self::versions)-1</code> after the call.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn update(
+        &mut self,
+        version: VersionId,
+        p: usize,
+        value: &<T as Node>::Value,
+    ) -> VersionId {
+        span!(
+            TRACE,
+            "update",
+            tree = "ArcPersistent",
+            n = self.n,
+            version,
+            p
+        );
+        let _op = self.last_op.measure();
+        assert_not_empty(self.n, "update");
+        let new_root = self
+            .roots
+            .link(version)
+            .map(|root| Self::update_helper(&root, p, value, 0, self.n - 1));
+        self.roots.push(new_root);
+        self.roots.len() - 1
+    }
+
+    fn update_helper(
+        curr_node: &Link<T>,
+        p: usize,
+        value: &<T as Node>::Value,
+        i: usize,
+        j: usize,
+    ) -> Link<T> {
+        let _depth = enter();
+        if j < p || p < i {
+            return Arc::clone(curr_node);
+        }
+        let Some((left_node, right_node)) = &curr_node.children else {
+            record_allocations(1);
+            return Arc::new(ArcNode {
+                node: Node::initialize(value),
+                children: None,
+            });
+        };
+        let mid = usize::midpoint(i, j);
+        let left_node = Self::update_helper(left_node, p, value, i, mid);
+        let right_node = Self::update_helper(right_node, p, value, mid + 1, j);
+        Self::combine(left_node, right_node)
+    }
+
+    /// Returns the amount of elements of the segment tree.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.n
+    }
+
+    /// Returns `true` if the segment tree has no elements.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Returns the amount of different versions the current segment tree has. Essentially this will be how many calls to [`update`](Self::update) have happened.
+    #[must_use]
+    pub fn versions(&self) -> usize {
+        self.roots.len()
+    }
+
+    /// Returns the versions of the segment tree in creation order, that is, <code>0..[versions](Self::versions)</code>, so they can be enumerated without tracking their amount.
+    /// ```
+    /// # use seg_tree::{ArcPersistent,utils::Sum,nodes::Node};
+    /// # let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+    /// let mut seg_tree = ArcPersistent::build(&nodes);
+    /// seg_tree.update(0, 3, &7);
+    /// let sums: Vec<usize> = seg_tree
+    ///     .versions_iter()
+    ///     .map(|version| *seg_tree.query(version, 0, 9).unwrap().value())
+    ///     .collect();
+    /// assert_eq!(sums, [45, 49]);
+    /// ```
+    #[must_use]
+    pub fn versions_iter(&self) -> Range<VersionId> {
+        0..self.roots.len()
+    }
+
+    /// Returns a handle to the version, which shares its nodes with the tree and can be queried independently of it.
+    /// It will **panic** if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(1)`.
+    #[must_use]
+    pub fn version(&self, version: VersionId) -> ArcVersion<T> {
+        ArcVersion {
+            root: self.roots.link(version),
+            n: self.n,
+        }
+    }
+
+    /// Returns a reader of the versions of the segment tree, which can be sent to another thread and sees the versions created after it.
+    /// It has time complexity of `O(1)`.
+    #[must_use]
+    pub fn reader(&self) -> ArcReader<T> {
+        ArcReader {
+            roots: Arc::clone(&self.roots),
+            n: self.n,
+        }
+    }
+
+    /// Returns the counters of the last build, query or update of the segment tree, see [`OpStats`](crate::instrument::OpStats).
+    #[cfg(feature = "instrument")]
+    #[must_use]
+    pub fn stats(&self) -> crate::instrument::OpStats {
+        self.last_op.get()
+    }
+
+    /// Checks that every internal node of every version is the combination of its children, so a node whose [`combine`](Node::combine) isn't deterministic, or corrupted nodes, are caught where they happen instead of as wrong answers later on.
+    /// It's meant for tests and debug assertions.
+    /// It will **panic** at the first node which doesn't match.
+    /// It has time complexity of `O(m*log(m))`, where `m` is the amount of nodes reachable from any version, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn validate(&self)
+    where
+        <T as Node>::Value: PartialEq,
+    {
+        let mut visited = BTreeSet::new();
+        for version in 0..self.roots.len() {
+            if let Some(root) = self.roots.get(version) {
+                validate_helper(root, &mut visited, 0, self.n - 1);
+            }
+        }
+    }
+}
+
+impl<T> ArcReader<T>
+where
+    T: Clone + Node,
+{
+    /// Returns the result from the range `[left,right]` from the version of the segment tree.
+    /// It returns None if and only if range is empty or the segment tree has no elements.
+    /// It will **panic** if left or right are not in `[0,n)`, or if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn query(&self, version: VersionId, left: usize, right: usize) -> Option<T> {
+        span!(
+            TRACE,
+            "query",
+            tree = "ArcReader",
+            n = self.n,
+            version,
+            left,
+            right
+        );
+        self.roots
+            .get(version)
+            .and_then(|root| query_helper(root, left, right, 0, self.n - 1))
+    }
+
+    /// Returns the amount of versions published so far, it never decreases.
+    #[must_use]
+    pub fn versions(&self) -> usize {
+        self.roots.len()
+    }
+
+    /// Returns the versions published so far in creation order, see [`ArcPersistent::versions_iter`].
+    /// A version published after the call isn't included.
+    #[must_use]
+    pub fn versions_iter(&self) -> Range<VersionId> {
+        0..self.roots.len()
+    }
+
+    /// Returns a handle to the version, see [`ArcPersistent::version`].
+    /// It will **panic** if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(1)`.
+    #[must_use]
+    pub fn version(&self, version: VersionId) -> ArcVersion<T> {
+        ArcVersion {
+            root: self.roots.link(version),
+            n: self.n,
+        }
+    }
+}
+
+impl<T> ArcVersion<T>
+where
+    T: Clone + Node,
+{
+    /// Returns the result from the range `[left,right]`.
+    /// It returns None if and only if range is empty or the segment tree has no elements.
+    /// It will **panic** if left or right are not in `[0,n)`.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn query(&self, left: usize, right: usize) -> Option<T> {
+        span!(TRACE, "query", tree = "ArcVersion", n = self.n, left, right);
+        self.root
+            .as_ref()
+            .and_then(|root| query_helper(root, left, right, 0, self.n - 1))
+    }
+}
+
+fn query_helper<T>(
+    curr_node: &ArcNode<T>,
+    left: usize,
+    right: usize,
+    i: usize,
+    j: usize,
+) -> Option<T>
+where
+    T: Clone + Node,
+{
+    let _depth = enter();
+    if j < left || right < i {
+        return None;
+    }
+    if left <= i && j <= right {
+        return Some(curr_node.node.clone());
+    }
+    let mid = usize::midpoint(i, j);
+    let (left_node, right_node) = curr_node.children.as_ref().unwrap();
+    match (
+        query_helper(left_node, left, right, i, mid),
+        query_helper(right_node, left, right, mid + 1, j),
+    ) {
+        (Some(ans_left), Some(ans_right)) => Some(combine(&ans_left, &ans_right)),
+        (Some(ans_left), None) => Some(ans_left),
+        (None, Some(ans_right)) => Some(ans_right),
+        (None, None) => None,
+    }
+}
+
+/// Checks the subtree of `[i,j]` rooted at `curr_node`, skipping the nodes in `visited` and adding every checked node.
+fn validate_helper<T>(
+    curr_node: &ArcNode<T>,
+    visited: &mut BTreeSet<*const ArcNode<T>>,
+    i: usize,
+    j: usize,
+) where
+    T: Node,
+    T::Value: PartialEq,
+{
+    let Some((left_node, right_node)) = &curr_node.children else {
+        assert!(i == j, "node of [{i}, {j}] is missing its children");
+        return;
+    };
+    if !visited.insert(curr_node) {
+        return;
+    }
+    let mid = usize::midpoint(i, j);
+    validate_helper(left_node, visited, i, mid);
+    validate_helper(right_node, visited, mid + 1, j);
+    assert_combines(
+        &curr_node.node,
+        &left_node.node,
+        &right_node.node,
+        format_args!("[{i}, {j}]"),
+    );
+}
+
+impl<T> Clone for ArcVersion<T> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            n: self.n,
+        }
+    }
+}
+
+impl<T> core::fmt::Debug for ArcPersistent<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ArcPersistent")
+            .field("n", &self.n)
+            .field("versions", &self.roots.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> Clone for ArcReader<T> {
+    fn clone(&self) -> Self {
+        Self {
+            roots: Arc::clone(&self.roots),
+            n: self.n,
+        }
+    }
+}
+
+impl<T> core::fmt::Debug for ArcReader<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ArcReader")
+            .field("n", &self.n)
+            .field("versions", &self.roots.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> core::fmt::Debug for ArcVersion<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ArcVersion")
+            .field("n", &self.n)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::{nodes::Node, utils::Sum};
+
+    use super::ArcPersistent;
+
+    #[test]
+    fn empty_query_returns_none() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let segment_tree = ArcPersistent::build(&nodes);
+        assert!(segment_tree.query(0, 10, 0).is_none());
+        assert!(segment_tree.version(0).query(10, 0).is_none());
+    }
+
+    #[test]
+    fn branched_update_works() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = ArcPersistent::build(&nodes);
+        let value = 20;
+        segment_tree.update(0, 0, &value);
+        segment_tree.update(0, 1, &value);
+        assert_eq!(segment_tree.query(0, 0, 10).unwrap().value(), &55);
+        assert_eq!(segment_tree.query(1, 0, 10).unwrap().value(), &75);
+        assert_eq!(segment_tree.query(2, 0, 0).unwrap().value(), &0);
+        assert_eq!(segment_tree.query(2, 1, 1).unwrap().value(), &value);
+    }
+
+    #[test]
+    fn version_works_across_threads() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = ArcPersistent::build(&nodes);
+        segment_tree.update(0, 0, &20);
+        let version = segment_tree.version(1);
+        let handle = std::thread::spawn(move || *version.query(0, 10).unwrap().value());
+        segment_tree.update(1, 1, &20);
+        assert_eq!(handle.join().unwrap(), 75);
+        assert_eq!(segment_tree.version(2).query(0, 10).unwrap().value(), &94);
+    }
+
+    #[test]
+    fn reader_sees_new_versions() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = ArcPersistent::build(&nodes);
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let reader = segment_tree.reader();
+                std::thread::spawn(move || {
+                    let mut seen = 0;
+                    while seen < 1000 {
+                        let versions = reader.versions();
+                        for version in seen..versions {
+                            assert_eq!(
+                                reader.query(version, 0, 10).unwrap().value(),
+                                &(55 + version)
+                            );
+                        }
+                        seen = versions;
+                    }
+                })
+            })
+            .collect();
+        for version in 0..999 {
+            segment_tree.update(version, 0, &(version + 1));
+        }
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        assert_eq!(
+            segment_tree
+                .reader()
+                .version(999)
+                .query(0, 0)
+                .unwrap()
+                .value(),
+            &999
+        );
+    }
+
+    #[test]
+    fn roots_are_dropped() {
+        #[derive(Clone)]
+        struct Counted(Rc<()>);
+        impl Node for Counted {
+            type Value = Rc<()>;
+            fn initialize(value: &Self::Value) -> Self {
+                Self(Rc::clone(value))
+            }
+            fn combine(a: &Self, _: &Self) -> Self {
+                a.clone()
+            }
+            fn value(&self) -> &Self::Value {
+                &self.0
+            }
+        }
+        let counter = Rc::new(());
+        let nodes = vec![Counted::initialize(&counter); 4];
+        let mut segment_tree = ArcPersistent::build(&nodes);
+        drop(nodes);
+        for version in 0..100 {
+            segment_tree.update(version, version % 4, &counter);
+        }
+        let reader = segment_tree.reader();
+        drop(segment_tree);
+        assert!(Rc::strong_count(&counter) > 1);
+        let version = reader.version(50);
+        drop(reader);
+        assert_eq!(Rc::strong_count(&counter), 8);
+        drop(version);
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+
+    #[test]
+    fn validate_works() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = ArcPersistent::build(&nodes);
+        segment_tree.update(0, 3, &20);
+        segment_tree.update(0, 10, &20);
+        segment_tree.update(1, 4, &20);
+        segment_tree.validate();
+    }
+
+    #[test]
+    fn empty_tree_works() {
+        let segment_tree = ArcPersistent::<Sum<usize>>::build(&[]);
+        assert!(segment_tree.is_empty());
+        assert_eq!(segment_tree.versions(), 1);
+        assert!(segment_tree.query(0, 0, 0).is_none());
+        assert!(segment_tree.reader().query(0, 0, 0).is_none());
+        assert!(segment_tree.version(0).query(0, 0).is_none());
+        segment_tree.validate();
+    }
+
+    #[test]
+    #[should_panic = "can't update an empty segment tree"]
+    fn update_on_empty_tree_panics() {
+        let mut segment_tree = ArcPersistent::<Sum<usize>>::build(&[]);
+        segment_tree.update(0, 0, &1);
+    }
+
+    #[test]
+    fn query_clamped_works() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = ArcPersistent::build(&nodes);
+        segment_tree.update(0, 10, &0);
+        assert_eq!(segment_tree.query_clamped(0, 5, 20).unwrap().value(), &45);
+        assert_eq!(segment_tree.query_clamped(1, 5, 20).unwrap().value(), &35);
+        assert!(segment_tree.query_clamped(1, 11, 20).is_none());
+    }
+}
@@ -0,0 +1,287 @@
+use alloc::{borrow::Cow, vec::Vec};
+use core::marker::PhantomData;
+
+use crate::{
+    interval::{Inclusive, Interval},
+    nodes::{LazyNode, MonoidNode, Node},
+    segment_tree::{
+        Iterative, LazyPersistent, LazyRecursive, NodeIndex, Persistent, PersistentWrapper, Ranged,
+        Recursive,
+    },
+    storage::{Arena, GrowableArena, Growth, Storage},
+};
+
+/// Builder which gathers the options of a segment tree before building it, so the options don't have to be positional arguments of each constructor.
+///
+/// The options are:
+/// - The convention `C` of the ranges, the tree is wrapped in a [`Ranged`] of it, which is [`Inclusive`] by default, see [`interval`](SegTreeBuilder::interval).
+/// - The growth policy of the arena of the persistent trees, see [`growth`](SegTreeBuilder::growth).
+/// - The capacity reserved for the updates of the persistent trees, see [`expected_updates`](SegTreeBuilder::expected_updates).
+/// - Padding the elements with the identity, see [`pad_to`](SegTreeBuilder::pad_to).
+/// - The storage backend, which is chosen by building with the `_in` methods, e.g. [`recursive_in`](SegTreeBuilder::recursive_in).
+/// ```
+/// # use seg_tree::{SegTreeBuilder,interval::HalfOpen,storage::Growth,utils::Sum,nodes::Node};
+/// # let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+/// let mut seg_tree = SegTreeBuilder::new(&nodes) // [0,1,2,3,4,5,6,7,8,9] with Sum<usize> nodes
+///     .interval::<HalfOpen>()
+///     .growth(Growth::Exact)
+///     .expected_updates(100)
+///     .persistent();
+/// let version = seg_tree.update(0, 3, &10);
+/// assert_eq!(seg_tree.query(version, 0, 10).unwrap().value(), &52);
+/// assert!(seg_tree.query(version, 3, 3).is_none());
+/// ```
+pub struct SegTreeBuilder<'a, T, C = Inclusive>
+where
+    T: Clone,
+{
+    values: Cow<'a, [T]>,
+    growth: Growth,
+    expected_updates: usize,
+    _interval: PhantomData<C>,
+}
+
+impl<'a, T> SegTreeBuilder<'a, T>
+where
+    T: Node + Clone,
+{
+    /// Creates a builder of segment trees whose elements are values, with the default options.
+    #[must_use]
+    pub const fn new(values: &'a [T]) -> Self {
+        Self {
+            values: Cow::Borrowed(values),
+            growth: Growth::Doubling,
+            expected_updates: 0,
+            _interval: PhantomData,
+        }
+    }
+}
+
+impl<'a, T, C> SegTreeBuilder<'a, T, C>
+where
+    T: Node + Clone,
+    C: Interval,
+{
+    /// Sets the convention of the ranges given to the built tree, see [`Ranged`].
+    #[must_use]
+    pub fn interval<D>(self) -> SegTreeBuilder<'a, T, D>
+    where
+        D: Interval,
+    {
+        SegTreeBuilder {
+            values: self.values,
+            growth: self.growth,
+            expected_updates: self.expected_updates,
+            _interval: PhantomData,
+        }
+    }
+
+    /// Sets the policy used to grow the arena of the persistent trees, see [`GrowableArena`].
+    /// It's [`Growth::Doubling`] by default.
+    #[must_use]
+    pub const fn growth(mut self, growth: Growth) -> Self {
+        self.growth = growth;
+        self
+    }
+
+    /// Reserves memory for `q` updates when building a persistent tree, see [`reserve_updates`](Persistent::reserve_updates).
+    /// It's `0` by default.
+    #[must_use]
+    pub const fn expected_updates(mut self, q: usize) -> Self {
+        self.expected_updates = q;
+        self
+    }
+
+    /// Pads the elements with the [`identity`](MonoidNode::identity) until there are at least `len` of them, e.g. to leave room for elements set later with updates.
+    /// It has time complexity of `O(len)`.
+    #[must_use]
+    pub fn pad_to(mut self, len: usize) -> Self
+    where
+        T: MonoidNode,
+    {
+        let missing = len.saturating_sub(self.values.len());
+        if missing > 0 {
+            let values: &mut Vec<T> = self.values.to_mut();
+            values.extend((0..missing).map(|_| T::identity()));
+        }
+        self
+    }
+
+    /// Builds a [`Recursive`] segment tree, see [`Recursive::build`].
+    #[must_use]
+    pub fn recursive(self) -> Ranged<Recursive<T>, C> {
+        Ranged::new(Recursive::build(&self.values))
+    }
+
+    /// Builds a [`Recursive`] segment tree whose nodes are stored in `S` allocated with `allocator`, see [`Recursive::build_in`].
+    #[must_use]
+    pub fn recursive_in<S>(self, allocator: S::Allocator) -> Ranged<Recursive<T, S>, C>
+    where
+        S: Storage<T>,
+    {
+        Ranged::new(Recursive::build_in(&self.values, allocator))
+    }
+
+    /// Builds an [`Iterative`] segment tree, see [`Iterative::build`].
+    #[must_use]
+    pub fn iterative(self) -> Ranged<Iterative<T>, C> {
+        Ranged::new(Iterative::build(&self.values))
+    }
+
+    /// Builds an [`Iterative`] segment tree whose nodes are stored in `S` allocated with `allocator`, see [`Iterative::build_in`].
+    #[must_use]
+    pub fn iterative_in<S>(self, allocator: S::Allocator) -> Ranged<Iterative<T, S>, C>
+    where
+        S: Storage<T>,
+    {
+        Ranged::new(Iterative::build_in(&self.values, allocator))
+    }
+
+    /// Builds a [`Persistent`] segment tree whose nodes are stored in a [`GrowableArena`] following the growth policy.
+    /// The arena holds the nodes wrapped in [`PersistentWrapper`], so the type of the tree can be written out:
+    /// ```
+    /// # use seg_tree::{Persistent,PersistentWrapper,Ranged,SegTreeBuilder,interval::Inclusive,storage::GrowableArena,utils::Sum,nodes::Node};
+    /// # let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+    /// type Tree = Persistent<Sum<usize>, u32, GrowableArena<PersistentWrapper<Sum<usize>>>>;
+    /// let seg_tree: Ranged<Tree, Inclusive> = SegTreeBuilder::new(&nodes).persistent();
+    /// assert_eq!(seg_tree.query(0, 0, 9).unwrap().value(), &45);
+    /// ```
+    #[must_use]
+    pub fn persistent(self) -> Ranged<Persistent<T, u32, GrowableArena<PersistentWrapper<T>>>, C> {
+        let arena = GrowableArena::new(self.growth);
+        self.persistent_in(arena)
+    }
+
+    /// Builds a [`Persistent`] segment tree whose nodes are stored in `nodes`, see [`Persistent::build_in`], the growth policy is ignored.
+    /// It will **panic** if `nodes` isn't empty.
+    #[must_use]
+    pub fn persistent_in<I, A>(self, nodes: A) -> Ranged<Persistent<T, I, A>, C>
+    where
+        I: NodeIndex,
+        A: Arena<PersistentWrapper<T, I>>,
+    {
+        let mut tree = Persistent::build_in(&self.values, nodes);
+        tree.reserve_updates(self.expected_updates);
+        Ranged::new(tree)
+    }
+}
+
+impl<T, C> SegTreeBuilder<'_, T, C>
+where
+    T: LazyNode + Clone,
+    C: Interval,
+{
+    /// Builds a [`LazyRecursive`] segment tree, see [`LazyRecursive::build`].
+    #[must_use]
+    pub fn lazy_recursive(self) -> Ranged<LazyRecursive<T>, C> {
+        Ranged::new(LazyRecursive::build(&self.values))
+    }
+
+    /// Builds a [`LazyRecursive`] segment tree whose nodes are stored in `S` allocated with `allocator`, see [`LazyRecursive::build_in`].
+    #[must_use]
+    pub fn lazy_recursive_in<S>(self, allocator: S::Allocator) -> Ranged<LazyRecursive<T, S>, C>
+    where
+        S: Storage<T>,
+    {
+        Ranged::new(LazyRecursive::build_in(&self.values, allocator))
+    }
+
+    /// Builds a [`LazyPersistent`] segment tree whose nodes are stored in a [`GrowableArena`] following the growth policy.
+    /// The arena holds the nodes wrapped in [`PersistentWrapper`], as in [`persistent`](SegTreeBuilder::persistent).
+    #[must_use]
+    pub fn lazy_persistent(
+        self,
+    ) -> Ranged<LazyPersistent<T, u32, GrowableArena<PersistentWrapper<T>>>, C> {
+        let arena = GrowableArena::new(self.growth);
+        self.lazy_persistent_in(arena)
+    }
+
+    /// Builds a [`LazyPersistent`] segment tree whose nodes are stored in `nodes`, see [`LazyPersistent::build_in`], the growth policy is ignored.
+    /// It will **panic** if `nodes` isn't empty.
+    #[must_use]
+    pub fn lazy_persistent_in<I, A>(self, nodes: A) -> Ranged<LazyPersistent<T, I, A>, C>
+    where
+        I: NodeIndex,
+        A: Arena<PersistentWrapper<T, I>>,
+    {
+        let mut tree = LazyPersistent::build_in(&self.values, nodes);
+        tree.reserve_updates(self.expected_updates);
+        Ranged::new(tree)
+    }
+}
+
+impl<T, C> core::fmt::Debug for SegTreeBuilder<'_, T, C>
+where
+    T: Node + Clone,
+    T::Value: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SegTreeBuilder")
+            .field(
+                "values",
+                &self.values.iter().map(Node::value).collect::<Vec<_>>(),
+            )
+            .field("interval", &core::any::type_name::<C>())
+            .field("growth", &self.growth)
+            .field("expected_updates", &self.expected_updates)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        interval::HalfOpen,
+        nodes::Node,
+        storage::{ChunkedArena, Growth},
+        testing::small_values,
+        utils::{LazySetWrapper, Min, Sum},
+    };
+
+    use super::SegTreeBuilder;
+
+    #[test]
+    fn builder_builds_every_tree() {
+        let values: Vec<usize> = small_values(13);
+        let nodes: Vec<Sum<usize>> = values.iter().map(Sum::initialize).collect();
+        let recursive = SegTreeBuilder::new(&nodes).recursive();
+        let iterative = SegTreeBuilder::new(&nodes).iterative_in::<Vec<_>>(());
+        let persistent = SegTreeBuilder::new(&nodes)
+            .growth(Growth::Step(8))
+            .persistent();
+        let chunked = SegTreeBuilder::new(&nodes)
+            .expected_updates(10)
+            .persistent_in::<u32, _>(ChunkedArena::<_, 16>::new());
+        for i in 0..values.len() {
+            for j in i..values.len() {
+                let expected: usize = values[i..=j].iter().sum();
+                assert_eq!(recursive.query(i, j).unwrap().value(), &expected);
+                assert_eq!(iterative.query(i, j).unwrap().value(), &expected);
+                assert_eq!(persistent.query(0, i, j).unwrap().value(), &expected);
+                assert_eq!(chunked.query(0, i, j).unwrap().value(), &expected);
+            }
+        }
+        assert!(recursive.query(5, 4).is_none());
+    }
+
+    #[test]
+    fn builder_options_work() {
+        let nodes: Vec<LazySetWrapper<Min<usize>>> =
+            (0..10).map(|x| LazySetWrapper::initialize(&x)).collect();
+        let mut lazy = SegTreeBuilder::new(&nodes)
+            .interval::<HalfOpen>()
+            .lazy_recursive();
+        lazy.update(0, 5, &7);
+        assert_eq!(lazy.query(0, 6).unwrap().value(), &5);
+        assert_eq!(lazy.query(0, 5).unwrap().value(), &7);
+        let mut lazy = SegTreeBuilder::new(&nodes).lazy_persistent();
+        let version = lazy.update(0, 0, 5, &7);
+        assert_eq!(lazy.query(version, 0, 5).unwrap().value(), &7);
+        let nodes: Vec<Sum<usize>> = (0..3).map(|x| Sum::initialize(&x)).collect();
+        let mut padded = SegTreeBuilder::new(&nodes).pad_to(8).iterative();
+        assert_eq!(padded.len(), 8);
+        padded.update(7, &4);
+        assert_eq!(padded.query(0, 7).unwrap().value(), &7);
+        assert_eq!(SegTreeBuilder::new(&nodes).pad_to(2).recursive().len(), 3);
+    }
+}
@@ -0,0 +1,447 @@
+use alloc::{vec, vec::Vec};
+use core::ops::{Range, RangeInclusive};
+
+use crate::{
+    codec::Codec,
+    internal_utils::{
+        codec_utils::{decode_values, encode_values},
+        dbg_utils::display_values,
+        empty_utils::assert_not_empty,
+        observer_utils::Observers,
+        range_utils::clamp,
+        tracing_utils::span,
+        validate_utils::assert_matches,
+    },
+    nodes::{FlatNode, Node},
+    segment_tree::MemoryStats,
+};
+
+/// Amount of leaves which are aggregated by a plain loop before reaching the tree.
+const BLOCK_SIZE: usize = 32;
+
+/// Segment tree with range queries and point updates, specialized for [`FlatNode`]s.
+///
+/// It stores the leaves as a flat array of values split in blocks of 32 elements, and an [`Iterative`](crate::segment_tree::Iterative)-like tree over the blocks.
+/// Both build and the partial blocks of a query are plain loops over slices which the compiler can auto-vectorize, so it's usually faster than [`Iterative`](crate::segment_tree::Iterative) for primitive nodes such as [`Sum<i64>`](crate::utils::Sum) or [`Min<i64>`](crate::utils::Min).
+/// It uses `O(n)` space.
+pub struct Flat<T>
+where
+    T: FlatNode,
+{
+    values: Vec<T::Value>,
+    blocks: Vec<T::Value>,
+    n: usize,
+    observers: Observers<T>,
+}
+
+impl<T> Flat<T>
+where
+    T: FlatNode,
+{
+    /// Builds segment tree from slice, each element of the slice will correspond to a leaf of the segment tree.
+    /// It has time complexity of `O(n)`.
+    #[must_use]
+    pub fn build(values: &[T]) -> Self {
+        span!(DEBUG, "build", tree = "Flat", n = values.len());
+        let n = values.len();
+        let values: Vec<T::Value> = values.iter().map(|node| *node.value()).collect();
+        let blocks = Self::build_blocks(&values, &[], n.div_ceil(BLOCK_SIZE));
+        Self {
+            values,
+            blocks,
+            n,
+            observers: Observers::default(),
+        }
+    }
+
+    /// Builds the tree over the blocks of values with room for `m` blocks, `folded` are the results of the first full blocks, which are reused instead of folded again.
+    fn build_blocks(values: &[T::Value], folded: &[T::Value], m: usize) -> Vec<T::Value> {
+        if values.is_empty() {
+            return Vec::new();
+        }
+        // blocks[0] and the blocks past the values are never read, so any value works as filler.
+        let mut blocks = vec![values[0]; m];
+        blocks.extend_from_slice(folded);
+        blocks.extend(
+            values[folded.len() * BLOCK_SIZE..]
+                .chunks(BLOCK_SIZE)
+                .map(T::fold_values),
+        );
+        blocks.resize(2 * m, values[0]);
+        for i in (1..m).rev() {
+            blocks[i] = T::combine_values(blocks[2 * i], blocks[2 * i + 1]);
+        }
+        blocks
+    }
+
+    /// Sets the i-th element of the segment tree to value T and update the segment tree correspondingly.
+    /// It will panic if i is not in `[0,n)`
+    /// It has time complexity of `O(log(n))`.
+    pub fn update(&mut self, i: usize, value: &<T as Node>::Value) {
+        span!(TRACE, "update", tree = "Flat", n = self.n, p = i);
+        assert_not_empty(self.n, "update");
+        self.values[i] = *value;
+        let block = i / BLOCK_SIZE;
+        let start = block * BLOCK_SIZE;
+        let end = (start + BLOCK_SIZE).min(self.n);
+        let m = self.blocks.len() / 2;
+        let mut p = block + m;
+        self.blocks[p] = T::fold_values(&self.values[start..end]);
+        p >>= 1;
+        while p > 0 {
+            self.blocks[p] = T::combine_values(self.blocks[2 * p], self.blocks[2 * p + 1]);
+            p >>= 1;
+        }
+        self.observers.notify(i..=i, value);
+    }
+
+    /// Returns the result from the range `[left,right]`.
+    /// It returns None if and only if range is empty or the segment tree has no elements.
+    /// It will **panic** if left or right are not in `[0,n)`.
+    /// It has time complexity of `O(log(n))`.
+    #[must_use]
+    pub fn query(&self, left: usize, right: usize) -> Option<T> {
+        span!(TRACE, "query", tree = "Flat", n = self.n, left, right);
+        if left > right || self.n == 0 {
+            return None;
+        }
+        let (left_block, right_block) = (left / BLOCK_SIZE, right / BLOCK_SIZE);
+        if left_block == right_block {
+            return Some(T::initialize(&T::fold_values(&self.values[left..=right])));
+        }
+        let ans_left = T::fold_values(&self.values[left..(left_block + 1) * BLOCK_SIZE]);
+        let ans_right = T::fold_values(&self.values[right_block * BLOCK_SIZE..=right]);
+        let value = self.query_blocks(left_block + 1, right_block).map_or_else(
+            || T::combine_values(ans_left, ans_right),
+            |middle| T::combine_values(T::combine_values(ans_left, middle), ans_right),
+        );
+        Some(T::initialize(&value))
+    }
+
+    /// Same as [`query`](Self::query), but the range is clamped to `[0,n)` instead of **panicking** when it's out of bounds, and the range that was actually aggregated is returned with the result, as a half-open range.
+    /// It returns None if and only if the clamped range is empty.
+    /// It has time complexity of `O(log(n))`.
+    #[must_use]
+    pub fn query_with_range(&self, left: usize, right: usize) -> Option<(Range<usize>, T)> {
+        let range = clamp(left, right, self.n)?;
+        self.query(range.start, range.end - 1)
+            .map(|node| (range, node))
+    }
+
+    /// Same as [`query_with_range`](Self::query_with_range), but only the result is returned.
+    /// It returns None if and only if the range clamped to `[0,n)` is empty.
+    #[must_use]
+    pub fn query_clamped(&self, left: usize, right: usize) -> Option<T> {
+        self.query_with_range(left, right).map(|(_, node)| node)
+    }
+
+    /// Returns the result from the blocks in `[l,r)`.
+    fn query_blocks(&self, l: usize, r: usize) -> Option<T::Value> {
+        let m = self.blocks.len() / 2;
+        let (mut l, mut r) = (l + m, r + m);
+        let mut ans_left: Option<T::Value> = None;
+        let mut ans_right: Option<T::Value> = None;
+        while l < r {
+            if l & 1 != 0 {
+                ans_left = Some(ans_left.map_or(self.blocks[l], |value| {
+                    T::combine_values(value, self.blocks[l])
+                }));
+                l += 1;
+            }
+            if r & 1 != 0 {
+                r -= 1;
+                ans_right = Some(ans_right.map_or(self.blocks[r], |value| {
+                    T::combine_values(self.blocks[r], value)
+                }));
+            }
+            l >>= 1;
+            r >>= 1;
+        }
+        match (ans_left, ans_right) {
+            (Some(ans_left), Some(ans_right)) => Some(T::combine_values(ans_left, ans_right)),
+            (ans, None) | (None, ans) => ans,
+        }
+    }
+
+    /// Subscribes callback to the updates of the segment tree, it's called after every update with the updated range and the value given to the update, so external indexes or caches can be kept in sync with the tree.
+    /// Callbacks are called in subscription order.
+    pub fn on_update<F>(&mut self, callback: F)
+    where
+        F: FnMut(RangeInclusive<usize>, &<T as Node>::Value) + Send + Sync + 'static,
+    {
+        self.observers.push(callback);
+    }
+
+    /// Unsubscribes every callback subscribed with [`on_update`](Self::on_update).
+    pub fn clear_observers(&mut self) {
+        self.observers.clear();
+    }
+
+    /// Consumes the segment tree, returning its values in order.
+    /// It has time complexity of `O(1)`.
+    #[must_use]
+    pub fn into_values(self) -> Vec<T::Value> {
+        self.values
+    }
+
+    /// Returns the amount of elements of the segment tree.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.n
+    }
+
+    /// Returns `true` if the segment tree has no elements.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Returns the memory used by the segment tree, see [`MemoryStats`].
+    #[must_use]
+    pub const fn memory_stats(&self) -> MemoryStats {
+        MemoryStats {
+            nodes: self.values.len() + self.blocks.len(),
+            capacity: self.values.capacity() + self.blocks.capacity(),
+            node_size: core::mem::size_of::<T::Value>(),
+            versions: 1,
+            overhead_bytes: 0,
+        }
+    }
+
+    /// Checks that every block is the combination of its values and every internal node of the tree over the blocks is the combination of its children, so a node whose [`combine_values`](FlatNode::combine_values) isn't deterministic, or a [`fold_values`](FlatNode::fold_values) which doesn't match it, are caught where they happen instead of as wrong answers later on.
+    /// It's meant for tests and debug assertions.
+    /// It will **panic** at the first block or node which doesn't match.
+    /// It has time complexity of `O(n)`.
+    pub fn validate(&self)
+    where
+        T::Value: PartialEq,
+    {
+        let m = self.blocks.len() / 2;
+        for (block, values) in self.values.chunks(BLOCK_SIZE).enumerate() {
+            let expected = values[1..]
+                .iter()
+                .fold(values[0], |acc, &value| T::combine_values(acc, value));
+            assert_matches(
+                self.blocks[block + m] == expected,
+                format_args!("block {block}"),
+                "its values",
+            );
+        }
+        for i in (1..m).rev() {
+            assert_matches(
+                self.blocks[i] == T::combine_values(self.blocks[2 * i], self.blocks[2 * i + 1]),
+                format_args!("node of index {i}"),
+                "its children",
+            );
+        }
+    }
+}
+
+impl<T> Extend<T::Value> for Flat<T>
+where
+    T: FlatNode,
+{
+    /// Appends the values as new leaves at the end of the segment tree.
+    /// Only the last partial block and the new blocks are folded, and only their ancestors in the tree over the blocks are combined. Like a [`Vec`], if the blocks don't fit in its capacity the tree over the blocks is rebuilt, reusing the full blocks, with room for at least double the blocks.
+    /// It has amortized time complexity of `O(k+log(n))`, where `k` is the amount of new values.
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = T::Value>,
+    {
+        span!(DEBUG, "extend", tree = "Flat", n = self.n);
+        let full_blocks = self.n / BLOCK_SIZE;
+        self.values.extend(iter);
+        if self.values.len() == self.n {
+            return;
+        }
+        self.n = self.values.len();
+        let (m, new_blocks) = (self.blocks.len() / 2, self.n.div_ceil(BLOCK_SIZE));
+        if new_blocks > m {
+            let folded = &self.blocks[m..m + full_blocks];
+            self.blocks = Self::build_blocks(&self.values, folded, new_blocks.max(2 * m));
+            return;
+        }
+        for (block, values) in self.values.chunks(BLOCK_SIZE).enumerate().skip(full_blocks) {
+            self.blocks[block + m] = T::fold_values(values);
+        }
+        let (mut l, mut r) = ((full_blocks + m) >> 1, (new_blocks - 1 + m) >> 1);
+        while l > 0 {
+            for i in l..=r {
+                self.blocks[i] = T::combine_values(self.blocks[2 * i], self.blocks[2 * i + 1]);
+            }
+            l >>= 1;
+            r >>= 1;
+        }
+    }
+}
+
+impl<T> IntoIterator for Flat<T>
+where
+    T: FlatNode,
+{
+    type Item = T::Value;
+    type IntoIter = vec::IntoIter<T::Value>;
+
+    /// Same as [`into_values`](Flat::into_values).
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_values().into_iter()
+    }
+}
+
+impl<T> Flat<T>
+where
+    T: FlatNode,
+    T::Value: Codec,
+{
+    /// Appends the encoding of the values of the segment tree to bytes, see [`Codec`].
+    /// It has time complexity of `O(n)`, assuming that [`encode`](Codec::encode) has constant time complexity.
+    pub fn encode_into(&self, bytes: &mut Vec<u8>) {
+        encode_values(&self.values, bytes);
+    }
+
+    /// Decodes a segment tree encoded by [`encode_into`](Flat::encode_into) from the start of bytes, and advances bytes past it.
+    /// It returns None if and only if bytes don't start with a valid encoding.
+    /// It has time complexity of `O(n)`, assuming that [`decode`](Codec::decode) and [`combine`](Node::combine) have constant time complexity.
+    pub fn decode_from(bytes: &mut &[u8]) -> Option<Self> {
+        let values: Vec<T::Value> = decode_values(bytes)?;
+        let nodes: Vec<T> = values.iter().map(T::initialize).collect();
+        Some(Self::build(&nodes))
+    }
+}
+
+/// Two segment trees are equal if they have the same values, in the same order.
+impl<T> PartialEq for Flat<T>
+where
+    T: FlatNode<Value: PartialEq>,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.values == other.values
+    }
+}
+
+/// Writes the values of the segment tree like a slice, e.g. `[1, 2, 3]`, a precision limits how many values are written, e.g. `{:.2}` writes `[1, 2, ...]`.
+impl<T> core::fmt::Display for Flat<T>
+where
+    T: FlatNode,
+    T::Value: core::fmt::Display,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        display_values(f, &self.values)
+    }
+}
+
+impl<T> core::fmt::Debug for Flat<T>
+where
+    T: FlatNode,
+    T::Value: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Flat")
+            .field("n", &self.n)
+            .field("values", &self.values)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        nodes::Node,
+        utils::{Max, Min, Sum},
+    };
+
+    use super::Flat;
+
+    const N: i64 = 100;
+
+    #[test]
+    fn non_empty_query_returns_some() {
+        let nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let segment_tree = Flat::build(&nodes);
+        assert!(segment_tree.query(0, 10).is_some());
+    }
+    #[test]
+    fn empty_query_returns_none() {
+        let nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let segment_tree = Flat::build(&nodes);
+        assert!(segment_tree.query(10, 0).is_none());
+    }
+    #[test]
+    fn update_works() {
+        let nodes: Vec<Sum<i64>> = (0..N).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = Flat::build(&nodes);
+        segment_tree.update(40, &-20);
+        let expected: i64 = (0..N).sum::<i64>() - 60;
+        assert_eq!(
+            segment_tree.query(0, nodes.len() - 1).unwrap().value(),
+            &expected
+        );
+        assert_eq!(segment_tree.query(40, 40).unwrap().value(), &-20);
+    }
+    #[test]
+    fn query_works() {
+        let values: Vec<i64> = (0..N).map(|x| (x * 37) % 101 - 50).collect();
+        let sums: Vec<Sum<i64>> = values.iter().map(Sum::initialize).collect();
+        let mins: Vec<Min<i64>> = values.iter().map(Min::initialize).collect();
+        let maxs: Vec<Max<i64>> = values.iter().map(Max::initialize).collect();
+        let (sums, mins, maxs) = (Flat::build(&sums), Flat::build(&mins), Flat::build(&maxs));
+        for i in 0..values.len() {
+            for j in i..values.len() {
+                let range = &values[i..=j];
+                assert_eq!(sums.query(i, j).unwrap().value(), &range.iter().sum::<i64>());
+                assert_eq!(
+                    mins.query(i, j).unwrap().value(),
+                    range.iter().min().unwrap()
+                );
+                assert_eq!(
+                    maxs.query(i, j).unwrap().value(),
+                    range.iter().max().unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn validate_works() {
+        let nodes: Vec<Sum<i64>> = (0..N).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = Flat::build(&nodes);
+        segment_tree.validate();
+        segment_tree.update(40, &-20);
+        segment_tree.update(99, &7);
+        segment_tree.validate();
+    }
+
+    #[test]
+    fn empty_tree_works() {
+        let segment_tree = Flat::<Sum<i64>>::build(&[]);
+        assert!(segment_tree.is_empty());
+        assert!(segment_tree.query(0, 0).is_none());
+        segment_tree.validate();
+    }
+
+    #[test]
+    fn into_values_works() {
+        let nodes: Vec<Sum<i64>> = (0..N).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = Flat::build(&nodes);
+        segment_tree.update(50, &0);
+        let values: Vec<i64> = segment_tree.into_iter().collect();
+        assert_eq!(values, (0..N).map(|x| if x == 50 { 0 } else { x }).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn extend_works() {
+        let values: Vec<i64> = (0..3 * N).map(|x| (x * 37) % 101).collect();
+        let mut segment_tree = Flat::<Min<i64>>::build(&[]);
+        let mut len = 0;
+        for batch in [1, 31, 0, 40, 100, 128] {
+            segment_tree.extend(values[len..len + batch].iter().copied());
+            len += batch;
+            assert_eq!(segment_tree.len(), len);
+            segment_tree.validate();
+            for i in 0..len {
+                let expected = values[i..len].iter().min().unwrap();
+                assert_eq!(segment_tree.query(i, len - 1).unwrap().value(), expected);
+            }
+        }
+    }
+}
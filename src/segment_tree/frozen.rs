@@ -0,0 +1,263 @@
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::{
+    algorithms::{BlockSparseTable, StaticRmq},
+    internal_utils::{
+        dbg_utils::display_values, instrument_utils::combine, partial_utils::Partial,
+        tracing_utils::span,
+    },
+    nodes::{IdempotentNode, LazyNode, Node, SelectionNode},
+    segment_tree::{Iterative, LazyRecursive, Recursive},
+};
+
+/// Read only segment tree, created by consuming a segment tree once it won't be updated anymore, e.g. with [`Recursive::freeze`].
+///
+/// The nodes are stored bottom-up in a single allocation of exactly `2n-1` nodes, without the callbacks, storage or lazy values of the original tree, so it's the smallest layout which still answers any query in `O(log(n))`.
+/// For selection nodes it can be turned into a [`StaticRmq`] with [`into_rmq`](Frozen::into_rmq), and for any idempotent node into a [`BlockSparseTable`] with [`into_sparse_table`](Frozen::into_sparse_table), which answer queries in `O(1)`.
+/// ```
+/// # use seg_tree::{Recursive,utils::Sum,nodes::Node};
+/// # let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+/// let mut seg_tree = Recursive::build(&nodes); // [0,1,2,3,4,5,6,7,8,9] with Sum<usize> nodes
+/// seg_tree.update(3, &10);
+/// let frozen = seg_tree.freeze();
+/// assert_eq!(frozen.query(0, 9).unwrap().value(), &52);
+/// assert_eq!(frozen.query(2, 4).unwrap().value(), &16);
+/// ```
+pub struct Frozen<T> {
+    // Node `k` of the bottom-up layout is stored at `k-1`, so the leaves are the last `n` nodes.
+    nodes: Box<[T]>,
+    n: usize,
+}
+
+impl<T> Frozen<T>
+where
+    T: Node + Clone,
+{
+    /// Builds the tree from its leaves, in order.
+    fn from_leaves(leaves: Vec<T>) -> Self {
+        let n = leaves.len();
+        span!(DEBUG, "build", tree = "Frozen", n);
+        let mut nodes = Vec::with_capacity((2 * n).saturating_sub(1));
+        // The internal nodes are combined from the end, so they are pushed in reverse and the nodes are reversed at the end.
+        nodes.extend(leaves.into_iter().rev());
+        for k in (1..n).rev() {
+            let (left, right) = (2 * k, 2 * k + 1);
+            let node = combine(&nodes[2 * n - left - 1], &nodes[2 * n - right - 1]);
+            nodes.push(node);
+        }
+        nodes.reverse();
+        Self {
+            nodes: nodes.into_boxed_slice(),
+            n,
+        }
+    }
+
+    fn node(&self, k: usize) -> &T {
+        &self.nodes[k - 1]
+    }
+
+    /// Returns the result from the range `[left,right]`.
+    /// It returns None if and only if range is empty or the tree has no elements.
+    /// It will **panic** if left or right are not in `[0,n)`.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn query(&self, left: usize, right: usize) -> Option<T> {
+        span!(TRACE, "query", tree = "Frozen", n = self.n, left, right);
+        if self.n == 0 {
+            return None;
+        }
+        let mut l = left + self.n;
+        let mut r = right + self.n + 1;
+        let mut ans_left = Partial::Empty;
+        let mut ans_right = Partial::Empty;
+        while l < r {
+            if l & 1 != 0 {
+                ans_left = ans_left.push_back(self.node(l));
+                l += 1;
+            }
+            if r & 1 != 0 {
+                r -= 1;
+                ans_right = ans_right.push_front(self.node(r));
+            }
+            l >>= 1;
+            r >>= 1;
+        }
+        match (ans_left.as_ref(), ans_right.as_ref()) {
+            (Some(ans_left), Some(ans_right)) => Some(combine(ans_left, ans_right)),
+            _ => ans_left.into_owned().or_else(|| ans_right.into_owned()),
+        }
+    }
+}
+
+impl<T> Frozen<T> {
+    /// Returns the leaves, in order.
+    #[must_use]
+    pub fn leaves(&self) -> &[T] {
+        &self.nodes[self.nodes.len() - self.n..]
+    }
+
+    /// Returns the amount of elements.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.n
+    }
+
+    /// Returns `true` if there are no elements.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+}
+
+impl<T> Frozen<T>
+where
+    T: IdempotentNode + Clone,
+{
+    /// Consumes the tree, returning a [`BlockSparseTable`] with the same leaves, which answers queries in `O(1)`.
+    /// It has time complexity of `O(n+(n/32)*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn into_sparse_table(self) -> BlockSparseTable<T> {
+        BlockSparseTable::build(self.leaves())
+    }
+}
+
+impl<T> Frozen<T>
+where
+    T: SelectionNode + Clone,
+{
+    /// Consumes the tree, returning a [`StaticRmq`] with the same leaves, which answers queries in `O(1)`.
+    /// It has time complexity of `O(n)`, assuming that [`combine`](Node::combine) and [`selects_left`](SelectionNode::selects_left) have constant time complexity.
+    #[must_use]
+    pub fn into_rmq(self) -> StaticRmq<T> {
+        StaticRmq::build(self.leaves())
+    }
+}
+
+impl<T> Recursive<T>
+where
+    T: Node + Clone,
+{
+    /// Consumes the segment tree, returning a read only [`Frozen`] tree with the same leaves.
+    /// It has time complexity of `O(n)`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn freeze(self) -> Frozen<T> {
+        Frozen::from_leaves(self.into_leaves())
+    }
+}
+
+impl<T> Iterative<T>
+where
+    T: Node + Clone,
+{
+    /// Consumes the segment tree, returning a read only [`Frozen`] tree with the same leaves.
+    /// It has time complexity of `O(n)`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn freeze(self) -> Frozen<T> {
+        Frozen::from_leaves(self.into_leaves().collect())
+    }
+}
+
+impl<T> LazyRecursive<T>
+where
+    T: LazyNode + Clone,
+{
+    /// Consumes the segment tree, returning a read only [`Frozen`] tree with the same leaves, every pending lazy value is applied first.
+    /// It has time complexity of `O(n)`, assuming that [`combine`](Node::combine), [`update_lazy_value`](LazyNode::update_lazy_value) and [`lazy_update`](LazyNode::lazy_update) have constant time complexity.
+    #[must_use]
+    pub fn freeze(self) -> Frozen<T> {
+        Frozen::from_leaves(self.into_leaves())
+    }
+}
+
+impl<T> core::fmt::Debug for Frozen<T>
+where
+    T: Node,
+    T::Value: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list()
+            .entries(self.leaves().iter().map(Node::value))
+            .finish()
+    }
+}
+
+/// Writes the values of the tree like a slice, e.g. `[1, 2, 3]`, a precision limits how many values are written, e.g. `{:.2}` writes `[1, 2, ...]`.
+impl<T> core::fmt::Display for Frozen<T>
+where
+    T: Node,
+    T::Value: core::fmt::Display,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        display_values(f, self.leaves().iter().map(Node::value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        nodes::Node,
+        segment_tree::{Iterative, LazyRecursive, Recursive},
+        testing::small_values,
+        utils::{LazySetWrapper, Max, Min, Sum},
+    };
+
+    #[test]
+    fn freeze_works() {
+        let values: Vec<usize> = small_values(13);
+        let nodes: Vec<Sum<usize>> = values.iter().map(Sum::initialize).collect();
+        let frozen = [
+            Recursive::build(&nodes).freeze(),
+            Iterative::build(&nodes).freeze(),
+        ];
+        for frozen in frozen {
+            assert_eq!(frozen.len(), values.len());
+            for i in 0..values.len() {
+                for j in i..values.len() {
+                    let expected: usize = values[i..=j].iter().sum();
+                    assert_eq!(frozen.query(i, j).unwrap().value(), &expected);
+                }
+            }
+            assert!(frozen.query(5, 4).is_none());
+        }
+    }
+
+    #[test]
+    fn freeze_applies_pending_values() {
+        let nodes: Vec<LazySetWrapper<Sum<usize>>> =
+            (0..10).map(|x| LazySetWrapper::initialize(&x)).collect();
+        let mut segment_tree = LazyRecursive::build(&nodes);
+        segment_tree.update(2, 6, &1);
+        let frozen = segment_tree.freeze();
+        assert_eq!(format!("{frozen}"), "[0, 1, 1, 1, 1, 1, 1, 7, 8, 9]");
+        assert_eq!(frozen.query(0, 9).unwrap().value(), &30);
+        assert_eq!(frozen.query(3, 5).unwrap().value(), &3);
+    }
+
+    #[test]
+    fn into_rmq_works() {
+        let nodes: Vec<Min<usize>> = (0..100).map(|x| Min::initialize(&((x * 7) % 11))).collect();
+        let frozen = Recursive::build(&nodes).freeze();
+        let rmq = frozen.into_rmq();
+        assert_eq!(rmq.len(), 100);
+        assert_eq!(rmq.query(1, 3).unwrap().value(), &3);
+        assert_eq!(rmq.query(0, 99).unwrap().value(), &0);
+    }
+
+    #[test]
+    fn into_sparse_table_works() {
+        let nodes: Vec<Max<usize>> = (0..100).map(|x| Max::initialize(&((x * 7) % 11))).collect();
+        let frozen = Recursive::build(&nodes).freeze();
+        let table = frozen.into_sparse_table();
+        assert_eq!(table.len(), 100);
+        assert_eq!(table.query(1, 2).unwrap().value(), &7);
+        assert_eq!(table.query(0, 99).unwrap().value(), &10);
+    }
+
+    #[test]
+    fn empty_frozen_works() {
+        let frozen = Recursive::<Sum<usize>>::build(&[]).freeze();
+        assert!(frozen.is_empty());
+        assert!(frozen.leaves().is_empty());
+        assert_eq!(format!("{frozen:?}"), "[]");
+    }
+}
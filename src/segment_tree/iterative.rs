@@ -1,15 +1,56 @@
-use core::mem::MaybeUninit;
+use alloc::{vec, vec::Vec};
+use core::{
+    marker::PhantomData,
+    ops::{Range, RangeInclusive},
+};
 
-use crate::{internal_utils::dbg_utils::as_dbg_tree, nodes::Node};
+use crate::{
+    codec::Codec,
+    internal_utils::{
+        codec_utils::{decode_values, encode_values},
+        dbg_utils::{as_dbg_tree, display_values},
+        empty_utils::{assert_not_empty, assert_same_len},
+        instrument_utils::{combine, record_allocations, LastOp},
+        observer_utils::Observers,
+        partial_utils::Partial,
+        range_utils::{assert_in_bounds, assert_split_index, clamp},
+        tracing_utils::span,
+        validate_utils::assert_combines,
+    },
+    nodes::Node,
+    segment_tree::{MemoryStats, Recursive},
+    storage::{Inline, Storage},
+    utils::{compress_pairs, Compressor},
+};
 
 /// Segment tree with range queries and point updates.
+///
 /// It uses `O(n)` space, assuming that each node uses `O(1)` space.
 /// Note if you need to use `lower_bound`, just use [`Recursive`](crate::segment_tree::Recursive) it uses double the memory though and it's less performant.
-pub struct Iterative<T> {
-    nodes: Vec<T>,
+/// The nodes are stored in `S`, see [`Storage`].
+pub struct Iterative<T, S = Vec<T>> {
+    nodes: S,
     n: usize,
+    // The leaves are stored in `[offset,offset+n)`, the ones in `[offset+n,2*offset)` are left over from resizes and never read.
+    offset: usize,
+    linear_threshold: usize,
+    observers: Observers<T>,
+    _node: PhantomData<T>,
+    last_op: LastOp,
 }
 
+/// [`Iterative`] segment tree with room for up to `N` elements, whose nodes are stored inside the tree itself, so it never allocates.
+///
+/// It's built with [`build_in`](Iterative::build_in), and it will **panic** if it's built with more than `N` elements.
+/// ```
+/// # use seg_tree::{StaticTree,utils::Sum,nodes::Node};
+/// let nodes = [1, 2, 3, 4].map(|x| Sum::initialize(&x));
+/// let mut seg_tree: StaticTree<_, 8> = StaticTree::build_in(&nodes, ());
+/// seg_tree.update(0, &5);
+/// assert_eq!(seg_tree.query(0, 3).unwrap().value(), &14);
+/// ```
+pub type StaticTree<T, const N: usize> = Iterative<T, Inline<T, N>>;
+
 impl<T> Iterative<T>
 where
     T: Node + Clone,
@@ -17,105 +58,644 @@ where
     /// Builds segment tree from slice, each element of the slice will correspond to a leaf of the segment tree.
     /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
     pub fn build(values: &[T]) -> Self {
+        Self::build_in(values, ())
+    }
+
+    /// Builds segment tree with n elements, each of them equal to value.
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn filled(n: usize, value: &<T as Node>::Value) -> Self {
+        Self::build(&vec![T::initialize(value); n])
+    }
+
+    /// Same as [`filled`](Iterative::filled), but every element is the [`Default`] value, e.g. `0` for [`Sum`](crate::utils::Sum) nodes.
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn with_len(n: usize) -> Self
+    where
+        <T as Node>::Value: Default,
+    {
+        Self::filled(n, &<T as Node>::Value::default())
+    }
+
+    /// Builds segment tree with n elements, the i-th of them equal to `f(i)`, without collecting the values first.
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) and `f` have constant time complexity.
+    #[must_use]
+    pub fn build_from_fn<F>(n: usize, mut f: F) -> Self
+    where
+        F: FnMut(usize) -> <T as Node>::Value,
+    {
+        Self::build_from((0..n).map(|i| T::initialize(&f(i))), ())
+    }
+
+    /// Consumes the segment tree, returning the values of its leaves in order, see [`into_value`](Node::into_value).
+    /// It's only available for the default storage, as the nodes are moved out of it.
+    /// It has time complexity of `O(n)`.
+    #[must_use]
+    pub fn into_values(self) -> Vec<T::Value> {
+        self.into_leaves().map(Node::into_value).collect()
+    }
+
+    /// Consumes the segment tree, returning its leaves in order.
+    pub(crate) fn into_leaves(self) -> impl ExactSizeIterator<Item = T> {
+        let (n, offset) = (self.n, self.offset);
+        self.nodes.into_iter().skip(offset).take(n)
+    }
+
+    /// Splits the segment tree in two at index `at`, it keeps the elements in `[0,at)` and returns a segment tree with the elements in `[at,n)`.
+    /// The leaves are moved, but both segment trees are rebuilt from them, so every internal node is combined again, the callbacks subscribed with [`on_update`](Self::on_update) stay in this segment tree.
+    /// It will **panic** if `at` is not in `[0,n]`.
+    /// It has time complexity of `O(n)`, the same as building both segment trees, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn split_off(&mut self, at: usize) -> Self {
+        assert_split_index(at, self.n);
+        let mut leaves = self.take_leaves();
+        let right = leaves.split_off(at);
+        self.set_leaves(leaves);
+        Self::build_from(right.into_iter(), ())
+    }
+
+    /// Appends the elements of `other` at the end of the segment tree.
+    /// The leaves are moved, but the segment tree is rebuilt from them, so every internal node of both segment trees is combined again, the callbacks subscribed to `other` are dropped.
+    /// It has time complexity of `O(n+m)`, the same as building the appended segment tree, where `m` is the amount of elements of `other`, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn append(&mut self, other: Self) {
+        let mut leaves = self.take_leaves();
+        leaves.extend(other.into_leaves());
+        self.set_leaves(leaves);
+    }
+
+    /// Resizes the segment tree to `new_len` elements, keeping the first ones, like [`Vec::resize`].
+    /// If it grows, the new elements are equal to value and only they and their ancestors are combined, and if it shrinks, the last elements are left out without touching any node, their memory is reused if it grows again.
+    /// Like a [`Vec`], if it grows past its capacity every node is combined again with room for at least double the elements, the callbacks subscribed with [`on_update`](Self::on_update) stay in this segment tree.
+    /// It has amortized time complexity of `O(k+log(n))`, where `k` is the amount of elements added, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn resize(&mut self, new_len: usize, value: &<T as Node>::Value) {
+        if new_len <= self.n {
+            self.n = new_len;
+            return;
+        }
+        self.push_leaves(vec![T::initialize(value); new_len - self.n]);
+    }
+
+    /// Keeps only the elements whose value satisfies the predicate f, in the same order, like [`Vec::retain`], so the i-th element afterwards is the i-th one kept.
+    /// The kept leaves are moved and the rest of the nodes are combined again, the callbacks subscribed with [`on_update`](Self::on_update) stay in this segment tree.
+    /// It has time complexity of `O(n)`, assuming that [`combine`](Node::combine) and f have constant time complexity.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&<T as Node>::Value) -> bool,
+    {
+        let mut leaves = self.take_leaves();
+        leaves.retain(|leaf| f(leaf.value()));
+        self.set_leaves(leaves);
+    }
+
+    /// Returns a segment tree whose i-th element is the [`combine`](Node::combine) of the i-th elements of both segment trees, e.g. to add up partial results computed over different data.
+    /// The rest of the nodes are combined again from the new elements, so it works for any node, and the callbacks subscribed with [`on_update`](Self::on_update) aren't copied.
+    /// It will **panic** if the segment trees don't have the same amount of elements.
+    /// It has time complexity of `O(n)`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn merge_with(&self, other: &Self) -> Self {
+        assert_same_len(self.n, other.n, "merge");
+        let leaves = self.leaves().iter().zip(other.leaves());
+        Self::build_from(leaves.map(|(a, b)| combine(a, b)), ())
+    }
+
+    /// Builds a segment tree over the distinct keys of pairs, in increasing order, returning it with the [`Compressor`] which maps the keys to their leaves.
+    /// The leaf of each key is the combination of the nodes with that key, in the order they appear in pairs.
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn build_compressed<K>(pairs: &[(K, T)]) -> (Compressor<K>, Self)
+    where
+        K: Ord + Clone,
+    {
+        let (compressor, leaves) = compress_pairs(pairs);
+        (compressor, Self::build_from(leaves.into_iter(), ()))
+    }
+
+    /// Appends leaves at the end of the segment tree, combining only them and their ancestors if they fit in its capacity, see [`resize`](Self::resize).
+    fn push_leaves(&mut self, leaves: Vec<T>) {
+        let (old_len, new_len) = (self.n, self.n + leaves.len());
+        if old_len == new_len {
+            return;
+        }
+        if new_len > self.offset {
+            let capacity = new_len.max(2 * self.offset);
+            let mut all_leaves = self.take_leaves();
+            all_leaves.extend(leaves);
+            let filler = all_leaves[new_len - 1].clone();
+            all_leaves.resize(capacity, filler);
+            self.set_leaves(all_leaves);
+            self.n = new_len;
+            return;
+        }
+        for (i, leaf) in leaves.into_iter().enumerate() {
+            self.nodes[old_len + i + self.offset] = leaf;
+        }
+        self.n = new_len;
+        self.recombine(old_len, new_len - 1);
+    }
+
+    /// Moves the leaves out of the segment tree, leaving it empty.
+    fn take_leaves(&mut self) -> Vec<T> {
+        let n = core::mem::take(&mut self.n);
+        let offset = core::mem::take(&mut self.offset);
+        core::mem::take(&mut self.nodes)
+            .into_iter()
+            .skip(offset)
+            .take(n)
+            .collect()
+    }
+
+    /// Rebuilds the segment tree from leaves, keeping its callbacks.
+    fn set_leaves(&mut self, leaves: Vec<T>) {
+        let segment_tree = Self::build_from(leaves.into_iter(), ());
+        self.nodes = segment_tree.nodes;
+        self.n = segment_tree.n;
+        self.offset = segment_tree.offset;
+    }
+}
+
+impl<T, S> Iterative<T, S>
+where
+    T: Node + Clone,
+    S: Storage<T>,
+{
+    /// Same as [`build`](Iterative::build), but the nodes are stored in `S` allocated with `allocator`, see [`Storage`].
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn build_in(values: &[T], allocator: S::Allocator) -> Self {
+        Self::build_from(values.iter().cloned(), allocator)
+    }
+
+    /// Same as [`build_in`](Iterative::build_in), but the leaves are moved out of values instead of cloned.
+    pub(crate) fn build_from<I>(values: I, allocator: S::Allocator) -> Self
+    where
+        I: ExactSizeIterator<Item = T>,
+    {
+        span!(DEBUG, "build", tree = "Iterative", n = values.len());
+        let last_op = LastOp::default();
+        let _op = last_op.measure();
         let n = values.len();
-        let mut nodes: Vec<MaybeUninit<T>> = Vec::with_capacity(2 * n);
-        unsafe { nodes.set_len(2 * n) };
-        for i in 0..n {
-            nodes[i + n].write(values[i].clone());
+        let mut nodes = S::allocate(2 * n, allocator);
+        record_allocations(2 * n);
+        for (i, value) in values.enumerate() {
+            nodes[i + n].write(value);
         }
         for i in (1..n).rev() {
             let (bottom_nodes, top_nodes) = nodes.split_at_mut(i + 1);
-            bottom_nodes[i].write(Node::combine(
+            bottom_nodes[i].write(combine(
                 unsafe { top_nodes[i - 1].assume_init_ref() },
                 unsafe { top_nodes[i].assume_init_ref() },
             ));
         }
-        let ptr = nodes.as_mut_ptr();
-        core::mem::forget(nodes);
-        let nodes = unsafe { Vec::from_raw_parts(ptr.cast(), 2 * n, 2 * n) };
-        Self { nodes, n }
+        if n > 0 {
+            // The node at 0 is never used, but it's initialized so every node can be safely dropped.
+            let root = unsafe { nodes[1].assume_init_ref() }.clone();
+            nodes[0].write(root);
+        }
+        let nodes = unsafe { S::assume_init(nodes) };
+        Self {
+            nodes,
+            n,
+            offset: n,
+            linear_threshold: 0,
+            observers: Observers::default(),
+            _node: PhantomData,
+            last_op,
+        }
+    }
+
+    /// Sets the amount of elements up to which [`query`](Self::query) combines the leaves of the range one by one instead of traversing the tree, which is faster for small trees as the leaves are contiguous in memory.
+    /// The results are the same either way, and updates keep every node up to date regardless, so every other method is unaffected. It's `0` by default, which always traverses the tree, and around `32` is a good choice for nodes with cheap [`combine`](Node::combine).
+    #[must_use]
+    pub const fn with_linear_threshold(mut self, threshold: usize) -> Self {
+        self.linear_threshold = threshold;
+        self
+    }
+
+    /// Returns the amount of elements up to which queries scan the leaves, see [`with_linear_threshold`](Self::with_linear_threshold).
+    #[must_use]
+    pub const fn linear_threshold(&self) -> usize {
+        self.linear_threshold
     }
 
     /// Sets the i-th element of the segment tree to value T and update the segment tree correspondingly.
     /// It will panic if i is not in `[0,n)`
     /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
     pub fn update(&mut self, i: usize, value: &<T as Node>::Value) {
-        let mut i = i;
-        i += self.n;
-        self.nodes[i] = Node::initialize(value);
-        i >>= 1;
-        while i > 0 {
-            self.nodes[i] = Node::combine(&self.nodes[2 * i], &self.nodes[2 * i + 1]);
-            i >>= 1;
+        span!(TRACE, "update", tree = "Iterative", n = self.n, p = i);
+        let _op = self.last_op.measure();
+        assert_not_empty(self.n, "update");
+        self.set_node(i, Node::initialize(value));
+        self.observers.notify(i..=i, value);
+    }
+
+    /// Sets the i-th leaf to node and updates the segment tree correspondingly, without notifying the observers.
+    pub(crate) fn set_node(&mut self, i: usize, node: T) {
+        assert_in_bounds(i, self.n);
+        self.nodes[i + self.offset] = node;
+        self.recombine(i, i);
+    }
+
+    /// Combines again the ancestors of the leaves in `[l,r]`, level by level.
+    fn recombine(&mut self, l: usize, r: usize) {
+        let (mut l, mut r) = ((l + self.offset) >> 1, (r + self.offset) >> 1);
+        while l > 0 {
+            for i in l..=r {
+                self.nodes[i] = combine(&self.nodes[2 * i], &self.nodes[2 * i + 1]);
+            }
+            l >>= 1;
+            r >>= 1;
         }
     }
 
     /// Returns the result from the range `[left,right]`.
-    /// It returns None if and only if range is empty.
+    /// It returns None if and only if range is empty or the segment tree has no elements.
     /// It will **panic** if left or right are not in `[0,n)`.
     /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
     #[allow(clippy::must_use_candidate)]
     pub fn query(&self, l: usize, r: usize) -> Option<T> {
+        span!(TRACE, "query", tree = "Iterative", n = self.n, left = l, right = r);
+        let _op = self.last_op.measure();
+        if self.n == 0 {
+            return None;
+        }
+        if l <= r {
+            assert_in_bounds(r, self.n);
+        }
+        if self.n <= self.linear_threshold {
+            return self.linear_query(l, r);
+        }
         let (mut l, mut r) = (l, r);
-        let mut ans_left = None;
-        let mut ans_right = None;
-        l += self.n;
-        r += self.n + 1;
+        let mut ans_left = Partial::Empty;
+        let mut ans_right = Partial::Empty;
+        l += self.offset;
+        r += self.offset + 1;
+        while l < r {
+            if l & 1 != 0 {
+                ans_left = ans_left.push_back(&self.nodes[l]);
+                l += 1;
+            }
+            if r & 1 != 0 {
+                r -= 1;
+                ans_right = ans_right.push_front(&self.nodes[r]);
+            }
+            l >>= 1;
+            r >>= 1;
+        }
+        match (ans_left.as_ref(), ans_right.as_ref()) {
+            (Some(ans_left), Some(ans_right)) => Some(combine(ans_left, ans_right)),
+            _ => ans_left.into_owned().or_else(|| ans_right.into_owned()),
+        }
+    }
+
+    /// Combines the leaves of the range `[l,r]` from left to right.
+    fn linear_query(&self, l: usize, r: usize) -> Option<T> {
+        if l > r {
+            return None;
+        }
+        let (first, rest) = self.leaves()[l..=r].split_first()?;
+        Some(rest.iter().fold(first.clone(), |ans, node| combine(&ans, node)))
+    }
+
+    /// Same as [`query`](Self::query), but the range is clamped to `[0,n)` instead of **panicking** when it's out of bounds, and the range that was actually aggregated is returned with the result, as a half-open range.
+    /// It returns None if and only if the clamped range is empty.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn query_with_range(&self, left: usize, right: usize) -> Option<(Range<usize>, T)> {
+        let range = clamp(left, right, self.n)?;
+        self.query(range.start, range.end - 1)
+            .map(|node| (range, node))
+    }
+
+    /// Same as [`query_with_range`](Self::query_with_range), but only the result is returned.
+    /// It returns None if and only if the range clamped to `[0,n)` is empty.
+    #[must_use]
+    pub fn query_clamped(&self, left: usize, right: usize) -> Option<T> {
+        self.query_with_range(left, right).map(|(_, node)| node)
+    }
+
+    /// Same as [`query`](Self::query), but the nodes which cover the range are combined from left to right and it returns as soon as `stop` is `true` for the result so far, e.g. once a sum exceeds some value.
+    /// The result is the one of a prefix `[left,k]` of the range, which is the whole range if `stop` is never `true`, so `stop` should be monotonic over the prefixes of the range for it to be meaningful.
+    /// It returns None if and only if range is empty or the segment tree has no elements.
+    /// It will **panic** if left or right are not in `[0,n)`.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) and `stop` have constant time complexity.
+    pub fn query_until<F>(&self, l: usize, r: usize, stop: F) -> Option<T>
+    where
+        F: Fn(&T) -> bool,
+    {
+        span!(
+            TRACE,
+            "query_until",
+            tree = "Iterative",
+            n = self.n,
+            left = l,
+            right = r
+        );
+        let _op = self.last_op.measure();
+        let mut result: Option<T> = None;
+        self.visit_range(l, r, |node| {
+            let combined = result
+                .take()
+                .map_or_else(|| node.clone(), |result| combine(&result, node));
+            stop(result.insert(combined))
+        });
+        result
+    }
+
+    /// Combines the result from the range `[left,right]` into acc, i.e. acc becomes the [`combine`](Node::combine) of acc and the nodes which cover the range, from left to right.
+    /// Unlike [`query`](Self::query) no intermediate node is cloned and no [`Option`] is built, so it's cheaper in hot loops over small ranges, and acc is left as is if the range is empty.
+    /// It will **panic** if left or right are not in `[0,n)`.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    /// ```
+    /// # use seg_tree::{Iterative,utils::Sum,nodes::Node};
+    /// # let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+    /// let seg_tree = Iterative::build(&nodes); // [0,1,2,3,4,5,6,7,8,9] with Sum<usize> nodes
+    /// let mut acc = Sum::initialize(&0);
+    /// for i in 0..5 {
+    ///     seg_tree.query_into(i, i + 1, &mut acc);
+    /// }
+    /// assert_eq!(acc.value(), &25); // The sum of [i,i+1] for every i in [0,5).
+    /// ```
+    pub fn query_into(&self, left: usize, right: usize, acc: &mut T) {
+        self.visit_range(left, right, |node| {
+            *acc = combine(acc, node);
+            false
+        });
+    }
+
+    /// Calls visit with the nodes which cover the range `[l,r]` from left to right, until it returns `true`.
+    fn visit_range<F>(&self, l: usize, r: usize, mut visit: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        if self.n == 0 {
+            return;
+        }
+        if l <= r {
+            assert_in_bounds(r, self.n);
+        }
+        let (mut l, mut r) = (l + self.offset, r + self.offset + 1);
+        // The nodes of the right side are found from right to left, so they're visited last.
+        let mut right_nodes = [0; usize::BITS as usize];
+        let mut len = 0;
         while l < r {
             if l & 1 != 0 {
-                ans_left = Some(match ans_left {
-                    None => Node::initialize(self.nodes[l].value()),
-                    Some(node) => Node::combine(&node, &self.nodes[l]),
-                });
+                if visit(&self.nodes[l]) {
+                    return;
+                }
                 l += 1;
             }
             if r & 1 != 0 {
                 r -= 1;
-                ans_right = Some(match ans_right {
-                    None => Node::initialize(self.nodes[r].value()),
-                    Some(node) => Node::combine(&self.nodes[r], &node),
-                });
+                right_nodes[len] = r;
+                len += 1;
             }
             l >>= 1;
             r >>= 1;
         }
-        match (ans_left, ans_right) {
-            (Some(ans_left), Some(ans_right)) => Some(Node::combine(&ans_left, &ans_right)),
-            (Some(ans_left), None) => Some(Node::initialize(ans_left.value())),
-            (None, Some(ans_right)) => Some(Node::initialize(ans_right.value())),
-            (None, None) => None,
+        for &node in right_nodes[..len].iter().rev() {
+            if visit(&self.nodes[node]) {
+                return;
+            }
+        }
+    }
+
+    /// Subscribes callback to the updates of the segment tree, it's called after every update with the updated range and the value given to the update, so external indexes or caches can be kept in sync with the tree.
+    /// Callbacks are called in subscription order.
+    pub fn on_update<F>(&mut self, callback: F)
+    where
+        F: FnMut(RangeInclusive<usize>, &<T as Node>::Value) + Send + Sync + 'static,
+    {
+        self.observers.push(callback);
+    }
+
+    /// Unsubscribes every callback subscribed with [`on_update`](Self::on_update).
+    pub fn clear_observers(&mut self) {
+        self.observers.clear();
+    }
+
+    /// Returns the amount of elements of the segment tree.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.n
+    }
+
+    /// Returns `true` if the segment tree has no elements.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Returns the counters of the last build, query or update of the segment tree, see [`OpStats`](crate::instrument::OpStats).
+    #[cfg(feature = "instrument")]
+    #[must_use]
+    pub fn stats(&self) -> crate::instrument::OpStats {
+        self.last_op.get()
+    }
+
+    /// Returns the memory used by the segment tree, see [`MemoryStats`].
+    #[must_use]
+    pub fn memory_stats(&self) -> MemoryStats {
+        MemoryStats {
+            nodes: self.nodes.len(),
+            capacity: self.nodes.len(),
+            node_size: core::mem::size_of::<T>(),
+            versions: 1,
+            overhead_bytes: 0,
+        }
+    }
+
+    /// Checks that every internal node is the combination of its children, so a node whose [`combine`](Node::combine) isn't deterministic, or corrupted nodes, are caught where they happen instead of as wrong answers later on.
+    /// It's meant for tests and debug assertions.
+    /// It will **panic** at the first node which doesn't match.
+    /// It has time complexity of `O(n)`, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn validate(&self)
+    where
+        <T as Node>::Value: PartialEq,
+    {
+        for i in (1..self.offset).rev() {
+            assert_combines(
+                &self.nodes[i],
+                &self.nodes[2 * i],
+                &self.nodes[2 * i + 1],
+                format_args!("index {i}"),
+            );
         }
     }
 }
 
-impl<T> Iterative<T>
+#[cfg(feature = "rayon")]
+impl<T, S> Iterative<T, S>
+where
+    T: Node + Clone + Send + Sync,
+    S: Storage<T> + Sync,
+{
+    /// Returns the result from each range `[left,right]` in `queries`, in the same order, running the queries in parallel with [`rayon`].
+    /// It will **panic** if any `left` or `right` is not in `[0,n)`.
+    /// It has time complexity of `O(q*log(n)/p)`, where `q` is the amount of queries and `p` the amount of threads, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn par_query_batch(&self, queries: &[(usize, usize)]) -> Vec<Option<T>> {
+        use rayon::prelude::*;
+        queries
+            .par_iter()
+            .map(|&(left, right)| self.query(left, right))
+            .collect()
+    }
+}
+
+impl<T, S> Iterative<T, S>
+where
+    T: Node,
+    S: Storage<T>,
+{
+    /// Returns the leaves of the segment tree in order.
+    fn leaves(&self) -> &[T] {
+        &self.nodes[self.offset..self.offset + self.n]
+    }
+}
+
+impl<T, S> Iterative<T, S>
 where
     T: Node + core::fmt::Debug,
+    S: Storage<T>,
 {
-    fn dbg_visitor<'a>(n: usize, f: &mut dyn FnMut(usize, usize, &'a T), nodes: &'a [T]) {
-        let mut segments = vec![(0, 0); 2 * n];
-        for i in 0..n {
-            segments[i + n] = (i, i);
-            f(i, i, &nodes[n + i]);
+    fn dbg_visitor<'a>(
+        (n, offset): (usize, usize),
+        f: &mut dyn FnMut(usize, usize, &'a T),
+        nodes: &'a [T],
+    ) {
+        let mut segments = vec![(0, 0); 2 * offset];
+        for i in 0..offset {
+            segments[i + offset] = (i, i);
+            if i < n {
+                f(i, i, &nodes[offset + i]);
+            }
         }
         let helper = |(a1, b1): (usize, usize), (a2, b2)| (a1.min(a2), b1.max(b2));
-        for i in (1..n).rev() {
+        for i in (1..offset).rev() {
             segments[i] = helper(segments[2 * i], segments[2 * i + 1]);
-            f(segments[i].0, segments[i].1, &nodes[i]);
+            // The nodes which cover leftover leaves aren't part of the segment tree.
+            if segments[i].1 < n {
+                f(segments[i].0, segments[i].1, &nodes[i]);
+            }
         }
     }
 }
 
-impl<T> core::fmt::Debug for Iterative<T>
+/// Converts the segment tree into an [`Iterative`] with the same values, the leaves are moved and the rest of the nodes are combined again, as both trees are shaped differently.
+/// The callbacks subscribed with [`on_update`](Recursive::on_update) are dropped.
+/// It has time complexity of `O(n)`, assuming that [`combine`](Node::combine) has constant time complexity.
+impl<T> From<Recursive<T>> for Iterative<T>
+where
+    T: Node + Clone,
+{
+    fn from(segment_tree: Recursive<T>) -> Self {
+        Self::build_from(segment_tree.into_leaves().into_iter(), ())
+    }
+}
+
+impl<T> Extend<T::Value> for Iterative<T>
+where
+    T: Node + Clone,
+{
+    /// Appends the values as new leaves at the end of the segment tree.
+    /// Like [`resize`](Iterative::resize), only the new leaves and their ancestors are combined, unless it grows past its capacity.
+    /// It has amortized time complexity of `O(k+log(n))`, where `k` is the amount of new values, assuming that [`combine`](Node::combine) has constant time complexity.
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = T::Value>,
+    {
+        span!(DEBUG, "extend", tree = "Iterative", n = self.n);
+        let _op = self.last_op.measure();
+        self.push_leaves(iter.into_iter().map(|value| T::initialize(&value)).collect());
+    }
+}
+
+impl<T> IntoIterator for Iterative<T>
+where
+    T: Node + Clone,
+{
+    type Item = T::Value;
+    type IntoIter = vec::IntoIter<T::Value>;
+
+    /// Same as [`into_values`](Iterative::into_values).
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_values().into_iter()
+    }
+}
+
+impl<T> Iterative<T>
+where
+    T: Node + Clone,
+    T::Value: Codec,
+{
+    /// Appends the encoding of the values of the segment tree to bytes, see [`Codec`].
+    /// It has time complexity of `O(n)`, assuming that [`encode`](Codec::encode) has constant time complexity.
+    /// ```
+    /// # use seg_tree::{Iterative,utils::Sum,nodes::Node};
+    /// # let nodes: Vec<Sum<u32>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+    /// let seg_tree = Iterative::build(&nodes); // [0,1,2,3,4,5,6,7,8,9] with Sum<u32> nodes
+    /// let mut bytes = Vec::new();
+    /// seg_tree.encode_into(&mut bytes);
+    /// let decoded = Iterative::<Sum<u32>>::decode_from(&mut &bytes[..]).unwrap();
+    /// assert_eq!(decoded, seg_tree);
+    /// ```
+    pub fn encode_into(&self, bytes: &mut Vec<u8>) {
+        encode_values(self.leaves().iter().map(Node::value), bytes);
+    }
+
+    /// Decodes a segment tree encoded by [`encode_into`](Iterative::encode_into) from the start of bytes, and advances bytes past it.
+    /// It returns None if and only if bytes don't start with a valid encoding.
+    /// It has time complexity of `O(n)`, assuming that [`decode`](Codec::decode) and [`combine`](Node::combine) have constant time complexity.
+    pub fn decode_from(bytes: &mut &[u8]) -> Option<Self> {
+        let values: Vec<T::Value> = decode_values(bytes)?;
+        let nodes: Vec<T> = values.iter().map(T::initialize).collect();
+        Some(Self::build(&nodes))
+    }
+}
+
+/// Two segment trees are equal if they have the same values, in the same order.
+impl<T, S> PartialEq for Iterative<T, S>
+where
+    T: Node,
+    T::Value: PartialEq,
+    S: Storage<T>,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.n == other.n
+            && self
+                .leaves()
+                .iter()
+                .zip(other.leaves())
+                .all(|(a, b)| a.value() == b.value())
+    }
+}
+
+/// Writes the values of the segment tree like a slice, e.g. `[1, 2, 3]`, a precision limits how many values are written, e.g. `{:.2}` writes `[1, 2, ...]`.
+impl<T, S> core::fmt::Display for Iterative<T, S>
+where
+    T: Node,
+    T::Value: core::fmt::Display,
+    S: Storage<T>,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        display_values(f, self.leaves().iter().map(Node::value))
+    }
+}
+
+#[allow(clippy::missing_fields_in_debug)]
+impl<T, S> core::fmt::Debug for Iterative<T, S>
 where
     T: Node + core::fmt::Debug,
+    S: Storage<T>,
 {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("Iterative")
             .field("n", &self.n)
             .field(
                 "nodes",
-                &as_dbg_tree(&self.nodes, |nodes, f| Self::dbg_visitor(self.n, f, nodes)),
+                &as_dbg_tree(&self.nodes[..], |nodes, f| {
+                    Self::dbg_visitor((self.n, self.offset), f, nodes);
+                }),
             )
             .finish()
     }
@@ -123,7 +703,11 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::{nodes::Node, utils::Min};
+    use crate::{
+        nodes::Node,
+        testing::{check_split_off_and_append, small_values},
+        utils::{MaxSubArraySum, Min, Sum},
+    };
 
     use super::Iterative;
 
@@ -156,6 +740,32 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_query_batch_works() {
+        use crate::testing::NaiveRangeQuery;
+
+        let nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let segment_tree = Iterative::build(&nodes);
+        let naive = NaiveRangeQuery::build(&nodes);
+        let queries: Vec<(usize, usize)> = (0..=10)
+            .flat_map(|i| (i..=10).map(move |j| (i, j)))
+            .collect();
+        let results = segment_tree.par_query_batch(&queries);
+        for (&(i, j), result) in queries.iter().zip(results) {
+            assert_eq!(result.unwrap().value(), naive.query(i, j).unwrap().value());
+        }
+    }
+
+    #[test]
+    fn memory_stats_works() {
+        let nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let segment_tree = Iterative::build(&nodes);
+        let stats = segment_tree.memory_stats();
+        assert_eq!(stats.nodes, 22);
+        assert_eq!(stats.used_bytes(), 22 * core::mem::size_of::<Min<usize>>());
+    }
+
     #[test]
     fn dbg_works(){
         let nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
@@ -165,4 +775,333 @@ mod tests {
         let expected = "Iterative { n: 11, nodes: {[0, 0]: Min { value: 2 }, [1, 1]: Min { value: 1 }, [2, 2]: Min { value: 2 }, [3, 3]: Min { value: 3 }, [4, 4]: Min { value: 4 }, [5, 5]: Min { value: 5 }, [6, 6]: Min { value: 6 }, [7, 7]: Min { value: 7 }, [8, 8]: Min { value: 8 }, [9, 9]: Min { value: 9 }, [10, 10]: Min { value: 10 }, [9, 10]: Min { value: 9 }, [7, 8]: Min { value: 7 }, [5, 6]: Min { value: 5 }, [3, 4]: Min { value: 3 }, [1, 2]: Min { value: 1 }, [0, 10]: Min { value: 2 }, [5, 8]: Min { value: 5 }, [1, 4]: Min { value: 1 }, [0, 10]: Min { value: 2 }, [0, 10]: Min { value: 1 }} }";
         assert_eq!(dbg, expected);
     }
+
+    #[test]
+    fn validate_works() {
+        let nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let mut segment_tree = Iterative::build(&nodes);
+        segment_tree.validate();
+        segment_tree.update(3, &0);
+        segment_tree.update(10, &20);
+        segment_tree.validate();
+        Iterative::<Min<usize>>::build(&[]).validate();
+    }
+
+    #[test]
+    #[should_panic = "node of index 3 doesn't match the combination of its children"]
+    fn validate_catches_corrupted_nodes() {
+        let nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let mut segment_tree = Iterative::build(&nodes);
+        segment_tree.nodes[3] = Min::initialize(&20);
+        segment_tree.validate();
+    }
+
+    #[test]
+    fn empty_tree_works() {
+        let segment_tree = Iterative::<Min<usize>>::build(&[]);
+        assert_eq!(segment_tree.len(), 0);
+        assert!(segment_tree.is_empty());
+        assert!(segment_tree.query(0, 0).is_none());
+        assert_eq!(format!("{segment_tree:?}"), "Iterative { n: 0, nodes: {} }");
+    }
+
+    #[test]
+    #[should_panic = "can't update an empty segment tree"]
+    fn update_on_empty_tree_panics() {
+        let mut segment_tree = Iterative::<Min<usize>>::build(&[]);
+        segment_tree.update(0, &1);
+    }
+
+    #[test]
+    fn query_with_range_works() {
+        let nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let segment_tree = Iterative::build(&nodes);
+        let (range, node) = segment_tree.query_with_range(4, 20).unwrap();
+        assert_eq!(range, 4..11);
+        assert_eq!(node.value(), &4);
+        let (range, node) = segment_tree.query_with_range(0, usize::MAX).unwrap();
+        assert_eq!(range, 0..11);
+        assert_eq!(node.value(), &0);
+        assert!(segment_tree.query_with_range(11, 20).is_none());
+        assert!(segment_tree.query_with_range(5, 4).is_none());
+        assert!(Iterative::<Min<usize>>::build(&[]).query_with_range(0, 5).is_none());
+    }
+
+    #[test]
+    fn into_values_works() {
+        let nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let mut segment_tree = Iterative::build(&nodes);
+        segment_tree.update(3, &20);
+        let values: Vec<usize> = segment_tree.into_iter().collect();
+        assert_eq!(values, [0, 1, 2, 20, 4, 5, 6, 7, 8, 9, 10]);
+        assert!(Iterative::<Min<usize>>::build(&[]).into_values().is_empty());
+    }
+
+    #[test]
+    fn eq_compares_values() {
+        let nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let mut a = Iterative::build(&nodes);
+        let b = Iterative::build(&nodes);
+        assert_eq!(a, b);
+        a.update(3, &20);
+        assert_ne!(a, b);
+        a.update(3, &3);
+        assert_eq!(a, b);
+        assert_ne!(a, Iterative::build(&nodes[1..]));
+    }
+
+    #[test]
+    fn split_off_and_append_work() {
+        check_split_off_and_append(Iterative::build);
+    }
+
+    #[test]
+    fn resize_works() {
+        let nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let mut segment_tree = Iterative::build(&nodes);
+        segment_tree.resize(14, &1);
+        segment_tree.validate();
+        assert_eq!(segment_tree.len(), 14);
+        assert_eq!(segment_tree.query(2, 10).unwrap().value(), &2);
+        assert_eq!(segment_tree.query(2, 13).unwrap().value(), &1);
+        assert_eq!(segment_tree.query(11, 13).unwrap().value(), &1);
+        segment_tree.resize(3, &1);
+        segment_tree.validate();
+        assert_eq!(segment_tree.len(), 3);
+        assert_eq!(segment_tree.query(1, 2).unwrap().value(), &1);
+        segment_tree.resize(0, &1);
+        assert!(segment_tree.is_empty());
+    }
+
+    #[test]
+    fn extend_works() {
+        let values: Vec<usize> = (0..100).map(|x| (x * 37) % 101).collect();
+        let mut segment_tree = Iterative::<Min<usize>>::build(&[]);
+        let mut len = 0;
+        for batch in [1, 31, 0, 5, 40, 23] {
+            segment_tree.extend(values[len..len + batch].iter().copied());
+            len += batch;
+            assert_eq!(segment_tree.len(), len);
+            segment_tree.validate();
+            for i in 0..len {
+                let expected = values[i..len].iter().min().unwrap();
+                assert_eq!(segment_tree.query(i, len - 1).unwrap().value(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn resize_reuses_capacity() {
+        let mut segment_tree = Iterative::<Sum<usize>>::build_from_fn(4, |i| i);
+        for i in 4..20 {
+            segment_tree.resize(i + 1, &i);
+            segment_tree.validate();
+            assert_eq!(segment_tree.query(0, i).unwrap().value(), &(i * (i + 1) / 2));
+        }
+        let capacity = segment_tree.memory_stats().capacity;
+        segment_tree.resize(5, &0);
+        segment_tree.resize(9, &1);
+        segment_tree.validate();
+        assert_eq!(segment_tree.memory_stats().capacity, capacity);
+        assert_eq!(segment_tree.query(0, 8).unwrap().value(), &14);
+        assert_eq!(segment_tree.query(3, 6).unwrap().value(), &9);
+        assert_eq!(segment_tree, Iterative::build_from_fn(9, |i| if i < 5 { i } else { 1 }));
+        assert_eq!(format!("{segment_tree}"), "[0, 1, 2, 3, 4, 1, 1, 1, 1]");
+    }
+
+    #[test]
+    #[should_panic(expected = "index 5 is out of bounds")]
+    fn resize_hides_dropped_elements() {
+        let mut segment_tree = Iterative::<Sum<usize>>::build_from_fn(8, |i| i);
+        segment_tree.resize(5, &0);
+        let _ = segment_tree.query(0, 5);
+    }
+
+    #[test]
+    fn retain_works() {
+        let values: Vec<usize> = small_values(13);
+        let nodes: Vec<Sum<usize>> = values.iter().map(Sum::initialize).collect();
+        let mut segment_tree = Iterative::build(&nodes);
+        segment_tree.retain(|&x| x % 2 == 1);
+        segment_tree.validate();
+        let kept: Vec<usize> = values.into_iter().filter(|x| x % 2 == 1).collect();
+        assert_eq!(segment_tree.len(), kept.len());
+        for i in 0..kept.len() {
+            for j in i..kept.len() {
+                let expected: usize = kept[i..=j].iter().sum();
+                assert_eq!(segment_tree.query(i, j).unwrap().value(), &expected);
+            }
+        }
+        segment_tree.retain(|_| false);
+        assert!(segment_tree.is_empty());
+    }
+
+    #[test]
+    #[should_panic = "split index 12 is out of bounds, it must be at most 11"]
+    fn split_off_checks_index() {
+        let nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let _ = Iterative::build(&nodes).split_off(12);
+    }
+
+    #[test]
+    fn merge_with_works() {
+        let a: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let b: Vec<Min<usize>> = (0..=10).rev().map(|x| Min::initialize(&x)).collect();
+        let segment_tree = Iterative::build(&a).merge_with(&Iterative::build(&b));
+        segment_tree.validate();
+        for i in 0..=10 {
+            assert_eq!(segment_tree.query(i, i).unwrap().value(), &i.min(10 - i));
+        }
+    }
+
+    #[test]
+    #[should_panic = "can't merge segment trees of different lengths, 11 != 10"]
+    fn merge_with_checks_lengths() {
+        let nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let _ = Iterative::build(&nodes).merge_with(&Iterative::build(&nodes[1..]));
+    }
+
+    #[test]
+    fn build_compressed_works() {
+        let pairs: Vec<(i64, Sum<usize>)> = [(-7, 1), (1_000_000_000, 2), (3, 4), (-7, 8)]
+            .into_iter()
+            .map(|(key, value)| (key, Sum::initialize(&value)))
+            .collect();
+        let (compressor, segment_tree) = Iterative::build_compressed(&pairs);
+        segment_tree.validate();
+        assert_eq!(segment_tree.len(), 3);
+        let (l, r) = (compressor.lower_bound(&0), compressor.upper_bound(&1_000_000_000));
+        assert_eq!(segment_tree.query(l, r - 1).unwrap().value(), &6);
+        assert_eq!(segment_tree.query(0, 0).unwrap().value(), &9);
+    }
+
+    #[test]
+    fn display_works() {
+        let nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let mut segment_tree = Iterative::build(&nodes);
+        segment_tree.update(1, &7);
+        assert_eq!(format!("{segment_tree}"), "[0, 7, 2, 3, 4, 5, 6, 7, 8, 9, 10]");
+        assert_eq!(format!("{segment_tree:.3}"), "[0, 7, 2, ...]");
+        assert_eq!(format!("{segment_tree:.11}"), "[0, 7, 2, 3, 4, 5, 6, 7, 8, 9, 10]");
+        assert_eq!(format!("{}", Iterative::<Min<usize>>::build(&[])), "[]");
+    }
+
+    #[test]
+    fn query_until_works() {
+        let values: Vec<usize> = small_values(13).into_iter().map(|x: usize| x + 1).collect();
+        let nodes: Vec<Sum<usize>> = values.iter().map(Sum::initialize).collect();
+        let segment_tree = Iterative::build(&nodes);
+        for left in 0..values.len() {
+            for right in left..values.len() {
+                for target in [1, 5, 20, 60] {
+                    let result = segment_tree
+                        .query_until(left, right, |node| *node.value() >= target)
+                        .unwrap();
+                    let sums: Vec<usize> = (left..=right)
+                        .map(|k| values[left..=k].iter().sum())
+                        .collect();
+                    assert!(sums.contains(result.value()));
+                    assert!(*result.value() >= target || result.value() == sums.last().unwrap());
+                }
+            }
+        }
+        assert!(segment_tree.query_until(1, 0, |_| true).is_none());
+    }
+
+    #[test]
+    fn query_into_works() {
+        let values: Vec<usize> = small_values(13);
+        let nodes: Vec<Sum<usize>> = values.iter().map(Sum::initialize).collect();
+        let segment_tree = Iterative::build(&nodes);
+        for left in 0..values.len() {
+            for right in 0..values.len() {
+                let mut acc = Sum::initialize(&100);
+                segment_tree.query_into(left, right, &mut acc);
+                let sum: usize = values
+                    .get(left..=right)
+                    .map_or(0, |range| range.iter().sum());
+                assert_eq!(acc.value(), &(100 + sum));
+            }
+        }
+    }
+
+    #[test]
+    fn query_keeps_internal_nodes() {
+        // The result of a maximum subarray depends on fields besides its value, and on the order.
+        let values: Vec<i64> = small_values(13).into_iter().map(|x: i64| x - 2).collect();
+        let nodes: Vec<MaxSubArraySum> = values.iter().map(MaxSubArraySum::initialize).collect();
+        let segment_tree = Iterative::build(&nodes);
+        for left in 0..values.len() {
+            for right in left..values.len() {
+                let expected = (left..=right)
+                    .flat_map(|i| (i..=right).map(move |j| (i, j)))
+                    .map(|(i, j)| values[i..=j].iter().sum::<i64>())
+                    .max();
+                let result = segment_tree.query(left, right).unwrap();
+                assert_eq!(Some(*result.value()), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn query_until_keeps_internal_nodes() {
+        let values: Vec<i64> = small_values(13).into_iter().map(|x: i64| x - 2).collect();
+        let nodes: Vec<MaxSubArraySum> = values.iter().map(MaxSubArraySum::initialize).collect();
+        let segment_tree = Iterative::build(&nodes);
+        for left in 0..values.len() {
+            for right in left..values.len() {
+                let expected = segment_tree.query(left, right).unwrap();
+                let result = segment_tree.query_until(left, right, |_| false).unwrap();
+                assert_eq!(result.value(), expected.value());
+            }
+        }
+    }
+
+    #[test]
+    fn linear_query_matches_tree_query() {
+        let values: Vec<i64> = small_values(20).into_iter().map(|x: i64| x - 2).collect();
+        let nodes: Vec<MaxSubArraySum> = values.iter().map(MaxSubArraySum::initialize).collect();
+        let mut linear = Iterative::build(&nodes).with_linear_threshold(32);
+        let mut tree = Iterative::build(&nodes);
+        assert_eq!(tree.linear_threshold(), 0);
+        linear.update(4, &7);
+        tree.update(4, &7);
+        for left in 0..values.len() {
+            for right in 0..values.len() {
+                let expected = tree.query(left, right).map(MaxSubArraySum::into_value);
+                assert_eq!(
+                    linear.query(left, right).map(MaxSubArraySum::into_value),
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn on_update_works() {
+        use std::sync::{Arc, Mutex};
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = Iterative::build(&nodes);
+        let log = Arc::new(Mutex::new(Vec::new()));
+        for id in [0, 1] {
+            let log_clone = Arc::clone(&log);
+            segment_tree.on_update(move |range, value| {
+                log_clone.lock().unwrap().push((id, range, *value));
+            });
+        }
+        segment_tree.update(3, &1);
+        segment_tree.update(10, &2);
+        segment_tree.clear_observers();
+        segment_tree.update(0, &3);
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                (0, 3..=3, 1),
+                (1, 3..=3, 1),
+                (0, 10..=10, 2),
+                (1, 10..=10, 2)
+            ]
+        );
+        assert_eq!(segment_tree.query(0, 10).unwrap().value(), &48);
+    }
 }
@@ -0,0 +1,269 @@
+use alloc::vec::Vec;
+use core::ops::Deref;
+
+use crate::{
+    nodes::{LazyNode, Node},
+    segment_tree::{Iterative, LazyRecursive, Recursive},
+    storage::Storage,
+};
+
+/// Segment trees whose updates set a single element, so an update can be undone by setting the previous value back.
+///
+/// It's implemented for [`Iterative`], [`Recursive`] and [`LazyRecursive`], whose elements are set with [`set`](LazyRecursive::set), so its range updates can't be journaled, as undoing them would take a point write per element of the range.
+pub trait PointTree {
+    /// Node of the segment tree.
+    type Node: Node;
+
+    /// Returns the value of the p-th element, it takes `&mut self` as the lazy trees push their pending values while querying.
+    /// It will **panic** if p is not in `[0,n)`.
+    fn get(&mut self, p: usize) -> <Self::Node as Node>::Value;
+
+    /// Sets the p-th element to value.
+    /// It will **panic** if p is not in `[0,n)`.
+    fn set(&mut self, p: usize, value: &<Self::Node as Node>::Value);
+}
+
+impl<T, S> PointTree for Iterative<T, S>
+where
+    T: Node + Clone,
+    T::Value: Clone,
+    S: Storage<T>,
+{
+    type Node = T;
+
+    fn get(&mut self, p: usize) -> T::Value {
+        self.query(p, p).unwrap().value().clone()
+    }
+
+    fn set(&mut self, p: usize, value: &T::Value) {
+        self.update(p, value);
+    }
+}
+
+impl<T, S> PointTree for Recursive<T, S>
+where
+    T: Node + Clone,
+    T::Value: Clone,
+    S: Storage<T>,
+{
+    type Node = T;
+
+    fn get(&mut self, p: usize) -> T::Value {
+        self.query(p, p).unwrap().value().clone()
+    }
+
+    fn set(&mut self, p: usize, value: &T::Value) {
+        self.update(p, value);
+    }
+}
+
+impl<T, S> PointTree for LazyRecursive<T, S>
+where
+    T: LazyNode + Clone,
+    T::Value: Clone,
+    S: Storage<T>,
+{
+    type Node = T;
+
+    fn get(&mut self, p: usize) -> T::Value {
+        self.query(p, p).unwrap().value().clone()
+    }
+
+    fn set(&mut self, p: usize, value: &T::Value) {
+        Self::set(self, p, value);
+    }
+}
+
+/// Wrapper around a [`PointTree`] which records its updates, so they can be undone and redone.
+///
+/// Each update stores the value it overwrote, so the history uses `O(1)` space per update, instead of the `O(log(n))` nodes per update of the persistent trees.
+/// The wrapped tree can be queried through [`Deref`], but it can only be updated through the wrapper.
+/// ```
+/// # use seg_tree::{Iterative,Journaled,utils::Sum,nodes::Node};
+/// # let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+/// let mut seg_tree = Journaled::new(Iterative::build(&nodes)); // [0,1,2,3,4,5,6,7,8,9] with Sum<usize> nodes
+/// seg_tree.update(0, &10);
+/// assert_eq!(seg_tree.query(0, 9).unwrap().value(), &55);
+/// assert!(seg_tree.undo());
+/// assert_eq!(seg_tree.query(0, 9).unwrap().value(), &45);
+/// assert!(seg_tree.redo());
+/// assert_eq!(seg_tree.query(0, 9).unwrap().value(), &55);
+/// ```
+pub struct Journaled<S>
+where
+    S: PointTree,
+{
+    tree: S,
+    undo: Vec<(usize, <S::Node as Node>::Value)>,
+    redo: Vec<(usize, <S::Node as Node>::Value)>,
+}
+
+impl<S> Journaled<S>
+where
+    S: PointTree,
+{
+    /// Wraps tree, starting with an empty history.
+    pub const fn new(tree: S) -> Self {
+        Self {
+            tree,
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    /// Sets the p-th element of the segment tree to value and records the update, discarding every undone update.
+    /// It will **panic** if p is not in `[0,n)`.
+    /// It has the time complexity of [`get`](PointTree::get) plus [`set`](PointTree::set).
+    pub fn update(&mut self, p: usize, value: &<S::Node as Node>::Value) {
+        let old_value = self.tree.get(p);
+        self.tree.set(p, value);
+        self.undo.push((p, old_value));
+        self.redo.clear();
+    }
+
+    /// Undoes the last update which hasn't been undone, it returns `false` if there is none.
+    pub fn undo(&mut self) -> bool {
+        Self::replay(&mut self.tree, &mut self.undo, &mut self.redo)
+    }
+
+    /// Redoes the last undone update, it returns `false` if there is none.
+    pub fn redo(&mut self) -> bool {
+        Self::replay(&mut self.tree, &mut self.redo, &mut self.undo)
+    }
+
+    /// Sets back the last value in `from`, recording the overwritten value in `to`.
+    fn replay(
+        tree: &mut S,
+        from: &mut Vec<(usize, <S::Node as Node>::Value)>,
+        to: &mut Vec<(usize, <S::Node as Node>::Value)>,
+    ) -> bool {
+        let Some((p, value)) = from.pop() else {
+            return false;
+        };
+        to.push((p, tree.get(p)));
+        tree.set(p, &value);
+        true
+    }
+
+    /// Returns the amount of updates which can be undone.
+    #[must_use]
+    pub const fn undo_len(&self) -> usize {
+        self.undo.len()
+    }
+
+    /// Returns the amount of updates which can be redone.
+    #[must_use]
+    pub const fn redo_len(&self) -> usize {
+        self.redo.len()
+    }
+
+    /// Forgets the whole history, keeping the current state of the tree.
+    pub fn clear_history(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    /// Returns the wrapped tree, dropping the history.
+    pub fn into_inner(self) -> S {
+        self.tree
+    }
+}
+
+impl<T, S> Journaled<LazyRecursive<T, S>>
+where
+    T: LazyNode + Clone,
+    T::Value: Clone,
+    S: Storage<T>,
+{
+    /// Same as [`LazyRecursive::query`], which can't be called through [`Deref`] as it pushes the pending values.
+    pub fn query(&mut self, left: usize, right: usize) -> Option<T> {
+        self.tree.query(left, right)
+    }
+}
+
+impl<S> Deref for Journaled<S>
+where
+    S: PointTree,
+{
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        &self.tree
+    }
+}
+
+impl<S> core::fmt::Debug for Journaled<S>
+where
+    S: PointTree + core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Journaled")
+            .field("tree", &self.tree)
+            .field("undo_len", &self.undo.len())
+            .field("redo_len", &self.redo.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        nodes::Node,
+        segment_tree::{Iterative, LazyRecursive, Recursive},
+        utils::{LazySetWrapper, Max, Min, Sum},
+    };
+
+    use super::Journaled;
+
+    #[test]
+    fn undo_redo_works() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = Journaled::new(Recursive::build(&nodes));
+        segment_tree.update(0, &10);
+        segment_tree.update(0, &20);
+        segment_tree.update(5, &0);
+        assert_eq!(segment_tree.query(0, 10).unwrap().value(), &70);
+        assert!(segment_tree.undo());
+        assert!(segment_tree.undo());
+        assert_eq!(segment_tree.query(0, 10).unwrap().value(), &65);
+        assert_eq!((segment_tree.undo_len(), segment_tree.redo_len()), (1, 2));
+        assert!(segment_tree.redo());
+        assert_eq!(segment_tree.query(0, 0).unwrap().value(), &20);
+        assert!(segment_tree.undo());
+        assert!(segment_tree.undo());
+        assert!(!segment_tree.undo());
+        assert_eq!(segment_tree.query(0, 10).unwrap().value(), &55);
+    }
+
+    #[test]
+    fn update_discards_redo() {
+        let nodes: Vec<Max<usize>> = (0..=10).map(|x| Max::initialize(&x)).collect();
+        let mut segment_tree = Journaled::new(Iterative::build(&nodes));
+        segment_tree.update(3, &30);
+        assert!(segment_tree.undo());
+        segment_tree.update(4, &40);
+        assert!(!segment_tree.redo());
+        assert_eq!(segment_tree.query(0, 10).unwrap().value(), &40);
+        let segment_tree = segment_tree.into_inner();
+        assert_eq!(segment_tree.query(3, 3).unwrap().value(), &3);
+    }
+
+    #[test]
+    fn lazy_recursive_works() {
+        let nodes: Vec<LazySetWrapper<Min<usize>>> =
+            (0..=10).map(|x| LazySetWrapper::initialize(&x)).collect();
+        let mut lazy_tree = LazyRecursive::build(&nodes);
+        lazy_tree.update(2, 8, &20);
+        let mut segment_tree = Journaled::new(lazy_tree);
+        segment_tree.update(5, &1);
+        segment_tree.update(0, &30);
+        assert_eq!(segment_tree.query(0, 10).unwrap().value(), &1);
+        assert!(segment_tree.undo());
+        assert!(segment_tree.undo());
+        assert_eq!(segment_tree.query(0, 10).unwrap().value(), &0);
+        assert_eq!(segment_tree.query(5, 5).unwrap().value(), &20);
+        assert!(segment_tree.redo());
+        let mut lazy_tree = segment_tree.into_inner();
+        assert_eq!(lazy_tree.query(2, 8).unwrap().value(), &1);
+    }
+}
@@ -1,19 +1,40 @@
+use alloc::{string::String, vec, vec::Vec};
+
 use bit_vec::BitVec;
+use core::{marker::PhantomData, ops::Range};
 
 use crate::{
     internal_utils::{
         dbg_utils::{as_dbg_tree, lazy_persistent_visitor},
-        persistent_utils::PersistentWrapper,
+        empty_utils::assert_not_empty,
+        instrument_utils::{combine, enter, record_allocations, record_push, LastOp},
+        persistent_utils::{
+            assert_empty, assert_roots, compact, version_stats, NodeIndex, PersistentWrapper,
+        },
+        range_utils::clamp,
+        tracing_utils::span,
+        validate_utils::validate_lazy,
+        version_utils::{partition_versions, VersionHistory},
     },
     nodes::{LazyNode, Node},
+    segment_tree::{MemoryStats, VersionId, VersionStats},
+    storage::Arena,
 };
 
 /// Lazy persistent segment tree, it saves every version of itself, it has range queries and range updates.
+///
 /// It uses `O(n+q*log(n))` space, where `q` is the amount of updates, and assuming that each node uses `O(1)` space.
-pub struct LazyPersistent<T> {
-    nodes: Vec<PersistentWrapper<T>>,
+///
+/// The indices of the children of each node are stored as `I`, see [`NodeIndex`].
+///
+/// The nodes are stored in `A`, see [`Arena`].
+pub struct LazyPersistent<T, I = u32, A = Vec<PersistentWrapper<T, I>>> {
+    nodes: A,
     roots: Vec<usize>,
+    history: VersionHistory,
     n: usize,
+    _node: PhantomData<PersistentWrapper<T, I>>,
+    last_op: LastOp,
 }
 
 impl<T> LazyPersistent<T>
@@ -23,31 +44,156 @@ where
     /// Builds a lazy persistent segment tree from slice, each element of the slice will correspond to a leaf of the segment tree.
     /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
     pub fn build(values: &[T]) -> Self {
+        Self::build_with_index(values)
+    }
+
+    /// Same as [`build`](LazyPersistent::build), but it also reserves memory for `expected_updates` updates, see [`reserve_updates`](LazyPersistent::reserve_updates).
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn build_with_capacity(values: &[T], expected_updates: usize) -> Self {
+        let mut temp = Self::build_with_index(values);
+        temp.reserve_updates(expected_updates);
+        temp
+    }
+
+    /// Builds segment tree with n elements, each of them equal to value.
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn filled(n: usize, value: &<T as Node>::Value) -> Self {
+        Self::build(&vec![T::initialize(value); n])
+    }
+
+    /// Same as [`filled`](LazyPersistent::filled), but every element is the [`Default`] value, e.g. `0` for [`Sum`](crate::utils::Sum) nodes.
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn with_len(n: usize) -> Self
+    where
+        <T as Node>::Value: Default,
+    {
+        Self::filled(n, &<T as Node>::Value::default())
+    }
+
+    /// Builds segment tree with n elements, the i-th of them equal to `f(i)`, without collecting the values first.
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) and `f` have constant time complexity.
+    #[must_use]
+    pub fn build_from_fn<F>(n: usize, mut f: F) -> Self
+    where
+        F: FnMut(usize) -> <T as Node>::Value,
+    {
+        Self::build_from((0..n).map(|i| T::initialize(&f(i))), Vec::with_capacity(4 * n))
+    }
+}
+
+impl<T, I> LazyPersistent<T, I>
+where
+    T: LazyNode + Clone,
+    I: NodeIndex,
+{
+    /// Same as [`build`](LazyPersistent::build), but the indices of the children of each node are stored as `I` instead of `u32`, see [`NodeIndex`].
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn build_with_index(values: &[T]) -> Self {
+        Self::build_in(values, Vec::with_capacity(4 * values.len()))
+    }
+}
+
+impl<T, I, A> LazyPersistent<T, I, A>
+where
+    T: LazyNode + Clone,
+    I: NodeIndex,
+    A: Arena<PersistentWrapper<T, I>>,
+{
+    /// Same as [`build_with_index`](LazyPersistent::build_with_index), but the nodes are stored in `nodes`, see [`Arena`].
+    /// It will **panic** if `nodes` isn't empty.
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn build_in(values: &[T], nodes: A) -> Self {
+        Self::build_from(values.iter().cloned(), nodes)
+    }
+
+    /// Reopens a segment tree of n elements whose nodes are already in `nodes`, e.g. an arena kept in a memory mapped file by a previous segment tree, see [`into_arena`](Self::into_arena).
+    /// The i-th version is the one whose root is `roots[i]`, as returned by [`roots`](Self::roots), the nodes aren't copied nor checked, so they must be the nodes of a segment tree of n elements.
+    /// The parents and labels of the versions aren't stored in the arena, so no version has a parent afterwards.
+    /// ```
+    /// # use seg_tree::{LazyPersistent,utils::Sum,nodes::Node};
+    /// # let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+    /// let mut seg_tree: LazyPersistent<Sum<usize>> = LazyPersistent::build(&nodes);
+    /// seg_tree.update(0, 0, 6, &1);
+    /// let roots = seg_tree.roots().to_vec();
+    /// let arena = seg_tree.into_arena();
+    /// let seg_tree: LazyPersistent<Sum<usize>> = LazyPersistent::reopen_in(arena, 10, roots);
+    /// assert_eq!(seg_tree.query(1, 0, 9).unwrap().value(), &52);
+    /// ```
+    /// It will **panic** if roots is empty, or if some root isn't in the arena.
+    /// It has time complexity of `O(k)`, where `k` is the amount of versions.
+    pub fn reopen_in(nodes: A, n: usize, roots: Vec<usize>) -> Self {
+        assert_roots(&nodes, n, &roots);
+        Self {
+            nodes,
+            history: VersionHistory::without_parents(roots.len()),
+            roots,
+            n,
+            _node: PhantomData,
+            last_op: LastOp::default(),
+        }
+    }
+
+    /// Returns the index in the arena of the root of each version, in version order, see [`reopen_in`](Self::reopen_in).
+    #[must_use]
+    pub fn roots(&self) -> &[usize] {
+        &self.roots
+    }
+
+    /// Consumes the segment tree, returning the arena with its nodes, see [`reopen_in`](Self::reopen_in).
+    #[must_use]
+    pub fn into_arena(self) -> A {
+        self.nodes
+    }
+
+    /// Builds the segment tree from the leaves, in order.
+    fn build_from<V>(mut values: V, nodes: A) -> Self
+    where
+        V: ExactSizeIterator<Item = T>,
+    {
+        span!(DEBUG, "build", tree = "LazyPersistent", n = values.len());
+        let last_op = LastOp::default();
+        let _op = last_op.measure();
+        assert_empty(&nodes);
         let n = values.len();
         let mut temp = Self {
-            nodes: Vec::with_capacity(4 * n),
+            nodes,
             roots: Vec::with_capacity(1),
+            history: VersionHistory::default(),
             n,
+            _node: PhantomData,
+            last_op,
+        };
+        // An empty tree still has version 0, its root is never read as there are no nodes.
+        let root = if n == 0 {
+            0
+        } else {
+            temp.build_helper(&mut values, 0, n - 1)
         };
-        if n == 0 {
-            return temp;
-        }
-        let root = temp.build_helper(values, 0, n - 1);
         temp.roots.push(root);
+        temp.history.push(None);
         temp
     }
 
-    fn build_helper(&mut self, values: &[T], i: usize, j: usize) -> usize {
+    fn build_helper<V>(&mut self, values: &mut V, i: usize, j: usize) -> usize
+    where
+        V: Iterator<Item = T>,
+    {
+        let _depth = enter();
         if i == j {
             let curr_node = self.nodes.len();
-            self.nodes.push(values[i].clone().into());
+            // The leaves are visited in order, so the i-th value is the next one.
+            self.nodes.push(values.next().unwrap().into());
+            record_allocations(1);
             return curr_node;
         }
-        let mid = (i + j) / 2;
+        let mid = usize::midpoint(i, j);
         let left_node = self.build_helper(values, i, mid);
         let right_node = self.build_helper(values, mid + 1, j);
         let curr_node = self.nodes.len();
-        self.nodes.push(Node::combine(
+        record_allocations(1);
+        self.nodes.push(combine(
             &self.nodes[left_node],
             &self.nodes[right_node],
         ));
@@ -56,74 +202,107 @@ where
     }
 
     /// Returns the result from the range `[left,right]` from the version of the segment tree.
-    /// It returns None if and only if range is empty.
-    /// It will **panic** if left or right are not in `[0,n)`, or if version is not in `[0,`[`versions`](Self::versions)`)`.
+    /// It returns None if and only if range is empty or the segment tree has no elements.
+    /// It will **panic** if left or right are not in `[0,n)`, or if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// The lazy values are applied to copies of the visited nodes instead of being pushed to the children, so queries never allocate new nodes in the tree, and its memory growth is bounded by the updates.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine), [`update_lazy_value`](LazyNode::update_lazy_value) and [`lazy_update`](LazyNode::lazy_update) have constant time complexity.
+    #[must_use]
+    pub fn query(&self, version: usize, left: usize, right: usize) -> Option<T> {
+        span!(TRACE, "query", tree = "LazyPersistent", n = self.n, version, left, right);
+        let _op = self.last_op.measure();
+        let root = self.roots[version];
+        if left > right || self.n == 0 {
+            return None;
+        }
+        let root = self.nodes[root].clone();
+        Some(self.query_helper(root, left, right, 0, self.n - 1).into_inner())
+    }
+
+    /// Same as [`query`](Self::query), but the range is clamped to `[0,n)` instead of **panicking** when it's out of bounds, and the range that was actually aggregated is returned with the result, as a half-open range.
+    /// It returns None if and only if the clamped range is empty.
+    /// It will **panic** if version is not in <code>[0,[versions](Self::versions))</code>.
     /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine), [`update_lazy_value`](LazyNode::update_lazy_value) and [`lazy_update`](LazyNode::lazy_update) have constant time complexity.
-    pub fn query(&mut self, version: usize, left: usize, right: usize) -> Option<T> {
-        self.query_helper(self.roots[version], left, right, 0, self.n - 1)
-            .map(PersistentWrapper::into_inner)
+    #[must_use]
+    pub fn query_with_range(
+        &self,
+        version: usize,
+        left: usize,
+        right: usize,
+    ) -> Option<(Range<usize>, T)> {
+        let range = clamp(left, right, self.n);
+        let (left, right) = range
+            .as_ref()
+            .map_or((1, 0), |range| (range.start, range.end - 1));
+        range.zip(self.query(version, left, right))
+    }
+
+    /// Same as [`query_with_range`](Self::query_with_range), but only the result is returned.
+    /// It returns None if and only if the range clamped to `[0,n)` is empty.
+    /// It will **panic** if version is not in <code>[0,[versions](Self::versions))</code>.
+    #[must_use]
+    pub fn query_clamped(&self, version: usize, left: usize, right: usize) -> Option<T> {
+        self.query_with_range(version, left, right)
+            .map(|(_, node)| node)
     }
 
     fn push(&mut self, curr_node: usize, i: usize, j: usize) {
         if self.nodes[curr_node].lazy_value().is_some() && i != j {
+            record_push();
+            record_allocations(2);
             let left_node = self.nodes.len();
             let right_node = self.nodes.len() + 1;
-            self.nodes.push(
-                self.nodes[self.nodes[curr_node]
-                    .left_child()
-                    .unwrap_or_else(|| panic!("[{i}, {j}]"))
-                    .get()]
-                .clone(),
-            );
-            self.nodes.push(
-                self.nodes[self.nodes[curr_node]
-                    .right_child()
-                    .unwrap_or_else(|| panic!("[{i}, {j}]"))
-                    .get()]
-                .clone(),
-            );
-            let (parent_slice, sons_slice) = self.nodes.split_at_mut(curr_node + 1);
-            let value = parent_slice[curr_node].lazy_value().unwrap();
-            sons_slice[left_node - curr_node - 1].update_lazy_value(value);
-            sons_slice[right_node - curr_node - 1].update_lazy_value(value);
+            let parent = &self.nodes[curr_node];
+            let value = parent.lazy_value().unwrap();
+            let mut left =
+                self.nodes[parent.left_child().unwrap_or_else(|| panic!("[{i}, {j}]"))].clone();
+            let mut right =
+                self.nodes[parent.right_child().unwrap_or_else(|| panic!("[{i}, {j}]"))].clone();
+            left.update_lazy_value(value);
+            right.update_lazy_value(value);
+            self.nodes.push(left);
+            self.nodes.push(right);
             self.nodes[curr_node].set_children(left_node, right_node);
         }
         self.nodes[curr_node].lazy_update(i, j);
     }
 
+    /// Returns the result from the intersection of `[left,right]` and `[i,j]`, which must be non-empty.
+    /// `curr_node` is a copy of the node of `[i,j]` holding every lazy value pending from its ancestors.
     fn query_helper(
-        &mut self,
-        curr_node: usize,
+        &self,
+        mut curr_node: PersistentWrapper<T, I>,
         left: usize,
         right: usize,
         i: usize,
         j: usize,
-    ) -> Option<PersistentWrapper<T>> {
-        if j < left || right < i {
-            return None;
-        }
-        if self.nodes[curr_node].lazy_value().is_some() {
-            self.push(curr_node, i, j);
-        }
+    ) -> PersistentWrapper<T, I> {
+        let _depth = enter();
         if left <= i && j <= right {
-            return Some(self.nodes[curr_node].clone());
+            curr_node.lazy_update(i, j);
+            return curr_node;
         }
-        let mid = (i + j) / 2;
-        let left_node = self.nodes[curr_node].left_child().unwrap().get();
-        let right_node = self.nodes[curr_node].right_child().unwrap().get();
-        match (
-            self.query_helper(left_node, left, right, i, mid),
-            self.query_helper(right_node, left, right, mid + 1, j),
-        ) {
-            (Some(ans_left), Some(ans_right)) => Some(Node::combine(&ans_left, &ans_right)),
-            (Some(ans_left), None) => Some(ans_left),
-            (None, Some(ans_right)) => Some(ans_right),
-            (None, None) => None,
+        let mid = usize::midpoint(i, j);
+        let child = |index: Option<usize>| {
+            let mut child = self.nodes[index.unwrap()].clone();
+            if let Some(value) = curr_node.lazy_value() {
+                child.update_lazy_value(value);
+            }
+            child
+        };
+        let ans_left = (left <= mid)
+            .then(|| self.query_helper(child(curr_node.left_child()), left, right, i, mid));
+        let ans_right = (mid < right)
+            .then(|| self.query_helper(child(curr_node.right_child()), left, right, mid + 1, j));
+        match (ans_left, ans_right) {
+            (Some(ans_left), Some(ans_right)) => combine(&ans_left, &ans_right),
+            (Some(ans), None) | (None, Some(ans)) => ans,
+            (None, None) => unreachable!("[{left}, {right}] doesn't intersect [{i}, {j}]"),
         }
     }
 
     /// Creates a new segment tree version from version were the p-th element of the segment tree to value T and update the segment tree correspondingly.
-    /// It will panic if p is not in `[0,n)`, or if version is not in `[0,`[`versions`](Self::versions)`)`.
+    /// It will panic if p is not in `[0,n)`, or if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It returns the new version, i.e. <code>[versions](Self::versions)-1</code> after the call.
     /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine), [`update_lazy_value`](LazyNode::update_lazy_value) and [`lazy_update`](LazyNode::lazy_update) have constant time complexity.
     pub fn update(
         &mut self,
@@ -131,9 +310,14 @@ where
         left: usize,
         right: usize,
         value: &<T as Node>::Value,
-    ) {
+    ) -> VersionId {
+        span!(TRACE, "update", tree = "LazyPersistent", n = self.n, version, left, right);
+        let _op = self.last_op.measure();
+        assert_not_empty(self.n, "update");
         let new_root = self.update_helper(self.roots[version], left, right, value, 0, self.n - 1);
         self.roots.push(new_root);
+        self.history.push(Some(version));
+        self.roots.len() - 1
     }
 
     fn update_helper(
@@ -145,19 +329,32 @@ where
         i: usize,
         j: usize,
     ) -> usize {
+        let _depth = enter();
+        // Nodes with a pending lazy value have a stale value,
+        // so they are replaced by a pushed copy before being combined.
+        let curr_node = if self.nodes[curr_node].lazy_value().is_some() {
+            let x = self.nodes.len();
+            self.nodes.push(self.nodes[curr_node].clone());
+            record_allocations(1);
+            self.push(x, i, j);
+            x
+        } else {
+            curr_node
+        };
         if j < left || right < i {
             return curr_node;
         }
         let x = self.nodes.len();
         self.nodes.push(self.nodes[curr_node].clone());
+        record_allocations(1);
         if left <= i && j <= right {
             self.nodes[x].update_lazy_value(value);
             self.push(x, i, j);
             return x;
         }
-        let mid = (i + j) / 2;
+        let mid = usize::midpoint(i, j);
         let left_node = self.update_helper(
-            self.nodes[x].left_child().unwrap().get(),
+            self.nodes[x].left_child().unwrap(),
             left,
             right,
             value,
@@ -165,24 +362,53 @@ where
             mid,
         );
         let right_node = self.update_helper(
-            self.nodes[x].right_child().unwrap().get(),
+            self.nodes[x].right_child().unwrap(),
             left,
             right,
             value,
             mid + 1,
             j,
         );
-        self.nodes[x] = Node::combine(&self.nodes[left_node], &self.nodes[right_node]);
+        self.nodes[x] = combine(&self.nodes[left_node], &self.nodes[right_node]);
         self.nodes[x].set_children(left_node, right_node);
         x
     }
 
+    /// Returns the amount of elements of the segment tree.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.n
+    }
+
+    /// Returns `true` if the segment tree has no elements.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
     /// Returns the amount of different versions the current segment tree has. Essentially this will be how many calls to [`update`](Self::update) have happened. 
     #[allow(clippy::must_use_candidate)]
-    pub fn versions(&self) -> usize {
+    pub const fn versions(&self) -> usize {
         self.roots.len()
     }
 
+    /// Returns the versions of the segment tree in creation order, that is, <code>0..[versions](Self::versions)</code>, so they can be enumerated without tracking their amount.
+    /// ```
+    /// # use seg_tree::{LazyPersistent,utils::Sum,nodes::Node};
+    /// # let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+    /// let mut seg_tree = LazyPersistent::build(&nodes);
+    /// seg_tree.update(0, 3, 3, &4); // Adds 4 to the 4th element.
+    /// let sums: Vec<usize> = seg_tree
+    ///     .versions_iter()
+    ///     .map(|version| *seg_tree.query(version, 0, 9).unwrap().value())
+    ///     .collect();
+    /// assert_eq!(sums, [45, 49]);
+    /// ```
+    #[must_use]
+    pub const fn versions_iter(&self) -> Range<VersionId> {
+        0..self.roots.len()
+    }
+
     /// A method that finds the smallest prefix[^note] `u` such that `predicate(u.value(), value)` is `true`. The following must be true:
     /// - `predicate` is monotonic over prefixes[^note2].
     /// - `g` will satisfy the following, given segments `[i,j]` and `[i,k]` with `j<k` we have that `predicate([i,k].value(),value)` implies `predicate([j+1,k].value(),g([i,j].value(),value))`.
@@ -214,6 +440,9 @@ where
     /// # }
     /// ```
     ///
+    /// The pending lazy values of the nodes visited are pushed, so the search sees the values after every update.
+    /// It will **panic** if the segment tree has no elements.
+    ///
     /// [^note]: A prefix is a segment of the form `[0,i]`.
     ///
     /// [^note2]: Given two prefixes `u` and `v` if `u` is contained in `v` then `predicate(u.value(), value)` implies `predicate(v.value(), value)`.
@@ -228,6 +457,31 @@ where
         F: Fn(&<T as Node>::Value, &<T as Node>::Value) -> bool,
         G: Fn(&<T as Node>::Value, <T as Node>::Value) -> <T as Node>::Value,
     {
+        self.lower_bound_by_node(
+            version,
+            |node: &T, value: &<T as Node>::Value| predicate(node.value(), value),
+            |node: &T, value| g(node.value(), value),
+            value,
+        )
+    }
+
+    /// Same as [`lower_bound`](Self::lower_bound), but `predicate` and `g` receive the node of the segment instead of its value, so nodes with several fields can be searched by any of them.
+    /// The same conditions must be true, taking the node of each segment instead of its value.
+    ///
+    /// It will **panic** if the segment tree has no elements, or if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine), `predicate` and `g` have constant time complexity.
+    pub fn lower_bound_by_node<F, G>(
+        &mut self,
+        version: usize,
+        predicate: F,
+        g: G,
+        value: <T as Node>::Value,
+    ) -> usize
+    where
+        F: Fn(&T, &<T as Node>::Value) -> bool,
+        G: Fn(&T, <T as Node>::Value) -> <T as Node>::Value,
+    {
+        assert_not_empty(self.n, "search");
         self.lower_bound_helper(self.roots[version], 0, self.n - 1, predicate, g, value)
     }
     fn lower_bound_helper<F, G>(
@@ -239,33 +493,393 @@ where
         g: G,
         value: <T as Node>::Value,
     ) -> usize
+    where
+        F: Fn(&T, &<T as Node>::Value) -> bool,
+        G: Fn(&T, <T as Node>::Value) -> <T as Node>::Value,
+    {
+        let _depth = enter();
+        self.push(curr_node, i, j);
+        if i == j {
+            return i;
+        }
+        let mid = usize::midpoint(i, j);
+        let left_node = self.nodes[curr_node].left_child().unwrap();
+        let right_node = self.nodes[curr_node].right_child().unwrap();
+        self.push(left_node, i, mid);
+        let left = &self.nodes[left_node].get_inner();
+        if predicate(left, &value) {
+            self.lower_bound_helper(left_node, i, mid, predicate, g, value)
+        } else {
+            let value = g(left, value);
+            self.lower_bound_helper(right_node, mid + 1, j, predicate, g, value)
+        }
+    }
+
+    /// Mirror of [`lower_bound`](Self::lower_bound), it finds the smallest suffix[^note] `u` from the version of the segment tree such that `predicate(u.value(), value)` is `true`, and returns the index where `u` starts. The following must be true:
+    /// - `predicate` is monotonic over suffixes[^note2].
+    /// - `g` will satisfy the following, given segments `[j,k]` and `[i,k]` with `i<j` we have that `predicate([i,k].value(),value)` implies `predicate([i,j-1].value(),g([j,k].value(),value))`.
+    ///
+    /// The pending lazy values of the nodes visited are pushed, so the search sees the values after every update.
+    /// It will **panic** if the segment tree has no elements, or if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine), `predicate` and `g` have constant time complexity.
+    ///
+    /// [^note]: A suffix is a segment of the form `[i,n-1]`.
+    ///
+    /// [^note2]: Given two suffixes `u` and `v` if `u` is contained in `v` then `predicate(u.value(), value)` implies `predicate(v.value(), value)`.
+    pub fn upper_bound<F, G>(
+        &mut self,
+        version: usize,
+        predicate: F,
+        g: G,
+        value: <T as Node>::Value,
+    ) -> usize
     where
         F: Fn(&<T as Node>::Value, &<T as Node>::Value) -> bool,
         G: Fn(&<T as Node>::Value, <T as Node>::Value) -> <T as Node>::Value,
     {
+        assert_not_empty(self.n, "search");
+        self.upper_bound_helper(self.roots[version], 0, self.n - 1, predicate, g, value)
+    }
+    fn upper_bound_helper<F, G>(
+        &mut self,
+        curr_node: usize,
+        i: usize,
+        j: usize,
+        predicate: F,
+        g: G,
+        value: <T as Node>::Value,
+    ) -> usize
+    where
+        F: Fn(&<T as Node>::Value, &<T as Node>::Value) -> bool,
+        G: Fn(&<T as Node>::Value, <T as Node>::Value) -> <T as Node>::Value,
+    {
+        let _depth = enter();
+        self.push(curr_node, i, j);
         if i == j {
             return i;
         }
-        let mid = (i + j) / 2;
-        let left_node = if let Some(l) = self.nodes[curr_node].left_child() {
-            l.get()
+        let mid = usize::midpoint(i, j);
+        let left_node = self.nodes[curr_node].left_child().unwrap();
+        let right_node = self.nodes[curr_node].right_child().unwrap();
+        self.push(right_node, mid + 1, j);
+        let right_value = self.nodes[right_node].value();
+        if predicate(right_value, &value) {
+            self.upper_bound_helper(right_node, mid + 1, j, predicate, g, value)
         } else {
-            self.push(curr_node, i, j);
-            self.nodes[curr_node].left_child().unwrap().get()
+            let value = g(right_value, value);
+            self.upper_bound_helper(left_node, i, mid, predicate, g, value)
+        }
+    }
+
+    /// Same as [`lower_bound`](Self::lower_bound), but it finds the smallest segment `[left,k]` with `k` in `[left,right]` such that `predicate([left,k].value(), value)` is `true`, from the version of the segment tree, and returns `k`.
+    /// `predicate` and `g` must satisfy the same conditions, over the segments which start at left instead of the prefixes.
+    /// The range is split into the nodes which cover it, and the search only descends into the first one where `predicate` becomes `true`.
+    /// It returns None if and only if there's no such `k`, in particular if the range is empty.
+    /// It will **panic** if the segment tree has no elements, or if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine), `predicate` and `g` have constant time complexity.
+    pub fn lower_bound_in<F, G>(
+        &mut self,
+        version: usize,
+        left: usize,
+        right: usize,
+        predicate: F,
+        g: G,
+        value: <T as Node>::Value,
+    ) -> Option<usize>
+    where
+        F: Fn(&<T as Node>::Value, &<T as Node>::Value) -> bool,
+        G: Fn(&<T as Node>::Value, <T as Node>::Value) -> <T as Node>::Value,
+    {
+        assert_not_empty(self.n, "search");
+        let root = self.roots[version];
+        let fns = (&predicate, &g);
+        self.lower_bound_in_helper(root, 0, self.n - 1, (left, right), fns, value).ok()
+    }
+
+    /// Returns the index found in the intersection of `[i,j]` and `range`, or the value left for the rest of the range if there's none.
+    fn lower_bound_in_helper<F, G>(
+        &mut self,
+        curr_node: usize,
+        i: usize,
+        j: usize,
+        (left, right): (usize, usize),
+        (predicate, g): (&F, &G),
+        value: <T as Node>::Value,
+    ) -> Result<usize, <T as Node>::Value>
+    where
+        F: Fn(&<T as Node>::Value, &<T as Node>::Value) -> bool,
+        G: Fn(&<T as Node>::Value, <T as Node>::Value) -> <T as Node>::Value,
+    {
+        let _depth = enter();
+        if j < left || right < i {
+            return Err(value);
+        }
+        self.push(curr_node, i, j);
+        if left <= i && j <= right {
+            let curr_value = self.nodes[curr_node].value();
+            if !predicate(curr_value, &value) {
+                return Err(g(curr_value, value));
+            }
+            let predicate = |node: &T, value: &<T as Node>::Value| predicate(node.value(), value);
+            let g = |node: &T, value| g(node.value(), value);
+            return Ok(self.lower_bound_helper(curr_node, i, j, predicate, g, value));
+        }
+        let mid = usize::midpoint(i, j);
+        let left_node = self.nodes[curr_node].left_child().unwrap();
+        let right_node = self.nodes[curr_node].right_child().unwrap();
+        let (range, fns) = ((left, right), (predicate, g));
+        match self.lower_bound_in_helper(left_node, i, mid, range, fns, value) {
+            Ok(index) => Ok(index),
+            Err(value) => self.lower_bound_in_helper(right_node, mid + 1, j, range, fns, value),
+        }
+    }
+
+    /// Returns the counters of the last build, query or update of the segment tree, see [`OpStats`](crate::instrument::OpStats).
+    #[cfg(feature = "instrument")]
+    #[must_use]
+    pub fn stats(&self) -> crate::instrument::OpStats {
+        self.last_op.get()
+    }
+
+    /// Returns the memory used by the segment tree, see [`MemoryStats`].
+    /// The roots and metadata of each version are accounted for in [`overhead_bytes`](MemoryStats::overhead_bytes).
+    /// See [`memory_stats_by_version`](Self::memory_stats_by_version) for the nodes owned and shared by each version.
+    #[must_use]
+    pub fn memory_stats(&self) -> MemoryStats {
+        MemoryStats {
+            nodes: self.nodes.len(),
+            capacity: self.nodes.capacity(),
+            node_size: core::mem::size_of::<PersistentWrapper<T, I>>(),
+            versions: self.roots.len(),
+            overhead_bytes: self.roots.capacity() * core::mem::size_of::<usize>()
+                + self.history.reserved_bytes(),
+        }
+    }
+
+    /// Returns how many nodes the version introduced and how many it shares with its [parent](Self::version_parent), see [`VersionStats`].
+    /// It will **panic** if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(k)`, where `k` is the amount of nodes reachable from the version and its parent.
+    #[must_use]
+    pub fn version_stats(&self, version: VersionId) -> VersionStats {
+        let parent = self.history.parent(version).map(|parent| self.roots[parent]);
+        version_stats(&self.nodes, self.roots[version], parent)
+    }
+
+    /// Returns the [`version_stats`](Self::version_stats) of every version, in version order, so the nodes of [`memory_stats`](Self::memory_stats) can be broken down into the ones each version owns, its [`introduced`](VersionStats::introduced) nodes, and the ones it [`shares`](VersionStats::shared) with its parent.
+    /// It has time complexity of `O(v*m)`, where `v` is the amount of versions and `m` the amount of nodes reachable from a version and its parent.
+    #[must_use]
+    pub fn memory_stats_by_version(&self) -> Vec<VersionStats> {
+        self.versions_iter()
+            .map(|version| self.version_stats(version))
+            .collect()
+    }
+
+    /// Checks that every internal node of every version, with the lazy values pending from its ancestors applied, is the combination of its children, and that [`update_lazy_value`](LazyNode::update_lazy_value) and [`lazy_update`](LazyNode::lazy_update) keep their invariants.
+    /// A node whose [`lazy_update`](LazyNode::lazy_update) doesn't distribute over [`combine`](Node::combine) is caught where it happens instead of as wrong answers later on.
+    /// It's meant for tests and debug assertions.
+    /// It will **panic** at the first node which doesn't match.
+    /// Subtrees shared between versions are checked once per version, as the lazy values pending on them may differ, so it has time complexity of `O(v*n)`, where `v` is the amount of versions, assuming that [`combine`](Node::combine), [`update_lazy_value`](LazyNode::update_lazy_value) and [`lazy_update`](LazyNode::lazy_update) have constant time complexity.
+    pub fn validate(&self)
+    where
+        <T as Node>::Value: PartialEq,
+    {
+        let children = |curr_node: usize| {
+            let node = &self.nodes[curr_node];
+            node.left_child().zip(node.right_child())
         };
-        let right_node = self.nodes[curr_node].right_child().unwrap().get();
-        let left_value = self.nodes[left_node].value();
-        if predicate(left_value, &value) {
-            self.lower_bound_helper(left_node, i, mid, predicate, g, value)
-        } else {
-            let value = g(left_value, value);
-            self.lower_bound_helper(right_node, mid + 1, j, predicate, g, value)
+        if self.n == 0 {
+            return;
+        }
+        for &root in &self.roots {
+            validate_lazy(&self.nodes, &children, root, None, 0, self.n - 1);
         }
     }
+
+    /// Reserves memory for at least `q` more updates, so that long update sequences don't repeatedly reallocate and copy the nodes.
+    /// A range update creates at most `4*(log(n)+2)` nodes, counting the pushed children.
+    pub fn reserve_updates(&mut self, q: usize) {
+        #[allow(clippy::cast_possible_truncation)]
+        let nodes_per_update = 4 * (self.n.next_power_of_two().ilog2() as usize + 2);
+        self.nodes.reserve(q.saturating_mul(nodes_per_update));
+        self.roots.reserve(q);
+        self.history.reserve(q);
+    }
+
+    /// Releases the memory reserved for nodes and versions which isn't currently used.
+    /// Building reserves memory for `4n` nodes, which is often more than needed.
+    pub fn shrink_to_fit(&mut self) {
+        self.nodes.shrink_to_fit();
+        self.roots.shrink_to_fit();
+        self.history.shrink_to_fit();
+    }
+
+    /// Keeps only the given versions, discarding every other version and freeing every node which isn't reachable from the kept versions.
+    /// After this call version `i` is the version which was `versions[i]`, parents and labels of discarded versions are dropped.
+    /// It will **panic** if any version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(m)`, where `m` is the amount of nodes currently stored.
+    pub fn retain_versions(&mut self, versions: &[VersionId]) {
+        self.roots = versions.iter().map(|&version| self.roots[version]).collect();
+        self.history.retain(versions);
+        self.gc();
+    }
+
+    /// Returns the first version for which `predicate` applied to the result from the range `[left,right]` is `false`, or [`versions`](Self::versions) if there is none, binary searching the versions like [`partition_point`](slice::partition_point).
+    /// `predicate` must be `true` for a prefix of the versions and `false` for the rest, which is the case when the versions are a sequence of updates which make the predicate monotonic, e.g. the first version where the sum of `[left,right]` is at least `k`:
+    /// ```
+    /// # use seg_tree::{LazyPersistent,utils::Sum,nodes::Node};
+    /// # let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+    /// let mut seg_tree = LazyPersistent::build(&nodes); // [0,1,2,3,4,5,6,7,8,9] with Sum<usize> nodes
+    /// for i in 0..10 {
+    ///     seg_tree.update(i, i, i, &10); // Version i+1 has its first i+1 elements increased by 10.
+    /// }
+    /// let version = seg_tree.partition_versions(0, 2, |node| *node.value() < 25);
+    /// assert_eq!(version, 3); // [10,11,12] is the first to reach 25.
+    /// ```
+    /// It will **panic** if left or right are not in `[0,n)`, or if the range is empty.
+    /// It has time complexity of `O(log(v)*log(n))`, where `v` is the amount of versions, assuming that [`combine`](Node::combine), [`update_lazy_value`](LazyNode::update_lazy_value) and [`lazy_update`](LazyNode::lazy_update) have constant time complexity.
+    pub fn partition_versions<P>(&self, left: usize, right: usize, predicate: P) -> VersionId
+    where
+        P: Fn(&T) -> bool,
+    {
+        partition_versions(self.roots.len(), |version| {
+            predicate(&self.non_empty_query(version, left, right))
+        })
+    }
+
+    /// Returns the first version where `predicate` applied to the result from the range `[left,right]` is `true`, or None if there is none, see [`partition_versions`](Self::partition_versions).
+    /// `predicate` must be `false` for a prefix of the versions and `true` for the rest, e.g. the first version where the sum of `[left,right]` reaches a threshold:
+    /// ```
+    /// # use seg_tree::{LazyPersistent,utils::Sum,nodes::Node};
+    /// # let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+    /// let mut seg_tree = LazyPersistent::build(&nodes); // [0,1,2,3,4,5,6,7,8,9] with Sum<usize> nodes
+    /// for i in 0..10 {
+    ///     seg_tree.update(i, i, i, &10); // Version i+1 has its first i+1 elements increased by 10.
+    /// }
+    /// assert_eq!(seg_tree.first_version_where(0, 2, |node| *node.value() >= 25), Some(3));
+    /// assert_eq!(seg_tree.first_version_where(0, 2, |node| *node.value() >= 100), None);
+    /// ```
+    /// It will **panic** if left or right are not in `[0,n)`, or if the range is empty.
+    /// It has time complexity of `O(log(v)*log(n))`, where `v` is the amount of versions, assuming that [`combine`](Node::combine), [`update_lazy_value`](LazyNode::update_lazy_value) and [`lazy_update`](LazyNode::lazy_update) have constant time complexity.
+    pub fn first_version_where<P>(
+        &self,
+        left: usize,
+        right: usize,
+        predicate: P,
+    ) -> Option<VersionId>
+    where
+        P: Fn(&T) -> bool,
+    {
+        let version = self.partition_versions(left, right, |node| !predicate(node));
+        (version < self.roots.len()).then_some(version)
+    }
+
+    fn non_empty_query(&self, version: VersionId, left: usize, right: usize) -> T {
+        self.query(version, left, right)
+            .unwrap_or_else(|| panic!("range [{left}, {right}] is empty"))
+    }
+
+    /// Returns the values the p-th element has had across versions, the i-th value is the p-th element in version `i`.
+    /// The values are computed lazily, one version at a time, so they aren't cloned from the nodes, see [`into_value`](Node::into_value).
+    /// It will **panic** if p is not in `[0,n)`.
+    /// It has time complexity of `O(log(n))` per version, assuming that [`update_lazy_value`](LazyNode::update_lazy_value) and [`lazy_update`](LazyNode::lazy_update) have constant time complexity.
+    pub fn history(&self, p: usize) -> impl Iterator<Item = <T as Node>::Value> + '_ {
+        self.versions_iter()
+            .map(move |version| self.leaf_value(version, p))
+    }
+
+    /// Same as [`history`](Self::history), but it only returns the versions in which the p-th element was changed, that is, the versions created by the build and the versions whose p-th element differs from the one in their [parent](Self::version_parent), along with the value.
+    /// The pending lazy values make the nodes of a version differ from the ones of its parent even if the element didn't change, so elements are compared by value.
+    /// It will **panic** if p is not in `[0,n)`.
+    /// It has time complexity of `O(log(n))` per version, assuming that [`update_lazy_value`](LazyNode::update_lazy_value) and [`lazy_update`](LazyNode::lazy_update) have constant time complexity.
+    pub fn history_changes(
+        &self,
+        p: usize,
+    ) -> impl Iterator<Item = (VersionId, <T as Node>::Value)> + '_
+    where
+        <T as Node>::Value: PartialEq,
+    {
+        self.versions_iter().filter_map(move |version| {
+            let value = self.leaf_value(version, p);
+            let changed = self
+                .history
+                .parent(version)
+                .is_none_or(|parent| self.leaf_value(parent, p) != value);
+            changed.then_some((version, value))
+        })
+    }
+
+    fn leaf_value(&self, version: VersionId, p: usize) -> <T as Node>::Value {
+        assert!(p < self.n, "index {p} out of range for length {}", self.n);
+        self.non_empty_query(version, p, p).into_value()
+    }
+
+    /// Discards every version after version, so version becomes the last version, and frees the nodes which aren't reachable from the kept versions.
+    /// It's meant for undo/redo, where the redo branch must be discarded when there is a new update after an undo.
+    /// Pushing a lazy value stores the new children of a node after it, so the nodes of the kept versions aren't a prefix of the arena and they are compacted as in [`gc`](Self::gc).
+    /// It will **panic** if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(m)`, where `m` is the amount of nodes currently stored.
+    pub fn rollback_to(&mut self, version: VersionId) {
+        self.history.assert_exists(version);
+        self.roots.truncate(version + 1);
+        self.history.truncate(version + 1);
+        self.gc();
+    }
+
+    /// Creates a new version identical to version, whose parent is version, and returns it.
+    /// It's useful to start a new branch of the history which can be labelled before any update, see [`label_version`](Self::label_version).
+    /// It will **panic** if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(1)`.
+    pub fn branch_from(&mut self, version: VersionId) -> VersionId {
+        self.roots.push(self.roots[version]);
+        self.history.push(Some(version));
+        self.roots.len() - 1
+    }
+
+    /// Returns the version from which version was created by [`update`](Self::update) or [`branch_from`](Self::branch_from), or None if it was created by the build.
+    /// It will **panic** if version is not in <code>[0,[versions](Self::versions))</code>.
+    #[must_use]
+    pub fn version_parent(&self, version: VersionId) -> Option<VersionId> {
+        self.history.parent(version)
+    }
+
+    /// Labels version with label, so it can be found with [`find_version`](Self::find_version).
+    /// A label refers to a single version, so it returns the version the label referred to before, if any.
+    /// It will **panic** if version is not in <code>[0,[versions](Self::versions))</code>.
+    pub fn label_version(
+        &mut self,
+        version: VersionId,
+        label: impl Into<String>,
+    ) -> Option<VersionId> {
+        self.history.set_label(label.into(), version)
+    }
+
+    /// Returns the version labelled with label, see [`label_version`](Self::label_version).
+    #[must_use]
+    pub fn find_version(&self, label: &str) -> Option<VersionId> {
+        self.history.find(label)
+    }
+
+    /// Frees every node which isn't reachable from any version, compacting the remaining nodes.
+    /// It has time complexity of `O(m)`, where `m` is the amount of nodes currently stored.
+    pub fn gc(&mut self) {
+        span!(
+            DEBUG,
+            "gc",
+            tree = "LazyPersistent",
+            n = self.n,
+            versions = self.roots.len(),
+            nodes = self.nodes.len()
+        );
+        let _op = self.last_op.measure();
+        compact(&mut self.nodes, &mut self.roots, None);
+    }
 }
 
-impl<T> core::fmt::Debug for LazyPersistent<T>
+#[allow(clippy::missing_fields_in_debug)]
+impl<T, I, A> core::fmt::Debug for LazyPersistent<T, I, A>
 where
+    I: NodeIndex,
+    A: Arena<PersistentWrapper<T, I>>,
     T: core::fmt::Debug + LazyNode,
 {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
@@ -277,6 +891,9 @@ where
                 "nodes",
                 &as_dbg_tree(&self.nodes, {
                     |nodes, f| {
+                        if self.n == 0 {
+                            return;
+                        }
                         let mut visited = BitVec::from_elem(len, false);
                         for root_node in &self.roots {
                             lazy_persistent_visitor(
@@ -297,17 +914,20 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::{nodes::Node, segment_tree::lazy_persistent::LazyPersistent, utils::Sum};
+    use crate::{
+        nodes::Node, segment_tree::lazy_persistent::LazyPersistent, testing::small_values,
+        utils::Sum,
+    };
     #[test]
     fn non_empty_query_returns_some() {
         let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
-        let mut segment_tree = LazyPersistent::build(&nodes);
+        let segment_tree = LazyPersistent::build(&nodes);
         assert!(segment_tree.query(0, 0, 10).is_some());
     }
     #[test]
     fn empty_query_returns_none() {
         let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
-        let mut segment_tree = LazyPersistent::build(&nodes);
+        let segment_tree = LazyPersistent::build(&nodes);
         assert!(segment_tree.query(0, 10, 0).is_none());
     }
     #[test]
@@ -333,10 +953,154 @@ mod tests {
     #[test]
     fn query_works() {
         let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
-        let mut segment_tree = LazyPersistent::build(&nodes);
+        let segment_tree = LazyPersistent::build(&nodes);
         assert_eq!(segment_tree.query(0, 0, 10).unwrap().value(), &55);
     }
 
+    #[test]
+    fn wider_index_works() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree: LazyPersistent<_, u64> = LazyPersistent::build_with_index(&nodes);
+        segment_tree.update(0, 0, 10, &1);
+        assert_eq!(segment_tree.query(0, 0, 10).unwrap().value(), &55);
+        assert_eq!(segment_tree.query(1, 0, 10).unwrap().value(), &66);
+    }
+
+    #[test]
+    fn shrink_to_fit_works() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = LazyPersistent::build(&nodes);
+        let stats = segment_tree.memory_stats();
+        assert_eq!(stats.nodes, 21);
+        assert_eq!(stats.versions, 1);
+        assert!(stats.capacity >= 44);
+        segment_tree.shrink_to_fit();
+        let stats = segment_tree.memory_stats();
+        assert_eq!(stats.capacity, stats.nodes);
+        assert!(stats.reserved_bytes() >= stats.nodes * stats.node_size);
+    }
+
+    #[test]
+    fn memory_stats_by_version_works() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = LazyPersistent::build(&nodes);
+        segment_tree.update(0, 0, 10, &1);
+        segment_tree.update(1, 2, 5, &1);
+        let stats = segment_tree.memory_stats_by_version();
+        assert_eq!(stats.len(), 3);
+        assert_eq!((stats[0].introduced, stats[0].shared), (21, 0));
+        for stats in &stats {
+            assert_eq!(stats.introduced + stats.shared, stats.nodes);
+        }
+        // The copies made to push the pending lazy values aren't reachable from any version until they are collected.
+        let owned: usize = stats.iter().map(|stats| stats.introduced).sum();
+        assert!(owned < segment_tree.memory_stats().nodes);
+        segment_tree.gc();
+        let stats = segment_tree.memory_stats_by_version();
+        let owned: usize = stats.iter().map(|stats| stats.introduced).sum();
+        assert_eq!(owned, segment_tree.memory_stats().nodes);
+    }
+
+    #[test]
+    fn gc_works() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = LazyPersistent::build(&nodes);
+        segment_tree.update(0, 0, 10, &1);
+        segment_tree.update(0, 2, 5, &1);
+        let before = segment_tree.memory_stats().nodes;
+        segment_tree.gc();
+        assert_eq!(segment_tree.memory_stats().nodes, before);
+        segment_tree.retain_versions(&[2]);
+        assert_eq!(segment_tree.versions(), 1);
+        assert!(segment_tree.memory_stats().nodes < before);
+        assert_eq!(segment_tree.query(0, 0, 10).unwrap().value(), &59);
+        assert_eq!(segment_tree.query(0, 3, 4).unwrap().value(), &9);
+    }
+
+    #[test]
+    fn version_metadata_works() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = LazyPersistent::build(&nodes);
+        let branch = segment_tree.branch_from(0);
+        segment_tree.label_version(branch, "draft");
+        segment_tree.update(branch, 0, 10, &1);
+        segment_tree.label_version(2, "draft");
+        assert_eq!(segment_tree.version_parent(2), Some(branch));
+        assert_eq!(segment_tree.version_parent(branch), Some(0));
+        let draft = segment_tree.find_version("draft").unwrap();
+        assert_eq!(segment_tree.query(draft, 0, 10).unwrap().value(), &66);
+        segment_tree.retain_versions(&[2]);
+        assert_eq!(segment_tree.version_parent(0), None);
+        assert_eq!(segment_tree.find_version("draft"), Some(0));
+    }
+
+    #[test]
+    fn history_works() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = LazyPersistent::build(&nodes);
+        segment_tree.update(0, 0, 10, &1);
+        segment_tree.update(0, 2, 5, &2);
+        segment_tree.update(0, 3, 3, &3);
+        assert_eq!(segment_tree.history(3).collect::<Vec<_>>(), vec![3, 4, 5, 6]);
+        assert_eq!(segment_tree.history(0).collect::<Vec<_>>(), vec![0, 1, 0, 0]);
+        let changes: Vec<(usize, usize)> = segment_tree.history_changes(0).collect();
+        assert_eq!(changes, vec![(0, 0), (1, 1)]);
+        let changes: Vec<(usize, usize)> = segment_tree.history_changes(3).collect();
+        assert_eq!(changes, vec![(0, 3), (1, 4), (2, 5), (3, 6)]);
+    }
+
+    #[test]
+    fn rollback_to_works() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = LazyPersistent::build(&nodes);
+        segment_tree.update(0, 0, 10, &1);
+        let nodes_before = segment_tree.memory_stats().nodes;
+        segment_tree.update(0, 0, 10, &2);
+        segment_tree.rollback_to(1);
+        assert_eq!(segment_tree.versions(), 2);
+        assert_eq!(segment_tree.memory_stats().nodes, nodes_before);
+        segment_tree.update(0, 0, 10, &3);
+        assert_eq!(segment_tree.query(2, 0, 10).unwrap().value(), &88);
+        assert_eq!(segment_tree.query(1, 0, 10).unwrap().value(), &66);
+        assert_eq!(segment_tree.versions(), 3);
+    }
+
+    #[test]
+    #[should_panic = "version 18446744073709551615 doesn't exist, there are 1 versions"]
+    fn rollback_to_checks_version() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = LazyPersistent::build(&nodes);
+        segment_tree.rollback_to(usize::MAX);
+    }
+
+    #[test]
+    fn query_doesnt_allocate() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = LazyPersistent::build(&nodes);
+        segment_tree.update(0, 0, 10, &1);
+        segment_tree.update(1, 3, 7, &2);
+        let before = segment_tree.memory_stats().nodes;
+        for i in 0..=10 {
+            for j in i..=10 {
+                let expected: usize = (i..=j).map(|x| x + 1).sum();
+                assert_eq!(segment_tree.query(1, i, j).unwrap().value(), &expected);
+            }
+        }
+        assert_eq!(segment_tree.query(2, 3, 3).unwrap().value(), &6);
+        assert_eq!(segment_tree.memory_stats().nodes, before);
+    }
+
+    #[test]
+    fn reserve_updates_works() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = LazyPersistent::build_with_capacity(&nodes, 100);
+        let capacity = segment_tree.memory_stats().capacity;
+        for i in 0..100 {
+            segment_tree.update(i, i % 11, 10 - i % 11, &i);
+        }
+        assert_eq!(segment_tree.memory_stats().capacity, capacity);
+    }
+
     #[test]
     fn dbg_works() {
         let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
@@ -346,4 +1110,111 @@ mod tests {
         let expected = "LazyPersistent { n: 11, root_nodes: [20, 21], nodes: {[0, 10]: Sum { value: 55, lazy_value: None }, [0, 5]: Sum { value: 15, lazy_value: None }, [0, 2]: Sum { value: 3, lazy_value: None }, [0, 1]: Sum { value: 1, lazy_value: None }, [0, 0]: Sum { value: 0, lazy_value: None }, [1, 1]: Sum { value: 1, lazy_value: None }, [2, 2]: Sum { value: 2, lazy_value: None }, [3, 5]: Sum { value: 12, lazy_value: None }, [3, 4]: Sum { value: 7, lazy_value: None }, [3, 3]: Sum { value: 3, lazy_value: None }, [4, 4]: Sum { value: 4, lazy_value: None }, [5, 5]: Sum { value: 5, lazy_value: None }, [6, 10]: Sum { value: 40, lazy_value: None }, [6, 8]: Sum { value: 21, lazy_value: None }, [6, 7]: Sum { value: 13, lazy_value: None }, [6, 6]: Sum { value: 6, lazy_value: None }, [7, 7]: Sum { value: 7, lazy_value: None }, [8, 8]: Sum { value: 8, lazy_value: None }, [9, 10]: Sum { value: 19, lazy_value: None }, [9, 9]: Sum { value: 9, lazy_value: None }, [10, 10]: Sum { value: 10, lazy_value: None }, [0, 10]: Sum { value: 59, lazy_value: None }, [0, 5]: Sum { value: 19, lazy_value: None }, [0, 2]: Sum { value: 7, lazy_value: None }, [0, 1]: Sum { value: 5, lazy_value: None }, [0, 0]: Sum { value: 0, lazy_value: Some(2) }, [1, 1]: Sum { value: 1, lazy_value: Some(2) }} }";
         assert_eq!(dbg, expected);
     }
+
+    #[test]
+    fn validate_works() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = LazyPersistent::build(&nodes);
+        segment_tree.update(0, 0, 5, &1);
+        segment_tree.update(1, 3, 9, &2);
+        segment_tree.update(0, 2, 2, &3);
+        segment_tree.validate();
+        let _ = segment_tree.lower_bound(2, |a, b| a >= b, |a, b| b - a, 20);
+        segment_tree.validate();
+    }
+
+    #[test]
+    fn empty_tree_works() {
+        let mut segment_tree = LazyPersistent::<Sum<usize>>::build(&[]);
+        assert_eq!(segment_tree.len(), 0);
+        assert!(segment_tree.is_empty());
+        assert_eq!(segment_tree.versions(), 1);
+        assert!(segment_tree.query(0, 0, 0).is_none());
+        let branch = segment_tree.branch_from(0);
+        assert!(segment_tree.query(branch, 0, 0).is_none());
+        segment_tree.gc();
+        segment_tree.validate();
+    }
+
+    #[test]
+    #[should_panic = "can't update an empty segment tree"]
+    fn update_on_empty_tree_panics() {
+        let mut segment_tree = LazyPersistent::<Sum<usize>>::build(&[]);
+        segment_tree.update(0, 0, 0, &1);
+    }
+
+    #[test]
+    fn query_with_range_works() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = LazyPersistent::build(&nodes);
+        segment_tree.update(0, 8, 15, &1);
+        let (range, node) = segment_tree.query_with_range(1, 6, 15).unwrap();
+        assert_eq!(range, 6..11);
+        assert_eq!(node.value(), &(40 + 3));
+        assert_eq!(segment_tree.query_with_range(0, 6, 15).unwrap().1.value(), &40);
+        assert!(segment_tree.query_with_range(1, 11, 15).is_none());
+    }
+
+    #[test]
+    #[should_panic = "index out of bounds"]
+    fn query_with_range_checks_version() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let segment_tree = LazyPersistent::build(&nodes);
+        let _ = segment_tree.query_with_range(1, 11, 15);
+    }
+
+    #[test]
+    fn lower_bound_in_works() {
+        let mut values: Vec<usize> = small_values(13);
+        let nodes: Vec<Sum<usize>> = values.iter().map(Sum::initialize).collect();
+        let mut segment_tree = LazyPersistent::build(&nodes);
+        segment_tree.update(0, 3, 9, &2);
+        values[3..=9].iter_mut().for_each(|value| *value += 2);
+        let expected = |left: usize, right: usize, target: usize| {
+            (left..=right).find(|&k| values[left..=k].iter().sum::<usize>() >= target)
+        };
+        let predicate = |sum: &usize, value: &usize| *sum >= *value;
+        let g = |sum: &usize, value: usize| value.saturating_sub(*sum);
+        for left in 0..values.len() {
+            for right in 0..values.len() {
+                for target in [1, 5, 20, 60] {
+                    assert_eq!(
+                        segment_tree.lower_bound_in(1, left, right, predicate, g, target),
+                        expected(left, right, target)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn filled_works() {
+        let mut segment_tree = LazyPersistent::<Sum<usize>>::filled(5, &2);
+        let version = segment_tree.update(0, 1, 3, &1);
+        assert_eq!(segment_tree.query(0, 0, 4).unwrap().value(), &10);
+        assert_eq!(segment_tree.query(version, 0, 4).unwrap().value(), &13);
+    }
+
+    #[test]
+    fn upper_bound_works() {
+        let mut values: Vec<usize> = small_values(13);
+        let nodes: Vec<Sum<usize>> = values.iter().map(Sum::initialize).collect();
+        let mut segment_tree = LazyPersistent::build(&nodes);
+        segment_tree.update(0, 3, 9, &2);
+        values[3..=9].iter_mut().for_each(|value| *value += 2);
+        let expected = |target: usize| {
+            (0..values.len())
+                .rev()
+                .find(|&i| values[i..].iter().sum::<usize>() >= target)
+                .unwrap_or(0)
+        };
+        let predicate = |sum: &usize, value: &usize| *sum >= *value;
+        let g = |sum: &usize, value: usize| value.saturating_sub(*sum);
+        for target in 0..70 {
+            assert_eq!(
+                segment_tree.upper_bound(1, predicate, g, target),
+                expected(target)
+            );
+        }
+    }
 }
@@ -1,55 +1,189 @@
-use core::mem::MaybeUninit;
+use alloc::{vec, vec::Vec};
+use core::{
+    marker::PhantomData,
+    mem::MaybeUninit,
+    ops::{Range, RangeInclusive},
+};
 
 use crate::{
-    internal_utils::dbg_utils::{as_dbg_tree, recursive_visitor},
+    codec::Codec,
+    internal_utils::{
+        codec_utils::{decode_values, encode_values},
+        dbg_utils::{as_dbg_tree, display_values, recursive_visitor},
+        empty_utils::assert_not_empty,
+        instrument_utils::{combine, enter, record_allocations, record_push, LastOp},
+        leaf_utils::{
+            fill_padding, into_leaf_nodes, into_leaf_values, lazy_leaf_values, lazy_leaves_eq,
+        },
+        observer_utils::Observers,
+        range_utils::{assert_in_bounds, clamp},
+        tracing_utils::span,
+        validate_utils::validate_lazy,
+    },
     nodes::{LazyNode, Node},
+    segment_tree::{MemoryStats, Recursive},
+    storage::Storage,
 };
 
 /// Lazy segment tree with range queries and range updates.
 /// It uses `O(n)` space, assuming that each node uses `O(1)` space.
-pub struct LazyRecursive<T> {
-    nodes: Vec<T>,
+/// The nodes are stored in `S`, see [`Storage`].
+pub struct LazyRecursive<T, S = Vec<T>> {
+    nodes: S,
     n: usize,
+    observers: Observers<T>,
+    _node: PhantomData<T>,
+    last_op: LastOp,
 }
 
 impl<T: LazyNode + Clone> LazyRecursive<T> {
     /// Builds lazy segment tree from slice, each element of the slice will correspond to a leaf of the segment tree.
     /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
     pub fn build(values: &[T]) -> Self {
+        Self::build_in(values, ())
+    }
+
+    /// Builds segment tree with n elements, each of them equal to value.
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn filled(n: usize, value: &<T as Node>::Value) -> Self {
+        Self::build(&vec![T::initialize(value); n])
+    }
+
+    /// Same as [`filled`](LazyRecursive::filled), but every element is the [`Default`] value, e.g. `0` for [`Sum`](crate::utils::Sum) nodes.
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn with_len(n: usize) -> Self
+    where
+        <T as Node>::Value: Default,
+    {
+        Self::filled(n, &<T as Node>::Value::default())
+    }
+
+    /// Builds segment tree with n elements, the i-th of them equal to `f(i)`, without collecting the values first.
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) and `f` have constant time complexity.
+    #[must_use]
+    pub fn build_from_fn<F>(n: usize, mut f: F) -> Self
+    where
+        F: FnMut(usize) -> <T as Node>::Value,
+    {
+        Self::build_from((0..n).map(|i| T::initialize(&f(i))), ())
+    }
+
+    /// Consumes the segment tree, returning the values of its leaves in order, see [`into_value`](Node::into_value).
+    /// Every pending lazy value is applied first, and it's only available for the default storage, as the nodes are moved out of it.
+    /// It has time complexity of `O(n)`, assuming that [`update_lazy_value`](LazyNode::update_lazy_value) and [`lazy_update`](LazyNode::lazy_update) have constant time complexity.
+    #[must_use]
+    pub fn into_values(mut self) -> Vec<T::Value> {
+        if self.n > 0 {
+            self.push_all(0, 0, self.n - 1);
+        }
+        into_leaf_values(self.nodes, self.n)
+    }
+
+    /// Consumes the segment tree, returning its leaves in order with every pending lazy value applied.
+    pub(crate) fn into_leaves(mut self) -> Vec<T> {
+        if self.n > 0 {
+            self.push_all(0, 0, self.n - 1);
+        }
+        into_leaf_nodes(self.nodes, self.n)
+    }
+
+    /// Converts a [`Recursive`] segment tree into a lazy segment tree with the same values, by wrapping each of its nodes into `T`, e.g. [`LazySetWrapper`](crate::utils::LazySetWrapper).
+    /// The nodes aren't combined again, so wrapping the combination of two nodes must give the same node as combining them after wrapping them, which holds for wrappers which delegate [`combine`](Node::combine) to the wrapped node.
+    /// The callbacks subscribed with [`on_update`](Recursive::on_update) are dropped.
+    /// It has time complexity of `O(n)`.
+    /// ```
+    /// # use seg_tree::{LazyRecursive,Recursive,utils::{LazySetWrapper,Min},nodes::Node};
+    /// # let nodes: Vec<Min<usize>> = (0..10).map(|x| Min::initialize(&x)).collect();
+    /// let seg_tree = Recursive::build(&nodes); // [0,1,2,3,4,5,6,7,8,9] with Min<usize> nodes
+    /// let mut seg_tree: LazyRecursive<LazySetWrapper<_>> = LazyRecursive::from_recursive(seg_tree);
+    /// seg_tree.update(0, 4, &7);
+    /// assert_eq!(seg_tree.query(0, 9).unwrap().value(), &5);
+    /// ```
+    #[must_use]
+    pub fn from_recursive<U>(segment_tree: Recursive<U>) -> Self
+    where
+        U: Node + Clone,
+        T: From<U>,
+    {
+        let (nodes, n) = segment_tree.into_parts();
+        Self {
+            // Every node of the buffer is initialized, including the padding, so all of them are converted.
+            nodes: nodes.into_iter().map(T::from).collect(),
+            n,
+            observers: Observers::default(),
+            last_op: LastOp::default(),
+            _node: PhantomData,
+        }
+    }
+
+    fn push_all(&mut self, curr_node: usize, i: usize, j: usize) {
+        let _depth = enter();
+        self.push(curr_node, i, j);
+        if i != j {
+            let mid = usize::midpoint(i, j);
+            self.push_all(2 * curr_node + 1, i, mid);
+            self.push_all(2 * curr_node + 2, mid + 1, j);
+        }
+    }
+}
+
+impl<T: LazyNode + Clone, S: Storage<T>> LazyRecursive<T, S> {
+    /// Same as [`build`](LazyRecursive::build), but the nodes are stored in `S` allocated with `allocator`, see [`Storage`].
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn build_in(values: &[T], allocator: S::Allocator) -> Self {
+        Self::build_from(values.iter().cloned(), allocator)
+    }
+
+    /// Builds the segment tree from the leaves, in order.
+    fn build_from<I>(mut values: I, allocator: S::Allocator) -> Self
+    where
+        I: ExactSizeIterator<Item = T>,
+    {
+        span!(DEBUG, "build", tree = "LazyRecursive", n = values.len());
+        let last_op = LastOp::default();
+        let _op = last_op.measure();
         let n = values.len();
-        if n == 0 {
-            return Self {
-                nodes: Vec::new(),
-                n,
-            };
-        }
-        let mut nodes = Vec::with_capacity(4 * n);
-        unsafe { nodes.set_len(4 * n) };
-        Self::build_helper(0, 0, n - 1, values, &mut nodes);
-        let ptr = nodes.as_mut_ptr();
-        core::mem::forget(nodes);
-        let nodes = unsafe { Vec::from_raw_parts(ptr.cast::<T>(), 4 * n, 4 * n) };
-        Self { nodes, n }
-    }
-
-    fn build_helper(
+        let mut nodes = S::allocate(4 * n, allocator);
+        record_allocations(4 * n);
+        if n > 0 {
+            Self::build_helper(0, 0, n - 1, &mut values, &mut nodes);
+        }
+        fill_padding(&mut nodes, n);
+        // SAFETY: `build_helper` initializes the nodes of the tree and `fill_padding` the rest of them.
+        let nodes = unsafe { S::assume_init(nodes) };
+        Self {
+            nodes,
+            n,
+            observers: Observers::default(),
+            _node: PhantomData,
+            last_op,
+        }
+    }
+
+    fn build_helper<I>(
         curr_node: usize,
         i: usize,
         j: usize,
-        values: &[T],
+        values: &mut I,
         nodes: &mut [MaybeUninit<T>],
-    ) {
+    ) where
+        I: Iterator<Item = T>,
+    {
+        let _depth = enter();
         if i == j {
-            nodes[curr_node].write(values[i].clone());
+            // The leaves are visited in order, so the i-th value is the next one.
+            nodes[curr_node].write(values.next().unwrap());
             return;
         }
-        let mid = (i + j) / 2;
+        let mid = usize::midpoint(i, j);
         let left_node = 2 * curr_node + 1;
         let right_node = 2 * curr_node + 2;
         Self::build_helper(left_node, i, mid, values, nodes);
         Self::build_helper(right_node, mid + 1, j, values, nodes);
         let (top_nodes, bottom_nodes) = nodes.split_at_mut(curr_node + 1);
-        top_nodes[curr_node].write(Node::combine(
+        top_nodes[curr_node].write(combine(
             unsafe { bottom_nodes[left_node - curr_node - 1].assume_init_ref() },
             unsafe { bottom_nodes[right_node - curr_node - 1].assume_init_ref() },
         ));
@@ -60,6 +194,7 @@ impl<T: LazyNode + Clone> LazyRecursive<T> {
         let (parent_slice, sons_slice) = self.nodes.split_at_mut(u + 1);
         if let Some(value) = parent_slice[u].lazy_value() {
             if i != j {
+                record_push();
                 sons_slice[u].update_lazy_value(value); // At 2*u + 1 - (u + 1)
                 sons_slice[u + 1].update_lazy_value(value); // At 2*u + 2 - (u + 1)
             }
@@ -71,7 +206,11 @@ impl<T: LazyNode + Clone> LazyRecursive<T> {
     /// It will panic if `i` or `j` is not in `[0,n)`.
     /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine), [`update_lazy_value`](LazyNode::update_lazy_value) and [`lazy_update`](LazyNode::lazy_update) have constant time complexity.
     pub fn update(&mut self, i: usize, j: usize, value: &<T as Node>::Value) {
+        span!(TRACE, "update", tree = "LazyRecursive", n = self.n, left = i, right = j);
+        let _op = self.last_op.measure();
+        assert_not_empty(self.n, "update");
         self.update_helper(i, j, value, 0, 0, self.n - 1);
+        self.observers.notify(i..=j, value);
     }
 
     fn update_helper(
@@ -83,6 +222,7 @@ impl<T: LazyNode + Clone> LazyRecursive<T> {
         i: usize,
         j: usize,
     ) {
+        let _depth = enter();
         if self.nodes[curr_node].lazy_value().is_some() {
             self.push(curr_node, i, j);
         }
@@ -94,22 +234,112 @@ impl<T: LazyNode + Clone> LazyRecursive<T> {
             self.push(curr_node, i, j);
             return;
         }
-        let mid = (i + j) / 2;
+        let mid = usize::midpoint(i, j);
         let left_node = 2 * curr_node + 1;
         let right_node = 2 * curr_node + 2;
         self.update_helper(left, right, value, left_node, i, mid);
         self.update_helper(left, right, value, right_node, mid + 1, j);
-        self.nodes[curr_node] = Node::combine(&self.nodes[left_node], &self.nodes[right_node]);
+        self.nodes[curr_node] = combine(&self.nodes[left_node], &self.nodes[right_node]);
+    }
+
+    /// Sets the p-th element to value, instead of applying value to it as [`update`](Self::update) does, so the update can be undone by setting the previous value back, see [`Journaled`](crate::Journaled).
+    /// It will panic if `p` is not in `[0,n)`.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine), [`update_lazy_value`](LazyNode::update_lazy_value) and [`lazy_update`](LazyNode::lazy_update) have constant time complexity.
+    pub fn set(&mut self, p: usize, value: &<T as Node>::Value) {
+        span!(TRACE, "set", tree = "LazyRecursive", n = self.n, p);
+        let _op = self.last_op.measure();
+        assert_not_empty(self.n, "set");
+        assert_in_bounds(p, self.n);
+        self.set_helper(p, value, 0, 0, self.n - 1);
+        self.observers.notify(p..=p, value);
+    }
+
+    fn set_helper(
+        &mut self,
+        p: usize,
+        value: &<T as Node>::Value,
+        curr_node: usize,
+        i: usize,
+        j: usize,
+    ) {
+        let _depth = enter();
+        if i == j {
+            self.nodes[curr_node] = T::initialize(value);
+            return;
+        }
+        if self.nodes[curr_node].lazy_value().is_some() {
+            self.push(curr_node, i, j);
+        }
+        let mid = usize::midpoint(i, j);
+        let left_node = 2 * curr_node + 1;
+        let right_node = 2 * curr_node + 2;
+        // The other child isn't visited, so its pending value is applied here before combining it.
+        if p <= mid {
+            self.set_helper(p, value, left_node, i, mid);
+            if self.nodes[right_node].lazy_value().is_some() {
+                self.push(right_node, mid + 1, j);
+            }
+        } else {
+            self.set_helper(p, value, right_node, mid + 1, j);
+            if self.nodes[left_node].lazy_value().is_some() {
+                self.push(left_node, i, mid);
+            }
+        }
+        self.nodes[curr_node] = combine(&self.nodes[left_node], &self.nodes[right_node]);
     }
 
     /// Returns the result from the range `[left,right]`.
-    /// It returns None if and only if range is empty.
+    /// It returns None if and only if range is empty or the segment tree has no elements.
     /// It will **panic** if `left` or `right` are not in `[0,n)`.
     /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine), [`update_lazy_value`](LazyNode::update_lazy_value) and [`lazy_update`](LazyNode::lazy_update) have constant time complexity.
     pub fn query(&mut self, left: usize, right: usize) -> Option<T> {
+        span!(TRACE, "query", tree = "LazyRecursive", n = self.n, left, right);
+        let _op = self.last_op.measure();
+        if self.n == 0 {
+            return None;
+        }
         self.query_helper(left, right, 0, 0, self.n - 1)
     }
 
+    /// Same as [`query`](Self::query), but the range is clamped to `[0,n)` instead of **panicking** when it's out of bounds, and the range that was actually aggregated is returned with the result, as a half-open range.
+    /// It returns None if and only if the clamped range is empty.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine), [`update_lazy_value`](LazyNode::update_lazy_value) and [`lazy_update`](LazyNode::lazy_update) have constant time complexity.
+    pub fn query_with_range(&mut self, left: usize, right: usize) -> Option<(Range<usize>, T)> {
+        let range = clamp(left, right, self.n)?;
+        self.query(range.start, range.end - 1)
+            .map(|node| (range, node))
+    }
+
+    /// Same as [`query_with_range`](Self::query_with_range), but only the result is returned.
+    /// It returns None if and only if the range clamped to `[0,n)` is empty.
+    pub fn query_clamped(&mut self, left: usize, right: usize) -> Option<T> {
+        self.query_with_range(left, right).map(|(_, node)| node)
+    }
+
+    /// Returns the node which covers exactly the segment `[i,j]`, e.g. to implement custom descents on top of the segment tree.
+    /// The pending lazy values of the nodes in the path to it are pushed, so its value is up to date, but its descendants may still have pending values.
+    /// It returns None if and only if no node covers exactly `[i,j]`, which is always the case if `i>j` or `j` is not in `[0,n)`.
+    /// It has time complexity of `O(log(n))`, assuming that [`update_lazy_value`](LazyNode::update_lazy_value) and [`lazy_update`](LazyNode::lazy_update) have constant time complexity.
+    pub fn get_segment(&mut self, i: usize, j: usize) -> Option<&T> {
+        if i > j || j >= self.n {
+            return None;
+        }
+        let (mut curr_node, mut l, mut r) = (0, 0, self.n - 1);
+        self.push(curr_node, l, r);
+        while (l, r) != (i, j) {
+            let mid = usize::midpoint(l, r);
+            if j <= mid {
+                (curr_node, r) = (2 * curr_node + 1, mid);
+            } else if mid < i {
+                (curr_node, l) = (2 * curr_node + 2, mid + 1);
+            } else {
+                return None;
+            }
+            self.push(curr_node, l, r);
+        }
+        Some(&self.nodes[curr_node])
+    }
+
     fn query_helper(
         &mut self,
         left: usize,
@@ -118,10 +348,11 @@ impl<T: LazyNode + Clone> LazyRecursive<T> {
         i: usize,
         j: usize,
     ) -> Option<T> {
+        let _depth = enter();
         if j < left || right < i {
             return None;
         }
-        let mid = (i + j) / 2;
+        let mid = usize::midpoint(i, j);
         let left_node = 2 * curr_node + 1;
         let right_node = 2 * curr_node + 2;
         if self.nodes[curr_node].lazy_value().is_some() {
@@ -134,7 +365,7 @@ impl<T: LazyNode + Clone> LazyRecursive<T> {
             self.query_helper(left, right, left_node, i, mid),
             self.query_helper(left, right, right_node, mid + 1, j),
         ) {
-            (Some(ans_left), Some(ans_right)) => Some(Node::combine(&ans_left, &ans_right)),
+            (Some(ans_left), Some(ans_right)) => Some(combine(&ans_left, &ans_right)),
             (Some(ans_left), None) => Some(ans_left),
             (None, Some(ans_right)) => Some(ans_right),
             (None, None) => None,
@@ -151,7 +382,7 @@ impl<T: LazyNode + Clone> LazyRecursive<T> {
     /// let predicate = |left_value:&usize, value:&usize|{*left_value>=*value}; // Is the sum greater or equal to value?
     /// let g = |left_node:&usize,value:usize|{value-*left_node}; // Subtract the sum of the prefix.
     /// # let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
-    /// let seg_tree = LazyRecursive::build(&nodes); // [0,1,2,3,4,5,6,7,8,9] with Sum<usize> nodes
+    /// let mut seg_tree = LazyRecursive::build(&nodes); // [0,1,2,3,4,5,6,7,8,9] with Sum<usize> nodes
     /// let index = seg_tree.lower_bound(predicate, g, 3); // Will return 2 as sum([0,1,2])>=3
     /// # let sums = vec![0,1,3,6,10,15,21,28,36,45];
     /// # for i in 0..10{
@@ -165,25 +396,51 @@ impl<T: LazyNode + Clone> LazyRecursive<T> {
     /// let predicate = |left_value:&usize, value:&usize|{*left_value>=*value}; // Is the maximum greater or equal to value?
     /// let g = |_left_node:&usize,value:usize|{value}; // Do nothing
     /// # let nodes: Vec<LSMax<usize>> = (0..10).map(|x| LSMax::initialize(&x)).collect();
-    /// let seg_tree = LazyRecursive::build(&nodes); // [0,1,2,3,4,5,6,7,8,9] with Max<usize> nodes
+    /// let mut seg_tree = LazyRecursive::build(&nodes); // [0,1,2,3,4,5,6,7,8,9] with Max<usize> nodes
     /// let index = seg_tree.lower_bound(predicate, g, 3); // Will return 3 as 3>=3
     /// # for i in 0..10{
     /// #    assert_eq!(seg_tree.lower_bound(predicate, g, i), i);
     /// # }
     /// ```
     ///
+    /// The pending lazy values of the nodes visited are pushed, so the search sees the values after every update.
+    /// It will **panic** if the segment tree has no elements.
+    ///
     /// [^note]: A prefix is a segment of the form `[0,i]`.
     ///
     /// [^note2]: Given two prefixes `u` and `v` if `u` is contained in `v` then `predicate(u.value(), value)` implies `predicate(v.value(), value)`.
-    pub fn lower_bound<F, G>(&self, predicate: F, g: G, value: <T as Node>::Value) -> usize
+    pub fn lower_bound<F, G>(&mut self, predicate: F, g: G, value: <T as Node>::Value) -> usize
     where
         F: Fn(&<T as Node>::Value, &<T as Node>::Value) -> bool,
         G: Fn(&<T as Node>::Value, <T as Node>::Value) -> <T as Node>::Value,
     {
+        self.lower_bound_by_node(
+            |node: &T, value: &<T as Node>::Value| predicate(node.value(), value),
+            |node: &T, value| g(node.value(), value),
+            value,
+        )
+    }
+
+    /// Same as [`lower_bound`](Self::lower_bound), but `predicate` and `g` receive the node of the segment instead of its value, so nodes with several fields can be searched by any of them.
+    /// The same conditions must be true, taking the node of each segment instead of its value.
+    ///
+    /// It will **panic** if the segment tree has no elements.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine), `predicate` and `g` have constant time complexity.
+    pub fn lower_bound_by_node<F, G>(
+        &mut self,
+        predicate: F,
+        g: G,
+        value: <T as Node>::Value,
+    ) -> usize
+    where
+        F: Fn(&T, &<T as Node>::Value) -> bool,
+        G: Fn(&T, <T as Node>::Value) -> <T as Node>::Value,
+    {
+        assert_not_empty(self.n, "search");
         self.lower_bound_helper(0, 0, self.n - 1, predicate, g, value)
     }
     fn lower_bound_helper<F, G>(
-        &self,
+        &mut self,
         curr_node: usize,
         i: usize,
         j: usize,
@@ -192,36 +449,276 @@ impl<T: LazyNode + Clone> LazyRecursive<T> {
         value: <T as Node>::Value,
     ) -> usize
     where
-        F: Fn(&<T as Node>::Value, &<T as Node>::Value) -> bool,
-        G: Fn(&<T as Node>::Value, <T as Node>::Value) -> <T as Node>::Value,
+        F: Fn(&T, &<T as Node>::Value) -> bool,
+        G: Fn(&T, <T as Node>::Value) -> <T as Node>::Value,
     {
+        let _depth = enter();
+        self.push(curr_node, i, j);
         if i == j {
             return i;
         }
-        let mid = (i + j) / 2;
+        let mid = usize::midpoint(i, j);
         let left_node = 2 * curr_node + 1;
         let right_node = 2 * curr_node + 2;
-        let left_value = self.nodes[left_node].value();
-        if predicate(left_value, &value) {
+        self.push(left_node, i, mid);
+        let left = &self.nodes[left_node];
+        if predicate(left, &value) {
             self.lower_bound_helper(left_node, i, mid, predicate, g, value)
         } else {
-            let value = g(left_value, value);
+            let value = g(left, value);
             self.lower_bound_helper(right_node, mid + 1, j, predicate, g, value)
         }
     }
+
+    /// Mirror of [`lower_bound`](Self::lower_bound), it finds the smallest suffix[^note] `u` such that `predicate(u.value(), value)` is `true`, and returns the index where `u` starts. The following must be true:
+    /// - `predicate` is monotonic over suffixes[^note2].
+    /// - `g` will satisfy the following, given segments `[j,k]` and `[i,k]` with `i<j` we have that `predicate([i,k].value(),value)` implies `predicate([i,j-1].value(),g([j,k].value(),value))`.
+    ///
+    /// The pending lazy values of the nodes visited are pushed, so the search sees the values after every update.
+    /// It will **panic** if the segment tree has no elements.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine), `predicate` and `g` have constant time complexity.
+    ///
+    /// [^note]: A suffix is a segment of the form `[i,n-1]`.
+    ///
+    /// [^note2]: Given two suffixes `u` and `v` if `u` is contained in `v` then `predicate(u.value(), value)` implies `predicate(v.value(), value)`.
+    pub fn upper_bound<F, G>(&mut self, predicate: F, g: G, value: <T as Node>::Value) -> usize
+    where
+        F: Fn(&<T as Node>::Value, &<T as Node>::Value) -> bool,
+        G: Fn(&<T as Node>::Value, <T as Node>::Value) -> <T as Node>::Value,
+    {
+        assert_not_empty(self.n, "search");
+        self.upper_bound_helper(0, 0, self.n - 1, predicate, g, value)
+    }
+    fn upper_bound_helper<F, G>(
+        &mut self,
+        curr_node: usize,
+        i: usize,
+        j: usize,
+        predicate: F,
+        g: G,
+        value: <T as Node>::Value,
+    ) -> usize
+    where
+        F: Fn(&<T as Node>::Value, &<T as Node>::Value) -> bool,
+        G: Fn(&<T as Node>::Value, <T as Node>::Value) -> <T as Node>::Value,
+    {
+        let _depth = enter();
+        self.push(curr_node, i, j);
+        if i == j {
+            return i;
+        }
+        let mid = usize::midpoint(i, j);
+        let left_node = 2 * curr_node + 1;
+        let right_node = 2 * curr_node + 2;
+        self.push(right_node, mid + 1, j);
+        let right_value = self.nodes[right_node].value();
+        if predicate(right_value, &value) {
+            self.upper_bound_helper(right_node, mid + 1, j, predicate, g, value)
+        } else {
+            let value = g(right_value, value);
+            self.upper_bound_helper(left_node, i, mid, predicate, g, value)
+        }
+    }
+
+    /// Same as [`lower_bound`](Self::lower_bound), but it finds the smallest segment `[left,k]` with `k` in `[left,right]` such that `predicate([left,k].value(), value)` is `true`, and returns `k`.
+    /// `predicate` and `g` must satisfy the same conditions, over the segments which start at left instead of the prefixes.
+    /// The range is split into the nodes which cover it, and the search only descends into the first one where `predicate` becomes `true`.
+    /// It returns None if and only if there's no such `k`, in particular if the range is empty.
+    /// It will **panic** if the segment tree has no elements.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine), `predicate` and `g` have constant time complexity.
+    pub fn lower_bound_in<F, G>(
+        &mut self,
+        left: usize,
+        right: usize,
+        predicate: F,
+        g: G,
+        value: <T as Node>::Value,
+    ) -> Option<usize>
+    where
+        F: Fn(&<T as Node>::Value, &<T as Node>::Value) -> bool,
+        G: Fn(&<T as Node>::Value, <T as Node>::Value) -> <T as Node>::Value,
+    {
+        assert_not_empty(self.n, "search");
+        let root = 0;
+        let fns = (&predicate, &g);
+        self.lower_bound_in_helper(root, 0, self.n - 1, (left, right), fns, value).ok()
+    }
+
+    /// Returns the index found in the intersection of `[i,j]` and `range`, or the value left for the rest of the range if there's none.
+    fn lower_bound_in_helper<F, G>(
+        &mut self,
+        curr_node: usize,
+        i: usize,
+        j: usize,
+        (left, right): (usize, usize),
+        (predicate, g): (&F, &G),
+        value: <T as Node>::Value,
+    ) -> Result<usize, <T as Node>::Value>
+    where
+        F: Fn(&<T as Node>::Value, &<T as Node>::Value) -> bool,
+        G: Fn(&<T as Node>::Value, <T as Node>::Value) -> <T as Node>::Value,
+    {
+        let _depth = enter();
+        if j < left || right < i {
+            return Err(value);
+        }
+        self.push(curr_node, i, j);
+        if left <= i && j <= right {
+            let curr_value = self.nodes[curr_node].value();
+            if !predicate(curr_value, &value) {
+                return Err(g(curr_value, value));
+            }
+            let predicate = |node: &T, value: &<T as Node>::Value| predicate(node.value(), value);
+            let g = |node: &T, value| g(node.value(), value);
+            return Ok(self.lower_bound_helper(curr_node, i, j, predicate, g, value));
+        }
+        let mid = usize::midpoint(i, j);
+        let left_node = 2 * curr_node + 1;
+        let right_node = 2 * curr_node + 2;
+        let (range, fns) = ((left, right), (predicate, g));
+        match self.lower_bound_in_helper(left_node, i, mid, range, fns, value) {
+            Ok(index) => Ok(index),
+            Err(value) => self.lower_bound_in_helper(right_node, mid + 1, j, range, fns, value),
+        }
+    }
+
+    /// Subscribes callback to the updates of the segment tree, it's called after every update with the updated range and the value given to the update, so external indexes or caches can be kept in sync with the tree.
+    /// Callbacks are called in subscription order.
+    pub fn on_update<F>(&mut self, callback: F)
+    where
+        F: FnMut(RangeInclusive<usize>, &<T as Node>::Value) + Send + Sync + 'static,
+    {
+        self.observers.push(callback);
+    }
+
+    /// Unsubscribes every callback subscribed with [`on_update`](Self::on_update).
+    pub fn clear_observers(&mut self) {
+        self.observers.clear();
+    }
+
+    /// Returns the amount of elements of the segment tree.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.n
+    }
+
+    /// Returns `true` if the segment tree has no elements.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Returns the counters of the last build, query or update of the segment tree, see [`OpStats`](crate::instrument::OpStats).
+    #[cfg(feature = "instrument")]
+    #[must_use]
+    pub fn stats(&self) -> crate::instrument::OpStats {
+        self.last_op.get()
+    }
+
+    /// Returns the memory used by the segment tree, see [`MemoryStats`].
+    #[must_use]
+    pub fn memory_stats(&self) -> MemoryStats {
+        MemoryStats {
+            nodes: self.nodes.len(),
+            capacity: self.nodes.len(),
+            node_size: core::mem::size_of::<T>(),
+            versions: 1,
+            overhead_bytes: 0,
+        }
+    }
+
+    /// Checks that every internal node, with the lazy values pending from its ancestors applied, is the combination of its children, and that [`update_lazy_value`](LazyNode::update_lazy_value) and [`lazy_update`](LazyNode::lazy_update) keep their invariants.
+    /// A node whose [`lazy_update`](LazyNode::lazy_update) doesn't distribute over [`combine`](Node::combine) is caught where it happens instead of as wrong answers later on.
+    /// It's meant for tests and debug assertions.
+    /// It will **panic** at the first node which doesn't match.
+    /// It has time complexity of `O(n)`, assuming that [`combine`](Node::combine), [`update_lazy_value`](LazyNode::update_lazy_value) and [`lazy_update`](LazyNode::lazy_update) have constant time complexity.
+    pub fn validate(&self)
+    where
+        <T as Node>::Value: PartialEq,
+    {
+        if self.n == 0 {
+            return;
+        }
+        let children = |curr_node| Some((2 * curr_node + 1, 2 * curr_node + 2));
+        validate_lazy(&self.nodes[..], &children, 0, None, 0, self.n - 1);
+    }
+}
+
+impl<T> IntoIterator for LazyRecursive<T>
+where
+    T: LazyNode + Clone,
+{
+    type Item = T::Value;
+    type IntoIter = vec::IntoIter<T::Value>;
+
+    /// Same as [`into_values`](LazyRecursive::into_values).
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_values().into_iter()
+    }
+}
+
+impl<T> LazyRecursive<T>
+where
+    T: LazyNode + Clone,
+    T::Value: Codec,
+{
+    /// Appends the encoding of the values of the segment tree with the pending lazy values taken into account without being pushed to bytes, see [`Codec`].
+    /// It has time complexity of `O(n)`, assuming that [`encode`](Codec::encode) has constant time complexity.
+    pub fn encode_into(&self, bytes: &mut Vec<u8>) {
+        encode_values(&lazy_leaf_values(&self.nodes, self.n), bytes);
+    }
+
+    /// Decodes a segment tree encoded by [`encode_into`](LazyRecursive::encode_into) from the start of bytes, and advances bytes past it.
+    /// It returns None if and only if bytes don't start with a valid encoding.
+    /// It has time complexity of `O(n)`, assuming that [`decode`](Codec::decode) and [`combine`](Node::combine) have constant time complexity.
+    pub fn decode_from(bytes: &mut &[u8]) -> Option<Self> {
+        let values: Vec<T::Value> = decode_values(bytes)?;
+        let nodes: Vec<T> = values.iter().map(T::initialize).collect();
+        Some(Self::build(&nodes))
+    }
 }
 
-impl<T> core::fmt::Debug for LazyRecursive<T>
+/// Two segment trees are equal if they have the same values, in the same order, the pending lazy values are taken into account without being pushed.
+impl<T, S> PartialEq for LazyRecursive<T, S>
+where
+    T: LazyNode + Clone,
+    T::Value: PartialEq,
+    S: Storage<T>,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.n == other.n
+            && (self.n == 0
+                || lazy_leaves_eq((&self.nodes, &other.nodes), 0, (None, None), 0, self.n - 1))
+    }
+}
+
+/// Writes the values of the segment tree like a slice, e.g. `[1, 2, 3]`, with the pending lazy values taken into account without being pushed, a precision limits how many values are written, e.g. `{:.2}` writes `[1, 2, ...]`.
+impl<T, S> core::fmt::Display for LazyRecursive<T, S>
+where
+    T: LazyNode + Clone,
+    T::Value: core::fmt::Display,
+    S: Storage<T>,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        display_values(f, lazy_leaf_values(&self.nodes, self.n))
+    }
+}
+
+#[allow(clippy::missing_fields_in_debug)]
+impl<T, S> core::fmt::Debug for LazyRecursive<T, S>
 where
     T: core::fmt::Debug,
+    S: Storage<T>,
 {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("LazyRecursive")
             .field("n", &self.n)
             .field(
                 "nodes",
-                &as_dbg_tree(&self.nodes, |nodes, f| {
-                    recursive_visitor(0, 0, self.n - 1, f, nodes);
+                &as_dbg_tree(&self.nodes[..], |nodes, f| {
+                    if self.n > 0 {
+                        recursive_visitor(0, 0, self.n - 1, f, nodes);
+                    }
                 }),
             )
             .finish()
@@ -231,7 +728,9 @@ where
 mod tests {
     use crate::{
         nodes::Node,
-        utils::{LazySetWrapper, Min},
+        segment_tree::Recursive,
+        testing::small_values,
+        utils::{LazySetWrapper, Max, Min, Sum},
     };
 
     use super::LazyRecursive;
@@ -268,6 +767,21 @@ mod tests {
         segment_tree.update(0, 9, &value);
         assert_eq!(segment_tree.query(0, 1).unwrap().value(), &value);
     }
+
+    #[test]
+    fn set_works() {
+        let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = LazyRecursive::build(&nodes);
+        segment_tree.update(0, 9, &2);
+        segment_tree.set(3, &0);
+        segment_tree.set(9, &1);
+        let expected: Vec<usize> = [2, 3, 4, 0, 6, 7, 8, 9, 10, 1].into();
+        for (i, value) in expected.iter().enumerate() {
+            assert_eq!(segment_tree.query(i, i).unwrap().value(), value);
+        }
+        assert_eq!(segment_tree.query(0, 9).unwrap().value(), &50);
+    }
+
     #[test]
     fn query_works() {
         let nodes: Vec<LSMin<usize>> = (0..10).map(|x| LSMin::initialize(&x)).collect();
@@ -275,6 +789,24 @@ mod tests {
         assert_eq!(segment_tree.query(1, 9).unwrap().value(), &1);
     }
 
+    #[test]
+    fn on_update_works() {
+        use crate::utils::Sum;
+        use std::sync::{Arc, Mutex};
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = LazyRecursive::build(&nodes);
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let log_clone = Arc::clone(&log);
+        segment_tree.on_update(move |range, value| {
+            log_clone.lock().unwrap().push((range, *value));
+        });
+        segment_tree.update(0, 5, &1);
+        segment_tree.update(3, 3, &2);
+        segment_tree.clear_observers();
+        segment_tree.update(0, 10, &3);
+        assert_eq!(*log.lock().unwrap(), vec![(0..=5, 1), (3..=3, 2)]);
+    }
+
     #[test]
     fn dbg_works() {
         let nodes: Vec<LSMin<usize>> = (0..=10).map(|x| LSMin::initialize(&x)).collect();
@@ -284,4 +816,214 @@ mod tests {
         let expected = "LazyRecursive { n: 11, nodes: {[0, 10]: LazySetWrapper { node: Min { value: 2 }, lazy_value: None }, [0, 5]: LazySetWrapper { node: Min { value: 2 }, lazy_value: None }, [0, 2]: LazySetWrapper { node: Min { value: 2 }, lazy_value: None }, [0, 1]: LazySetWrapper { node: Min { value: 2 }, lazy_value: None }, [0, 0]: LazySetWrapper { node: Min { value: 0 }, lazy_value: Some(2) }, [1, 1]: LazySetWrapper { node: Min { value: 1 }, lazy_value: Some(2) }, [2, 2]: LazySetWrapper { node: Min { value: 2 }, lazy_value: None }, [3, 5]: LazySetWrapper { node: Min { value: 3 }, lazy_value: None }, [3, 4]: LazySetWrapper { node: Min { value: 3 }, lazy_value: None }, [3, 3]: LazySetWrapper { node: Min { value: 3 }, lazy_value: None }, [4, 4]: LazySetWrapper { node: Min { value: 4 }, lazy_value: None }, [5, 5]: LazySetWrapper { node: Min { value: 5 }, lazy_value: None }, [6, 10]: LazySetWrapper { node: Min { value: 6 }, lazy_value: None }, [6, 8]: LazySetWrapper { node: Min { value: 6 }, lazy_value: None }, [6, 7]: LazySetWrapper { node: Min { value: 6 }, lazy_value: None }, [6, 6]: LazySetWrapper { node: Min { value: 6 }, lazy_value: None }, [7, 7]: LazySetWrapper { node: Min { value: 7 }, lazy_value: None }, [8, 8]: LazySetWrapper { node: Min { value: 8 }, lazy_value: None }, [9, 10]: LazySetWrapper { node: Min { value: 9 }, lazy_value: None }, [9, 9]: LazySetWrapper { node: Min { value: 9 }, lazy_value: None }, [10, 10]: LazySetWrapper { node: Min { value: 10 }, lazy_value: None }} }";
         assert_eq!(dbg, expected);
     }
+
+    #[test]
+    fn validate_works() {
+        use crate::utils::Sum;
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = LazyRecursive::build(&nodes);
+        segment_tree.validate();
+        segment_tree.update(0, 5, &1);
+        segment_tree.update(3, 9, &2);
+        segment_tree.validate();
+        let _ = segment_tree.query(4, 4);
+        segment_tree.validate();
+    }
+
+    #[test]
+    #[should_panic = "node of [0, 1] doesn't match the combination of its children"]
+    fn validate_catches_non_distributive_lazy_update() {
+        use crate::{nodes::LazyNode, utils::Sum};
+        /// Range add which forgets to multiply by the length of the segment.
+        #[derive(Clone)]
+        struct AddOnce(Sum<usize>, Option<usize>);
+        impl Node for AddOnce {
+            type Value = usize;
+            fn initialize(value: &usize) -> Self {
+                Self(Sum::initialize(value), None)
+            }
+            fn combine(a: &Self, b: &Self) -> Self {
+                Self(Sum::combine(&a.0, &b.0), None)
+            }
+            fn value(&self) -> &usize {
+                self.0.value()
+            }
+        }
+        impl LazyNode for AddOnce {
+            fn lazy_update(&mut self, _i: usize, _j: usize) {
+                if let Some(value) = self.1.take() {
+                    self.0 = Sum::initialize(&(self.0.value() + value));
+                }
+            }
+            fn update_lazy_value(&mut self, new_value: &usize) {
+                self.1 = Some(self.1.unwrap_or(0) + new_value);
+            }
+            fn lazy_value(&self) -> Option<&usize> {
+                self.1.as_ref()
+            }
+        }
+        let nodes: Vec<AddOnce> = (0..=10).map(|x| AddOnce::initialize(&x)).collect();
+        let mut segment_tree = LazyRecursive::build(&nodes);
+        segment_tree.update(0, 1, &1);
+        segment_tree.validate();
+    }
+
+    #[test]
+    fn empty_tree_works() {
+        let mut segment_tree = LazyRecursive::<LSMin<usize>>::build(&[]);
+        assert_eq!(segment_tree.len(), 0);
+        assert!(segment_tree.is_empty());
+        assert!(segment_tree.query(0, 0).is_none());
+        assert_eq!(format!("{segment_tree:?}"), "LazyRecursive { n: 0, nodes: {} }");
+    }
+
+    #[test]
+    #[should_panic = "can't update an empty segment tree"]
+    fn update_on_empty_tree_panics() {
+        let mut segment_tree = LazyRecursive::<LSMin<usize>>::build(&[]);
+        segment_tree.update(0, 0, &1);
+    }
+
+    #[test]
+    fn into_values_applies_pending_updates() {
+        let nodes: Vec<LSMin<usize>> = (0..=10).map(|x| LSMin::initialize(&x)).collect();
+        let mut segment_tree = LazyRecursive::build(&nodes);
+        segment_tree.update(2, 8, &20);
+        segment_tree.update(4, 5, &0);
+        let values: Vec<usize> = segment_tree.into_iter().collect();
+        assert_eq!(values, [0, 1, 20, 20, 0, 0, 20, 20, 20, 9, 10]);
+    }
+
+    #[test]
+    fn eq_takes_pending_updates_into_account() {
+        let nodes: Vec<LSMin<usize>> = (0..=10).map(|x| LSMin::initialize(&x)).collect();
+        let mut a = LazyRecursive::build(&nodes);
+        let mut b = LazyRecursive::build(&nodes);
+        a.update(0, 10, &5);
+        a.update(2, 3, &1);
+        for i in 0..=10 {
+            b.update(i, i, &if (2..=3).contains(&i) { 1 } else { 5 });
+        }
+        assert_eq!(a, b);
+        b.update(7, 7, &6);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn from_recursive_works() {
+        let nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let mut recursive = Recursive::build(&nodes);
+        recursive.update(0, &7);
+        let mut segment_tree = LazyRecursive::<LSMin<usize>>::from_recursive(recursive);
+        segment_tree.validate();
+        assert_eq!(segment_tree.query(0, 10).unwrap().value(), &1);
+        segment_tree.update(1, 5, &8);
+        segment_tree.validate();
+        assert_eq!(segment_tree.query(0, 10).unwrap().value(), &6);
+        assert_eq!(segment_tree.into_values(), [7, 8, 8, 8, 8, 8, 6, 7, 8, 9, 10]);
+        let empty = Recursive::<Min<usize>>::build(&[]);
+        assert!(LazyRecursive::<LSMin<usize>>::from_recursive(empty).is_empty());
+    }
+
+    #[test]
+    fn display_takes_pending_values_into_account() {
+        let nodes: Vec<LSMin<usize>> = (0..8).map(|x| LSMin::initialize(&x)).collect();
+        let mut segment_tree = LazyRecursive::build(&nodes);
+        segment_tree.update(2, 5, &1);
+        assert_eq!(format!("{segment_tree}"), "[0, 1, 1, 1, 1, 1, 6, 7]");
+        assert_eq!(format!("{segment_tree:.4}"), "[0, 1, 1, 1, ...]");
+    }
+
+    #[test]
+    fn codec_takes_pending_values_into_account() {
+        let nodes: Vec<LSMin<u32>> = (0..8).map(|x| LSMin::initialize(&x)).collect();
+        let mut segment_tree = LazyRecursive::build(&nodes);
+        segment_tree.update(2, 5, &1);
+        let mut bytes = Vec::new();
+        segment_tree.encode_into(&mut bytes);
+        let mut decoded = LazyRecursive::<LSMin<u32>>::decode_from(&mut &bytes[..]).unwrap();
+        assert_eq!(decoded, segment_tree);
+        assert_eq!(decoded.query(3, 3).unwrap().value(), &1);
+    }
+
+    #[test]
+    fn lower_bound_pushes_pending_values() {
+        type LSMax<T> = LazySetWrapper<Max<T>>;
+        let predicate = |left_value: &usize, value: &usize| *left_value >= *value;
+        let g = |_left_value: &usize, value: usize| value;
+        let nodes: Vec<LSMax<usize>> = (0..16).map(|_| LSMax::initialize(&0)).collect();
+        let mut segment_tree = LazyRecursive::build(&nodes);
+        segment_tree.update(5, 12, &7);
+        assert_eq!(segment_tree.lower_bound(predicate, g, 7), 5);
+        segment_tree.update(0, 15, &1);
+        segment_tree.update(9, 9, &3);
+        assert_eq!(segment_tree.lower_bound(predicate, g, 2), 9);
+        segment_tree.validate();
+    }
+
+    #[test]
+    fn lower_bound_in_pushes_pending_values() {
+        let mut values: Vec<usize> = small_values(13);
+        let nodes: Vec<Sum<usize>> = values.iter().map(Sum::initialize).collect();
+        let mut segment_tree = LazyRecursive::build(&nodes);
+        segment_tree.update(3, 9, &2);
+        values[3..=9].iter_mut().for_each(|value| *value += 2);
+        let expected = |left: usize, right: usize, target: usize| {
+            (left..=right).find(|&k| values[left..=k].iter().sum::<usize>() >= target)
+        };
+        let predicate = |sum: &usize, value: &usize| *sum >= *value;
+        let g = |sum: &usize, value: usize| value.saturating_sub(*sum);
+        for left in 0..values.len() {
+            for right in 0..values.len() {
+                for target in [1, 5, 20, 60] {
+                    assert_eq!(
+                        segment_tree.lower_bound_in(left, right, predicate, g, target),
+                        expected(left, right, target)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn upper_bound_pushes_pending_values() {
+        let mut values: Vec<usize> = small_values(13);
+        let nodes: Vec<Sum<usize>> = values.iter().map(Sum::initialize).collect();
+        let mut segment_tree = LazyRecursive::build(&nodes);
+        segment_tree.update(3, 9, &2);
+        values[3..=9].iter_mut().for_each(|value| *value += 2);
+        let expected = |target: usize| {
+            (0..values.len())
+                .rev()
+                .find(|&i| values[i..].iter().sum::<usize>() >= target)
+                .unwrap_or(0)
+        };
+        let predicate = |sum: &usize, value: &usize| *sum >= *value;
+        let g = |sum: &usize, value: usize| value.saturating_sub(*sum);
+        for target in 0..70 {
+            assert_eq!(
+                segment_tree.upper_bound(predicate, g, target),
+                expected(target)
+            );
+        }
+    }
+
+    #[test]
+    fn get_segment_pushes_pending_values() {
+        let mut values: Vec<usize> = (0..13).collect();
+        let nodes: Vec<Sum<usize>> = values.iter().map(Sum::initialize).collect();
+        let mut segment_tree = LazyRecursive::build(&nodes);
+        segment_tree.update(2, 9, &3);
+        values[2..=9].iter_mut().for_each(|value| *value += 3);
+        let mut canonical = 0;
+        for i in 0..=values.len() {
+            for j in 0..=values.len() {
+                if let Some(node) = segment_tree.get_segment(i, j) {
+                    canonical += 1;
+                    assert_eq!(node.value(), &values[i..=j].iter().sum::<usize>());
+                }
+            }
+        }
+        assert_eq!(canonical, 2 * values.len() - 1);
+    }
 }
@@ -0,0 +1,48 @@
+/// Memory usage of a segment tree, as returned by the `memory_stats` method of each tree.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct MemoryStats {
+    /// Amount of nodes currently stored.
+    pub nodes: usize,
+    /// Amount of nodes which fit in the currently reserved memory, it's at least [`nodes`](MemoryStats::nodes).
+    pub capacity: usize,
+    /// Size in bytes of a single node.
+    pub node_size: usize,
+    /// Amount of versions stored, it's always `1` for non persistent trees.
+    pub versions: usize,
+    /// Bytes reserved for bookkeeping besides the nodes, such as the roots of each version.
+    pub overhead_bytes: usize,
+}
+
+impl MemoryStats {
+    /// Returns the amount of bytes used by the stored nodes and bookkeeping.
+    #[must_use]
+    pub const fn used_bytes(&self) -> usize {
+        self.nodes * self.node_size + self.overhead_bytes
+    }
+
+    /// Returns the amount of bytes reserved by the tree, including unused capacity.
+    #[must_use]
+    pub const fn reserved_bytes(&self) -> usize {
+        self.capacity * self.node_size + self.overhead_bytes
+    }
+
+    /// Returns the average amount of nodes per version.
+    #[must_use]
+    pub const fn nodes_per_version(&self) -> usize {
+        match self.nodes.checked_div(self.versions) {
+            Some(nodes) => nodes,
+            None => 0,
+        }
+    }
+}
+
+/// Memory attribution of a single version of a persistent segment tree, as returned by the `version_stats` method of each persistent tree.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct VersionStats {
+    /// Amount of distinct nodes reachable from the version.
+    pub nodes: usize,
+    /// Amount of those nodes which aren't reachable from the parent of the version, it's every node for versions without parent.
+    pub introduced: usize,
+    /// Amount of those nodes which are shared with the parent of the version.
+    pub shared: usize,
+}
@@ -1,10 +1,56 @@
+mod adaptive;
+mod arc_persistent;
+mod builder;
+mod flat;
+mod frozen;
 mod iterative;
+mod journaled;
 mod lazy_persistent;
 mod lazy_recursive;
+mod memory_stats;
+mod nested;
+mod offset;
+mod padded_iterative;
 mod persistent;
+mod ranged;
 mod recursive;
+#[cfg(feature = "std")]
+mod sharded;
 
 pub use self::{
-    iterative::Iterative, lazy_persistent::LazyPersistent, lazy_recursive::LazyRecursive,
-    persistent::Persistent, recursive::Recursive,
+    adaptive::Adaptive,
+    arc_persistent::{ArcPersistent, ArcReader, ArcVersion},
+    builder::SegTreeBuilder,
+    flat::Flat,
+    frozen::Frozen,
+    iterative::{Iterative, StaticTree},
+    journaled::{Journaled, PointTree},
+    lazy_persistent::LazyPersistent,
+    lazy_recursive::LazyRecursive,
+    memory_stats::{MemoryStats, VersionStats},
+    nested::{Nestable, NestedTree},
+    offset::Offset,
+    padded_iterative::PaddedIterative,
+    persistent::{Persistent, PersistentReader, PersistentVersion},
+    ranged::Ranged,
+    recursive::Recursive,
 };
+#[cfg(feature = "std")]
+pub use self::sharded::Sharded;
+pub use crate::internal_utils::persistent_utils::{NodeIndex, PersistentWrapper};
+
+/// Direction chosen at each node by the controller of a walk, e.g. [`Recursive::walk`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Descend {
+    /// Visits only the left child.
+    Left,
+    /// Visits only the right child.
+    Right,
+    /// Visits the left child and then the right child.
+    Both,
+    /// Visits neither child, which is also the only choice for leaves.
+    Stop,
+}
+
+/// Identifier of a version of a persistent segment tree, it's the index of the version in creation order, starting from `0` for the version created by `build`.
+pub type VersionId = usize;
@@ -0,0 +1,292 @@
+use alloc::vec::Vec;
+
+use crate::{
+    internal_utils::{empty_utils::assert_same_len, instrument_utils::combine},
+    nodes::Node,
+    segment_tree::Iterative,
+};
+
+/// Segment trees which can be the elements of a [`NestedTree`], i.e. which have point updates and range queries over the points of some dimension.
+///
+/// It's implemented for [`Iterative`], whose points are indices, and for [`NestedTree`], whose points are pairs of an index and a point of its inner trees, so segment trees of any dimension are built by nesting.
+pub trait Nestable: Sized {
+    /// Node of the segment tree.
+    type Node: Node;
+    /// Coordinates of an element, e.g. `usize` for [`Iterative`] and `(usize, usize)` for a [`NestedTree`] of them.
+    type Point: Clone;
+    /// Amount of elements along each dimension, e.g. `usize` for [`Iterative`] and `(usize, usize)` for a [`NestedTree`] of them.
+    type Shape;
+
+    /// Builds the segment tree with the given shape, whose element at point p is equal to `f(p)`.
+    fn build_from_fn<F>(shape: &Self::Shape, f: F) -> Self
+    where
+        F: FnMut(&Self::Point) -> <Self::Node as Node>::Value;
+
+    /// Returns the segment tree whose element at each point is the [`combine`](Node::combine) of the elements of both segment trees at that point.
+    /// It will **panic** if the segment trees don't have the same shape.
+    #[must_use]
+    fn merge_with(&self, other: &Self) -> Self;
+
+    /// Returns the element at point p.
+    /// It will **panic** if p is out of bounds.
+    fn get(&self, p: &Self::Point) -> Self::Node;
+
+    /// Sets the element at point p to value.
+    /// It will **panic** if p is out of bounds.
+    fn set(&mut self, p: &Self::Point, value: &<Self::Node as Node>::Value);
+
+    /// Returns the result of the box whose opposite corners are first and last.
+    /// It returns None if and only if the box is empty.
+    /// It will **panic** if first or last are out of bounds.
+    fn query(&self, first: &Self::Point, last: &Self::Point) -> Option<Self::Node>;
+}
+
+impl<T> Nestable for Iterative<T>
+where
+    T: Node + Clone,
+{
+    type Node = T;
+    type Point = usize;
+    type Shape = usize;
+
+    fn build_from_fn<F>(n: &usize, mut f: F) -> Self
+    where
+        F: FnMut(&usize) -> T::Value,
+    {
+        Self::build_from_fn(*n, |i| f(&i))
+    }
+
+    fn merge_with(&self, other: &Self) -> Self {
+        self.merge_with(other)
+    }
+
+    fn get(&self, p: &usize) -> T {
+        self.query(*p, *p).unwrap()
+    }
+
+    fn set(&mut self, p: &usize, value: &T::Value) {
+        self.update(*p, value);
+    }
+
+    fn query(&self, first: &usize, last: &usize) -> Option<T> {
+        self.query(*first, *last)
+    }
+}
+
+/// Segment tree whose elements are segment trees, e.g. a two-dimensional segment tree is a [`NestedTree`] of [`Iterative`] trees.
+///
+/// The element at point `(x, p)` is the element at point p of the x-th inner tree, and each internal node keeps the [`merge_with`](Nestable::merge_with) of its children, so a box is answered by querying the inner trees of `O(log(n))` nodes.
+/// After an update, the inner trees of the internal nodes are set to the [`value`](Node::value) of the combined elements, so the node must be determined by its value, as it's the case for [`Sum`](crate::utils::Sum), [`Min`](crate::utils::Min) or [`Max`](crate::utils::Max).
+/// It implements [`Nestable`] itself, so nesting it again adds a dimension, e.g. `NestedTree<NestedTree<Iterative<T>>>` is a three-dimensional segment tree.
+/// It uses `O(n*s)` space, where `n` is the amount of inner trees and `s` the space of each of them, e.g. `O(n*m)` for two dimensions.
+/// ```
+/// # use seg_tree::{Iterative,NestedTree,Nestable,utils::Sum,nodes::Node};
+/// // 3x4 grid whose element at (x, y) is 4*x+y, with Sum<usize> nodes.
+/// let mut grid: NestedTree<Iterative<Sum<usize>>> = NestedTree::build_from_fn(&(3, 4), |&(x, y)| 4 * x + y);
+/// assert_eq!(grid.query(&(0, 1), &(1, 2)).unwrap().value(), &14);
+/// grid.set(&(1, 1), &0);
+/// assert_eq!(grid.query(&(0, 1), &(1, 2)).unwrap().value(), &9);
+/// assert_eq!(grid.query(&(0, 0), &(2, 3)).unwrap().value(), &61);
+/// ```
+pub struct NestedTree<Inner> {
+    // The node i of the tree over the inner trees is at index i-1, the leaves are the nodes in [n,2*n).
+    nodes: Vec<Inner>,
+    n: usize,
+}
+
+impl<Inner> NestedTree<Inner>
+where
+    Inner: Nestable,
+{
+    /// Returns the amount of inner trees, i.e. the amount of elements along the first dimension.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.n
+    }
+
+    /// Returns true if there are no inner trees.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    fn node(&self, i: usize) -> &Inner {
+        &self.nodes[i - 1]
+    }
+}
+
+impl<Inner> Nestable for NestedTree<Inner>
+where
+    Inner: Nestable,
+{
+    type Node = Inner::Node;
+    type Point = (usize, Inner::Point);
+    type Shape = (usize, Inner::Shape);
+
+    /// It has time complexity of `O(n*b)`, where `b` is the time complexity of building an inner tree, assuming that [`combine`](Node::combine) has constant time complexity.
+    fn build_from_fn<F>((n, shape): &Self::Shape, mut f: F) -> Self
+    where
+        F: FnMut(&Self::Point) -> <Self::Node as Node>::Value,
+    {
+        let n = *n;
+        // The nodes are built from the last one to the first one, so the node i is at index 2*n-1-i until they are reversed.
+        let mut nodes: Vec<Inner> = (0..n)
+            .rev()
+            .map(|x| Inner::build_from_fn(shape, |p| f(&(x, p.clone()))))
+            .collect();
+        for i in (1..n).rev() {
+            let merged = nodes[2 * n - 1 - 2 * i].merge_with(&nodes[2 * n - 2 - 2 * i]);
+            nodes.push(merged);
+        }
+        nodes.reverse();
+        Self { nodes, n }
+    }
+
+    fn merge_with(&self, other: &Self) -> Self {
+        assert_same_len(self.n, other.n, "merge");
+        let nodes = self.nodes.iter().zip(&other.nodes);
+        Self {
+            nodes: nodes.map(|(a, b)| a.merge_with(b)).collect(),
+            n: self.n,
+        }
+    }
+
+    fn get(&self, (x, p): &Self::Point) -> Self::Node {
+        self.node(x + self.n).get(p)
+    }
+
+    /// It has time complexity of `O(log(n)*u)`, where `u` is the time complexity of updating an inner tree, e.g. `O(log(n)*log(m))` for two dimensions, assuming that [`combine`](Node::combine) has constant time complexity.
+    fn set(&mut self, (x, p): &Self::Point, value: &<Self::Node as Node>::Value) {
+        let mut i = x + self.n;
+        self.nodes[i - 1].set(p, value);
+        while i > 1 {
+            i >>= 1;
+            let node = combine(&self.node(2 * i).get(p), &self.node(2 * i + 1).get(p));
+            self.nodes[i - 1].set(p, node.value());
+        }
+    }
+
+    /// It has time complexity of `O(log(n)*q)`, where `q` is the time complexity of querying an inner tree, e.g. `O(log(n)*log(m))` for two dimensions, assuming that [`combine`](Node::combine) has constant time complexity.
+    fn query(
+        &self,
+        (first_x, first): &Self::Point,
+        (last_x, last): &Self::Point,
+    ) -> Option<Self::Node> {
+        if self.n == 0 {
+            return None;
+        }
+        let mut ans_left: Option<Self::Node> = None;
+        let mut ans_right: Option<Self::Node> = None;
+        let mut l = first_x + self.n;
+        let mut r = last_x + self.n + 1;
+        while l < r {
+            if l & 1 != 0 {
+                ans_left = combine_options(ans_left, self.node(l).query(first, last));
+                l += 1;
+            }
+            if r & 1 != 0 {
+                r -= 1;
+                ans_right = combine_options(self.node(r).query(first, last), ans_right);
+            }
+            l >>= 1;
+            r >>= 1;
+        }
+        combine_options(ans_left, ans_right)
+    }
+}
+
+/// Combines both results, where None is the result of an empty range.
+fn combine_options<T>(left: Option<T>, right: Option<T>) -> Option<T>
+where
+    T: Node,
+{
+    match (left, right) {
+        (Some(left), Some(right)) => Some(combine(&left, &right)),
+        (left, right) => left.or(right),
+    }
+}
+
+impl<Inner> core::fmt::Debug for NestedTree<Inner>
+where
+    Inner: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("NestedTree")
+            .field("nodes", &self.nodes)
+            .field("n", &self.n)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        nodes::Node,
+        segment_tree::Iterative,
+        utils::{Max, Sum},
+    };
+
+    use super::{Nestable, NestedTree};
+
+    #[test]
+    fn nested_tree_works_in_two_dimensions() {
+        let mut values: Vec<Vec<usize>> = (0..6)
+            .map(|x| (0..5).map(|y| (x * 7 + y * 3) % 5).collect())
+            .collect();
+        let mut grid: NestedTree<Iterative<Sum<usize>>> =
+            NestedTree::build_from_fn(&(6, 5), |&(x, y)| values[x][y]);
+        for step in 0..8 {
+            let (x, y) = ((step * 5) % 6, (step * 3) % 5);
+            values[x][y] = step;
+            grid.set(&(x, y), &step);
+            assert_eq!(grid.get(&(x, y)).value(), &step);
+            for x1 in 0..6 {
+                for x2 in x1..6 {
+                    for y1 in 0..5 {
+                        for y2 in y1..5 {
+                            let expected: usize =
+                                values[x1..=x2].iter().flat_map(|row| &row[y1..=y2]).sum();
+                            let result = grid.query(&(x1, y1), &(x2, y2)).unwrap();
+                            assert_eq!(result.value(), &expected);
+                        }
+                    }
+                }
+            }
+        }
+        assert!(grid.query(&(3, 0), &(2, 4)).is_none());
+        assert!(grid.query(&(0, 3), &(5, 2)).is_none());
+        assert_eq!(grid.len(), 6);
+    }
+
+    #[test]
+    fn nested_tree_works_in_three_dimensions() {
+        let value = |x: usize, y: usize, z: usize| (x * 7 + y * 3 + z * 11) % 13;
+        let mut cube: NestedTree<NestedTree<Iterative<Max<usize>>>> =
+            NestedTree::build_from_fn(&(4, (3, 5)), |&(x, (y, z))| value(x, y, z));
+        cube.set(&(2, (1, 3)), &20);
+        for x1 in 0..4 {
+            for x2 in x1..4 {
+                let result = cube.query(&(x1, (0, 1)), &(x2, (2, 4))).unwrap();
+                let expected = (x1..=x2)
+                    .flat_map(|x| (0..3).flat_map(move |y| (1..5).map(move |z| (x, y, z))))
+                    .map(|(x, y, z)| {
+                        if (x, y, z) == (2, 1, 3) {
+                            20
+                        } else {
+                            value(x, y, z)
+                        }
+                    })
+                    .max()
+                    .unwrap();
+                assert_eq!(result.value(), &expected);
+            }
+        }
+    }
+
+    #[test]
+    fn empty_nested_tree_returns_none() {
+        let grid: NestedTree<Iterative<Sum<usize>>> = NestedTree::build_from_fn(&(0, 3), |_| 1);
+        assert!(grid.is_empty());
+        assert!(grid.query(&(0, 0), &(0, 2)).is_none());
+    }
+}
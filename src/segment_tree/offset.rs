@@ -0,0 +1,318 @@
+use core::ops::{Deref, DerefMut};
+
+use crate::{
+    internal_utils::persistent_utils::PersistentWrapper,
+    nodes::{LazyNode, Node},
+    segment_tree::{
+        Iterative, LazyPersistent, LazyRecursive, NodeIndex, Persistent, Recursive, VersionId,
+    },
+    storage::{Arena, Storage},
+};
+
+/// Wrapper around a segment tree whose positions are coordinates starting at `min_index`, which may be negative, so the element `i` of the tree is at coordinate `min_index+i`.
+///
+/// The coordinates are translated to indices once, before reaching the tree, so the call sites don't have to.
+/// Every other method of the tree is reached through [`Deref`] and [`DerefMut`], and keeps taking indices.
+/// ```
+/// # use seg_tree::{Recursive,utils::Sum,nodes::Node};
+/// # let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+/// let mut seg_tree = Recursive::build_with_offset(&nodes, -5); // [0,1,2,3,4,5,6,7,8,9] at [-5,4] with Sum<usize> nodes
+/// assert_eq!(seg_tree.query(-5, 4).unwrap().value(), &45);
+/// assert_eq!(seg_tree.query(-1, 1).unwrap().value(), &15);
+/// seg_tree.update(0, &10);
+/// assert_eq!(seg_tree.query(0, 0).unwrap().value(), &10);
+/// ```
+pub struct Offset<S> {
+    tree: S,
+    min_index: isize,
+}
+
+impl<S> Offset<S> {
+    /// Wraps tree, so its first element is at coordinate `min_index`.
+    pub const fn new(tree: S, min_index: isize) -> Self {
+        Self { tree, min_index }
+    }
+
+    /// Returns the coordinate of the first element.
+    #[must_use]
+    pub const fn min_index(&self) -> isize {
+        self.min_index
+    }
+
+    /// Returns the wrapped tree.
+    pub fn into_inner(self) -> S {
+        self.tree
+    }
+
+    /// Returns the index in the tree of the element at coordinate.
+    /// It will **panic** if coordinate is less than [`min_index`](Self::min_index).
+    #[must_use]
+    pub fn index(&self, coordinate: isize) -> usize {
+        assert_at_least(coordinate, self.min_index);
+        coordinate.abs_diff(self.min_index)
+    }
+}
+
+/// It will **panic** if coordinate is less than `min_index`.
+fn assert_at_least(coordinate: isize, min_index: isize) {
+    assert!(
+        coordinate >= min_index,
+        "coordinate {coordinate} is less than the minimum coordinate {min_index}"
+    );
+}
+
+impl<T> Iterative<T>
+where
+    T: Node + Clone,
+{
+    /// Same as [`build`](Iterative::build), but the first element is at coordinate `min_index`, see [`Offset`].
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn build_with_offset(values: &[T], min_index: isize) -> Offset<Self> {
+        Offset::new(Self::build(values), min_index)
+    }
+}
+
+impl<T, St> Offset<Iterative<T, St>>
+where
+    T: Node + Clone,
+    St: Storage<T>,
+{
+    /// Same as [`Iterative::update`], but p is a coordinate.
+    /// It will **panic** if p is not in `[min_index,min_index+n)`.
+    pub fn update(&mut self, p: isize, value: &<T as Node>::Value) {
+        let p = self.index(p);
+        self.tree.update(p, value);
+    }
+
+    /// Same as [`Iterative::query`], but left and right are coordinates.
+    /// It will **panic** if left or right are not in `[min_index,min_index+n)`.
+    #[must_use]
+    pub fn query(&self, left: isize, right: isize) -> Option<T> {
+        self.tree.query(self.index(left), self.index(right))
+    }
+}
+
+impl<T> Recursive<T>
+where
+    T: Node + Clone,
+{
+    /// Same as [`build`](Recursive::build), but the first element is at coordinate `min_index`, see [`Offset`].
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn build_with_offset(values: &[T], min_index: isize) -> Offset<Self> {
+        Offset::new(Self::build(values), min_index)
+    }
+}
+
+impl<T, St> Offset<Recursive<T, St>>
+where
+    T: Node + Clone,
+    St: Storage<T>,
+{
+    /// Same as [`Recursive::update`], but p is a coordinate.
+    /// It will **panic** if p is not in `[min_index,min_index+n)`.
+    pub fn update(&mut self, p: isize, value: &<T as Node>::Value) {
+        let p = self.index(p);
+        self.tree.update(p, value);
+    }
+
+    /// Same as [`Recursive::query`], but left and right are coordinates.
+    /// It will **panic** if left or right are not in `[min_index,min_index+n)`.
+    #[must_use]
+    pub fn query(&self, left: isize, right: isize) -> Option<T> {
+        self.tree.query(self.index(left), self.index(right))
+    }
+}
+
+impl<T> LazyRecursive<T>
+where
+    T: LazyNode + Clone,
+{
+    /// Same as [`build`](LazyRecursive::build), but the first element is at coordinate `min_index`, see [`Offset`].
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn build_with_offset(values: &[T], min_index: isize) -> Offset<Self> {
+        Offset::new(Self::build(values), min_index)
+    }
+}
+
+impl<T, St> Offset<LazyRecursive<T, St>>
+where
+    T: LazyNode + Clone,
+    St: Storage<T>,
+{
+    /// Same as [`LazyRecursive::update`], but i and j are coordinates.
+    /// It will **panic** if i or j are not in `[min_index,min_index+n)`.
+    pub fn update(&mut self, i: isize, j: isize, value: &<T as Node>::Value) {
+        let (i, j) = (self.index(i), self.index(j));
+        self.tree.update(i, j, value);
+    }
+
+    /// Same as [`LazyRecursive::query`], but left and right are coordinates.
+    /// It will **panic** if left or right are not in `[min_index,min_index+n)`.
+    pub fn query(&mut self, left: isize, right: isize) -> Option<T> {
+        let (left, right) = (self.index(left), self.index(right));
+        self.tree.query(left, right)
+    }
+}
+
+impl<T> Persistent<T>
+where
+    T: Node + Clone,
+{
+    /// Same as [`build`](Persistent::build), but the first element is at coordinate `min_index`, see [`Offset`].
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn build_with_offset(values: &[T], min_index: isize) -> Offset<Self> {
+        Offset::new(Self::build(values), min_index)
+    }
+}
+
+impl<T, I, A> Offset<Persistent<T, I, A>>
+where
+    T: Node + Clone,
+    I: NodeIndex,
+    A: Arena<PersistentWrapper<T, I>>,
+{
+    /// Same as [`Persistent::update`], but p is a coordinate.
+    /// It will **panic** if p is not in `[min_index,min_index+n)`, or if version is not in <code>[0,[versions](Persistent::versions))</code>.
+    pub fn update(
+        &mut self,
+        version: VersionId,
+        p: isize,
+        value: &<T as Node>::Value,
+    ) -> VersionId {
+        let p = self.index(p);
+        self.tree.update(version, p, value)
+    }
+
+    /// Same as [`Persistent::query`], but left and right are coordinates.
+    /// It will **panic** if left or right are not in `[min_index,min_index+n)`, or if version is not in <code>[0,[versions](Persistent::versions))</code>.
+    #[must_use]
+    pub fn query(&self, version: VersionId, left: isize, right: isize) -> Option<T> {
+        self.tree
+            .query(version, self.index(left), self.index(right))
+    }
+}
+
+impl<T> LazyPersistent<T>
+where
+    T: LazyNode + Clone,
+{
+    /// Same as [`build`](LazyPersistent::build), but the first element is at coordinate `min_index`, see [`Offset`].
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn build_with_offset(values: &[T], min_index: isize) -> Offset<Self> {
+        Offset::new(Self::build(values), min_index)
+    }
+}
+
+impl<T, I, A> Offset<LazyPersistent<T, I, A>>
+where
+    T: LazyNode + Clone,
+    I: NodeIndex,
+    A: Arena<PersistentWrapper<T, I>>,
+{
+    /// Same as [`LazyPersistent::update`], but left and right are coordinates.
+    /// It will **panic** if left or right are not in `[min_index,min_index+n)`, or if version is not in <code>[0,[versions](LazyPersistent::versions))</code>.
+    pub fn update(
+        &mut self,
+        version: VersionId,
+        left: isize,
+        right: isize,
+        value: &<T as Node>::Value,
+    ) -> VersionId {
+        let (left, right) = (self.index(left), self.index(right));
+        self.tree.update(version, left, right, value)
+    }
+
+    /// Same as [`LazyPersistent::query`], but left and right are coordinates.
+    /// It will **panic** if left or right are not in `[min_index,min_index+n)`, or if version is not in <code>[0,[versions](LazyPersistent::versions))</code>.
+    #[must_use]
+    pub fn query(&self, version: VersionId, left: isize, right: isize) -> Option<T> {
+        self.tree
+            .query(version, self.index(left), self.index(right))
+    }
+}
+
+impl<S> Deref for Offset<S> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        &self.tree
+    }
+}
+
+impl<S> DerefMut for Offset<S> {
+    fn deref_mut(&mut self) -> &mut S {
+        &mut self.tree
+    }
+}
+
+impl<S> core::fmt::Debug for Offset<S>
+where
+    S: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Offset")
+            .field("tree", &self.tree)
+            .field("min_index", &self.min_index)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        nodes::Node,
+        segment_tree::{Iterative, LazyPersistent, LazyRecursive, Persistent},
+        testing::small_values,
+        utils::{LazySetWrapper, Min, Sum},
+    };
+
+    #[test]
+    fn offset_query_works() {
+        let values: Vec<usize> = small_values(13);
+        let nodes: Vec<Min<usize>> = values.iter().map(Min::initialize).collect();
+        let segment_tree = Iterative::build_with_offset(&nodes, -6);
+        for (i, left) in (-6..=6).enumerate() {
+            for (j, right) in (left..=6).enumerate() {
+                let expected = values[i..=i + j].iter().min();
+                assert_eq!(
+                    segment_tree.query(left, right).unwrap().value(),
+                    expected.unwrap()
+                );
+            }
+        }
+        assert_eq!(segment_tree.index(-6), 0);
+        assert_eq!(segment_tree.min_index(), -6);
+    }
+
+    #[test]
+    fn offset_update_works() {
+        let nodes: Vec<LazySetWrapper<Min<usize>>> =
+            (0..10).map(|x| LazySetWrapper::initialize(&x)).collect();
+        let mut segment_tree = LazyRecursive::build_with_offset(&nodes, isize::MIN);
+        segment_tree.update(isize::MIN, isize::MIN + 4, &7);
+        let min = isize::MIN;
+        assert_eq!(segment_tree.query(min, min + 4).unwrap().value(), &7);
+        assert_eq!(segment_tree.query(min + 3, min + 9).unwrap().value(), &5);
+    }
+
+    #[test]
+    fn offset_persistent_works() {
+        let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = Persistent::build_with_offset(&nodes, 100);
+        let version = segment_tree.update(0, 105, &0);
+        assert_eq!(segment_tree.query(0, 100, 109).unwrap().value(), &45);
+        assert_eq!(segment_tree.query(version, 100, 109).unwrap().value(), &40);
+        let mut segment_tree = LazyPersistent::build_with_offset(&nodes, -10);
+        let version = segment_tree.update(0, -10, -6, &1);
+        assert_eq!(segment_tree.query(version, -10, -1).unwrap().value(), &50);
+    }
+
+    #[test]
+    #[should_panic(expected = "less than the minimum coordinate")]
+    fn coordinate_below_min_panics() {
+        let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+        let segment_tree = Iterative::build_with_offset(&nodes, -5);
+        let _ = segment_tree.query(-6, 0);
+    }
+}
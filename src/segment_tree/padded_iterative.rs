@@ -0,0 +1,236 @@
+use alloc::vec::Vec;
+
+use crate::{
+    internal_utils::{
+        dbg_utils::display_values,
+        empty_utils::assert_not_empty,
+        instrument_utils::{combine, record_allocations},
+        range_utils::assert_in_bounds,
+        tracing_utils::span,
+    },
+    nodes::{MonoidNode, Node},
+};
+
+/// Same as [`Iterative`](crate::Iterative), but the leaves are padded with the [`identity`](MonoidNode::identity) up to the next power of two.
+///
+/// The padding makes every query run the same branch-free loop, without tracking whether each side has a result yet, and lets [`lower_bound`](PaddedIterative::lower_bound) descend from the root like [`Recursive`](crate::Recursive) does.
+/// It uses `O(n)` space, at most four times the nodes of the elements, assuming that each node uses `O(1)` space.
+/// ```
+/// # use seg_tree::{PaddedIterative,utils::Sum,nodes::Node};
+/// let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+/// let mut seg_tree = PaddedIterative::build(&nodes); // [0,1,2,3,4,5,6,7,8,9] with Sum<usize> nodes
+/// seg_tree.update(9, &0);
+/// assert_eq!(seg_tree.query(2, 9).unwrap().value(), &35);
+/// assert_eq!(seg_tree.lower_bound(|sum, value| *sum >= *value, |sum, value| value - sum, 6), 3);
+/// ```
+pub struct PaddedIterative<T> {
+    nodes: Vec<T>,
+    n: usize,
+    size: usize,
+}
+
+impl<T> PaddedIterative<T>
+where
+    T: MonoidNode + Clone,
+{
+    /// Builds segment tree from slice, each element of the slice will correspond to a leaf of the segment tree, the rest of the leaves are the identity.
+    /// It has time complexity of `O(n)`, assuming that [`combine`](Node::combine) and [`identity`](MonoidNode::identity) have constant time complexity.
+    pub fn build(values: &[T]) -> Self {
+        span!(DEBUG, "build", tree = "PaddedIterative", n = values.len());
+        let n = values.len();
+        let size = n.next_power_of_two();
+        let mut nodes = Vec::with_capacity(2 * size);
+        nodes.extend((0..size).map(|_| T::identity()));
+        nodes.extend_from_slice(values);
+        nodes.extend((n..size).map(|_| T::identity()));
+        record_allocations(2 * size);
+        for i in (1..size).rev() {
+            nodes[i] = combine(&nodes[2 * i], &nodes[2 * i + 1]);
+        }
+        Self { nodes, n, size }
+    }
+
+    /// Sets the p-th element of the segment tree to value T and update the segment tree correspondingly.
+    /// It will **panic** if p is not in `[0,n)`.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn update(&mut self, p: usize, value: &<T as Node>::Value) {
+        span!(TRACE, "update", tree = "PaddedIterative", n = self.n, p);
+        assert_in_bounds(p, self.n);
+        let mut i = p + self.size;
+        self.nodes[i] = Node::initialize(value);
+        while i > 1 {
+            i >>= 1;
+            self.nodes[i] = combine(&self.nodes[2 * i], &self.nodes[2 * i + 1]);
+        }
+    }
+
+    /// Returns the result from the range `[left,right]`.
+    /// It returns None if and only if range is empty or the segment tree has no elements.
+    /// It will **panic** if left or right are not in `[0,n)`.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) and [`identity`](MonoidNode::identity) have constant time complexity.
+    #[allow(clippy::must_use_candidate)]
+    pub fn query(&self, left: usize, right: usize) -> Option<T> {
+        span!(
+            TRACE,
+            "query",
+            tree = "PaddedIterative",
+            n = self.n,
+            left,
+            right
+        );
+        if left > right || self.n == 0 {
+            return None;
+        }
+        assert_in_bounds(right, self.n);
+        let (mut ans_left, mut ans_right) = (T::identity(), T::identity());
+        let (mut l, mut r) = (left + self.size, right + self.size + 1);
+        while l < r {
+            if l & 1 != 0 {
+                ans_left = combine(&ans_left, &self.nodes[l]);
+                l += 1;
+            }
+            if r & 1 != 0 {
+                r -= 1;
+                ans_right = combine(&self.nodes[r], &ans_right);
+            }
+            l >>= 1;
+            r >>= 1;
+        }
+        Some(combine(&ans_left, &ans_right))
+    }
+
+    /// Same as [`Recursive::lower_bound`](crate::Recursive::lower_bound), the padding is never returned, as the identity doesn't change the result of any prefix.
+    /// It will **panic** if the segment tree has no elements.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine), `predicate` and `g` have constant time complexity.
+    pub fn lower_bound<F, G>(&self, predicate: F, g: G, value: <T as Node>::Value) -> usize
+    where
+        F: Fn(&<T as Node>::Value, &<T as Node>::Value) -> bool,
+        G: Fn(&<T as Node>::Value, <T as Node>::Value) -> <T as Node>::Value,
+    {
+        assert_not_empty(self.n, "search");
+        let (mut curr_node, mut value) = (1, value);
+        while curr_node < self.size {
+            let left_node = 2 * curr_node;
+            let left_value = self.nodes[left_node].value();
+            if predicate(left_value, &value) {
+                curr_node = left_node;
+            } else {
+                value = g(left_value, value);
+                curr_node = left_node + 1;
+            }
+        }
+        (curr_node - self.size).min(self.n - 1)
+    }
+
+    /// Returns the amount of elements of the segment tree, without the padding.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.n
+    }
+
+    /// Returns `true` if the segment tree has no elements.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+}
+
+impl<T> core::fmt::Debug for PaddedIterative<T>
+where
+    T: Node,
+    T::Value: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let leaves = &self.nodes[self.size..self.size + self.n];
+        f.debug_list()
+            .entries(leaves.iter().map(Node::value))
+            .finish()
+    }
+}
+
+/// Writes the values of the segment tree like a slice, e.g. `[1, 2, 3]`, a precision limits how many values are written, e.g. `{:.2}` writes `[1, 2, ...]`.
+impl<T> core::fmt::Display for PaddedIterative<T>
+where
+    T: Node,
+    T::Value: core::fmt::Display,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let leaves = &self.nodes[self.size..self.size + self.n];
+        display_values(f, leaves.iter().map(Node::value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        nodes::Node,
+        segment_tree::{Iterative, Recursive},
+        testing::small_values,
+        utils::{Max, Min, Sum},
+    };
+
+    use super::PaddedIterative;
+
+    #[test]
+    fn query_matches_iterative() {
+        for n in [1, 2, 5, 8, 13] {
+            let mut nodes: Vec<Min<i64>> = small_values(n).iter().map(Min::initialize).collect();
+            let mut segment_tree = PaddedIterative::build(&nodes);
+            for (p, value) in (0_i64..).take(n).enumerate() {
+                nodes[p] = Min::initialize(&(value - 3));
+                segment_tree.update(p, nodes[p].value());
+                let iterative = Iterative::build(&nodes);
+                for left in 0..nodes.len() {
+                    for right in 0..nodes.len() {
+                        let expected = iterative.query(left, right).map(Min::into_value);
+                        assert_eq!(
+                            segment_tree.query(left, right).map(Min::into_value),
+                            expected
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn lower_bound_matches_recursive() {
+        for n in [1, 3, 8, 11] {
+            let nodes: Vec<Sum<usize>> = small_values(n).iter().map(Sum::initialize).collect();
+            let padded = PaddedIterative::build(&nodes);
+            let recursive = Recursive::build(&nodes);
+            let predicate = |sum: &usize, value: &usize| *sum >= *value;
+            let g = |sum: &usize, value: usize| value.saturating_sub(*sum);
+            for target in 0..40 {
+                assert_eq!(
+                    padded.lower_bound(predicate, g, target),
+                    recursive.lower_bound(predicate, g, target)
+                );
+            }
+            let nodes: Vec<Max<usize>> = (0..n).map(|x| Max::initialize(&((x * 3) % 7))).collect();
+            let padded = PaddedIterative::build(&nodes);
+            let recursive = Recursive::build(&nodes);
+            let predicate = |max: &usize, value: &usize| *max >= *value;
+            for target in 0..8 {
+                assert_eq!(
+                    padded.lower_bound(predicate, |_, value| value, target),
+                    recursive.lower_bound(predicate, |_, value| value, target)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn empty_tree_has_no_results() {
+        let segment_tree = PaddedIterative::<Sum<usize>>::build(&[]);
+        assert!(segment_tree.is_empty());
+        assert!(segment_tree.query(0, 0).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "index 5 is out of bounds")]
+    fn update_out_of_bounds_panics() {
+        let nodes: Vec<Sum<usize>> = (0..5).map(|x| Sum::initialize(&x)).collect();
+        PaddedIterative::build(&nodes).update(5, &0);
+    }
+}
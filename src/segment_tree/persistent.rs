@@ -1,13 +1,46 @@
+use alloc::{string::String, sync::Arc, vec, vec::Vec};
+
 use bit_vec::BitVec;
+use core::ops::{Index, Range};
 
-use crate::{internal_utils::{persistent_utils::PersistentWrapper, dbg_utils::{as_dbg_tree, persistent_visitor}}, nodes::Node};
+use crate::{
+    internal_utils::{
+        append_utils::AppendList,
+        dbg_utils::{as_dbg_tree, persistent_visitor},
+        empty_utils::assert_not_empty,
+        instrument_utils::{combine, enter, record_allocations, LastOp},
+        persistent_utils::{
+            assert_empty, assert_roots, compact, push_node, version_stats, Interner, NodeIndex,
+            PersistentWrapper,
+        },
+        range_utils::{assert_in_bounds, clamp},
+        tracing_utils::span,
+        validate_utils::validate,
+        version_utils::{partition_versions, VersionHistory},
+    },
+    nodes::{InvertibleNode, Node},
+    segment_tree::{MemoryStats, VersionId, VersionStats},
+    storage::{Arena, SharedArena},
+    utils::Compressor,
+};
 
 /// Persistent segment tree, it saves every version of itself, it has range queries and point updates.
+///
 /// It uses `O(n+q*log(n))` space, where `q` is the amount of updates, and assuming that each node uses `O(1)` space.
-pub struct Persistent<T> {
-    nodes: Vec<PersistentWrapper<T>>,
+///
+/// The indices of the children of each node are stored as `I`, see [`NodeIndex`].
+///
+/// Identical subtrees can be stored only once, see [`deduplicated`](Persistent::deduplicated).
+///
+/// The nodes are stored in `A`, see [`Arena`].
+/// If they're stored in a [`SharedArena`], readers on other threads can query the versions as they are created through a [`PersistentReader`], which never blocks nor is blocked by the writer.
+pub struct Persistent<T, I = u32, A = Vec<PersistentWrapper<T, I>>> {
+    nodes: A,
     roots: Vec<usize>,
+    history: VersionHistory,
     n: usize,
+    interner: Option<Interner<T, I>>,
+    last_op: LastOp,
 }
 
 impl<T> Persistent<T>
@@ -17,119 +50,528 @@ where
     /// Builds persistent segment tree from slice, each element of the slice will correspond to a leaf of the segment tree.
     /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
     pub fn build(values: &[T]) -> Self {
+        Self::build_with_index(values)
+    }
+
+    /// Same as [`build`](Persistent::build), but it also reserves memory for `expected_updates` updates, see [`reserve_updates`](Persistent::reserve_updates).
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn build_with_capacity(values: &[T], expected_updates: usize) -> Self {
+        let mut temp = Self::build_with_index(values);
+        temp.reserve_updates(expected_updates);
+        temp
+    }
+
+    /// Builds segment tree with n elements, each of them equal to value.
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn filled(n: usize, value: &<T as Node>::Value) -> Self {
+        Self::build(&vec![T::initialize(value); n])
+    }
+
+    /// Same as [`filled`](Persistent::filled), but every element is the [`Default`] value, e.g. `0` for [`Sum`](crate::utils::Sum) nodes.
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn with_len(n: usize) -> Self
+    where
+        <T as Node>::Value: Default,
+    {
+        Self::filled(n, &<T as Node>::Value::default())
+    }
+
+    /// Builds segment tree with n elements, the i-th of them equal to `f(i)`, without collecting the values first.
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) and `f` have constant time complexity.
+    #[must_use]
+    pub fn build_from_fn<F>(n: usize, mut f: F) -> Self
+    where
+        F: FnMut(usize) -> <T as Node>::Value,
+    {
+        Self::build_from((0..n).map(|i| T::initialize(&f(i))), Vec::with_capacity(4 * n))
+    }
+
+    /// Builds persistent segment tree whose i-th version has the values of the i-th snapshot, e.g. the ones returned by [`snapshot_values`](Persistent::snapshot_values).
+    /// Each version is created from the previous one, which becomes its parent, and shares with it the subtrees whose values didn't change.
+    /// If snapshots is empty, it's the same as building a segment tree with no elements.
+    /// It will **panic** if the snapshots don't have the same length.
+    /// It has time complexity of `O(k*n)`, where `k` is the amount of snapshots, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn from_version_snapshots(snapshots: &[Vec<<T as Node>::Value>]) -> Self
+    where
+        <T as Node>::Value: PartialEq,
+    {
+        let first = snapshots.first().map_or(&[][..], Vec::as_slice);
+        let nodes: Vec<T> = first.iter().map(T::initialize).collect();
+        let mut temp = Self::build(&nodes);
+        for (version, pair) in snapshots.windows(2).enumerate() {
+            temp.push_snapshot(version, &pair[0], &pair[1]);
+        }
+        temp
+    }
+}
+
+impl<T> Persistent<T>
+where
+    T: Clone + Node<Value = usize>,
+{
+    /// Builds persistent segment tree over the keys of domain whose i-th version has, at the leaf of each key, the amount of times it appears in `values[0..=i]`.
+    /// So the amount of values in `values[l..=r]` whose keys are in a range of the domain is the result of the range in version `r` minus the one in version `l-1`, when `T` is [`Sum`](crate::utils::Sum).
+    /// If values is empty, the tree only has version 0, with every count being 0.
+    /// It will **panic** if some value isn't a key of domain.
+    /// It has time complexity of `O(n*log(n)+k*log(n))`, where `n` is the amount of keys of domain and `k` the amount of values, assuming that [`combine`](Node::combine) has constant time complexity.
+    /// ```
+    /// # use seg_tree::{Persistent,utils::{Compressor,Sum},nodes::Node};
+    /// let values = [30, 10, 20, 10, 30];
+    /// let domain: Compressor<i32> = values.into_iter().collect(); // [10,20,30]
+    /// let seg_tree: Persistent<Sum<usize>> = Persistent::build_frequency(&values, &domain);
+    /// assert_eq!(seg_tree.query(3, 0, 0).unwrap().value(), &2); // 10 appears twice in values[0..=3].
+    /// let count = |version| *seg_tree.query(version, 1, 2).unwrap().value(); // Values 20 and 30.
+    /// assert_eq!(count(4) - count(1), 2); // 20 and 30 appear twice in values[2..=4].
+    /// ```
+    pub fn build_frequency<K>(values: &[K], domain: &Compressor<K>) -> Self
+    where
+        K: Ord,
+    {
+        let positions: Vec<usize> = values.iter().map(|key| key_index(domain, key)).collect();
+        let mut counts = vec![0; domain.len()];
+        if let Some(&first) = positions.first() {
+            counts[first] = 1;
+        }
+        let nodes: Vec<T> = counts.iter().map(T::initialize).collect();
+        let mut temp = Self::build(&nodes);
+        temp.reserve_updates(positions.len().saturating_sub(1));
+        for (version, &p) in positions.iter().skip(1).enumerate() {
+            temp.update_with(version, p, |count| count + 1);
+        }
+        temp
+    }
+}
+
+/// Returns the index of key in domain.
+/// It will **panic** if key isn't one of the keys of domain.
+fn key_index<K>(domain: &Compressor<K>, key: &K) -> usize
+where
+    K: Ord,
+{
+    domain
+        .index(key)
+        .expect("the value isn't a key of the domain")
+}
+
+impl<T, I, A> Persistent<T, I, A>
+where
+    T: Clone + Node<Value = usize>,
+    I: NodeIndex,
+    A: Arena<PersistentWrapper<T, I>>,
+{
+    /// Returns the smallest index `p` such that the counts of `[0,p]` in version `v_hi` minus the ones in version `v_lo` add up to more than `k`, descending both versions at the same time.
+    /// The counts of `v_hi` must be at least the ones of `v_lo`, e.g. with the versions of [`build_frequency`](Persistent::build_frequency) the k-th smallest value (counting from `0`) of `values[l..=r]` is at index `lower_bound_diff(r, l-1, k)`.
+    /// ```
+    /// # use seg_tree::{Persistent,utils::{Compressor,Sum},nodes::Node};
+    /// let values = [30, 10, 20, 10, 30];
+    /// let domain: Compressor<i32> = values.into_iter().collect(); // [10,20,30]
+    /// let seg_tree: Persistent<Sum<usize>> = Persistent::build_frequency(&values, &domain);
+    /// assert_eq!(seg_tree.lower_bound_diff(4, 1, 0), Some(0)); // values[2..=4] is [20,10,30], its smallest value is 10.
+    /// assert_eq!(seg_tree.lower_bound_diff(4, 1, 2), Some(2));
+    /// assert_eq!(seg_tree.lower_bound_diff(4, 1, 3), None);
+    /// ```
+    /// It returns None if and only if the counts of `v_hi` minus the ones of `v_lo` add up to at most `k`, in particular if the segment tree has no elements.
+    /// It will **panic** if `v_hi` or `v_lo` are not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn lower_bound_diff(&self, v_hi: VersionId, v_lo: VersionId, k: usize) -> Option<usize> {
+        if self.n == 0 {
+            return None;
+        }
+        let count = |version: VersionId| *self.nodes[self.roots[version]].get_inner().value();
+        if count(v_hi) - count(v_lo) <= k {
+            return None;
+        }
+        let mut k = k;
+        let index = self.descend2(v_hi, v_lo, |left_hi, left_lo| {
+            let left_count = left_hi.value() - left_lo.value();
+            if k < left_count {
+                true
+            } else {
+                k -= left_count;
+                false
+            }
+        });
+        Some(index)
+    }
+}
+
+impl<T, I> Persistent<T, I>
+where
+    T: Clone + Node,
+    I: NodeIndex,
+{
+    /// Same as [`build`](Persistent::build), but the indices of the children of each node are stored as `I` instead of `u32`, see [`NodeIndex`].
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn build_with_index(values: &[T]) -> Self {
+        Self::build_in(values, Vec::with_capacity(4 * values.len()))
+    }
+}
+
+impl<T, I, A> Persistent<T, I, A>
+where
+    T: Clone + Node,
+    I: NodeIndex,
+    A: Arena<PersistentWrapper<T, I>>,
+{
+    /// Same as [`build_with_index`](Persistent::build_with_index), but the nodes are stored in `nodes`, see [`Arena`].
+    /// It will **panic** if `nodes` isn't empty.
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn build_in(values: &[T], nodes: A) -> Self {
+        Self::build_from(values.iter().cloned(), nodes)
+    }
+
+    /// Reopens a segment tree of n elements whose nodes are already in `nodes`, e.g. an arena kept in a memory mapped file by a previous segment tree, see [`into_arena`](Self::into_arena).
+    /// The i-th version is the one whose root is `roots[i]`, as returned by [`roots`](Self::roots), the nodes aren't copied nor checked, so they must be the nodes of a segment tree of n elements.
+    /// The parents and labels of the versions aren't stored in the arena, so no version has a parent afterwards.
+    /// ```
+    /// # use seg_tree::{Persistent,utils::Sum,nodes::Node};
+    /// # let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+    /// let mut seg_tree: Persistent<Sum<usize>> = Persistent::build(&nodes);
+    /// seg_tree.update(0, 3, &10);
+    /// let roots = seg_tree.roots().to_vec();
+    /// let arena = seg_tree.into_arena();
+    /// let seg_tree: Persistent<Sum<usize>> = Persistent::reopen_in(arena, 10, roots);
+    /// assert_eq!(seg_tree.query(1, 0, 9).unwrap().value(), &52);
+    /// ```
+    /// It will **panic** if roots is empty, or if some root isn't in the arena.
+    /// It has time complexity of `O(k)`, where `k` is the amount of versions.
+    pub fn reopen_in(mut nodes: A, n: usize, roots: Vec<usize>) -> Self {
+        assert_roots(&nodes, n, &roots);
+        nodes.publish_roots(&roots);
+        Self {
+            nodes,
+            history: VersionHistory::without_parents(roots.len()),
+            roots,
+            n,
+            interner: None,
+            last_op: LastOp::default(),
+        }
+    }
+
+    /// Returns the index in the arena of the root of each version, in version order, see [`reopen_in`](Self::reopen_in).
+    #[must_use]
+    pub fn roots(&self) -> &[usize] {
+        &self.roots
+    }
+
+    /// Consumes the segment tree, returning the arena with its nodes, see [`reopen_in`](Self::reopen_in).
+    #[must_use]
+    pub fn into_arena(self) -> A {
+        self.nodes
+    }
+
+    /// Builds the segment tree from the leaves, in order.
+    fn build_from<V>(values: V, nodes: A) -> Self
+    where
+        V: ExactSizeIterator<Item = T>,
+    {
+        span!(DEBUG, "build", tree = "Persistent", n = values.len());
+        let last_op = LastOp::default();
+        let _op = last_op.measure();
+        assert_empty(&nodes);
         let n = values.len();
         let mut temp = Self {
-            nodes: Vec::with_capacity(4 * n),
+            nodes,
             roots: Vec::with_capacity(1),
+            history: VersionHistory::default(),
             n,
+            interner: None,
+            last_op,
         };
-        if n == 0 {
-            return temp;
-        }
-        let root = temp.build_helper(values, 0, n - 1);
-        temp.roots.push(root);
+        // An empty tree still has version 0, its root is never read as there are no nodes.
+        let root = if n == 0 {
+            0
+        } else {
+            temp.build_by_level(values)
+        };
+        temp.push_version(root, None);
         temp
     }
 
-    fn build_helper(&mut self, values: &[T], i: usize, j: usize) -> usize {
-        let mid = (i + j) / 2;
-        if i == j {
-            let curr_node = self.nodes.len();
-            self.nodes.push(values[i].clone().into());
-            return curr_node;
+    /// Pushes the leaves contiguously, then the internal nodes level by level from the deepest one up to the root, so each level is contiguous too.
+    /// The shape is the same as splitting recursively at the midpoint, returns the index of the root.
+    fn build_by_level<V>(&mut self, values: V) -> usize
+    where
+        V: Iterator<Item = T>,
+    {
+        let leaves: Vec<usize> = values.map(|value| self.push_node(value.into())).collect();
+        // The internal segments of each depth, the children of a segment are leaves or are in the next depth, in the same order.
+        let mut levels: Vec<Vec<(usize, usize)>> = Vec::new();
+        let mut level: Vec<(usize, usize)> = Vec::from([(0, leaves.len() - 1)]);
+        level.retain(|&(i, j)| i < j);
+        while !level.is_empty() {
+            let next = level
+                .iter()
+                .flat_map(|&(i, j)| {
+                    let mid = usize::midpoint(i, j);
+                    [(i, mid), (mid + 1, j)]
+                })
+                .filter(|&(i, j)| i < j)
+                .collect();
+            levels.push(level);
+            level = next;
         }
-        let left_node = self.build_helper(values, i, mid);
-        let right_node = self.build_helper(values, mid + 1, j);
-        let curr_node = self.nodes.len();
-        self.nodes
-            .push(Node::combine(&self.nodes[left_node], &self.nodes[right_node]));
-        self.nodes[curr_node].set_children(left_node, right_node);
-        curr_node
+        let mut below: Vec<usize> = Vec::new();
+        for level in levels.iter().rev() {
+            let mut next = 0;
+            let mut child = |i: usize, j: usize| {
+                if i == j {
+                    leaves[i]
+                } else {
+                    next += 1;
+                    below[next - 1]
+                }
+            };
+            let current: Vec<usize> = level
+                .iter()
+                .map(|&(i, j)| {
+                    let mid = usize::midpoint(i, j);
+                    let left_node = child(i, mid);
+                    let right_node = child(mid + 1, j);
+                    self.push_combined(left_node, right_node)
+                })
+                .collect();
+            below = current;
+        }
+        below.first().copied().unwrap_or(leaves[0])
+    }
+
+    /// Adds a version whose root is root and whose parent is parent, and returns it.
+    fn push_version(&mut self, root: usize, parent: Option<VersionId>) -> VersionId {
+        self.roots.push(root);
+        self.history.push(parent);
+        self.nodes.publish_roots(&self.roots);
+        self.roots.len() - 1
+    }
+
+    fn push_node(&mut self, node: PersistentWrapper<T, I>) -> usize {
+        let len = self.nodes.len();
+        let index = push_node(&mut self.nodes, self.interner.as_mut(), node);
+        record_allocations(self.nodes.len() - len);
+        index
+    }
+
+    fn push_combined(&mut self, left_node: usize, right_node: usize) -> usize {
+        let mut node: PersistentWrapper<T, I> =
+            combine(&self.nodes[left_node], &self.nodes[right_node]);
+        node.set_children(left_node, right_node);
+        self.push_node(node)
     }
 
     /// Returns the result from the range `[left,right]` from the version of the segment tree.
-    /// It returns None if and only if range is empty.
-    /// It will **panic** if left or right are not in `[0,n)`, or if version is not in `[0,`[`versions`](Self::versions)`)`.
+    /// It returns None if and only if range is empty or the segment tree has no elements.
+    /// It will **panic** if left or right are not in `[0,n)`, or if version is not in <code>[0,[versions](Self::versions))</code>.
     /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
     #[allow(clippy::must_use_candidate)]
     pub fn query(&self, version: usize, left: usize, right: usize) -> Option<T> {
-        self.query_helper(self.roots[version], left, right, 0, self.n - 1)
+        span!(TRACE, "query", tree = "Persistent", n = self.n, version, left, right);
+        let _op = self.last_op.measure();
+        let root = self.roots[version];
+        if self.n == 0 {
+            return None;
+        }
+        query_helper(&self.nodes, root, left, right, 0, self.n - 1)
             .map(PersistentWrapper::into_inner)
     }
 
-    fn query_helper(
+    /// Same as [`query`](Self::query), but the range is clamped to `[0,n)` instead of **panicking** when it's out of bounds, and the range that was actually aggregated is returned with the result, as a half-open range.
+    /// It returns None if and only if the clamped range is empty.
+    /// It will **panic** if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn query_with_range(
         &self,
-        curr_node: usize,
+        version: usize,
         left: usize,
         right: usize,
-        i: usize,
-        j: usize,
-    ) -> Option<PersistentWrapper<T>> {
-        if j < left || right < i {
+    ) -> Option<(Range<usize>, T)> {
+        let range = clamp(left, right, self.n);
+        let (left, right) = range
+            .as_ref()
+            .map_or((1, 0), |range| (range.start, range.end - 1));
+        range.zip(self.query(version, left, right))
+    }
+
+    /// Same as [`query_with_range`](Self::query_with_range), but only the result is returned.
+    /// It returns None if and only if the range clamped to `[0,n)` is empty.
+    /// It will **panic** if version is not in <code>[0,[versions](Self::versions))</code>.
+    #[must_use]
+    pub fn query_clamped(&self, version: usize, left: usize, right: usize) -> Option<T> {
+        self.query_with_range(version, left, right)
+            .map(|(_, node)| node)
+    }
+
+    /// Returns the node of the version of the segment tree which covers exactly the segment `[i,j]`, e.g. to implement custom descents on top of the segment tree.
+    /// It returns None if and only if no node covers exactly `[i,j]`, which is always the case if `i>j` or `j` is not in `[0,n)`.
+    /// It will **panic** if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(log(n))`.
+    pub fn get_segment(&self, version: usize, i: usize, j: usize) -> Option<&T> {
+        if i > j || j >= self.n {
             return None;
         }
-        if left <= i && j <= right {
-            return Some(self.nodes[curr_node].clone());
-        }
-        let mid = (i + j) / 2;
-        let left_node = self.nodes[curr_node].left_child().unwrap().get();
-        let right_node = self.nodes[curr_node].right_child().unwrap().get();
-        match (
-            self.query_helper(left_node, left, right, i, mid),
-            self.query_helper(right_node, left, right, mid + 1, j),
-        ) {
-            (Some(ans_left), Some(ans_right)) => Some(Node::combine(&ans_left, &ans_right)),
-            (Some(ans_left), None) => Some(ans_left),
-            (None, Some(ans_right)) => Some(ans_right),
-            (None, None) => None,
+        let (mut curr_node, mut l, mut r) = (self.roots[version], 0, self.n - 1);
+        while (l, r) != (i, j) {
+            let mid = usize::midpoint(l, r);
+            if j <= mid {
+                (curr_node, r) = (self.nodes[curr_node].left_child()?, mid);
+            } else if mid < i {
+                (curr_node, l) = (self.nodes[curr_node].right_child()?, mid + 1);
+            } else {
+                return None;
+            }
         }
+        Some(self.nodes[curr_node].get_inner())
     }
 
     /// Creates a new segment tree version from version were the p-th element of the segment tree to value T and update the segment tree correspondingly.
-    /// It will panic if p is not in `[0,n)`, or if version is not in `[0,`[`versions`](Self::versions)`)`.
+    /// It will panic if p is not in `[0,n)`, or if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It returns the new version, i.e. <code>[versions](Self::versions)-1</code> after the call.
     /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
-    pub fn update(&mut self, version: usize, p: usize, value: &<T as Node>::Value) {
-        let new_root = self.update_helper(self.roots[version], p, value, 0, self.n - 1);
-        self.roots.push(new_root);
+    pub fn update(&mut self, version: usize, p: usize, value: &<T as Node>::Value) -> VersionId {
+        span!(TRACE, "update", tree = "Persistent", n = self.n, version, p);
+        let _op = self.last_op.measure();
+        assert_not_empty(self.n, "update");
+        let leaf = |_: &<T as Node>::Value| T::initialize(value);
+        let new_root = self.update_helper(self.roots[version], p, leaf, 0, self.n - 1);
+        self.push_version(new_root, Some(version))
+    }
+
+    /// Same as [`update`](Self::update), but the new value of the p-th element is computed by `f` from its value in version, so a read-modify-write takes a single descent.
+    /// It will panic if p is not in `[0,n)`, or if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It returns the new version, i.e. <code>[versions](Self::versions)-1</code> after the call.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) and `f` have constant time complexity.
+    pub fn update_with<F>(&mut self, version: usize, p: usize, f: F) -> VersionId
+    where
+        F: FnOnce(&<T as Node>::Value) -> <T as Node>::Value,
+    {
+        span!(
+            TRACE,
+            "update_with",
+            tree = "Persistent",
+            n = self.n,
+            version,
+            p
+        );
+        let _op = self.last_op.measure();
+        assert_not_empty(self.n, "update");
+        let leaf = |old: &<T as Node>::Value| T::initialize(&f(old));
+        let new_root = self.update_helper(self.roots[version], p, leaf, 0, self.n - 1);
+        self.push_version(new_root, Some(version))
+    }
+
+    /// Creates a single new segment tree version from version where each `(p, value)` of updates sets the p-th element to value, sharing every subtree none of them touches.
+    /// If several updates have the same position the last one is kept, and if updates is empty the new version is equal to version.
+    /// It will panic if some p is not in `[0,n)`, or if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It returns the new version, i.e. <code>[versions](Self::versions)-1</code> after the call.
+    /// It has time complexity of `O(k*log(n))`, where `k` is the length of updates, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn update_many(
+        &mut self,
+        version: usize,
+        updates: &[(usize, <T as Node>::Value)],
+    ) -> VersionId {
+        span!(
+            TRACE,
+            "update_many",
+            tree = "Persistent",
+            n = self.n,
+            version,
+            k = updates.len()
+        );
+        let _op = self.last_op.measure();
+        assert_not_empty(self.n, "update");
+        let mut sorted: Vec<&(usize, <T as Node>::Value)> = updates.iter().collect();
+        sorted.sort_by_key(|(p, _)| *p);
+        let new_root = self.update_many_helper(self.roots[version], &sorted, 0, self.n - 1);
+        self.push_version(new_root, Some(version))
     }
 
-    fn update_helper(
+    fn update_many_helper(
         &mut self,
         curr_node: usize,
-        p: usize,
-        value: &<T as Node>::Value,
+        updates: &[&(usize, <T as Node>::Value)],
         i: usize,
         j: usize,
     ) -> usize {
+        let _depth = enter();
+        if updates.is_empty() {
+            return curr_node;
+        }
+        if i == j {
+            return match updates.iter().rev().find(|(p, _)| *p == i) {
+                Some((_, value)) => self.push_node(Node::initialize(value)),
+                None => curr_node,
+            };
+        }
+        let mid = usize::midpoint(i, j);
+        let (left_updates, right_updates) =
+            updates.split_at(updates.partition_point(|(p, _)| *p <= mid));
+        let left_node = self.nodes[curr_node].left_child().unwrap();
+        let right_node = self.nodes[curr_node].right_child().unwrap();
+        let left_node = self.update_many_helper(left_node, left_updates, i, mid);
+        let right_node = self.update_many_helper(right_node, right_updates, mid + 1, j);
+        self.push_combined(left_node, right_node)
+    }
+
+    fn update_helper<F>(&mut self, curr_node: usize, p: usize, leaf: F, i: usize, j: usize) -> usize
+    where
+        F: FnOnce(&<T as Node>::Value) -> T,
+    {
+        let _depth = enter();
         if j < p || p < i {
             return curr_node;
         }
-        let x = self.nodes.len();
-        self.nodes.push(self.nodes[curr_node].clone());
         if i == j {
-            self.nodes[x] = Node::initialize(value);
-            return x;
-        }
-        let mid = (i + j) / 2;
-        let left_node =
-            self.update_helper(self.nodes[x].left_child().unwrap().get(), p, value, i, mid);
-        let right_node = self.update_helper(
-            self.nodes[x].right_child().unwrap().get(),
-            p,
-            value,
-            mid + 1,
-            j,
-        );
-        self.nodes[x] = Node::combine(&self.nodes[left_node], &self.nodes[right_node]);
-        self.nodes[x].set_children(left_node, right_node);
-        x
+            let node = leaf(self.nodes[curr_node].value());
+            return self.push_node(node.into());
+        }
+        let mid = usize::midpoint(i, j);
+        let mut left_node = self.nodes[curr_node].left_child().unwrap();
+        let mut right_node = self.nodes[curr_node].right_child().unwrap();
+        if p <= mid {
+            left_node = self.update_helper(left_node, p, leaf, i, mid);
+        } else {
+            right_node = self.update_helper(right_node, p, leaf, mid + 1, j);
+        }
+        self.push_combined(left_node, right_node)
+    }
+    /// Returns the amount of elements of the segment tree.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.n
+    }
+
+    /// Returns `true` if the segment tree has no elements.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.n == 0
     }
+
     /// Returns the amount of different versions the current segment tree has. Essentially this will be how many calls to [`update`](Self::update) have happened. 
     #[allow(clippy::must_use_candidate)]
-    pub fn versions(&self) -> usize {
+    pub const fn versions(&self) -> usize {
         self.roots.len()
     }
 
+    /// Returns the versions of the segment tree in creation order, that is, <code>0..[versions](Self::versions)</code>, so they can be enumerated without tracking their amount.
+    /// ```
+    /// # use seg_tree::{Persistent,utils::Sum,nodes::Node};
+    /// # let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+    /// let mut seg_tree = Persistent::build(&nodes);
+    /// seg_tree.update(0, 3, &7);
+    /// let sums: Vec<usize> = seg_tree
+    ///     .versions_iter()
+    ///     .map(|version| *seg_tree.query(version, 0, 9).unwrap().value())
+    ///     .collect();
+    /// assert_eq!(sums, [45, 49]);
+    /// ```
+    #[must_use]
+    pub const fn versions_iter(&self) -> Range<VersionId> {
+        0..self.roots.len()
+    }
+
     /// A method that finds the smallest prefix[^note] `u` such that `predicate(u.value(), value)` is `true`. The following must be true:
     /// - `predicate` is monotonic over prefixes[^note2].
     /// - `g` will satisfy the following, given segments `[i,j]` and `[i,k]` with `j<k` we have that `predicate([i,k].value(),value)` implies `predicate([j+1,k].value(),g([i,j].value(),value))`.
@@ -160,6 +602,8 @@ where
     /// # }
     /// ```
     ///
+    /// It will **panic** if the segment tree has no elements.
+    ///
     /// [^note]: A prefix is a segment of the form `[0,i]`.
     ///
     /// [^note2]: Given two prefixes `u` and `v` if `u` is contained in `v` then `predicate(u.value(), value)` implies `predicate(v.value(), value)`.
@@ -174,6 +618,31 @@ where
         F: Fn(&<T as Node>::Value, &<T as Node>::Value) -> bool,
         G: Fn(&<T as Node>::Value, <T as Node>::Value) -> <T as Node>::Value,
     {
+        self.lower_bound_by_node(
+            version,
+            |node: &T, value: &<T as Node>::Value| predicate(node.value(), value),
+            |node: &T, value| g(node.value(), value),
+            value,
+        )
+    }
+
+    /// Same as [`lower_bound`](Self::lower_bound), but `predicate` and `g` receive the node of the segment instead of its value, so nodes with several fields can be searched by any of them.
+    /// The same conditions must be true, taking the node of each segment instead of its value.
+    ///
+    /// It will **panic** if the segment tree has no elements, or if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine), `predicate` and `g` have constant time complexity.
+    pub fn lower_bound_by_node<F, G>(
+        &self,
+        version: usize,
+        predicate: F,
+        g: G,
+        value: <T as Node>::Value,
+    ) -> usize
+    where
+        F: Fn(&T, &<T as Node>::Value) -> bool,
+        G: Fn(&T, <T as Node>::Value) -> <T as Node>::Value,
+    {
+        assert_not_empty(self.n, "search");
         self.lower_bound_helper(self.roots[version], 0, self.n - 1, predicate, g, value)
     }
     fn lower_bound_helper<F, G>(
@@ -186,64 +655,936 @@ where
         value: <T as Node>::Value,
     ) -> usize
     where
-        F: Fn(&<T as Node>::Value, &<T as Node>::Value) -> bool,
-        G: Fn(&<T as Node>::Value, <T as Node>::Value) -> <T as Node>::Value,
+        F: Fn(&T, &<T as Node>::Value) -> bool,
+        G: Fn(&T, <T as Node>::Value) -> <T as Node>::Value,
     {
+        let _depth = enter();
         if i == j {
             return i;
         }
-        let mid = (i + j) / 2;
-        let left_node = self.nodes[curr_node].left_child().unwrap().get();
-        let right_node = self.nodes[curr_node].right_child().unwrap().get();
-        let left_value = self.nodes[left_node].value();
-        if predicate(left_value, &value) {
+        let mid = usize::midpoint(i, j);
+        let left_node = self.nodes[curr_node].left_child().unwrap();
+        let right_node = self.nodes[curr_node].right_child().unwrap();
+        let left = &self.nodes[left_node].get_inner();
+        if predicate(left, &value) {
             self.lower_bound_helper(left_node, i, mid, predicate, g, value)
         } else {
-            let value = g(left_value, value);
+            let value = g(left, value);
             self.lower_bound_helper(right_node, mid + 1, j, predicate, g, value)
         }
     }
-}
 
+    /// Mirror of [`lower_bound`](Self::lower_bound), it finds the smallest suffix[^note] `u` from the version of the segment tree such that `predicate(u.value(), value)` is `true`, and returns the index where `u` starts. The following must be true:
+    /// - `predicate` is monotonic over suffixes[^note2].
+    /// - `g` will satisfy the following, given segments `[j,k]` and `[i,k]` with `i<j` we have that `predicate([i,k].value(),value)` implies `predicate([i,j-1].value(),g([j,k].value(),value))`.
+    ///
+    /// It will **panic** if the segment tree has no elements, or if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine), `predicate` and `g` have constant time complexity.
+    ///
+    /// [^note]: A suffix is a segment of the form `[i,n-1]`.
+    ///
+    /// [^note2]: Given two suffixes `u` and `v` if `u` is contained in `v` then `predicate(u.value(), value)` implies `predicate(v.value(), value)`.
+    pub fn upper_bound<F, G>(
+        &self,
+        version: usize,
+        predicate: F,
+        g: G,
+        value: <T as Node>::Value,
+    ) -> usize
+    where
+        F: Fn(&<T as Node>::Value, &<T as Node>::Value) -> bool,
+        G: Fn(&<T as Node>::Value, <T as Node>::Value) -> <T as Node>::Value,
+    {
+        assert_not_empty(self.n, "search");
+        self.upper_bound_helper(self.roots[version], 0, self.n - 1, predicate, g, value)
+    }
+    fn upper_bound_helper<F, G>(
+        &self,
+        curr_node: usize,
+        i: usize,
+        j: usize,
+        predicate: F,
+        g: G,
+        value: <T as Node>::Value,
+    ) -> usize
+    where
+        F: Fn(&<T as Node>::Value, &<T as Node>::Value) -> bool,
+        G: Fn(&<T as Node>::Value, <T as Node>::Value) -> <T as Node>::Value,
+    {
+        let _depth = enter();
+        if i == j {
+            return i;
+        }
+        let mid = usize::midpoint(i, j);
+        let left_node = self.nodes[curr_node].left_child().unwrap();
+        let right_node = self.nodes[curr_node].right_child().unwrap();
+        let right_value = self.nodes[right_node].value();
+        if predicate(right_value, &value) {
+            self.upper_bound_helper(right_node, mid + 1, j, predicate, g, value)
+        } else {
+            let value = g(right_value, value);
+            self.upper_bound_helper(left_node, i, mid, predicate, g, value)
+        }
+    }
 
-impl<T> core::fmt::Debug for Persistent<T>
-where
-    T: core::fmt::Debug,
-{
-    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        let len = self.nodes.len();
-        f.debug_struct("Persistent")
-            .field("n", &self.n)
-            .field(
-                "nodes",
-                &as_dbg_tree(&self.nodes, {
-                    |nodes, f| {
-                        let mut visited = BitVec::from_elem(len, false);
-                        for root_node in &self.roots {
-                            persistent_visitor(
-                                *root_node,
-                                0,
-                                self.n - 1,
-                                f,
-                                nodes,
-                                &mut visited,
-                            );
-                        }
-                    }
-                }),
-            )
-            .finish()
+    /// Same as [`lower_bound`](Self::lower_bound), but it finds the smallest segment `[left,k]` with `k` in `[left,right]` such that `predicate([left,k].value(), value)` is `true`, from the version of the segment tree, and returns `k`.
+    /// `predicate` and `g` must satisfy the same conditions, over the segments which start at left instead of the prefixes.
+    /// The range is split into the nodes which cover it, and the search only descends into the first one where `predicate` becomes `true`.
+    /// It returns None if and only if there's no such `k`, in particular if the range is empty.
+    /// It will **panic** if the segment tree has no elements, or if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine), `predicate` and `g` have constant time complexity.
+    pub fn lower_bound_in<F, G>(
+        &self,
+        version: usize,
+        left: usize,
+        right: usize,
+        predicate: F,
+        g: G,
+        value: <T as Node>::Value,
+    ) -> Option<usize>
+    where
+        F: Fn(&<T as Node>::Value, &<T as Node>::Value) -> bool,
+        G: Fn(&<T as Node>::Value, <T as Node>::Value) -> <T as Node>::Value,
+    {
+        assert_not_empty(self.n, "search");
+        let root = self.roots[version];
+        let fns = (&predicate, &g);
+        self.lower_bound_in_helper(root, 0, self.n - 1, (left, right), fns, value).ok()
     }
-}
 
+    /// Returns the index found in the intersection of `[i,j]` and `range`, or the value left for the rest of the range if there's none.
+    fn lower_bound_in_helper<F, G>(
+        &self,
+        curr_node: usize,
+        i: usize,
+        j: usize,
+        (left, right): (usize, usize),
+        (predicate, g): (&F, &G),
+        value: <T as Node>::Value,
+    ) -> Result<usize, <T as Node>::Value>
+    where
+        F: Fn(&<T as Node>::Value, &<T as Node>::Value) -> bool,
+        G: Fn(&<T as Node>::Value, <T as Node>::Value) -> <T as Node>::Value,
+    {
+        let _depth = enter();
+        if j < left || right < i {
+            return Err(value);
+        }
+        if left <= i && j <= right {
+            let curr_value = self.nodes[curr_node].value();
+            if !predicate(curr_value, &value) {
+                return Err(g(curr_value, value));
+            }
+            let predicate = |node: &T, value: &<T as Node>::Value| predicate(node.value(), value);
+            let g = |node: &T, value| g(node.value(), value);
+            return Ok(self.lower_bound_helper(curr_node, i, j, predicate, g, value));
+        }
+        let mid = usize::midpoint(i, j);
+        let left_node = self.nodes[curr_node].left_child().unwrap();
+        let right_node = self.nodes[curr_node].right_child().unwrap();
+        let (range, fns) = ((left, right), (predicate, g));
+        match self.lower_bound_in_helper(left_node, i, mid, range, fns, value) {
+            Ok(index) => Ok(index),
+            Err(value) => self.lower_bound_in_helper(right_node, mid + 1, j, range, fns, value),
+        }
+    }
 
-#[cfg(test)]
-mod tests {
-    use crate::{nodes::Node, segment_tree::Persistent, utils::Sum};
-    #[test]
-    fn non_empty_query_returns_some() {
-        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
-        let segment_tree = Persistent::build(&nodes);
+    /// Creates a new version by merging versions `a` and `b` node by node, and returns it. Its parent is `a`.
+    ///
+    /// Each pair of leaves is merged with `merge_leaves`, and the inner nodes are recomputed with [`combine`](Node::combine).
+    /// The recursion stops as soon as one of the subtrees is empty according to `is_empty`, reusing the other subtree as is, so merging many sparse trees, e.g. counting trees in small-to-large merging, has time complexity of `O(m*log(n))` in total, where `m` is the amount of non-empty leaves.
+    /// ```
+    /// # use seg_tree::{Persistent,utils::Sum,nodes::Node};
+    /// let nodes: Vec<Sum<usize>> = vec![Sum::initialize(&0); 8];
+    /// let mut seg_tree = Persistent::build(&nodes);
+    /// seg_tree.update(0, 1, &2); // Version 1 counts two 1s.
+    /// seg_tree.update(0, 5, &1); // Version 2 counts one 5.
+    /// let merged = seg_tree.merge(1, 2, |node| *node.value() == 0, |a, b| Sum::combine(a, b));
+    /// assert_eq!(seg_tree.query(merged, 0, 7).unwrap().value(), &3);
+    /// assert_eq!(seg_tree.query(merged, 5, 5).unwrap().value(), &1);
+    /// ```
+    /// It will **panic** if either version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(n)` in the worst case, assuming that [`combine`](Node::combine) and `merge_leaves` have constant time complexity.
+    pub fn merge<E, F>(
+        &mut self,
+        a: VersionId,
+        b: VersionId,
+        is_empty: E,
+        merge_leaves: F,
+    ) -> VersionId
+    where
+        E: Fn(&T) -> bool,
+        F: Fn(&T, &T) -> T,
+    {
+        assert_not_empty(self.n, "merge versions of");
+        let new_root = self.merge_helper(
+            self.roots[a],
+            self.roots[b],
+            0,
+            self.n - 1,
+            &is_empty,
+            &merge_leaves,
+        );
+        self.push_version(new_root, Some(a))
+    }
+
+    fn merge_helper<E, F>(
+        &mut self,
+        node_a: usize,
+        node_b: usize,
+        i: usize,
+        j: usize,
+        is_empty: &E,
+        merge_leaves: &F,
+    ) -> usize
+    where
+        E: Fn(&T) -> bool,
+        F: Fn(&T, &T) -> T,
+    {
+        let _depth = enter();
+        if is_empty(self.nodes[node_b].get_inner()) {
+            return node_a;
+        }
+        if is_empty(self.nodes[node_a].get_inner()) {
+            return node_b;
+        }
+        if i == j {
+            let node = merge_leaves(self.nodes[node_a].get_inner(), self.nodes[node_b].get_inner());
+            return self.push_node(node.into());
+        }
+        let mid = usize::midpoint(i, j);
+        let (left_a, right_a) = self.children(node_a);
+        let (left_b, right_b) = self.children(node_b);
+        let left_node = self.merge_helper(left_a, left_b, i, mid, is_empty, merge_leaves);
+        let right_node = self.merge_helper(right_a, right_b, mid + 1, j, is_empty, merge_leaves);
+        self.push_combined(left_node, right_node)
+    }
+
+    /// Descends from the roots of versions `va` and `vb` at the same time until reaching a leaf, and returns its index.
+    /// At each node `go_left` is called with the left children of both versions, and the descent continues on the left children if it returns `true`, so it can keep any state about the segments it skipped.
+    /// It's the base of the algorithms which compare two versions segment by segment, e.g. [`lower_bound_diff`](Persistent::lower_bound_diff), or finding a value which appears in more than half of a subarray.
+    /// ```
+    /// # use seg_tree::{Persistent,utils::{Compressor,Sum},nodes::Node};
+    /// let values = [1, 2, 2, 3, 2];
+    /// let domain: Compressor<i32> = values.into_iter().collect(); // [1,2,3]
+    /// let seg_tree: Persistent<Sum<usize>> = Persistent::build_frequency(&values, &domain);
+    /// // Goes to the half with more than half of values[1..=4], which holds the value appearing more than twice if there's one.
+    /// let heavy = seg_tree.descend2(4, 0, |left_hi, left_lo| 2 * (left_hi.value() - left_lo.value()) > 4);
+    /// assert_eq!(domain.key(heavy), &2);
+    /// ```
+    /// It will **panic** if the segment tree has no elements, or if `va` or `vb` are not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(log(n))`, assuming that `go_left` has constant time complexity.
+    pub fn descend2<F>(&self, va: VersionId, vb: VersionId, mut go_left: F) -> usize
+    where
+        F: FnMut(&T, &T) -> bool,
+    {
+        assert_not_empty(self.n, "search");
+        let (mut node_a, mut node_b) = (self.roots[va], self.roots[vb]);
+        let (mut i, mut j) = (0, self.n - 1);
+        while i != j {
+            let mid = usize::midpoint(i, j);
+            let (left_a, right_a) = self.children(node_a);
+            let (left_b, right_b) = self.children(node_b);
+            if go_left(
+                self.nodes[left_a].get_inner(),
+                self.nodes[left_b].get_inner(),
+            ) {
+                (node_a, node_b, j) = (left_a, left_b, mid);
+            } else {
+                (node_a, node_b, i) = (right_a, right_b, mid + 1);
+            }
+        }
+        i
+    }
+
+    fn children(&self, curr_node: usize) -> (usize, usize) {
+        let node = &self.nodes[curr_node];
+        (node.left_child().unwrap(), node.right_child().unwrap())
+    }
+
+    /// Returns the counters of the last build, query or update of the segment tree, see [`OpStats`](crate::instrument::OpStats).
+    #[cfg(feature = "instrument")]
+    #[must_use]
+    pub fn stats(&self) -> crate::instrument::OpStats {
+        self.last_op.get()
+    }
+
+    /// Returns the memory used by the segment tree, see [`MemoryStats`].
+    /// The roots and metadata of each version and the hash table of a [`deduplicated`](Persistent::deduplicated) tree are accounted for in [`overhead_bytes`](MemoryStats::overhead_bytes).
+    /// See [`memory_stats_by_version`](Self::memory_stats_by_version) for the nodes owned and shared by each version.
+    #[must_use]
+    pub fn memory_stats(&self) -> MemoryStats {
+        MemoryStats {
+            nodes: self.nodes.len(),
+            capacity: self.nodes.capacity(),
+            node_size: core::mem::size_of::<PersistentWrapper<T, I>>(),
+            versions: self.roots.len(),
+            overhead_bytes: self.roots.capacity() * core::mem::size_of::<usize>()
+                + self.history.reserved_bytes()
+                + self.interner.as_ref().map_or(0, Interner::reserved_bytes),
+        }
+    }
+
+    /// Returns how many nodes the version introduced and how many it shares with its [parent](Self::version_parent), see [`VersionStats`].
+    /// It will **panic** if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(k)`, where `k` is the amount of nodes reachable from the version and its parent.
+    #[must_use]
+    pub fn version_stats(&self, version: VersionId) -> VersionStats {
+        let parent = self.history.parent(version).map(|parent| self.roots[parent]);
+        version_stats(&self.nodes, self.roots[version], parent)
+    }
+
+    /// Returns the [`version_stats`](Self::version_stats) of every version, in version order, so the nodes of [`memory_stats`](Self::memory_stats) can be broken down into the ones each version owns, its [`introduced`](VersionStats::introduced) nodes, and the ones it [`shares`](VersionStats::shared) with its parent.
+    /// It has time complexity of `O(v*m)`, where `v` is the amount of versions and `m` the amount of nodes reachable from a version and its parent.
+    #[must_use]
+    pub fn memory_stats_by_version(&self) -> Vec<VersionStats> {
+        self.versions_iter()
+            .map(|version| self.version_stats(version))
+            .collect()
+    }
+
+    /// Checks that every internal node of every version is the combination of its children, so a node whose [`combine`](Node::combine) isn't deterministic, or corrupted nodes, are caught where they happen instead of as wrong answers later on.
+    /// It's meant for tests and debug assertions.
+    /// It will **panic** at the first node which doesn't match.
+    /// It has time complexity of `O(m)`, where `m` is the amount of nodes reachable from any version, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn validate(&self)
+    where
+        <T as Node>::Value: PartialEq,
+    {
+        let mut visited = BitVec::from_elem(self.nodes.len(), false);
+        let children = |curr_node: usize| {
+            let node = &self.nodes[curr_node];
+            node.left_child().zip(node.right_child())
+        };
+        if self.n == 0 {
+            return;
+        }
+        for &root in &self.roots {
+            validate(&self.nodes, &children, &mut visited, root, 0, self.n - 1);
+        }
+    }
+
+    /// Reserves memory for at least `q` more updates, so that long update sequences don't repeatedly reallocate and copy the nodes.
+    /// An update creates at most `log(n)+2` nodes.
+    pub fn reserve_updates(&mut self, q: usize) {
+        #[allow(clippy::cast_possible_truncation)]
+        let nodes_per_update = self.n.next_power_of_two().ilog2() as usize + 2;
+        self.nodes.reserve(q.saturating_mul(nodes_per_update));
+        self.roots.reserve(q);
+        self.history.reserve(q);
+    }
+
+    /// Releases the memory reserved for nodes and versions which isn't currently used.
+    /// Building reserves memory for `4n` nodes, which is often more than needed.
+    pub fn shrink_to_fit(&mut self) {
+        self.nodes.shrink_to_fit();
+        self.roots.shrink_to_fit();
+        self.history.shrink_to_fit();
+    }
+
+    /// Keeps only the given versions, discarding every other version and freeing every node which isn't reachable from the kept versions.
+    /// After this call version `i` is the version which was `versions[i]`, parents and labels of discarded versions are dropped.
+    /// It will **panic** if any version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(m)`, where `m` is the amount of nodes currently stored.
+    pub fn retain_versions(&mut self, versions: &[VersionId]) {
+        self.roots = versions.iter().map(|&version| self.roots[version]).collect();
+        self.history.retain(versions);
+        self.gc();
+    }
+
+    /// Returns the first version for which `predicate` applied to the result from the range `[left,right]` is `false`, or [`versions`](Self::versions) if there is none, binary searching the versions like [`partition_point`](slice::partition_point).
+    /// `predicate` must be `true` for a prefix of the versions and `false` for the rest, which is the case when the versions are a sequence of updates which make the predicate monotonic, e.g. the first version where the sum of `[left,right]` is at least `k`:
+    /// ```
+    /// # use seg_tree::{Persistent,utils::Sum,nodes::Node};
+    /// # let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+    /// let mut seg_tree = Persistent::build(&nodes); // [0,1,2,3,4,5,6,7,8,9] with Sum<usize> nodes
+    /// for i in 0..10 {
+    ///     seg_tree.update(i, i, &10); // Version i+1 has its first i+1 elements set to 10.
+    /// }
+    /// let version = seg_tree.partition_versions(0, 2, |node| *node.value() < 25);
+    /// assert_eq!(version, 3); // [10,10,10] is the first to reach 25.
+    /// ```
+    /// It will **panic** if left or right are not in `[0,n)`, or if the range is empty.
+    /// It has time complexity of `O(log(v)*log(n))`, where `v` is the amount of versions, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn partition_versions<P>(&self, left: usize, right: usize, predicate: P) -> VersionId
+    where
+        P: Fn(&T) -> bool,
+    {
+        partition_versions(self.roots.len(), |version| {
+            predicate(&self.non_empty_query(version, left, right))
+        })
+    }
+
+    /// Returns the first version where `predicate` applied to the result from the range `[left,right]` is `true`, or None if there is none, see [`partition_versions`](Self::partition_versions).
+    /// `predicate` must be `false` for a prefix of the versions and `true` for the rest, e.g. the first version where the sum of `[left,right]` reaches a threshold:
+    /// ```
+    /// # use seg_tree::{Persistent,utils::Sum,nodes::Node};
+    /// # let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+    /// let mut seg_tree = Persistent::build(&nodes); // [0,1,2,3,4,5,6,7,8,9] with Sum<usize> nodes
+    /// for i in 0..10 {
+    ///     seg_tree.update(i, i, &10); // Version i+1 has its first i+1 elements set to 10.
+    /// }
+    /// assert_eq!(seg_tree.first_version_where(0, 2, |node| *node.value() >= 25), Some(3));
+    /// assert_eq!(seg_tree.first_version_where(0, 2, |node| *node.value() >= 100), None);
+    /// ```
+    /// It will **panic** if left or right are not in `[0,n)`, or if the range is empty.
+    /// It has time complexity of `O(log(v)*log(n))`, where `v` is the amount of versions, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn first_version_where<P>(
+        &self,
+        left: usize,
+        right: usize,
+        predicate: P,
+    ) -> Option<VersionId>
+    where
+        P: Fn(&T) -> bool,
+    {
+        let version = self.partition_versions(left, right, |node| !predicate(node));
+        (version < self.roots.len()).then_some(version)
+    }
+
+    fn non_empty_query(&self, version: VersionId, left: usize, right: usize) -> T {
+        self.query(version, left, right)
+            .unwrap_or_else(|| panic!("range [{left}, {right}] is empty"))
+    }
+
+    /// Returns the values the p-th element has had across versions, the i-th value is the p-th element in version `i`.
+    /// It will **panic** if p is not in `[0,n)`.
+    /// It has time complexity of `O(log(n))` per version, and doesn't clone any node.
+    pub fn history(&self, p: usize) -> impl Iterator<Item = &<T as Node>::Value> + '_ {
+        self.roots
+            .iter()
+            .map(move |&root| self.nodes[self.leaf(root, p)].value())
+    }
+
+    /// Same as [`history`](Self::history), but it only returns the versions in which the p-th element was changed, that is, the versions created by the build and the versions whose p-th element differs from the one in their [parent](Self::version_parent), along with the value.
+    /// Elements are compared by identity, so an update which sets the p-th element to the value it already had counts as a change.
+    /// It will **panic** if p is not in `[0,n)`.
+    /// It has time complexity of `O(log(n))` per version.
+    pub fn history_changes(
+        &self,
+        p: usize,
+    ) -> impl Iterator<Item = (VersionId, &<T as Node>::Value)> + '_ {
+        self.versions_iter().filter_map(move |version| {
+            let leaf = self.leaf(self.roots[version], p);
+            let changed = self
+                .history
+                .parent(version)
+                .is_none_or(|parent| self.leaf(self.roots[parent], p) != leaf);
+            changed.then(|| (version, self.nodes[leaf].value()))
+        })
+    }
+
+    /// Returns the values of the leaves of version, in order, so the version can be exported as a plain array and imported back with [`from_version_snapshots`](Persistent::from_version_snapshots).
+    /// It will **panic** if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(n)`.
+    #[must_use]
+    pub fn snapshot_values(&self, version: VersionId) -> Vec<<T as Node>::Value> {
+        let root = self.roots[version];
+        let mut values = Vec::with_capacity(self.n);
+        if self.n > 0 {
+            self.snapshot_helper(root, 0, self.n - 1, &mut values);
+        }
+        values
+    }
+
+    fn snapshot_helper(
+        &self,
+        curr_node: usize,
+        i: usize,
+        j: usize,
+        values: &mut Vec<<T as Node>::Value>,
+    ) {
+        let node = &self.nodes[curr_node];
+        if i == j {
+            values.push(node.value().clone());
+            return;
+        }
+        let mid = usize::midpoint(i, j);
+        self.snapshot_helper(node.left_child().unwrap(), i, mid, values);
+        self.snapshot_helper(node.right_child().unwrap(), mid + 1, j, values);
+    }
+
+    /// Creates a new version from version, whose values are `previous`, with the given values.
+    fn push_snapshot(
+        &mut self,
+        version: VersionId,
+        previous: &[<T as Node>::Value],
+        values: &[<T as Node>::Value],
+    ) where
+        <T as Node>::Value: PartialEq,
+    {
+        assert!(
+            values.len() == self.n,
+            "snapshot {} has length {}, but the first one has length {}",
+            version + 1,
+            values.len(),
+            self.n
+        );
+        let root = self.roots[version];
+        let new_root = if self.n == 0 {
+            root
+        } else {
+            self.push_snapshot_helper(root, previous, values, 0, self.n - 1)
+        };
+        self.push_version(new_root, Some(version));
+    }
+
+    fn push_snapshot_helper(
+        &mut self,
+        curr_node: usize,
+        previous: &[<T as Node>::Value],
+        values: &[<T as Node>::Value],
+        i: usize,
+        j: usize,
+    ) -> usize
+    where
+        <T as Node>::Value: PartialEq,
+    {
+        let _depth = enter();
+        if i == j {
+            if previous[i] == values[i] {
+                return curr_node;
+            }
+            return self.push_node(Node::initialize(&values[i]));
+        }
+        let mid = usize::midpoint(i, j);
+        let left_node = self.nodes[curr_node].left_child().unwrap();
+        let right_node = self.nodes[curr_node].right_child().unwrap();
+        let new_left = self.push_snapshot_helper(left_node, previous, values, i, mid);
+        let new_right = self.push_snapshot_helper(right_node, previous, values, mid + 1, j);
+        if (new_left, new_right) == (left_node, right_node) {
+            return curr_node;
+        }
+        self.push_combined(new_left, new_right)
+    }
+
+    /// Returns the index of the p-th leaf of the tree with the given root.
+    fn leaf(&self, root: usize, p: usize) -> usize {
+        assert!(p < self.n, "index {p} out of range for length {}", self.n);
+        let (mut curr_node, mut i, mut j) = (root, 0, self.n - 1);
+        while i != j {
+            let mid = usize::midpoint(i, j);
+            if p <= mid {
+                curr_node = self.nodes[curr_node].left_child().unwrap();
+                j = mid;
+            } else {
+                curr_node = self.nodes[curr_node].right_child().unwrap();
+                i = mid + 1;
+            }
+        }
+        curr_node
+    }
+
+    /// Discards every version after version, so version becomes the last version, and frees the nodes created after it.
+    /// It's meant for undo/redo, where the redo branch must be discarded when there is a new update after an undo.
+    /// Nodes are stored in creation order, so only the nodes created after every kept version are freed, [`gc`](Self::gc) frees the rest.
+    /// It will **panic** if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(k)`, where `k` is the amount of versions and nodes discarded.
+    pub fn rollback_to(&mut self, version: VersionId) {
+        self.history.assert_exists(version);
+        self.roots.truncate(version + 1);
+        self.history.truncate(version + 1);
+        self.nodes.publish_roots(&self.roots);
+        let len = self.roots.iter().max().map_or(0, |&root| root + 1);
+        self.nodes.truncate(len);
+        if let Some(interner) = &mut self.interner {
+            interner.truncate(len);
+        }
+    }
+
+    /// Creates a new version identical to version, whose parent is version, and returns it.
+    /// It's useful to start a new branch of the history which can be labelled before any update, see [`label_version`](Self::label_version).
+    /// It will **panic** if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(1)`.
+    pub fn branch_from(&mut self, version: VersionId) -> VersionId {
+        self.push_version(self.roots[version], Some(version))
+    }
+
+    /// Returns the version from which version was created by [`update`](Self::update) or [`branch_from`](Self::branch_from), or None if it was created by the build.
+    /// It will **panic** if version is not in <code>[0,[versions](Self::versions))</code>.
+    #[must_use]
+    pub fn version_parent(&self, version: VersionId) -> Option<VersionId> {
+        self.history.parent(version)
+    }
+
+    /// Labels version with label, so it can be found with [`find_version`](Self::find_version).
+    /// A label refers to a single version, so it returns the version the label referred to before, if any.
+    /// It will **panic** if version is not in <code>[0,[versions](Self::versions))</code>.
+    pub fn label_version(
+        &mut self,
+        version: VersionId,
+        label: impl Into<String>,
+    ) -> Option<VersionId> {
+        self.history.set_label(label.into(), version)
+    }
+
+    /// Returns the version labelled with label, see [`label_version`](Self::label_version).
+    #[must_use]
+    pub fn find_version(&self, label: &str) -> Option<VersionId> {
+        self.history.find(label)
+    }
+
+    /// Frees every node which isn't reachable from any version, compacting the remaining nodes.
+    /// It has time complexity of `O(m)`, where `m` is the amount of nodes currently stored.
+    pub fn gc(&mut self) {
+        span!(
+            DEBUG,
+            "gc",
+            tree = "Persistent",
+            n = self.n,
+            versions = self.roots.len(),
+            nodes = self.nodes.len()
+        );
+        let _op = self.last_op.measure();
+        compact(&mut self.nodes, &mut self.roots, self.interner.as_mut());
+        self.nodes.publish_roots(&self.roots);
+    }
+}
+
+impl<T, I, A> Persistent<T, I, A>
+where
+    T: Clone + Node + core::hash::Hash + Eq,
+    I: NodeIndex,
+    A: Arena<PersistentWrapper<T, I>>,
+{
+    /// Enables hash-consing, every node with the same value and children as an already stored node is replaced by the stored one, so identical subtrees are stored only once.
+    ///
+    /// The nodes already stored are deduplicated immediately, and every node created afterwards is deduplicated when it's inserted.
+    /// This drastically cuts the memory used when many versions converge to similar states, e.g. counting trees over a value domain, at the cost of hashing every new node.
+    /// It has time complexity of `O(m)`, where `m` is the amount of nodes currently stored.
+    /// ```
+    /// # use seg_tree::{Persistent,utils::Sum,nodes::Node};
+    /// let nodes: Vec<Sum<usize>> = vec![Sum::initialize(&0); 8];
+    /// let mut seg_tree = Persistent::build(&nodes).deduplicated(); // Every level of the tree is a single node.
+    /// assert_eq!(seg_tree.memory_stats().nodes, 4);
+    /// seg_tree.update(0, 3, &1);
+    /// seg_tree.update(1, 3, &0); // Same as version 0, so no node is added.
+    /// assert_eq!(seg_tree.memory_stats().nodes, 8);
+    /// ```
+    #[must_use]
+    pub fn deduplicated(mut self) -> Self {
+        self.interner = Some(Interner::new());
+        self.gc();
+        self
+    }
+}
+
+impl<T, I, A> Persistent<T, I, A>
+where
+    T: Clone + InvertibleNode,
+    I: NodeIndex,
+    A: Arena<PersistentWrapper<T, I>>,
+{
+    /// Creates a new segment tree version from version where delta is added to every element in `[l,r]`, for segment trees which store the differences between consecutive elements.
+    ///
+    /// In such a segment tree the i-th element is the result of the prefix `[0,i]`, see [`point_query`](Self::point_query), so the range update is two point updates at `l` and `r+1` in a single new version, without the per-query allocations of [`LazyPersistent`](crate::LazyPersistent).
+    /// It assumes that [`combine`](Node::combine) is commutative, e.g. sums or xors. If `l>r` the new version is equal to version.
+    /// It will **panic** if l or r are not in `[0,n)`, or if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It returns the new version, i.e. <code>[versions](Self::versions)-1</code> after the call.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) and [`uncombine`](InvertibleNode::uncombine) have constant time complexity.
+    /// ```
+    /// # use seg_tree::{Persistent,utils::Sum,nodes::Node};
+    /// let nodes: Vec<Sum<i64>> = vec![Sum::initialize(&0); 8]; // The differences of [0,0,0,0,0,0,0,0]
+    /// let mut seg_tree = Persistent::build(&nodes);
+    /// seg_tree.update_range(0, 2, 5, &3); // [0,0,3,3,3,3,0,0]
+    /// seg_tree.update_range(1, 4, 7, &1); // [0,0,3,3,4,4,1,1]
+    /// assert_eq!(seg_tree.point_query(2, 4).unwrap().value(), &4);
+    /// assert_eq!(seg_tree.point_query(2, 6).unwrap().value(), &1);
+    /// assert_eq!(seg_tree.point_query(1, 6).unwrap().value(), &0);
+    /// ```
+    pub fn update_range(
+        &mut self,
+        version: usize,
+        l: usize,
+        r: usize,
+        delta: &<T as Node>::Value,
+    ) -> VersionId {
+        assert_in_bounds(l, self.n);
+        assert_in_bounds(r, self.n);
+        let delta = T::initialize(delta);
+        let mut updates = Vec::with_capacity(2);
+        if l <= r {
+            // Every leaf in bounds is a segment, so only the point update past the last element is skipped.
+            if let Some(leaf) = self.get_segment(version, l, l) {
+                updates.push((l, combine(leaf, &delta).value().clone()));
+            }
+            if r + 1 < self.n {
+                if let Some(leaf) = self.get_segment(version, r + 1, r + 1) {
+                    updates.push((r + 1, T::uncombine(leaf, &delta).value().clone()));
+                }
+            }
+        }
+        self.update_many(version, &updates)
+    }
+
+    /// Returns the p-th element of the version of a segment tree which stores the differences between consecutive elements, i.e. the result of the prefix `[0,p]`, see [`update_range`](Self::update_range).
+    /// It returns None if and only if the segment tree has no elements.
+    /// It will **panic** if `p` is not in [0,n), or if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn point_query(&self, version: usize, p: usize) -> Option<T> {
+        self.query(version, 0, p)
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<T, I, A> Persistent<T, I, A>
+where
+    T: Node + Clone + Send + Sync,
+    I: NodeIndex + Send + Sync,
+    A: Arena<PersistentWrapper<T, I>> + Sync,
+{
+    /// Returns the result from each query `(version, left, right)` in `queries`, in the same order, running the queries in parallel with [`rayon`].
+    /// It will **panic** if any `left` or `right` is not in `[0,n)`, or if any version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(q*log(n)/p)`, where `q` is the amount of queries and `p` the amount of threads, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn par_query_batch(&self, queries: &[(usize, usize, usize)]) -> Vec<Option<T>> {
+        use rayon::prelude::*;
+        queries
+            .par_iter()
+            .map(|&(version, left, right)| self.query(version, left, right))
+            .collect()
+    }
+}
+
+/// Handle to every version of a [`Persistent`] segment tree built in a [`SharedArena`], including the versions created after the handle, see [`reader`](Persistent::reader).
+///
+/// The nodes and versions are published atomically, so a reader sees the version once the update which created it returns, without any locking between the reader and the writer.
+pub struct PersistentReader<T, I = u32> {
+    nodes: Arc<AppendList<PersistentWrapper<T, I>>>,
+    roots: Arc<AppendList<usize>>,
+    n: usize,
+}
+
+/// A single version of a [`Persistent`] segment tree built in a [`SharedArena`], see [`version`](Persistent::version).
+///
+/// Cloning it has time complexity of `O(1)`, and it's [`Send`] and [`Sync`] whenever `T` is.
+pub struct PersistentVersion<T, I = u32> {
+    nodes: Arc<AppendList<PersistentWrapper<T, I>>>,
+    root: usize,
+    n: usize,
+}
+
+impl<T, I> Persistent<T, I, SharedArena<PersistentWrapper<T, I>>>
+where
+    T: Clone + Node,
+    I: NodeIndex,
+{
+    /// Returns a reader of the versions of the segment tree, which can be sent to another thread and sees the versions created after it.
+    /// While it's alive the nodes and versions can't be discarded, see [`SharedArena`].
+    /// It has time complexity of `O(1)`.
+    #[must_use]
+    pub fn reader(&self) -> PersistentReader<T, I> {
+        let (nodes, roots) = self.nodes.share();
+        PersistentReader {
+            nodes,
+            roots,
+            n: self.n,
+        }
+    }
+
+    /// Returns a handle to the version, which shares its nodes with the tree and can be queried independently of it, e.g. from another thread.
+    /// While it's alive the nodes can't be discarded, see [`SharedArena`].
+    /// It will **panic** if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(1)`.
+    #[must_use]
+    pub fn version(&self, version: VersionId) -> PersistentVersion<T, I> {
+        PersistentVersion {
+            nodes: self.nodes.share().0,
+            root: self.roots[version],
+            n: self.n,
+        }
+    }
+}
+
+impl<T, I> PersistentReader<T, I>
+where
+    T: Clone + Node,
+    I: NodeIndex,
+{
+    /// Returns the result from the range `[left,right]` from the version of the segment tree.
+    /// It returns None if and only if range is empty or the segment tree has no elements.
+    /// It will **panic** if left or right are not in `[0,n)`, or if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn query(&self, version: VersionId, left: usize, right: usize) -> Option<T> {
+        self.version(version).query(left, right)
+    }
+
+    /// Returns the amount of versions published so far, it never decreases.
+    #[must_use]
+    pub fn versions(&self) -> usize {
+        self.roots.len()
+    }
+
+    /// Returns the versions published so far in creation order, see [`Persistent::versions_iter`].
+    /// A version published after the call isn't included.
+    #[must_use]
+    pub fn versions_iter(&self) -> Range<VersionId> {
+        0..self.roots.len()
+    }
+
+    /// Returns a handle to the version, see [`Persistent::version`].
+    /// It will **panic** if version is not in <code>[0,[versions](Self::versions))</code>.
+    /// It has time complexity of `O(1)`.
+    #[must_use]
+    pub fn version(&self, version: VersionId) -> PersistentVersion<T, I> {
+        PersistentVersion {
+            nodes: Arc::clone(&self.nodes),
+            root: self.root(version),
+            n: self.n,
+        }
+    }
+
+    /// It will **panic** if version is not in <code>[0,[versions](Self::versions))</code>.
+    fn root(&self, version: VersionId) -> usize {
+        let versions = self.roots.len();
+        *self.roots.get(version).unwrap_or_else(|| {
+            panic!("version {version} doesn't exist, there are {versions} versions")
+        })
+    }
+}
+
+impl<T, I> PersistentVersion<T, I>
+where
+    T: Clone + Node,
+    I: NodeIndex,
+{
+    /// Returns the result from the range `[left,right]`.
+    /// It returns None if and only if range is empty or the segment tree has no elements.
+    /// It will **panic** if left or right are not in `[0,n)`.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn query(&self, left: usize, right: usize) -> Option<T> {
+        span!(TRACE, "query", tree = "PersistentVersion", n = self.n, left, right);
+        if self.n == 0 {
+            return None;
+        }
+        query_helper(&*self.nodes, self.root, left, right, 0, self.n - 1)
+            .map(PersistentWrapper::into_inner)
+    }
+}
+
+/// Returns the result from the range `[left,right]` of the subtree of `[i,j]` rooted at `curr_node`.
+fn query_helper<T, I, A>(
+    nodes: &A,
+    curr_node: usize,
+    left: usize,
+    right: usize,
+    i: usize,
+    j: usize,
+) -> Option<PersistentWrapper<T, I>>
+where
+    T: Clone + Node,
+    I: NodeIndex,
+    A: Index<usize, Output = PersistentWrapper<T, I>> + ?Sized,
+{
+    let _depth = enter();
+    if j < left || right < i {
+        return None;
+    }
+    if left <= i && j <= right {
+        return Some(nodes[curr_node].clone());
+    }
+    let mid = usize::midpoint(i, j);
+    let left_node = nodes[curr_node].left_child().unwrap();
+    let right_node = nodes[curr_node].right_child().unwrap();
+    match (
+        query_helper(nodes, left_node, left, right, i, mid),
+        query_helper(nodes, right_node, left, right, mid + 1, j),
+    ) {
+        (Some(ans_left), Some(ans_right)) => Some(combine(&ans_left, &ans_right)),
+        (Some(ans_left), None) => Some(ans_left),
+        (None, Some(ans_right)) => Some(ans_right),
+        (None, None) => None,
+    }
+}
+
+impl<T, I> Clone for PersistentReader<T, I> {
+    fn clone(&self) -> Self {
+        Self {
+            nodes: Arc::clone(&self.nodes),
+            roots: Arc::clone(&self.roots),
+            n: self.n,
+        }
+    }
+}
+
+impl<T, I> Clone for PersistentVersion<T, I> {
+    fn clone(&self) -> Self {
+        Self {
+            nodes: Arc::clone(&self.nodes),
+            root: self.root,
+            n: self.n,
+        }
+    }
+}
+
+impl<T, I> core::fmt::Debug for PersistentReader<T, I> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PersistentReader")
+            .field("n", &self.n)
+            .field("versions", &self.roots.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, I> core::fmt::Debug for PersistentVersion<T, I> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PersistentVersion")
+            .field("n", &self.n)
+            .finish_non_exhaustive()
+    }
+}
+
+#[allow(clippy::missing_fields_in_debug)]
+impl<T, I, A> core::fmt::Debug for Persistent<T, I, A>
+where
+    I: NodeIndex,
+    A: Arena<PersistentWrapper<T, I>>,
+    T: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let len = self.nodes.len();
+        f.debug_struct("Persistent")
+            .field("n", &self.n)
+            .field(
+                "nodes",
+                &as_dbg_tree(&self.nodes, {
+                    |nodes, f| {
+                        if self.n == 0 {
+                            return;
+                        }
+                        let mut visited = BitVec::from_elem(len, false);
+                        for root_node in &self.roots {
+                            persistent_visitor(
+                                *root_node,
+                                0,
+                                self.n - 1,
+                                f,
+                                nodes,
+                                &mut visited,
+                            );
+                        }
+                    }
+                }),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        nodes::Node,
+        segment_tree::{LazyPersistent, Persistent},
+        storage::SharedArena,
+        testing::small_values,
+        utils::{Compressor, Max, Pair, Sum},
+    };
+    #[test]
+    fn non_empty_query_returns_some() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let segment_tree = Persistent::build(&nodes);
         assert!(segment_tree.query(0, 0, 10).is_some());
     }
     #[test]
@@ -279,6 +1620,227 @@ mod tests {
         assert_eq!(segment_tree.query(0, 0, 10).unwrap().value(), &55);
     }
 
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_query_batch_works() {
+        use crate::testing::NaiveRangeQuery;
+
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = Persistent::build(&nodes);
+        segment_tree.update(0, 3, &20);
+        let naive_v0 = NaiveRangeQuery::build(&nodes);
+        let mut naive_v1 = NaiveRangeQuery::build(&nodes);
+        naive_v1.update(3, &20);
+        let queries: Vec<(usize, usize, usize)> = (0..=1)
+            .flat_map(|v| (0..=10).flat_map(move |i| (i..=10).map(move |j| (v, i, j))))
+            .collect();
+        let results = segment_tree.par_query_batch(&queries);
+        for (&(v, i, j), result) in queries.iter().zip(results) {
+            let naive = if v == 0 { &naive_v0 } else { &naive_v1 };
+            assert_eq!(result.unwrap().value(), naive.query(i, j).unwrap().value());
+        }
+    }
+
+    #[test]
+    fn wider_index_works() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree: Persistent<_, u64> = Persistent::build_with_index(&nodes);
+        segment_tree.update(0, 0, &20);
+        assert_eq!(segment_tree.query(0, 0, 10).unwrap().value(), &55);
+        assert_eq!(segment_tree.query(1, 0, 10).unwrap().value(), &75);
+    }
+
+    #[test]
+    fn shrink_to_fit_works() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = Persistent::build(&nodes);
+        let stats = segment_tree.memory_stats();
+        assert_eq!(stats.nodes, 21);
+        assert_eq!(stats.versions, 1);
+        assert!(stats.capacity >= 44);
+        segment_tree.shrink_to_fit();
+        let stats = segment_tree.memory_stats();
+        assert_eq!(stats.capacity, stats.nodes);
+        assert!(stats.reserved_bytes() >= stats.nodes * stats.node_size);
+    }
+
+    #[test]
+    fn retain_versions_works() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = Persistent::build(&nodes);
+        for i in 0..10 {
+            segment_tree.update(i, i, &20);
+        }
+        let before = segment_tree.memory_stats().nodes;
+        segment_tree.retain_versions(&[10, 5]);
+        assert_eq!(segment_tree.versions(), 2);
+        assert!(segment_tree.memory_stats().nodes < before);
+        assert_eq!(segment_tree.query(0, 0, 10).unwrap().value(), &(55 + 20 * 10 - 45));
+        assert_eq!(segment_tree.query(1, 0, 10).unwrap().value(), &(55 + 20 * 5 - 10));
+        segment_tree.update(1, 10, &0);
+        assert_eq!(segment_tree.query(2, 0, 10).unwrap().value(), &(55 + 20 * 5 - 20));
+    }
+
+    #[test]
+    fn deduplicated_works() {
+        let nodes: Vec<Sum<usize>> = (0..16).map(|x| Sum::initialize(&(x % 2))).collect();
+        let mut segment_tree = Persistent::build(&nodes).deduplicated();
+        // Leaves 0 and 1 and a single node for every other level.
+        assert_eq!(segment_tree.memory_stats().nodes, 6);
+        for i in 0..16 {
+            segment_tree.update(i, i, &1);
+        }
+        for i in 0..16 {
+            segment_tree.update(16 + i, i, &(i % 2));
+        }
+        assert_eq!(segment_tree.query(16, 0, 15).unwrap().value(), &16);
+        assert_eq!(segment_tree.query(32, 0, 15).unwrap().value(), &8);
+        assert_eq!(segment_tree.query(24, 0, 7).unwrap().value(), &4);
+        assert_eq!(segment_tree.query(24, 8, 15).unwrap().value(), &8);
+        let plain = {
+            let mut segment_tree = Persistent::build(&nodes);
+            for i in 0..16 {
+                segment_tree.update(i, i, &1);
+            }
+            segment_tree.memory_stats().nodes
+        };
+        assert!(segment_tree.memory_stats().nodes < plain);
+    }
+
+    #[test]
+    fn version_metadata_works() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = Persistent::build(&nodes);
+        segment_tree.update(0, 0, &20);
+        let branch = segment_tree.branch_from(0);
+        assert_eq!(segment_tree.label_version(branch, "draft"), None);
+        segment_tree.update(branch, 1, &20);
+        assert_eq!(segment_tree.label_version(3, "draft"), Some(branch));
+        assert_eq!(segment_tree.version_parent(0), None);
+        assert_eq!(segment_tree.version_parent(1), Some(0));
+        assert_eq!(segment_tree.version_parent(2), Some(0));
+        assert_eq!(segment_tree.version_parent(3), Some(2));
+        assert_eq!(segment_tree.query(2, 0, 10).unwrap().value(), &55);
+        let draft = segment_tree.find_version("draft").unwrap();
+        assert_eq!(segment_tree.query(draft, 0, 10).unwrap().value(), &74);
+        assert_eq!(segment_tree.find_version("final"), None);
+        segment_tree.retain_versions(&[0, 3]);
+        assert_eq!(segment_tree.version_parent(1), None);
+        assert_eq!(segment_tree.find_version("draft"), Some(1));
+    }
+
+    #[test]
+    fn history_works() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = Persistent::build(&nodes);
+        segment_tree.update(0, 3, &20);
+        segment_tree.update(1, 4, &20);
+        segment_tree.update(2, 3, &30);
+        segment_tree.update(1, 3, &40);
+        let history: Vec<usize> = segment_tree.history(3).copied().collect();
+        assert_eq!(history, vec![3, 20, 20, 30, 40]);
+        let changes: Vec<(usize, usize)> = segment_tree
+            .history_changes(3)
+            .map(|(version, value)| (version, *value))
+            .collect();
+        assert_eq!(changes, vec![(0, 3), (1, 20), (3, 30), (4, 40)]);
+    }
+
+    #[test]
+    fn merge_works() {
+        let nodes: Vec<Sum<usize>> = vec![Sum::initialize(&0); 16];
+        let mut segment_tree = Persistent::build(&nodes).deduplicated();
+        let mut versions = Vec::new();
+        for i in 0..8 {
+            segment_tree.update(0, 2 * i, &(i + 1));
+            versions.push(segment_tree.versions() - 1);
+        }
+        // Small-to-large style merging of every version into one.
+        let is_empty = |node: &Sum<usize>| *node.value() == 0;
+        let merge_leaves = |a: &Sum<usize>, b: &Sum<usize>| Sum::combine(a, b);
+        let mut merged = versions[0];
+        for &version in &versions[1..] {
+            merged = segment_tree.merge(merged, version, is_empty, merge_leaves);
+        }
+        let merged = segment_tree.merge(merged, versions[3], is_empty, merge_leaves);
+        assert_eq!(segment_tree.query(merged, 0, 15).unwrap().value(), &40);
+        assert_eq!(segment_tree.query(merged, 6, 6).unwrap().value(), &8);
+        assert_eq!(segment_tree.query(merged, 7, 7).unwrap().value(), &0);
+        assert_eq!(segment_tree.version_parent(merged), Some(merged - 1));
+    }
+
+    #[test]
+    fn rollback_to_works() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = Persistent::build(&nodes);
+        segment_tree.update(0, 0, &20);
+        let nodes_before = segment_tree.memory_stats().nodes;
+        segment_tree.update(1, 1, &20);
+        segment_tree.update(2, 2, &20);
+        segment_tree.label_version(2, "redo");
+        segment_tree.rollback_to(1);
+        assert_eq!(segment_tree.versions(), 2);
+        assert_eq!(segment_tree.memory_stats().nodes, nodes_before);
+        assert_eq!(segment_tree.find_version("redo"), None);
+        segment_tree.update(1, 3, &20);
+        assert_eq!(segment_tree.query(2, 0, 10).unwrap().value(), &92);
+        assert_eq!(segment_tree.versions(), 3);
+    }
+
+    #[test]
+    #[should_panic = "version 3 doesn't exist, there are 3 versions"]
+    fn rollback_to_checks_version() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = Persistent::build(&nodes);
+        segment_tree.update(0, 0, &20);
+        segment_tree.update(1, 1, &20);
+        segment_tree.rollback_to(3);
+    }
+
+    #[test]
+    fn version_stats_works() {
+        let nodes: Vec<Sum<usize>> = (0..16).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = Persistent::build(&nodes);
+        segment_tree.update(0, 0, &20);
+        let branch = segment_tree.branch_from(1);
+        let stats = segment_tree.version_stats(0);
+        assert_eq!((stats.nodes, stats.introduced, stats.shared), (31, 31, 0));
+        let stats = segment_tree.version_stats(1);
+        assert_eq!((stats.nodes, stats.introduced, stats.shared), (31, 5, 26));
+        let stats = segment_tree.version_stats(branch);
+        assert_eq!((stats.nodes, stats.introduced, stats.shared), (31, 0, 31));
+    }
+
+    #[test]
+    fn memory_stats_by_version_works() {
+        let nodes: Vec<Sum<usize>> = (0..16).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = Persistent::build(&nodes);
+        segment_tree.update(0, 0, &20);
+        segment_tree.update(1, 15, &20);
+        let stats = segment_tree.memory_stats_by_version();
+        let owned: Vec<usize> = stats.iter().map(|stats| stats.introduced).collect();
+        let shared: Vec<usize> = stats.iter().map(|stats| stats.shared).collect();
+        assert_eq!(owned, [31, 5, 5]);
+        assert_eq!(shared, [0, 26, 26]);
+        let total: usize = owned.iter().sum();
+        assert_eq!(total, segment_tree.memory_stats().nodes);
+    }
+
+    #[test]
+    fn reserve_updates_works() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = Persistent::build_with_capacity(&nodes, 100);
+        let capacity = segment_tree.memory_stats().capacity;
+        assert!(capacity >= 21 + 100 * 6);
+        for i in 0..100 {
+            segment_tree.update(i, i % 11, &i);
+        }
+        assert_eq!(segment_tree.memory_stats().capacity, capacity);
+        segment_tree.reserve_updates(1000);
+        let stats = segment_tree.memory_stats();
+        assert!(stats.capacity >= stats.nodes + 6000);
+    }
+
     #[test]
     fn dbg_works(){
         let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
@@ -288,4 +1850,414 @@ mod tests {
         let expected = "Persistent { n: 11, nodes: {[0, 10]: Sum { value: 55, lazy_value: None }, [0, 5]: Sum { value: 15, lazy_value: None }, [0, 2]: Sum { value: 3, lazy_value: None }, [0, 1]: Sum { value: 1, lazy_value: None }, [0, 0]: Sum { value: 0, lazy_value: None }, [1, 1]: Sum { value: 1, lazy_value: None }, [2, 2]: Sum { value: 2, lazy_value: None }, [3, 5]: Sum { value: 12, lazy_value: None }, [3, 4]: Sum { value: 7, lazy_value: None }, [3, 3]: Sum { value: 3, lazy_value: None }, [4, 4]: Sum { value: 4, lazy_value: None }, [5, 5]: Sum { value: 5, lazy_value: None }, [6, 10]: Sum { value: 40, lazy_value: None }, [6, 8]: Sum { value: 21, lazy_value: None }, [6, 7]: Sum { value: 13, lazy_value: None }, [6, 6]: Sum { value: 6, lazy_value: None }, [7, 7]: Sum { value: 7, lazy_value: None }, [8, 8]: Sum { value: 8, lazy_value: None }, [9, 10]: Sum { value: 19, lazy_value: None }, [9, 9]: Sum { value: 9, lazy_value: None }, [10, 10]: Sum { value: 10, lazy_value: None }, [0, 10]: Sum { value: 56, lazy_value: None }, [0, 5]: Sum { value: 16, lazy_value: None }, [0, 2]: Sum { value: 4, lazy_value: None }, [0, 1]: Sum { value: 2, lazy_value: None }, [1, 1]: Sum { value: 2, lazy_value: None }} }";
         assert_eq!(dbg, expected);
     }
+
+    #[test]
+    fn build_lays_out_leaves_first() {
+        let values: Vec<usize> = small_values(13);
+        let nodes: Vec<Sum<usize>> = values.iter().map(Sum::initialize).collect();
+        let segment_tree = Persistent::build(&nodes);
+        segment_tree.validate();
+        assert_eq!(segment_tree.nodes.len(), 2 * values.len() - 1);
+        for (node, value) in segment_tree.nodes.iter().zip(&values) {
+            assert!(node.left_child().is_none());
+            assert_eq!(node.value(), value);
+        }
+        assert_eq!(segment_tree.roots[0], segment_tree.nodes.len() - 1);
+        for i in 0..values.len() {
+            for j in i..values.len() {
+                let expected: usize = values[i..=j].iter().sum();
+                assert_eq!(segment_tree.query(0, i, j).unwrap().value(), &expected);
+            }
+        }
+    }
+
+    #[test]
+    fn validate_works() {
+        let nodes: Vec<Sum<usize>> = vec![Sum::initialize(&0); 11];
+        let mut segment_tree = Persistent::build(&nodes).deduplicated();
+        segment_tree.update(0, 3, &1);
+        segment_tree.update(1, 7, &2);
+        segment_tree.update(0, 10, &3);
+        segment_tree.validate();
+        segment_tree.retain_versions(&[2, 3]);
+        segment_tree.validate();
+    }
+
+    #[test]
+    fn empty_tree_works() {
+        let mut segment_tree = Persistent::<Sum<usize>>::build(&[]);
+        assert_eq!(segment_tree.len(), 0);
+        assert!(segment_tree.is_empty());
+        assert_eq!(segment_tree.versions(), 1);
+        assert!(segment_tree.query(0, 0, 0).is_none());
+        let branch = segment_tree.branch_from(0);
+        assert!(segment_tree.query(branch, 0, 0).is_none());
+        assert_eq!(segment_tree.version_stats(branch).nodes, 0);
+        segment_tree.retain_versions(&[1]);
+        segment_tree.validate();
+        assert_eq!(format!("{segment_tree:?}"), "Persistent { n: 0, nodes: {} }");
+    }
+
+    #[test]
+    #[should_panic = "can't update an empty segment tree"]
+    fn update_on_empty_tree_panics() {
+        let mut segment_tree = Persistent::<Sum<usize>>::build(&[]);
+        segment_tree.update(0, 0, &1);
+    }
+
+    #[test]
+    fn snapshots_round_trip() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = Persistent::build(&nodes);
+        segment_tree.update(0, 3, &20);
+        segment_tree.update(1, 7, &30);
+        let snapshots: Vec<Vec<usize>> = (0..segment_tree.versions())
+            .map(|version| segment_tree.snapshot_values(version))
+            .collect();
+        assert_eq!(snapshots[0], (0..=10).collect::<Vec<_>>());
+        assert_eq!(snapshots[2][3..=7], [20, 4, 5, 6, 30]);
+        let imported = Persistent::<Sum<usize>>::from_version_snapshots(&snapshots);
+        imported.validate();
+        assert_eq!(imported.versions(), 3);
+        assert_eq!(imported.memory_stats().nodes, segment_tree.memory_stats().nodes);
+        for (version, snapshot) in snapshots.iter().enumerate() {
+            assert_eq!(&imported.snapshot_values(version), snapshot);
+        }
+        let empty = Persistent::<Sum<usize>>::from_version_snapshots(&[]);
+        assert!(empty.is_empty());
+        assert_eq!(empty.versions(), 1);
+    }
+
+    #[test]
+    #[should_panic = "snapshot 1 has length 2, but the first one has length 3"]
+    fn from_version_snapshots_checks_lengths() {
+        let _ = Persistent::<Sum<usize>>::from_version_snapshots(&[vec![1, 2, 3], vec![1, 2]]);
+    }
+
+    #[test]
+    fn lower_bound_in_works() {
+        let mut values: Vec<usize> = small_values(13);
+        let nodes: Vec<Sum<usize>> = values.iter().map(Sum::initialize).collect();
+        let mut segment_tree = Persistent::build(&nodes);
+        segment_tree.update(0, 6, &9);
+        values[6] = 9;
+        let expected = |left: usize, right: usize, target: usize| {
+            (left..=right).find(|&k| values[left..=k].iter().sum::<usize>() >= target)
+        };
+        let predicate = |sum: &usize, value: &usize| *sum >= *value;
+        let g = |sum: &usize, value: usize| value.saturating_sub(*sum);
+        for left in 0..values.len() {
+            for right in 0..values.len() {
+                for target in [1, 5, 20, 60] {
+                    assert_eq!(
+                        segment_tree.lower_bound_in(1, left, right, predicate, g, target),
+                        expected(left, right, target)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn upper_bound_works() {
+        let mut values: Vec<usize> = small_values(13);
+        let nodes: Vec<Sum<usize>> = values.iter().map(Sum::initialize).collect();
+        let mut segment_tree = Persistent::build(&nodes);
+        segment_tree.update(0, 6, &9);
+        values[6] = 9;
+        let expected = |target: usize| {
+            (0..values.len())
+                .rev()
+                .find(|&i| values[i..].iter().sum::<usize>() >= target)
+                .unwrap_or(0)
+        };
+        let predicate = |sum: &usize, value: &usize| *sum >= *value;
+        let g = |sum: &usize, value: usize| value.saturating_sub(*sum);
+        for target in 0..70 {
+            assert_eq!(
+                segment_tree.upper_bound(1, predicate, g, target),
+                expected(target)
+            );
+        }
+    }
+
+    #[test]
+    fn lower_bound_by_node_works() {
+        let mut values: Vec<usize> = small_values(13);
+        let nodes: Vec<Pair<Sum<usize>, Max<usize>>> =
+            values.iter().map(|&x| Pair::initialize(&(x, x))).collect();
+        let mut segment_tree = Persistent::build(&nodes);
+        segment_tree.update(0, 6, &(9, 9));
+        values[6] = 9;
+        let predicate = |node: &Pair<Sum<usize>, Max<usize>>, value: &(usize, usize)| {
+            *node.first().value() >= value.0 || *node.second().value() >= value.1
+        };
+        let g = |node: &Pair<Sum<usize>, Max<usize>>, value: (usize, usize)| {
+            (value.0.saturating_sub(*node.first().value()), value.1)
+        };
+        for target in 0..70 {
+            for max in 0..8 {
+                let expected = (0..values.len())
+                    .find(|&k| values[..=k].iter().sum::<usize>() >= target || values[k] >= max)
+                    .unwrap_or(values.len() - 1);
+                assert_eq!(
+                    segment_tree.lower_bound_by_node(1, predicate, g, (target, max)),
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn get_segment_works() {
+        let nodes: Vec<Sum<usize>> = (0..13).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = Persistent::build(&nodes);
+        segment_tree.update(0, 6, &9);
+        let mut canonical = 0;
+        for i in 0..=nodes.len() {
+            for j in 0..=nodes.len() {
+                if let Some(node) = segment_tree.get_segment(1, i, j) {
+                    canonical += 1;
+                    let expected = segment_tree.query(1, i, j).unwrap();
+                    assert_eq!(node.value(), expected.value());
+                }
+            }
+        }
+        assert_eq!(canonical, 2 * nodes.len() - 1);
+        assert!(segment_tree.get_segment(1, 0, nodes.len() - 1).is_some());
+        assert!(segment_tree.get_segment(1, 1, 0).is_none());
+    }
+
+    #[test]
+    fn update_with_works() {
+        let mut values: Vec<usize> = small_values(13);
+        let nodes: Vec<Sum<usize>> = values.iter().map(Sum::initialize).collect();
+        let mut segment_tree = Persistent::build(&nodes);
+        for p in [0, 6, 12, 6] {
+            segment_tree.update_with(segment_tree.versions() - 1, p, |value| value + 10);
+            values[p] += 10;
+        }
+        assert_eq!(segment_tree.versions(), 5);
+        for (p, value) in values.iter().enumerate() {
+            assert_eq!(segment_tree.query(4, p, p).unwrap().value(), value);
+            assert_eq!(segment_tree.query(0, p, p).unwrap().value(), nodes[p].value());
+        }
+    }
+
+    #[test]
+    fn update_many_works() {
+        let mut values: Vec<usize> = small_values(13);
+        let nodes: Vec<Sum<usize>> = values.iter().map(Sum::initialize).collect();
+        let mut segment_tree = Persistent::build(&nodes);
+        let updates = [(6, 9), (0, 4), (12, 7), (6, 1), (7, 3)];
+        segment_tree.update_many(0, &updates);
+        for &(p, value) in &updates {
+            values[p] = value;
+        }
+        segment_tree.update_many(1, &[]);
+        assert_eq!(segment_tree.versions(), 3);
+        for (p, value) in values.iter().enumerate() {
+            assert_eq!(segment_tree.query(1, p, p).unwrap().value(), value);
+            assert_eq!(segment_tree.query(2, p, p).unwrap().value(), value);
+            assert_eq!(segment_tree.query(0, p, p).unwrap().value(), nodes[p].value());
+        }
+        let sum: usize = values.iter().sum();
+        assert_eq!(segment_tree.query(1, 0, 12).unwrap().value(), &sum);
+    }
+
+    #[test]
+    fn update_range_works() {
+        let mut values: Vec<i64> = small_values(13);
+        let differences: Vec<Sum<i64>> = (0..values.len())
+            .map(|i| Sum::initialize(&(values[i] - if i == 0 { 0 } else { values[i - 1] })))
+            .collect();
+        let mut segment_tree = Persistent::build(&differences);
+        let updates = [(3, 9, 2), (0, 12, -1), (12, 12, 5), (5, 4, 7), (0, 0, 3)];
+        for (version, &(l, r, delta)) in updates.iter().enumerate() {
+            segment_tree.update_range(version, l, r, &delta);
+            if l <= r {
+                values[l..=r].iter_mut().for_each(|value| *value += delta);
+            }
+            for (p, value) in values.iter().enumerate() {
+                assert_eq!(
+                    segment_tree.point_query(version + 1, p).unwrap().value(),
+                    value
+                );
+            }
+        }
+        assert_eq!(segment_tree.versions(), updates.len() + 1);
+    }
+
+    #[test]
+    #[should_panic = "index 13 is out of bounds, the segment tree has 13 elements"]
+    fn update_range_checks_bounds() {
+        let differences: Vec<Sum<i64>> = vec![Sum::initialize(&0); 13];
+        let mut segment_tree = Persistent::build(&differences);
+        segment_tree.update_range(0, 3, 13, &1);
+    }
+
+    #[test]
+    fn updates_return_the_new_version() {
+        let nodes: Vec<Sum<i64>> = (0..13).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = Persistent::build(&nodes);
+        assert_eq!(segment_tree.update(0, 3, &9), 1);
+        assert_eq!(segment_tree.update_with(0, 4, |value| value + 1), 2);
+        assert_eq!(segment_tree.update_many(1, &[(5, 1), (6, 2)]), 3);
+        assert_eq!(segment_tree.update_range(3, 2, 7, &1), 4);
+        let branch = segment_tree.update(2, 12, &0);
+        assert_eq!(branch, segment_tree.versions() - 1);
+        assert_eq!(segment_tree.version_parent(branch), Some(2));
+    }
+
+    #[test]
+    fn build_frequency_works() {
+        let values: Vec<usize> = small_values(13);
+        let domain: Compressor<usize> = values.iter().copied().collect();
+        let segment_tree: Persistent<Sum<usize>> = Persistent::build_frequency(&values, &domain);
+        assert_eq!(segment_tree.versions(), values.len());
+        for (version, prefix) in (1..=values.len()).map(|k| &values[..k]).enumerate() {
+            for a in 0..domain.len() {
+                for b in a..domain.len() {
+                    let expected = prefix.iter().filter(|&&x| (a..=b).contains(&x)).count();
+                    let count = segment_tree.query(version, a, b).unwrap();
+                    assert_eq!(count.value(), &expected);
+                }
+            }
+        }
+        let empty: Persistent<Sum<usize>> = Persistent::build_frequency(&[], &domain);
+        assert_eq!(empty.versions(), 1);
+        assert_eq!(empty.query(0, 0, 4).unwrap().value(), &0);
+    }
+
+    #[test]
+    fn descend2_finds_heavy_values() {
+        let values: Vec<usize> = [3, 1, 3, 3, 0, 3, 2, 3, 1, 3].to_vec();
+        let domain: Compressor<usize> = values.iter().copied().collect();
+        let segment_tree: Persistent<Sum<usize>> = Persistent::build_frequency(&values, &domain);
+        for lo in 0..values.len() {
+            for hi in lo + 1..values.len() {
+                let len = hi - lo;
+                let count = |key: usize| values[lo + 1..=hi].iter().filter(|&&x| x == key).count();
+                let heavy = segment_tree.descend2(hi, lo, |left_hi, left_lo| {
+                    2 * (left_hi.value() - left_lo.value()) > len
+                });
+                if let Some(expected) = (0..4).find(|&key| 2 * count(key) > len) {
+                    assert_eq!(heavy, expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn with_len_works() {
+        let mut segment_tree = Persistent::<Sum<u64>>::with_len(5);
+        let version = segment_tree.update(0, 1, &3);
+        assert_eq!(segment_tree.query(0, 0, 4).unwrap().value(), &0);
+        assert_eq!(segment_tree.query(version, 0, 4).unwrap().value(), &3);
+    }
+
+    #[test]
+    fn build_from_fn_works() {
+        let values: Vec<usize> = small_values(13);
+        let segment_tree = Persistent::<Sum<usize>>::build_from_fn(values.len(), |i| values[i]);
+        let lazy = LazyPersistent::<Sum<usize>>::build_from_fn(values.len(), |i| values[i]);
+        for i in 0..values.len() {
+            for j in i..values.len() {
+                let expected: usize = values[i..=j].iter().sum();
+                assert_eq!(segment_tree.query(0, i, j).unwrap().value(), &expected);
+                assert_eq!(lazy.query(0, i, j).unwrap().value(), &expected);
+            }
+        }
+    }
+
+    #[test]
+    fn lower_bound_diff_works() {
+        let values: Vec<usize> = small_values(13);
+        let domain: Compressor<usize> = values.iter().copied().collect();
+        let segment_tree: Persistent<Sum<usize>> = Persistent::build_frequency(&values, &domain);
+        for lo in 0..values.len() {
+            for hi in lo..values.len() {
+                let mut sorted = values[lo + 1..=hi].to_vec();
+                sorted.sort_unstable();
+                for k in 0..=sorted.len() {
+                    let expected = sorted.get(k).copied();
+                    assert_eq!(segment_tree.lower_bound_diff(hi, lo, k), expected);
+                }
+            }
+        }
+        let empty: Persistent<Sum<usize>> = Persistent::build(&[]);
+        assert_eq!(empty.lower_bound_diff(0, 0, 0), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "isn't a key of the domain")]
+    fn build_frequency_panics_outside_the_domain() {
+        let domain: Compressor<usize> = [1, 3].into_iter().collect();
+        let _segment_tree: Persistent<Sum<usize>> = Persistent::build_frequency(&[1, 2], &domain);
+    }
+
+    #[test]
+    fn reader_sees_new_versions() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree: Persistent<_, u32, _> =
+            Persistent::build_in(&nodes, SharedArena::new());
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let reader = segment_tree.reader();
+                std::thread::spawn(move || {
+                    let mut seen = 0;
+                    while seen < 1000 {
+                        let versions = reader.versions();
+                        for version in seen..versions {
+                            assert_eq!(
+                                reader.query(version, 0, 10).unwrap().value(),
+                                &(55 + version)
+                            );
+                        }
+                        seen = versions;
+                    }
+                })
+            })
+            .collect();
+        for version in 0..999 {
+            segment_tree.update(version, 0, &(version + 1));
+        }
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        let version = segment_tree.version(999);
+        assert_eq!(version.query(0, 0).unwrap().value(), &999);
+        assert_eq!(version.query(1, 10).unwrap().value(), &55);
+    }
+
+    #[test]
+    fn shared_arena_discards_without_readers() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree: Persistent<_, u32, _> =
+            Persistent::build_in(&nodes, SharedArena::new());
+        for version in 0..5 {
+            segment_tree.update(version, version, &0);
+        }
+        let version = segment_tree.version(3);
+        assert_eq!(version.query(0, 10).unwrap().value(), &52);
+        drop(version);
+        segment_tree.rollback_to(3);
+        segment_tree.retain_versions(&[0, 3]);
+        let reader = segment_tree.reader();
+        assert_eq!(reader.versions(), 2);
+        assert_eq!(reader.query(1, 0, 10).unwrap().value(), &52);
+        segment_tree.update(1, 10, &0);
+        assert_eq!(reader.query(2, 0, 10).unwrap().value(), &42);
+    }
+
+    #[test]
+    #[should_panic(expected = "while there are readers")]
+    fn gc_with_reader_panics() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree: Persistent<_, u32, _> =
+            Persistent::build_in(&nodes, SharedArena::new());
+        segment_tree.update(0, 0, &5);
+        let _reader = segment_tree.reader();
+        segment_tree.gc();
+    }
 }
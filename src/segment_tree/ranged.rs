@@ -0,0 +1,296 @@
+use core::{
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+#[cfg(feature = "std")]
+use crate::segment_tree::Sharded;
+use crate::{
+    internal_utils::persistent_utils::PersistentWrapper,
+    interval::{HalfOpen, Interval, Position},
+    nodes::{FlatNode, LazyNode, Node},
+    segment_tree::{
+        ArcPersistent, Flat, Iterative, LazyPersistent, LazyRecursive, NodeIndex, Persistent,
+        Recursive, VersionId,
+    },
+    storage::{Arena, Storage},
+};
+
+/// Wrapper around a segment tree whose queries and range updates take ranges in the convention `C`, see [`Interval`], with positions of type `P`, see [`Position`].
+///
+/// The ranges are converted to inclusive ranges of `usize` indices once, before reaching the tree, and empty ranges never reach it.
+/// Every other method of the tree is reached through [`Deref`] and [`DerefMut`], and keeps taking inclusive ranges.
+/// ```
+/// # use seg_tree::{Iterative,Ranged,interval::HalfOpen,utils::Sum,nodes::Node};
+/// # let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+/// let mut seg_tree: Ranged<_, HalfOpen> = Ranged::new(Iterative::build(&nodes)); // [0,1,2,3,4,5,6,7,8,9] with Sum<usize> nodes
+/// assert_eq!(seg_tree.query(0, 10).unwrap().value(), &45);
+/// assert_eq!(seg_tree.query(2, 4).unwrap().value(), &5);
+/// assert!(seg_tree.query(3, 3).is_none());
+/// seg_tree.update(9, &0); // Point updates are the same in every convention.
+/// assert_eq!(seg_tree.query(5, 10).unwrap().value(), &26);
+/// let seg_tree: Ranged<_, HalfOpen, u32> = Ranged::new(seg_tree.into_inner()); // Positions given as u32.
+/// assert_eq!(seg_tree.query(2, 4).unwrap().value(), &5);
+/// ```
+pub struct Ranged<S, C = HalfOpen, P = usize> {
+    tree: S,
+    _interval: PhantomData<(C, P)>,
+}
+
+impl<S, C, P> Ranged<S, C, P>
+where
+    C: Interval,
+    P: Position,
+{
+    /// Wraps tree, so its ranges are given in the convention `C` with positions of type `P`.
+    pub const fn new(tree: S) -> Self {
+        Self {
+            tree,
+            _interval: PhantomData,
+        }
+    }
+
+    /// Returns the wrapped tree.
+    pub fn into_inner(self) -> S {
+        self.tree
+    }
+
+    /// Converts the range from `left` to `right` to an inclusive range of indices, it returns None if it's empty.
+    fn to_inclusive(left: P, right: P) -> Option<(usize, usize)> {
+        C::to_inclusive(left.to_index(), right.to_index())
+    }
+}
+
+impl<T, St, C, P> Ranged<Iterative<T, St>, C, P>
+where
+    T: Node + Clone,
+    St: Storage<T>,
+    C: Interval,
+    P: Position,
+{
+    /// Same as [`Iterative::query`], but the range is in the convention `C`.
+    #[must_use]
+    pub fn query(&self, left: P, right: P) -> Option<T> {
+        Self::to_inclusive(left, right).and_then(|(left, right)| self.tree.query(left, right))
+    }
+}
+
+impl<T, St, C, P> Ranged<Recursive<T, St>, C, P>
+where
+    T: Node + Clone,
+    St: Storage<T>,
+    C: Interval,
+    P: Position,
+{
+    /// Same as [`Recursive::query`], but the range is in the convention `C`.
+    #[must_use]
+    pub fn query(&self, left: P, right: P) -> Option<T> {
+        Self::to_inclusive(left, right).and_then(|(left, right)| self.tree.query(left, right))
+    }
+}
+
+impl<T, C, P> Ranged<Flat<T>, C, P>
+where
+    T: FlatNode,
+    C: Interval,
+    P: Position,
+{
+    /// Same as [`Flat::query`], but the range is in the convention `C`.
+    #[must_use]
+    pub fn query(&self, left: P, right: P) -> Option<T> {
+        Self::to_inclusive(left, right).and_then(|(left, right)| self.tree.query(left, right))
+    }
+}
+
+impl<T, St, C, P> Ranged<LazyRecursive<T, St>, C, P>
+where
+    T: LazyNode + Clone,
+    St: Storage<T>,
+    C: Interval,
+    P: Position,
+{
+    /// Same as [`LazyRecursive::query`], but the range is in the convention `C`.
+    pub fn query(&mut self, left: P, right: P) -> Option<T> {
+        Self::to_inclusive(left, right).and_then(|(left, right)| self.tree.query(left, right))
+    }
+
+    /// Same as [`LazyRecursive::update`], but the range is in the convention `C`, an empty range does nothing.
+    pub fn update(&mut self, left: P, right: P, value: &<T as Node>::Value) {
+        if let Some((left, right)) = Self::to_inclusive(left, right) {
+            self.tree.update(left, right, value);
+        }
+    }
+}
+
+impl<T, I, A, C, P> Ranged<Persistent<T, I, A>, C, P>
+where
+    T: Clone + Node,
+    I: NodeIndex,
+    A: Arena<PersistentWrapper<T, I>>,
+    C: Interval,
+    P: Position,
+{
+    /// Same as [`Persistent::query`], but the range is in the convention `C`.
+    /// It will **panic** if version is not in <code>[0,[versions](Persistent::versions))</code>, even if the range is empty.
+    #[must_use]
+    pub fn query(&self, version: VersionId, left: P, right: P) -> Option<T> {
+        match Self::to_inclusive(left, right) {
+            Some((left, right)) => self.tree.query(version, left, right),
+            None => self.tree.query(version, 1, 0),
+        }
+    }
+}
+
+impl<T, I, A, C, P> Ranged<LazyPersistent<T, I, A>, C, P>
+where
+    T: LazyNode + Clone,
+    I: NodeIndex,
+    A: Arena<PersistentWrapper<T, I>>,
+    C: Interval,
+    P: Position,
+{
+    /// Same as [`LazyPersistent::query`], but the range is in the convention `C`.
+    /// It will **panic** if version is not in <code>[0,[versions](LazyPersistent::versions))</code>, even if the range is empty.
+    #[must_use]
+    pub fn query(&self, version: VersionId, left: P, right: P) -> Option<T> {
+        match Self::to_inclusive(left, right) {
+            Some((left, right)) => self.tree.query(version, left, right),
+            None => self.tree.query(version, 1, 0),
+        }
+    }
+
+    /// Same as [`LazyPersistent::update`], but the range is in the convention `C`.
+    /// An empty range still creates a new version, identical to version, see [`branch_from`](LazyPersistent::branch_from).
+    pub fn update(
+        &mut self,
+        version: VersionId,
+        left: P,
+        right: P,
+        value: &<T as Node>::Value,
+    ) -> VersionId {
+        match Self::to_inclusive(left, right) {
+            Some((left, right)) => self.tree.update(version, left, right, value),
+            None => self.tree.branch_from(version),
+        }
+    }
+}
+
+impl<T, C, P> Ranged<ArcPersistent<T>, C, P>
+where
+    T: Clone + Node,
+    C: Interval,
+    P: Position,
+{
+    /// Same as [`ArcPersistent::query`], but the range is in the convention `C`.
+    /// It will **panic** if version is not in <code>[0,[versions](ArcPersistent::versions))</code>, even if the range is empty.
+    #[must_use]
+    pub fn query(&self, version: VersionId, left: P, right: P) -> Option<T> {
+        match Self::to_inclusive(left, right) {
+            Some((left, right)) => self.tree.query(version, left, right),
+            None => self.tree.query(version, 1, 0),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T, C, P> Ranged<Sharded<T>, C, P>
+where
+    T: Node + Clone,
+    C: Interval,
+    P: Position,
+{
+    /// Same as [`Sharded::query`], but the range is in the convention `C`.
+    #[must_use]
+    pub fn query(&self, left: P, right: P) -> Option<T> {
+        Self::to_inclusive(left, right).and_then(|(left, right)| self.tree.query(left, right))
+    }
+}
+
+impl<S, C, P> Deref for Ranged<S, C, P> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        &self.tree
+    }
+}
+
+impl<S, C, P> DerefMut for Ranged<S, C, P> {
+    fn deref_mut(&mut self) -> &mut S {
+        &mut self.tree
+    }
+}
+
+impl<S, C, P> core::fmt::Debug for Ranged<S, C, P>
+where
+    S: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Ranged")
+            .field("tree", &self.tree)
+            .field("interval", &core::any::type_name::<C>())
+            .field("position", &core::any::type_name::<P>())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        interval::{HalfOpen, Inclusive},
+        nodes::Node,
+        segment_tree::{LazyPersistent, LazyRecursive, Persistent, Recursive},
+        utils::{LazySetWrapper, Min, Sum},
+    };
+
+    use super::Ranged;
+
+    #[test]
+    fn half_open_query_works() {
+        let nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let segment_tree: Ranged<_, HalfOpen> = Ranged::new(Recursive::build(&nodes));
+        for i in 0..=10 {
+            assert!(segment_tree.query(i, i).is_none());
+            assert_eq!(segment_tree.query(i, 11).unwrap().value(), &i);
+        }
+        let segment_tree: Ranged<_, Inclusive> = Ranged::new(segment_tree.into_inner());
+        assert_eq!(segment_tree.query(3, 3).unwrap().value(), &3);
+    }
+
+    #[test]
+    fn half_open_update_works() {
+        type LSMin = LazySetWrapper<Min<usize>>;
+        let nodes: Vec<LSMin> = (0..=10).map(|x| LSMin::initialize(&x)).collect();
+        let mut segment_tree: Ranged<_, HalfOpen> = Ranged::new(LazyRecursive::build(&nodes));
+        segment_tree.update(0, 3, &20);
+        segment_tree.update(5, 5, &0);
+        assert_eq!(segment_tree.query(0, 3).unwrap().value(), &20);
+        assert_eq!(segment_tree.query(0, 4).unwrap().value(), &3);
+        assert_eq!(segment_tree.query(5, 11).unwrap().value(), &5);
+    }
+
+    #[test]
+    fn half_open_persistent_works() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree: Ranged<_, HalfOpen> = Ranged::new(LazyPersistent::build(&nodes));
+        segment_tree.update(0, 0, 11, &1);
+        segment_tree.update(1, 4, 4, &1);
+        assert_eq!(segment_tree.versions(), 3);
+        assert_eq!(segment_tree.query(2, 0, 11).unwrap().value(), &66);
+        assert!(segment_tree.query(2, 11, 11).is_none());
+        let segment_tree: Ranged<_, HalfOpen> = Ranged::new(Persistent::build(&nodes));
+        assert_eq!(segment_tree.query(0, 1, 3).unwrap().value(), &3);
+    }
+
+    #[test]
+    fn narrow_positions_work() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree: Ranged<_, Inclusive, u32> =
+            Ranged::new(LazyPersistent::build(&nodes));
+        let version = segment_tree.update(0, 2, 4, &1);
+        assert_eq!(segment_tree.query(version, 0, 10).unwrap().value(), &58);
+        assert_eq!(segment_tree.query(version, 3, 3).unwrap().value(), &4);
+        let segment_tree: Ranged<_, HalfOpen, u8> = Ranged::new(Recursive::build(&nodes));
+        assert_eq!(segment_tree.query(1, 4).unwrap().value(), &6);
+        assert!(segment_tree.query(4, 4).is_none());
+    }
+}
@@ -1,16 +1,44 @@
-use std::mem::MaybeUninit;
+use alloc::{vec, vec::Vec};
+
+use bit_vec::BitVec;
+use core::{
+    marker::PhantomData,
+    mem::MaybeUninit,
+    ops::{Range, RangeInclusive},
+};
 
 use crate::{
-    internal_utils::dbg_utils::{as_dbg_tree, recursive_visitor},
+    codec::Codec,
+    internal_utils::{
+        codec_utils::{decode_values, encode_values},
+        dbg_utils::{as_dbg_tree, display_values, recursive_visitor},
+        empty_utils::{assert_not_empty, assert_same_len},
+        instrument_utils::{combine, enter, record_allocations, LastOp},
+        leaf_utils::{
+            fill_padding, into_leaf_nodes, into_leaf_values, leaves, leaves_eq, zip_leaves,
+        },
+        observer_utils::Observers,
+        range_utils::{assert_split_index, clamp},
+        tracing_utils::span,
+        validate_utils::validate,
+    },
     nodes::Node,
+    segment_tree::{Descend, Iterative, MemoryStats},
+    storage::Storage,
+    utils::{compress_pairs, Compressor, Max, Min, Pair},
 };
 
 /// Segment tree with range queries and point updates.
+///
 /// It uses `O(n)` space, assuming that each node uses `O(1)` space.
 /// Note if you don't need to use `lower_bound`, just use [`Iterative`](crate::segment_tree::Iterative) it uses half the memory and it's more performant.
-pub struct Recursive<T> {
-    nodes: Vec<T>,
+/// The nodes are stored in `S`, see [`Storage`].
+pub struct Recursive<T, S = Vec<T>> {
+    nodes: S,
     n: usize,
+    observers: Observers<T>,
+    _node: PhantomData<T>,
+    last_op: LastOp,
 }
 
 impl<T> Recursive<T>
@@ -21,42 +49,227 @@ where
     /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
     #[must_use]
     pub fn build(values: &[T]) -> Self {
+        Self::build_in(values, ())
+    }
+
+    /// Builds segment tree with n elements, each of them equal to value.
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn filled(n: usize, value: &<T as Node>::Value) -> Self {
+        Self::build(&vec![T::initialize(value); n])
+    }
+
+    /// Same as [`filled`](Recursive::filled), but every element is the [`Default`] value, e.g. `0` for [`Sum`](crate::utils::Sum) nodes.
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    /// ```
+    /// # use seg_tree::{Recursive,utils::Sum,nodes::Node};
+    /// let mut seg_tree = Recursive::<Sum<i64>>::with_len(10); // [0,0,0,0,0,0,0,0,0,0] with Sum<i64> nodes
+    /// seg_tree.update(3, &-4);
+    /// assert_eq!(seg_tree.query(0, 9).unwrap().value(), &-4);
+    /// ```
+    #[must_use]
+    pub fn with_len(n: usize) -> Self
+    where
+        <T as Node>::Value: Default,
+    {
+        Self::filled(n, &<T as Node>::Value::default())
+    }
+
+    /// Builds segment tree with n elements, the i-th of them equal to `f(i)`, without collecting the values first.
+    /// ```
+    /// # use seg_tree::{Recursive,utils::Max,nodes::Node};
+    /// let seg_tree = Recursive::<Max<usize>>::build_from_fn(10, |i| (i * 7) % 10); // [0,7,4,1,8,5,2,9,6,3] with Max<usize> nodes
+    /// assert_eq!(seg_tree.query(2, 6).unwrap().value(), &8);
+    /// ```
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) and `f` have constant time complexity.
+    #[must_use]
+    pub fn build_from_fn<F>(n: usize, mut f: F) -> Self
+    where
+        F: FnMut(usize) -> <T as Node>::Value,
+    {
+        Self::build_from((0..n).map(|i| T::initialize(&f(i))), ())
+    }
+
+    /// Consumes the segment tree, returning the values of its leaves in order, see [`into_value`](Node::into_value).
+    /// It's only available for the default storage, as the nodes are moved out of it.
+    /// It has time complexity of `O(n)`.
+    #[must_use]
+    pub fn into_values(self) -> Vec<T::Value> {
+        into_leaf_values(self.nodes, self.n)
+    }
+
+    /// Consumes the segment tree, returning its leaves in order.
+    pub(crate) fn into_leaves(self) -> Vec<T> {
+        into_leaf_nodes(self.nodes, self.n)
+    }
+
+    /// Consumes the segment tree, returning its nodes and its amount of elements.
+    pub(crate) fn into_parts(self) -> (Vec<T>, usize) {
+        (self.nodes, self.n)
+    }
+
+    /// Splits the segment tree in two at index `at`, it keeps the elements in `[0,at)` and returns a segment tree with the elements in `[at,n)`.
+    /// The leaves are moved, but both segment trees are rebuilt from them, so every internal node is combined again, the callbacks subscribed with [`on_update`](Self::on_update) stay in this segment tree.
+    /// It will **panic** if `at` is not in `[0,n]`.
+    /// It has time complexity of `O(n)`, the same as building both segment trees, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn split_off(&mut self, at: usize) -> Self {
+        assert_split_index(at, self.n);
+        let mut leaves = self.take_leaves();
+        let right = leaves.split_off(at);
+        self.set_leaves(leaves);
+        Self::build_from(right.into_iter(), ())
+    }
+
+    /// Appends the elements of `other` at the end of the segment tree.
+    /// The leaves are moved, but the segment tree is rebuilt from them, so every internal node of both segment trees is combined again, the callbacks subscribed to `other` are dropped.
+    /// It has time complexity of `O(n+m)`, the same as building the appended segment tree, where `m` is the amount of elements of `other`, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn append(&mut self, other: Self) {
+        let mut leaves = self.take_leaves();
+        leaves.extend(other.into_leaves());
+        self.set_leaves(leaves);
+    }
+
+    /// Resizes the segment tree to `new_len` elements, keeping the first ones, like [`Vec::resize`].
+    /// If it grows, the new elements are equal to value, and if it shrinks, the last elements are dropped.
+    /// The kept leaves are moved and the rest of the nodes are combined again, as the range of every node depends on the amount of elements, the callbacks subscribed with [`on_update`](Self::on_update) stay in this segment tree.
+    /// If the segment tree is resized often, e.g. to push elements one by one, use [`Iterative::resize`](crate::segment_tree::Iterative::resize) which only combines the new elements and their ancestors.
+    /// ```
+    /// # use seg_tree::{Recursive,utils::Sum,nodes::Node};
+    /// let mut seg_tree = Recursive::<Sum<usize>>::build_from_fn(5, |i| i); // [0,1,2,3,4] with Sum<usize> nodes
+    /// seg_tree.resize(7, &10); // [0,1,2,3,4,10,10]
+    /// assert_eq!(seg_tree.query(0, 6).unwrap().value(), &30);
+    /// seg_tree.resize(3, &10); // [0,1,2]
+    /// assert_eq!(seg_tree.query(0, 2).unwrap().value(), &3);
+    /// ```
+    /// It has time complexity of `O(n+new_len)`, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn resize(&mut self, new_len: usize, value: &<T as Node>::Value) {
+        if new_len == self.n {
+            return;
+        }
+        let mut leaves = self.take_leaves();
+        leaves.resize(new_len, T::initialize(value));
+        self.set_leaves(leaves);
+    }
+
+    /// Keeps only the elements whose value satisfies the predicate f, in the same order, like [`Vec::retain`], so the i-th element afterwards is the i-th one kept.
+    /// The kept leaves are moved and the rest of the nodes are combined again, the callbacks subscribed with [`on_update`](Self::on_update) stay in this segment tree.
+    /// ```
+    /// # use seg_tree::{Recursive,utils::Sum,nodes::Node};
+    /// let mut seg_tree = Recursive::<Sum<usize>>::build_from_fn(10, |i| i); // [0,1,2,3,4,5,6,7,8,9] with Sum<usize> nodes
+    /// seg_tree.retain(|x| x % 3 != 0); // [1,2,4,5,7,8]
+    /// assert_eq!(seg_tree.len(), 6);
+    /// assert_eq!(seg_tree.query(2, 3).unwrap().value(), &9);
+    /// ```
+    /// It has time complexity of `O(n)`, assuming that [`combine`](Node::combine) and f have constant time complexity.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&<T as Node>::Value) -> bool,
+    {
+        let mut leaves = self.take_leaves();
+        leaves.retain(|leaf| f(leaf.value()));
+        self.set_leaves(leaves);
+    }
+
+    /// Returns a segment tree whose i-th element is the [`combine`](Node::combine) of the i-th elements of both segment trees, e.g. to add up partial results computed over different data.
+    /// The rest of the nodes are combined again from the new elements, so it works for any node, and the callbacks subscribed with [`on_update`](Self::on_update) aren't copied.
+    /// It will **panic** if the segment trees don't have the same amount of elements.
+    /// It has time complexity of `O(n)`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn merge_with(&self, other: &Self) -> Self {
+        assert_same_len(self.n, other.n, "merge");
+        let leaves = zip_leaves(&self.nodes, &other.nodes, self.n, combine);
+        Self::build_from(leaves.into_iter(), ())
+    }
+
+    /// Builds a segment tree over the distinct keys of pairs, in increasing order, returning it with the [`Compressor`] which maps the keys to their leaves.
+    /// The leaf of each key is the combination of the nodes with that key, in the order they appear in pairs.
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn build_compressed<K>(pairs: &[(K, T)]) -> (Compressor<K>, Self)
+    where
+        K: Ord + Clone,
+    {
+        let (compressor, leaves) = compress_pairs(pairs);
+        (compressor, Self::build_from(leaves.into_iter(), ()))
+    }
+
+    /// Moves the leaves out of the segment tree, leaving it empty.
+    fn take_leaves(&mut self) -> Vec<T> {
+        let n = core::mem::take(&mut self.n);
+        into_leaf_nodes(core::mem::take(&mut self.nodes), n)
+    }
+
+    /// Rebuilds the segment tree from leaves, keeping its callbacks.
+    fn set_leaves(&mut self, leaves: Vec<T>) {
+        let segment_tree = Self::build_from(leaves.into_iter(), ());
+        self.nodes = segment_tree.nodes;
+        self.n = segment_tree.n;
+    }
+}
+
+impl<T, S> Recursive<T, S>
+where
+    T: Node + Clone,
+    S: Storage<T>,
+{
+    /// Same as [`build`](Recursive::build), but the nodes are stored in `S` allocated with `allocator`, see [`Storage`].
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn build_in(values: &[T], allocator: S::Allocator) -> Self {
+        Self::build_from(values.iter().cloned(), allocator)
+    }
+
+    /// Same as [`build_in`](Recursive::build_in), but the leaves are moved out of values instead of cloned.
+    pub(crate) fn build_from<I>(mut values: I, allocator: S::Allocator) -> Self
+    where
+        I: ExactSizeIterator<Item = T>,
+    {
+        span!(DEBUG, "build", tree = "Recursive", n = values.len());
+        let last_op = LastOp::default();
+        let _op = last_op.measure();
         let n = values.len();
-        let mut nodes = Vec::with_capacity(4 * n);
-        unsafe { nodes.set_len(4 * n) };
-        if n == 0 {
-            return Self {
-                nodes: Vec::new(),
-                n: 0,
-            };
+        let mut nodes = S::allocate(4 * n, allocator);
+        record_allocations(4 * n);
+        if n > 0 {
+            Self::build_helper(0, 0, n - 1, &mut values, &mut nodes);
         }
-        Self::build_helper(0, 0, n - 1, values, &mut nodes);
-        let ptr = nodes.as_mut_ptr();
-        core::mem::forget(nodes);
-        let nodes = unsafe { Vec::from_raw_parts(ptr.cast::<T>(), 4 * n, 4 * n) }; // Unsafe AF, but if it's coded correctly the only nodes which will ever be accessed are already initialized
+        fill_padding(&mut nodes, n);
+        // SAFETY: `build_helper` initializes the nodes of the tree and `fill_padding` the rest of them.
+        let nodes = unsafe { S::assume_init(nodes) };
 
-        Self { nodes, n }
+        Self {
+            nodes,
+            n,
+            observers: Observers::default(),
+            _node: PhantomData,
+            last_op,
+        }
     }
 
     #[inline]
-    fn build_helper(
+    fn build_helper<I>(
         curr_node: usize,
         i: usize,
         j: usize,
-        values: &[T],
+        values: &mut I,
         nodes: &mut [MaybeUninit<T>],
-    ) {
+    ) where
+        I: Iterator<Item = T>,
+    {
+        let _depth = enter();
         if i == j {
-            nodes[curr_node].write(values[i].clone());
+            // The leaves are visited in order, so the i-th value is the next one.
+            nodes[curr_node].write(values.next().unwrap());
             return;
         }
-        let mid = (i + j) / 2;
+        let mid = usize::midpoint(i, j);
         let left_node = 2 * curr_node + 1;
         let right_node = 2 * curr_node + 2;
         Self::build_helper(left_node, i, mid, values, nodes);
         Self::build_helper(right_node, mid + 1, j, values, nodes);
         let (top_nodes, bottom_nodes) = nodes.split_at_mut(curr_node + 1);
-        top_nodes[curr_node].write(Node::combine(
+        top_nodes[curr_node].write(combine(
             unsafe { bottom_nodes[left_node - curr_node - 1].assume_init_ref() },
             unsafe { bottom_nodes[right_node - curr_node - 1].assume_init_ref() },
         ));
@@ -66,7 +279,11 @@ where
     /// It will panic if p is not in `[0,n)`
     /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
     pub fn update(&mut self, p: usize, value: &<T as Node>::Value) {
+        span!(TRACE, "update", tree = "Recursive", n = self.n, p);
+        let _op = self.last_op.measure();
+        assert_not_empty(self.n, "update");
         self.update_helper(p, value, 0, 0, self.n - 1);
+        self.observers.notify(p..=p, value);
     }
 
     #[inline]
@@ -78,6 +295,7 @@ where
         i: usize,
         j: usize,
     ) {
+        let _depth = enter();
         if j < p || p < i {
             return;
         }
@@ -85,23 +303,66 @@ where
             self.nodes[curr_node] = Node::initialize(value);
             return;
         }
-        let mid = (i + j) / 2;
+        let mid = usize::midpoint(i, j);
         let left_node = 2 * curr_node + 1;
         let right_node = 2 * curr_node + 2;
         self.update_helper(p, value, left_node, i, mid);
         self.update_helper(p, value, right_node, mid + 1, j);
-        self.nodes[curr_node] = Node::combine(&self.nodes[left_node], &self.nodes[right_node]);
+        self.nodes[curr_node] = combine(&self.nodes[left_node], &self.nodes[right_node]);
     }
 
     /// Returns the result from the range `[left,right]`.
-    /// It returns None if and only if range is empty.
+    /// It returns None if and only if range is empty or the segment tree has no elements.
     /// It will **panic** if `left` or `right` are not in [0,n).
     /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
     #[allow(clippy::must_use_candidate)]
     pub fn query(&self, left: usize, right: usize) -> Option<T> {
+        span!(TRACE, "query", tree = "Recursive", n = self.n, left, right);
+        let _op = self.last_op.measure();
+        if self.n == 0 {
+            return None;
+        }
         self.query_helper(left, right, 0, 0, self.n - 1)
     }
 
+    /// Same as [`query`](Self::query), but the range is clamped to `[0,n)` instead of **panicking** when it's out of bounds, and the range that was actually aggregated is returned with the result, as a half-open range.
+    /// It returns None if and only if the clamped range is empty.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn query_with_range(&self, left: usize, right: usize) -> Option<(Range<usize>, T)> {
+        let range = clamp(left, right, self.n)?;
+        self.query(range.start, range.end - 1)
+            .map(|node| (range, node))
+    }
+
+    /// Same as [`query_with_range`](Self::query_with_range), but only the result is returned.
+    /// It returns None if and only if the range clamped to `[0,n)` is empty.
+    #[must_use]
+    pub fn query_clamped(&self, left: usize, right: usize) -> Option<T> {
+        self.query_with_range(left, right).map(|(_, node)| node)
+    }
+
+    /// Returns the node which covers exactly the segment `[i,j]`, e.g. to implement custom descents on top of the segment tree.
+    /// It returns None if and only if no node covers exactly `[i,j]`, which is always the case if `i>j` or `j` is not in `[0,n)`.
+    /// It has time complexity of `O(log(n))`.
+    pub fn get_segment(&self, i: usize, j: usize) -> Option<&T> {
+        if i > j || j >= self.n {
+            return None;
+        }
+        let (mut curr_node, mut l, mut r) = (0, 0, self.n - 1);
+        while (l, r) != (i, j) {
+            let mid = usize::midpoint(l, r);
+            if j <= mid {
+                (curr_node, r) = (2 * curr_node + 1, mid);
+            } else if mid < i {
+                (curr_node, l) = (2 * curr_node + 2, mid + 1);
+            } else {
+                return None;
+            }
+        }
+        Some(&self.nodes[curr_node])
+    }
+
     #[inline]
     fn query_helper(
         &self,
@@ -111,10 +372,11 @@ where
         i: usize,
         j: usize,
     ) -> Option<T> {
+        let _depth = enter();
         if j < left || right < i {
             return None;
         }
-        let mid = (i + j) / 2;
+        let mid = usize::midpoint(i, j);
         let left_node = 2 * curr_node + 1;
         let right_node = 2 * curr_node + 2;
         if left <= i && j <= right {
@@ -124,13 +386,75 @@ where
             self.query_helper(left, right, left_node, i, mid),
             self.query_helper(left, right, right_node, mid + 1, j),
         ) {
-            (Some(ans_left), Some(ans_right)) => Some(Node::combine(&ans_left, &ans_right)),
+            (Some(ans_left), Some(ans_right)) => Some(combine(&ans_left, &ans_right)),
             (Some(ans_left), None) => Some(ans_left),
             (None, Some(ans_right)) => Some(ans_right),
             (None, None) => None,
         }
     }
 
+    /// Same as [`query`](Self::query), but the nodes which cover the range are combined from left to right and it returns as soon as `stop` is `true` for the result so far, e.g. once a sum exceeds some value.
+    /// The result is the one of a prefix `[left,k]` of the range, which is the whole range if `stop` is never `true`, so `stop` should be monotonic over the prefixes of the range for it to be meaningful.
+    /// It returns None if and only if range is empty or the segment tree has no elements.
+    /// It will **panic** if `left` or `right` are not in [0,n).
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) and `stop` have constant time complexity.
+    /// ```
+    /// # use seg_tree::{Recursive,utils::Sum,nodes::Node};
+    /// # let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+    /// let seg_tree = Recursive::build(&nodes); // [0,1,2,3,4,5,6,7,8,9] with Sum<usize> nodes
+    /// let result = seg_tree.query_until(1, 9, |node| *node.value() >= 10).unwrap();
+    /// assert_eq!(result.value(), &10); // The result of [1,4], the rest of the range isn't visited.
+    /// assert_eq!(seg_tree.query_until(1, 9, |_| false).unwrap().value(), &45);
+    /// ```
+    pub fn query_until<F>(&self, left: usize, right: usize, stop: F) -> Option<T>
+    where
+        F: Fn(&T) -> bool,
+    {
+        span!(
+            TRACE,
+            "query_until",
+            tree = "Recursive",
+            n = self.n,
+            left,
+            right
+        );
+        let _op = self.last_op.measure();
+        let mut result = None;
+        if self.n > 0 {
+            self.query_until_helper((left, right), 0, 0, self.n - 1, &stop, &mut result);
+        }
+        result
+    }
+
+    /// Combines the nodes of `[i,j]` which cover the range into result, returning `true` if `stop` was `true` for it.
+    fn query_until_helper<F>(
+        &self,
+        (left, right): (usize, usize),
+        curr_node: usize,
+        i: usize,
+        j: usize,
+        stop: &F,
+        result: &mut Option<T>,
+    ) -> bool
+    where
+        F: Fn(&T) -> bool,
+    {
+        let _depth = enter();
+        if j < left || right < i {
+            return false;
+        }
+        if left <= i && j <= right {
+            let node = &self.nodes[curr_node];
+            let combined = result
+                .take()
+                .map_or_else(|| node.clone(), |result| combine(&result, node));
+            return stop(result.insert(combined));
+        }
+        let mid = usize::midpoint(i, j);
+        self.query_until_helper((left, right), 2 * curr_node + 1, i, mid, stop, result)
+            || self.query_until_helper((left, right), 2 * curr_node + 2, mid + 1, j, stop, result)
+    }
+
     /// A method that finds the smallest prefix[^note] `u` such that `predicate(u.value(), value)` is `true`. The following must be true:
     /// - `predicate` is monotonic over prefixes[^note2].
     /// - `g` will satisfy the following, given segments `[i,j]` and `[i,k]` with `j<k` we have that `predicate([i,k].value(),value)` implies `predicate([j+1,k].value(),g([i,j].value(),value))`.
@@ -161,6 +485,8 @@ where
     /// # }
     /// ```
     ///
+    /// It will **panic** if the segment tree has no elements.
+    ///
     /// [^note]: A prefix is a segment of the form `[0,i]`.
     ///
     /// [^note2]: Given two prefixes `u` and `v` if `u` is contained in `v` then `predicate(u.value(), value)` implies `predicate(v.value(), value)`.
@@ -169,6 +495,35 @@ where
         F: Fn(&<T as Node>::Value, &<T as Node>::Value) -> bool,
         G: Fn(&<T as Node>::Value, <T as Node>::Value) -> <T as Node>::Value,
     {
+        self.lower_bound_by_node(
+            |node: &T, value: &<T as Node>::Value| predicate(node.value(), value),
+            |node: &T, value| g(node.value(), value),
+            value,
+        )
+    }
+
+    /// Same as [`lower_bound`](Self::lower_bound), but `predicate` and `g` receive the node of the segment instead of its value, so nodes with several fields can be searched by any of them.
+    /// The same conditions must be true, taking the node of each segment instead of its value.
+    ///
+    /// For example, finding the smallest prefix which sums at least some value, while the nodes also keep the maximum.
+    /// ```
+    /// # use seg_tree::{Recursive,utils::{Max,Pair,Sum},nodes::Node};
+    /// type SumMax = Pair<Sum<usize>, Max<usize>>;
+    /// let predicate = |left_node: &SumMax, value: &(usize, usize)| left_node.first().value() >= &value.0; // Only look at the sum
+    /// let g = |left_node: &SumMax, value: (usize, usize)| (value.0 - left_node.first().value(), value.1);
+    /// # let nodes: Vec<SumMax> = (0..10).map(|x| SumMax::initialize(&(x, x))).collect();
+    /// let seg_tree = Recursive::build(&nodes); // [0,1,2,3,4,5,6,7,8,9] with Pair<Sum<usize>, Max<usize>> nodes
+    /// assert_eq!(seg_tree.lower_bound_by_node(predicate, g, (3, 0)), 2); // sum([0,1,2])>=3
+    /// assert_eq!(seg_tree.lower_bound_by_node(predicate, g, (45, 0)), 9);
+    /// ```
+    /// It will **panic** if the segment tree has no elements.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine), `predicate` and `g` have constant time complexity.
+    pub fn lower_bound_by_node<F, G>(&self, predicate: F, g: G, value: <T as Node>::Value) -> usize
+    where
+        F: Fn(&T, &<T as Node>::Value) -> bool,
+        G: Fn(&T, <T as Node>::Value) -> <T as Node>::Value,
+    {
+        assert_not_empty(self.n, "search");
         self.lower_bound_helper(0, 0, self.n - 1, predicate, g, value)
     }
     fn lower_bound_helper<F, G>(
@@ -181,36 +536,574 @@ where
         value: <T as Node>::Value,
     ) -> usize
     where
-        F: Fn(&<T as Node>::Value, &<T as Node>::Value) -> bool,
-        G: Fn(&<T as Node>::Value, <T as Node>::Value) -> <T as Node>::Value,
+        F: Fn(&T, &<T as Node>::Value) -> bool,
+        G: Fn(&T, <T as Node>::Value) -> <T as Node>::Value,
     {
+        let _depth = enter();
         if i == j {
             return i;
         }
-        let mid = (i + j) / 2;
+        let mid = usize::midpoint(i, j);
         let left_node = 2 * curr_node + 1;
         let right_node = 2 * curr_node + 2;
-        let left_value = self.nodes[left_node].value();
-        if predicate(left_value, &value) {
+        let left = &self.nodes[left_node];
+        if predicate(left, &value) {
             self.lower_bound_helper(left_node, i, mid, predicate, g, value)
         } else {
-            let value = g(left_value, value);
+            let value = g(left, value);
             self.lower_bound_helper(right_node, mid + 1, j, predicate, g, value)
         }
     }
+
+    /// Mirror of [`lower_bound`](Self::lower_bound), it finds the smallest suffix[^note] `u` such that `predicate(u.value(), value)` is `true`, and returns the index where `u` starts. The following must be true:
+    /// - `predicate` is monotonic over suffixes[^note2].
+    /// - `g` will satisfy the following, given segments `[j,k]` and `[i,k]` with `i<j` we have that `predicate([i,k].value(),value)` implies `predicate([i,j-1].value(),g([j,k].value(),value))`.
+    ///
+    /// For example, finding the position of the last value greater or equal to some value.
+    /// ```
+    /// # use seg_tree::{Recursive,utils::Max,nodes::Node};
+    /// let predicate = |right_value: &usize, value: &usize| *right_value >= *value; // Is the maximum greater or equal to value?
+    /// let g = |_right_value: &usize, value: usize| value; // Do nothing
+    /// # let nodes: Vec<Max<usize>> = [3, 9, 4, 7, 1].iter().map(Max::initialize).collect();
+    /// let seg_tree = Recursive::build(&nodes); // [3,9,4,7,1] with Max<usize> nodes
+    /// assert_eq!(seg_tree.upper_bound(predicate, g, 5), 3); // 7 is the last value >= 5
+    /// assert_eq!(seg_tree.upper_bound(predicate, g, 8), 1);
+    /// ```
+    /// It will **panic** if the segment tree has no elements.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine), `predicate` and `g` have constant time complexity.
+    ///
+    /// [^note]: A suffix is a segment of the form `[i,n-1]`.
+    ///
+    /// [^note2]: Given two suffixes `u` and `v` if `u` is contained in `v` then `predicate(u.value(), value)` implies `predicate(v.value(), value)`.
+    pub fn upper_bound<F, G>(&self, predicate: F, g: G, value: <T as Node>::Value) -> usize
+    where
+        F: Fn(&<T as Node>::Value, &<T as Node>::Value) -> bool,
+        G: Fn(&<T as Node>::Value, <T as Node>::Value) -> <T as Node>::Value,
+    {
+        assert_not_empty(self.n, "search");
+        self.upper_bound_helper(0, 0, self.n - 1, predicate, g, value)
+    }
+    fn upper_bound_helper<F, G>(
+        &self,
+        curr_node: usize,
+        i: usize,
+        j: usize,
+        predicate: F,
+        g: G,
+        value: <T as Node>::Value,
+    ) -> usize
+    where
+        F: Fn(&<T as Node>::Value, &<T as Node>::Value) -> bool,
+        G: Fn(&<T as Node>::Value, <T as Node>::Value) -> <T as Node>::Value,
+    {
+        let _depth = enter();
+        if i == j {
+            return i;
+        }
+        let mid = usize::midpoint(i, j);
+        let left_node = 2 * curr_node + 1;
+        let right_node = 2 * curr_node + 2;
+        let right_value = self.nodes[right_node].value();
+        if predicate(right_value, &value) {
+            self.upper_bound_helper(right_node, mid + 1, j, predicate, g, value)
+        } else {
+            let value = g(right_value, value);
+            self.upper_bound_helper(left_node, i, mid, predicate, g, value)
+        }
+    }
+
+    /// Same as [`lower_bound`](Self::lower_bound), but it finds the smallest segment `[left,k]` with `k` in `[left,right]` such that `predicate([left,k].value(), value)` is `true`, and returns `k`.
+    /// `predicate` and `g` must satisfy the same conditions, over the segments which start at left instead of the prefixes.
+    /// The range is split into the nodes which cover it, and the search only descends into the first one where `predicate` becomes `true`.
+    /// ```
+    /// # use seg_tree::{Recursive,utils::Sum,nodes::Node};
+    /// let predicate = |sum: &usize, value: &usize| *sum >= *value; // Is the sum greater or equal to value?
+    /// let g = |sum: &usize, value: usize| value - *sum; // Subtract the sum of the segment.
+    /// # let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+    /// let seg_tree = Recursive::build(&nodes); // [0,1,2,3,4,5,6,7,8,9] with Sum<usize> nodes
+    /// assert_eq!(seg_tree.lower_bound_in(4, 8, predicate, g, 9), Some(5)); // sum([4,5])>=9
+    /// assert_eq!(seg_tree.lower_bound_in(4, 8, predicate, g, 40), None); // sum([4,8])<40
+    /// ```
+    /// It returns None if and only if there's no such `k`, in particular if the range is empty.
+    /// It will **panic** if the segment tree has no elements.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine), `predicate` and `g` have constant time complexity.
+    pub fn lower_bound_in<F, G>(
+        &self,
+        left: usize,
+        right: usize,
+        predicate: F,
+        g: G,
+        value: <T as Node>::Value,
+    ) -> Option<usize>
+    where
+        F: Fn(&<T as Node>::Value, &<T as Node>::Value) -> bool,
+        G: Fn(&<T as Node>::Value, <T as Node>::Value) -> <T as Node>::Value,
+    {
+        assert_not_empty(self.n, "search");
+        let root = 0;
+        let fns = (&predicate, &g);
+        self.lower_bound_in_helper(root, 0, self.n - 1, (left, right), fns, value).ok()
+    }
+
+    /// Returns the index found in the intersection of `[i,j]` and `range`, or the value left for the rest of the range if there's none.
+    fn lower_bound_in_helper<F, G>(
+        &self,
+        curr_node: usize,
+        i: usize,
+        j: usize,
+        (left, right): (usize, usize),
+        (predicate, g): (&F, &G),
+        value: <T as Node>::Value,
+    ) -> Result<usize, <T as Node>::Value>
+    where
+        F: Fn(&<T as Node>::Value, &<T as Node>::Value) -> bool,
+        G: Fn(&<T as Node>::Value, <T as Node>::Value) -> <T as Node>::Value,
+    {
+        let _depth = enter();
+        if j < left || right < i {
+            return Err(value);
+        }
+        if left <= i && j <= right {
+            let curr_value = self.nodes[curr_node].value();
+            if !predicate(curr_value, &value) {
+                return Err(g(curr_value, value));
+            }
+            let predicate = |node: &T, value: &<T as Node>::Value| predicate(node.value(), value);
+            let g = |node: &T, value| g(node.value(), value);
+            return Ok(self.lower_bound_helper(curr_node, i, j, predicate, g, value));
+        }
+        let mid = usize::midpoint(i, j);
+        let left_node = 2 * curr_node + 1;
+        let right_node = 2 * curr_node + 2;
+        let (range, fns) = ((left, right), (predicate, g));
+        match self.lower_bound_in_helper(left_node, i, mid, range, fns, value) {
+            Ok(index) => Ok(index),
+            Err(value) => self.lower_bound_in_helper(right_node, mid + 1, j, range, fns, value),
+        }
+    }
+
+    /// Mirror of [`lower_bound_in_helper`](Self::lower_bound_in_helper), it returns the start of the smallest segment `[k,right]` found in the intersection of `[i,j]` and `range`.
+    fn upper_bound_in_helper<F, G>(
+        &self,
+        curr_node: usize,
+        i: usize,
+        j: usize,
+        (left, right): (usize, usize),
+        (predicate, g): (&F, &G),
+        value: <T as Node>::Value,
+    ) -> Result<usize, <T as Node>::Value>
+    where
+        F: Fn(&<T as Node>::Value, &<T as Node>::Value) -> bool,
+        G: Fn(&<T as Node>::Value, <T as Node>::Value) -> <T as Node>::Value,
+    {
+        let _depth = enter();
+        if j < left || right < i {
+            return Err(value);
+        }
+        if left <= i && j <= right {
+            let curr_value = self.nodes[curr_node].value();
+            if !predicate(curr_value, &value) {
+                return Err(g(curr_value, value));
+            }
+            return Ok(self.upper_bound_helper(curr_node, i, j, predicate, g, value));
+        }
+        let mid = usize::midpoint(i, j);
+        let left_node = 2 * curr_node + 1;
+        let right_node = 2 * curr_node + 2;
+        let (range, fns) = ((left, right), (predicate, g));
+        match self.upper_bound_in_helper(right_node, mid + 1, j, range, fns, value) {
+            Ok(index) => Ok(index),
+            Err(value) => self.upper_bound_in_helper(left_node, i, mid, range, fns, value),
+        }
+    }
+
+    /// Walks the segment tree from the root, calling `controller` with the segment and node of every visited node, and visiting the children it chooses, see [`Descend`].
+    /// The segment is half-open, so the leaves are the segments of the form `i..i+1`, and what's chosen for them is ignored.
+    /// It allows any top-down algorithm over the nodes, e.g. a binary search which can't be expressed with [`lower_bound`](Self::lower_bound).
+    /// ```
+    /// # use seg_tree::{Recursive,Descend,utils::Max,nodes::Node};
+    /// # let nodes: Vec<Max<usize>> = [3, 9, 4, 7, 1].iter().map(Max::initialize).collect();
+    /// let seg_tree = Recursive::build(&nodes); // [3,9,4,7,1] with Max<usize> nodes
+    /// let mut positions = Vec::new();
+    /// seg_tree.walk(|segment, node| {
+    ///     if *node.value() <= 5 {
+    ///         return Descend::Stop; // Skip the segments without values greater than 5.
+    ///     }
+    ///     if segment.len() == 1 {
+    ///         positions.push(segment.start);
+    ///     }
+    ///     Descend::Both
+    /// });
+    /// assert_eq!(positions, [1, 3]);
+    /// ```
+    /// It has time complexity of `O(k)`, where `k` is the amount of nodes visited, assuming that `controller` has constant time complexity.
+    pub fn walk<F>(&self, mut controller: F)
+    where
+        F: FnMut(Range<usize>, &T) -> Descend,
+    {
+        if self.n == 0 {
+            return;
+        }
+        self.walk_helper(0, 0, self.n - 1, &mut controller);
+    }
+    fn walk_helper<F>(&self, curr_node: usize, i: usize, j: usize, controller: &mut F)
+    where
+        F: FnMut(Range<usize>, &T) -> Descend,
+    {
+        let _depth = enter();
+        let descend = controller(i..j + 1, &self.nodes[curr_node]);
+        if i == j {
+            return;
+        }
+        let mid = usize::midpoint(i, j);
+        let left_node = 2 * curr_node + 1;
+        let right_node = 2 * curr_node + 2;
+        if matches!(descend, Descend::Left | Descend::Both) {
+            self.walk_helper(left_node, i, mid, controller);
+        }
+        if matches!(descend, Descend::Right | Descend::Both) {
+            self.walk_helper(right_node, mid + 1, j, controller);
+        }
+    }
+
+    /// Returns the position of the k-th leaf (counting from `0`) from the range `[left,right]` which matches, e.g. the k-th zero of an array of zeros and ones.
+    /// `count(node, len)` must return how many leaves of a segment of `len` elements match, given its node, so the nodes have to keep some count of the matching leaves, e.g. [`Sum`](crate::utils::Sum) nodes of the ones count the zeros as `len-sum`.
+    /// The range is split into the nodes which cover it, and the search only descends into the one which holds the k-th match.
+    /// ```
+    /// # use seg_tree::{Recursive,utils::Sum,nodes::Node};
+    /// # let nodes: Vec<Sum<usize>> = [1, 0, 0, 1, 0, 1, 0].iter().map(Sum::initialize).collect();
+    /// let seg_tree = Recursive::build(&nodes); // [1,0,0,1,0,1,0] with Sum<usize> nodes
+    /// let zeros = |node: &Sum<usize>, len: usize| len - node.value();
+    /// assert_eq!(seg_tree.kth_matching(2, 6, 1, zeros), Some(4)); // The zeros of [2,6] are at 2, 4 and 6.
+    /// assert_eq!(seg_tree.kth_matching(2, 6, 3, zeros), None);
+    /// assert_eq!(seg_tree.kth_matching(0, 6, 2, |node, _| *node.value()), Some(5)); // The third one.
+    /// ```
+    /// It returns None if and only if the range has at most `k` matching leaves, in particular if the range is empty or the segment tree has no elements.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) and `count` have constant time complexity.
+    pub fn kth_matching<F>(&self, left: usize, right: usize, k: usize, count: F) -> Option<usize>
+    where
+        F: Fn(&T, usize) -> usize,
+    {
+        if self.n == 0 {
+            return None;
+        }
+        self.kth_matching_helper(0, 0, self.n - 1, (left, right), &count, k)
+            .ok()
+    }
+
+    /// Returns the position of the k-th match in the intersection of `[i,j]` and `range`, or how many matches are left to skip for the rest of the range if there's none.
+    fn kth_matching_helper<F>(
+        &self,
+        curr_node: usize,
+        i: usize,
+        j: usize,
+        (left, right): (usize, usize),
+        count: &F,
+        k: usize,
+    ) -> Result<usize, usize>
+    where
+        F: Fn(&T, usize) -> usize,
+    {
+        let _depth = enter();
+        if j < left || right < i {
+            return Err(k);
+        }
+        if left <= i && j <= right {
+            let matches = count(&self.nodes[curr_node], j - i + 1);
+            if k >= matches {
+                return Err(k - matches);
+            }
+            let (mut curr_node, mut i, mut j, mut k) = (curr_node, i, j, k);
+            while i != j {
+                let mid = usize::midpoint(i, j);
+                let left_matches = count(&self.nodes[2 * curr_node + 1], mid - i + 1);
+                if k < left_matches {
+                    (curr_node, j) = (2 * curr_node + 1, mid);
+                } else {
+                    (curr_node, i, k) = (2 * curr_node + 2, mid + 1, k - left_matches);
+                }
+            }
+            return Ok(i);
+        }
+        let mid = usize::midpoint(i, j);
+        let left_node = 2 * curr_node + 1;
+        let right_node = 2 * curr_node + 2;
+        let range = (left, right);
+        match self.kth_matching_helper(left_node, i, mid, range, count, k) {
+            Ok(index) => Ok(index),
+            Err(k) => self.kth_matching_helper(right_node, mid + 1, j, range, count, k),
+        }
+    }
+
+    /// Subscribes callback to the updates of the segment tree, it's called after every update with the updated range and the value given to the update, so external indexes or caches can be kept in sync with the tree.
+    /// Callbacks are called in subscription order.
+    pub fn on_update<F>(&mut self, callback: F)
+    where
+        F: FnMut(RangeInclusive<usize>, &<T as Node>::Value) + Send + Sync + 'static,
+    {
+        self.observers.push(callback);
+    }
+
+    /// Unsubscribes every callback subscribed with [`on_update`](Self::on_update).
+    pub fn clear_observers(&mut self) {
+        self.observers.clear();
+    }
+
+    /// Returns the amount of elements of the segment tree.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.n
+    }
+
+    /// Returns `true` if the segment tree has no elements.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Returns the counters of the last build, query or update of the segment tree, see [`OpStats`](crate::instrument::OpStats).
+    #[cfg(feature = "instrument")]
+    #[must_use]
+    pub fn stats(&self) -> crate::instrument::OpStats {
+        self.last_op.get()
+    }
+
+    /// Returns the memory used by the segment tree, see [`MemoryStats`].
+    #[must_use]
+    pub fn memory_stats(&self) -> MemoryStats {
+        MemoryStats {
+            nodes: self.nodes.len(),
+            capacity: self.nodes.len(),
+            node_size: core::mem::size_of::<T>(),
+            versions: 1,
+            overhead_bytes: 0,
+        }
+    }
+
+    /// Checks that every internal node is the combination of its children, so a node whose [`combine`](Node::combine) isn't deterministic, or corrupted nodes, are caught where they happen instead of as wrong answers later on.
+    /// It's meant for tests and debug assertions.
+    /// It will **panic** at the first node which doesn't match.
+    /// It has time complexity of `O(n)`, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn validate(&self)
+    where
+        <T as Node>::Value: PartialEq,
+    {
+        if self.n == 0 {
+            return;
+        }
+        let mut visited = BitVec::from_elem(self.nodes.len(), false);
+        let children = |curr_node| Some((2 * curr_node + 1, 2 * curr_node + 2));
+        validate(&self.nodes[..], &children, &mut visited, 0, 0, self.n - 1);
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<T, S> Recursive<T, S>
+where
+    T: Node + Clone + Send + Sync,
+    S: Storage<T> + Sync,
+{
+    /// Returns the result from each range `[left,right]` in `queries`, in the same order, running the queries in parallel with [`rayon`].
+    /// It will **panic** if any `left` or `right` is not in `[0,n)`.
+    /// It has time complexity of `O(q*log(n)/p)`, where `q` is the amount of queries and `p` the amount of threads, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn par_query_batch(&self, queries: &[(usize, usize)]) -> Vec<Option<T>> {
+        use rayon::prelude::*;
+        queries
+            .par_iter()
+            .map(|&(left, right)| self.query(left, right))
+            .collect()
+    }
+}
+
+impl<A, B> Recursive<Pair<A, B>>
+where
+    A: Node + Clone,
+    B: Node + Clone,
+{
+    /// Pairs the nodes of two segment trees of the same elements, so a single query answers both, without going back to the values of the elements, see [`Pair`].
+    /// The leaves are paired and the rest of the nodes are combined again, and the callbacks subscribed with [`on_update`](Recursive::on_update) are dropped.
+    /// It will **panic** if the segment trees don't have the same amount of elements.
+    /// It has time complexity of `O(n)`, assuming that [`combine`](Node::combine) and cloning the values of the nodes have constant time complexity.
+    /// ```
+    /// # use seg_tree::{Recursive,utils::{Min,Sum},nodes::Node};
+    /// # let min_nodes: Vec<Min<usize>> = (0..10).map(|x| Min::initialize(&x)).collect();
+    /// # let sum_nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+    /// let mins = Recursive::build(&min_nodes); // [0,1,2,3,4,5,6,7,8,9] with Min<usize> nodes
+    /// let sums = Recursive::build(&sum_nodes); // [0,1,2,3,4,5,6,7,8,9] with Sum<usize> nodes
+    /// let seg_tree = Recursive::zip(mins, sums);
+    /// assert_eq!(seg_tree.query(2, 5).unwrap().value(), &(2, 14));
+    /// ```
+    #[must_use]
+    pub fn zip(a: Recursive<A>, b: Recursive<B>) -> Self {
+        assert_same_len(a.n, b.n, "zip");
+        let leaves = a.into_leaves().into_iter().zip(b.into_leaves());
+        Self::build_from(leaves.map(|(a, b)| Pair::new(a, b)), ())
+    }
+}
+
+impl<T, S> Recursive<Max<T>, S>
+where
+    T: Ord + Clone,
+    S: Storage<Max<T>>,
+{
+    /// Returns the first position `j>=i` whose value is greater than x, found by descending with [`lower_bound_in`](Recursive::lower_bound_in).
+    /// It returns None if and only if there's no such position, in particular if i is not in `[0,n)`.
+    /// ```
+    /// # use seg_tree::{Recursive,utils::Max,nodes::Node};
+    /// # let nodes: Vec<Max<usize>> = [3, 9, 4, 7, 1].iter().map(Max::initialize).collect();
+    /// let seg_tree = Recursive::build(&nodes); // [3,9,4,7,1] with Max<usize> nodes
+    /// assert_eq!(seg_tree.next_position_greater_than(2, &5), Some(3));
+    /// assert_eq!(seg_tree.next_position_greater_than(2, &7), None);
+    /// ```
+    /// It has time complexity of `O(log(n))`.
+    #[must_use]
+    pub fn next_position_greater_than(&self, i: usize, x: &T) -> Option<usize> {
+        if i >= self.n {
+            return None;
+        }
+        let predicate = |max: &T, x: &T| max > x;
+        let g = |_max: &T, x: T| x;
+        self.lower_bound_in(i, self.n - 1, predicate, g, x.clone())
+    }
+}
+
+impl<T, S> Recursive<Min<T>, S>
+where
+    T: Ord + Clone,
+    S: Storage<Min<T>>,
+{
+    /// Returns the last position `j<=i` whose value is less than x, found by descending like [`upper_bound`](Recursive::upper_bound) over `[0,i]`.
+    /// It returns None if and only if there's no such position, positions after the last one are treated as `n-1`.
+    /// ```
+    /// # use seg_tree::{Recursive,utils::Min,nodes::Node};
+    /// # let nodes: Vec<Min<usize>> = [3, 9, 4, 7, 1].iter().map(Min::initialize).collect();
+    /// let seg_tree = Recursive::build(&nodes); // [3,9,4,7,1] with Min<usize> nodes
+    /// assert_eq!(seg_tree.prev_position_less_than(3, &5), Some(2));
+    /// assert_eq!(seg_tree.prev_position_less_than(3, &3), None);
+    /// ```
+    /// It has time complexity of `O(log(n))`.
+    #[must_use]
+    pub fn prev_position_less_than(&self, i: usize, x: &T) -> Option<usize> {
+        if self.n == 0 {
+            return None;
+        }
+        let predicate = |min: &T, x: &T| min < x;
+        let g = |_min: &T, x: T| x;
+        let (range, fns) = ((0, i.min(self.n - 1)), (&predicate, &g));
+        self.upper_bound_in_helper(0, 0, self.n - 1, range, fns, x.clone())
+            .ok()
+    }
+}
+
+/// Converts the segment tree into a [`Recursive`] with the same values, e.g. to use [`lower_bound`](Recursive::lower_bound), the leaves are moved and the rest of the nodes are combined again, as both trees are shaped differently.
+/// The callbacks subscribed with [`on_update`](Iterative::on_update) are dropped.
+/// It has time complexity of `O(n)`, assuming that [`combine`](Node::combine) has constant time complexity.
+impl<T> From<Iterative<T>> for Recursive<T>
+where
+    T: Node + Clone,
+{
+    fn from(segment_tree: Iterative<T>) -> Self {
+        Self::build_from(segment_tree.into_leaves(), ())
+    }
+}
+
+impl<T> Extend<T::Value> for Recursive<T>
+where
+    T: Node + Clone,
+{
+    /// Appends the values as new leaves at the end of the segment tree.
+    /// Like [`resize`](Recursive::resize), the leaves are moved and the rest of the nodes are combined again, as the range of every node depends on the amount of elements, use [`Iterative`] to only combine the new leaves and their ancestors.
+    /// It has time complexity of `O(n+k)`, where `k` is the amount of new values, assuming that [`combine`](Node::combine) has constant time complexity.
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = T::Value>,
+    {
+        span!(DEBUG, "extend", tree = "Recursive", n = self.n);
+        let _op = self.last_op.measure();
+        let new_leaves: Vec<T> = iter.into_iter().map(|value| T::initialize(&value)).collect();
+        if new_leaves.is_empty() {
+            return;
+        }
+        let mut leaves = self.take_leaves();
+        leaves.extend(new_leaves);
+        self.set_leaves(leaves);
+    }
+}
+
+impl<T> IntoIterator for Recursive<T>
+where
+    T: Node + Clone,
+{
+    type Item = T::Value;
+    type IntoIter = vec::IntoIter<T::Value>;
+
+    /// Same as [`into_values`](Recursive::into_values).
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_values().into_iter()
+    }
+}
+
+impl<T> Recursive<T>
+where
+    T: Node + Clone,
+    T::Value: Codec,
+{
+    /// Appends the encoding of the values of the segment tree to bytes, see [`Codec`].
+    /// It has time complexity of `O(n)`, assuming that [`encode`](Codec::encode) has constant time complexity.
+    pub fn encode_into(&self, bytes: &mut Vec<u8>) {
+        encode_values(leaves(&self.nodes, self.n).into_iter().map(Node::value), bytes);
+    }
+
+    /// Decodes a segment tree encoded by [`encode_into`](Recursive::encode_into) from the start of bytes, and advances bytes past it.
+    /// It returns None if and only if bytes don't start with a valid encoding.
+    /// It has time complexity of `O(n)`, assuming that [`decode`](Codec::decode) and [`combine`](Node::combine) have constant time complexity.
+    pub fn decode_from(bytes: &mut &[u8]) -> Option<Self> {
+        let values: Vec<T::Value> = decode_values(bytes)?;
+        let nodes: Vec<T> = values.iter().map(T::initialize).collect();
+        Some(Self::build(&nodes))
+    }
+}
+
+/// Two segment trees are equal if they have the same values, in the same order.
+impl<T, S> PartialEq for Recursive<T, S>
+where
+    T: Node,
+    T::Value: PartialEq,
+    S: Storage<T>,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.n == other.n && (self.n == 0 || leaves_eq(&self.nodes, &other.nodes, 0, 0, self.n - 1))
+    }
+}
+
+/// Writes the values of the segment tree like a slice, e.g. `[1, 2, 3]`, a precision limits how many values are written, e.g. `{:.2}` writes `[1, 2, ...]`.
+impl<T, S> core::fmt::Display for Recursive<T, S>
+where
+    T: Node,
+    T::Value: core::fmt::Display,
+    S: Storage<T>,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        display_values(f, leaves(&self.nodes, self.n).into_iter().map(Node::value))
+    }
 }
 
-impl<T> core::fmt::Debug for Recursive<T>
+#[allow(clippy::missing_fields_in_debug)]
+impl<T, S> core::fmt::Debug for Recursive<T, S>
 where
     T: core::fmt::Debug,
+    S: Storage<T>,
 {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("Recursive")
             .field("n", &self.n)
             .field(
                 "nodes",
-                &as_dbg_tree(&self.nodes, |nodes, f| {
-                    recursive_visitor(0, 0, self.n - 1, f, nodes);
+                &as_dbg_tree(&self.nodes[..], |nodes, f| {
+                    if self.n > 0 {
+                        recursive_visitor(0, 0, self.n - 1, f, nodes);
+                    }
                 }),
             )
             .finish()
@@ -219,7 +1112,14 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::{nodes::Node, utils::Min};
+    use std::rc::Rc;
+
+    use crate::{
+        nodes::Node,
+        segment_tree::{Descend, Iterative, LazyRecursive},
+        testing::{check_split_off_and_append, small_values},
+        utils::{LazySetWrapper, Max, Min, Pair, Sum},
+    };
 
     use super::Recursive;
 
@@ -250,6 +1150,23 @@ mod tests {
         assert_eq!(segment_tree.query(1, 10).unwrap().value(), &1);
     }
 
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_query_batch_works() {
+        use crate::testing::NaiveRangeQuery;
+
+        let nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let segment_tree = Recursive::build(&nodes);
+        let naive = NaiveRangeQuery::build(&nodes);
+        let queries: Vec<(usize, usize)> = (0..=10)
+            .flat_map(|i| (i..=10).map(move |j| (i, j)))
+            .collect();
+        let results = segment_tree.par_query_batch(&queries);
+        for (&(i, j), result) in queries.iter().zip(results) {
+            assert_eq!(result.unwrap().value(), naive.query(i, j).unwrap().value());
+        }
+    }
+
     #[test]
     fn dbg_works(){
         let nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
@@ -259,4 +1176,496 @@ mod tests {
         let expected = "Recursive { n: 11, nodes: {[0, 10]: Min { value: 1 }, [0, 5]: Min { value: 1 }, [0, 2]: Min { value: 1 }, [0, 1]: Min { value: 1 }, [0, 0]: Min { value: 2 }, [1, 1]: Min { value: 1 }, [2, 2]: Min { value: 2 }, [3, 5]: Min { value: 3 }, [3, 4]: Min { value: 3 }, [3, 3]: Min { value: 3 }, [4, 4]: Min { value: 4 }, [5, 5]: Min { value: 5 }, [6, 10]: Min { value: 6 }, [6, 8]: Min { value: 6 }, [6, 7]: Min { value: 6 }, [6, 6]: Min { value: 6 }, [7, 7]: Min { value: 7 }, [8, 8]: Min { value: 8 }, [9, 10]: Min { value: 9 }, [9, 9]: Min { value: 9 }, [10, 10]: Min { value: 10 }} }";
         assert_eq!(dbg, expected);
     }
+
+    #[test]
+    fn validate_works() {
+        let nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let mut segment_tree = Recursive::build(&nodes);
+        segment_tree.validate();
+        segment_tree.update(3, &0);
+        segment_tree.update(10, &20);
+        segment_tree.validate();
+        Recursive::<Min<usize>>::build(&[]).validate();
+    }
+
+    #[test]
+    #[should_panic = "node of [0, 5] doesn't match the combination of its children"]
+    fn validate_catches_corrupted_nodes() {
+        let nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let mut segment_tree = Recursive::build(&nodes);
+        segment_tree.nodes[1] = Min::initialize(&20);
+        segment_tree.validate();
+    }
+
+    #[test]
+    fn empty_tree_works() {
+        let segment_tree = Recursive::<Min<usize>>::build(&[]);
+        assert_eq!(segment_tree.len(), 0);
+        assert!(segment_tree.is_empty());
+        assert!(segment_tree.query(0, 0).is_none());
+        assert_eq!(format!("{segment_tree:?}"), "Recursive { n: 0, nodes: {} }");
+    }
+
+    #[test]
+    #[should_panic = "can't update an empty segment tree"]
+    fn update_on_empty_tree_panics() {
+        let mut segment_tree = Recursive::<Min<usize>>::build(&[]);
+        segment_tree.update(0, &1);
+    }
+
+    #[test]
+    fn query_clamped_works() {
+        let nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let segment_tree = Recursive::build(&nodes);
+        for i in 0..=10 {
+            assert_eq!(segment_tree.query_clamped(i, i + 5).unwrap().value(), &i);
+        }
+        assert!(segment_tree.query_clamped(11, 15).is_none());
+        assert!(segment_tree.query_clamped(3, 2).is_none());
+    }
+
+    #[test]
+    fn into_values_works() {
+        let nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let mut segment_tree = Recursive::build(&nodes);
+        segment_tree.update(10, &0);
+        let values: Vec<usize> = segment_tree.into_iter().collect();
+        assert_eq!(values, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 0]);
+        assert!(Recursive::<Min<usize>>::build(&[]).into_values().is_empty());
+    }
+
+    #[test]
+    fn into_values_drops_every_node_once() {
+        #[derive(Clone)]
+        struct Shared(Rc<usize>);
+        impl Node for Shared {
+            type Value = Rc<usize>;
+            fn initialize(value: &Rc<usize>) -> Self {
+                Self(Rc::clone(value))
+            }
+            fn combine(a: &Self, b: &Self) -> Self {
+                Self(Rc::new(*a.0 + *b.0))
+            }
+            fn value(&self) -> &Rc<usize> {
+                &self.0
+            }
+            fn into_value(self) -> Rc<usize> {
+                self.0
+            }
+        }
+        let values: Vec<Rc<usize>> = (0..=10).map(Rc::new).collect();
+        let nodes: Vec<Shared> = values.iter().map(Shared::initialize).collect();
+        let segment_tree = Recursive::build(&nodes);
+        drop(nodes);
+        let result = segment_tree.into_values();
+        for (value, result) in values.iter().zip(&result) {
+            assert!(Rc::ptr_eq(value, result));
+            assert_eq!(Rc::strong_count(value), 2);
+        }
+        drop(result);
+        assert!(values.iter().all(|value| Rc::strong_count(value) == 1));
+    }
+
+    #[test]
+    fn eq_compares_values() {
+        let nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let mut a = Recursive::build(&nodes);
+        let mut b = Recursive::build(&nodes);
+        a.update(10, &0);
+        assert_ne!(a, b);
+        b.update(10, &0);
+        assert_eq!(a, b);
+        assert_eq!(Recursive::<Min<usize>>::build(&[]), Recursive::build(&[]));
+    }
+
+    #[test]
+    fn from_iterative_works() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut iterative = Iterative::build(&nodes);
+        iterative.update(4, &0);
+        let segment_tree = Recursive::from(iterative);
+        segment_tree.validate();
+        assert_eq!(segment_tree.query(0, 10).unwrap().value(), &51);
+        let predicate = |sum: &usize, value: &usize| sum >= value;
+        assert_eq!(segment_tree.lower_bound(predicate, |sum, value| value - sum, 7), 5);
+        let iterative = Iterative::from(segment_tree);
+        iterative.validate();
+        assert_eq!(iterative.query(3, 5).unwrap().value(), &8);
+        assert_eq!(Recursive::from(Iterative::<Sum<usize>>::build(&[])).len(), 0);
+    }
+
+    #[test]
+    fn zip_works() {
+        let min_nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let sum_nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut mins = Recursive::build(&min_nodes);
+        mins.update(4, &20);
+        let mut segment_tree = Recursive::zip(mins, Recursive::build(&sum_nodes));
+        segment_tree.validate();
+        assert_eq!(segment_tree.query(4, 6).unwrap().value(), &(5, 15));
+        segment_tree.update(5, &(0, 0));
+        assert_eq!(segment_tree.query(4, 6).unwrap().value(), &(0, 10));
+        let mins = Recursive::<Min<usize>>::build(&[]);
+        let sums = Recursive::<Sum<usize>>::build(&[]);
+        assert!(Recursive::zip(mins, sums).is_empty());
+    }
+
+    #[test]
+    #[should_panic = "can't zip segment trees of different lengths, 11 != 10"]
+    fn zip_checks_lengths() {
+        let nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let _ = Recursive::zip(Recursive::build(&nodes), Recursive::build(&nodes[1..]));
+    }
+
+    #[test]
+    fn split_off_and_append_work() {
+        check_split_off_and_append(Recursive::build);
+    }
+
+    #[test]
+    fn resize_works() {
+        let nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let mut segment_tree = Recursive::build(&nodes);
+        segment_tree.resize(14, &1);
+        segment_tree.validate();
+        assert_eq!(segment_tree.len(), 14);
+        assert_eq!(segment_tree.query(2, 10).unwrap().value(), &2);
+        assert_eq!(segment_tree.query(2, 13).unwrap().value(), &1);
+        assert_eq!(segment_tree.query(11, 13).unwrap().value(), &1);
+        segment_tree.resize(3, &1);
+        segment_tree.validate();
+        assert_eq!(segment_tree.len(), 3);
+        assert_eq!(segment_tree.query(1, 2).unwrap().value(), &1);
+        segment_tree.resize(0, &1);
+        assert!(segment_tree.is_empty());
+    }
+
+    #[test]
+    fn extend_works() {
+        let values: Vec<usize> = (0..100).map(|x| (x * 37) % 101).collect();
+        let mut segment_tree = Recursive::<Min<usize>>::build(&[]);
+        let mut len = 0;
+        for batch in [1, 31, 0, 5, 40, 23] {
+            segment_tree.extend(values[len..len + batch].iter().copied());
+            len += batch;
+            assert_eq!(segment_tree.len(), len);
+            segment_tree.validate();
+            for i in 0..len {
+                let expected = values[i..len].iter().min().unwrap();
+                assert_eq!(segment_tree.query(i, len - 1).unwrap().value(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn retain_works() {
+        let values: Vec<usize> = small_values(13);
+        let nodes: Vec<Sum<usize>> = values.iter().map(Sum::initialize).collect();
+        let mut segment_tree = Recursive::build(&nodes);
+        segment_tree.retain(|&x| x % 2 == 1);
+        segment_tree.validate();
+        let kept: Vec<usize> = values.into_iter().filter(|x| x % 2 == 1).collect();
+        assert_eq!(segment_tree.len(), kept.len());
+        for i in 0..kept.len() {
+            for j in i..kept.len() {
+                let expected: usize = kept[i..=j].iter().sum();
+                assert_eq!(segment_tree.query(i, j).unwrap().value(), &expected);
+            }
+        }
+        segment_tree.retain(|_| false);
+        assert!(segment_tree.is_empty());
+    }
+
+    #[test]
+    #[should_panic = "split index 12 is out of bounds, it must be at most 11"]
+    fn split_off_checks_index() {
+        let nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let _ = Recursive::build(&nodes).split_off(12);
+    }
+
+    #[test]
+    fn merge_with_works() {
+        let a: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let b: Vec<Min<usize>> = (0..=10).rev().map(|x| Min::initialize(&x)).collect();
+        let segment_tree = Recursive::build(&a).merge_with(&Recursive::build(&b));
+        segment_tree.validate();
+        for i in 0..=10 {
+            assert_eq!(segment_tree.query(i, i).unwrap().value(), &i.min(10 - i));
+        }
+    }
+
+    #[test]
+    fn build_compressed_works() {
+        let pairs: Vec<(i64, Sum<usize>)> = [(-7, 1), (1_000_000_000, 2), (3, 4), (-7, 8)]
+            .into_iter()
+            .map(|(key, value)| (key, Sum::initialize(&value)))
+            .collect();
+        let (compressor, segment_tree) = Recursive::build_compressed(&pairs);
+        segment_tree.validate();
+        assert_eq!(segment_tree.len(), 3);
+        let (l, r) = (compressor.lower_bound(&0), compressor.upper_bound(&1_000_000_000));
+        assert_eq!(segment_tree.query(l, r - 1).unwrap().value(), &6);
+        assert_eq!(segment_tree.query(0, 0).unwrap().value(), &9);
+    }
+
+    #[test]
+    fn display_works() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let segment_tree = Recursive::build(&nodes);
+        assert_eq!(format!("{segment_tree}"), "[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10]");
+        assert_eq!(format!("{segment_tree:.0}"), "[...]");
+    }
+
+    #[test]
+    fn codec_round_trip_works() {
+        let nodes: Vec<Min<i64>> = (-5..=5).map(|x| Min::initialize(&(x * x))).collect();
+        let segment_tree = Recursive::build(&nodes);
+        let mut bytes = Vec::new();
+        segment_tree.encode_into(&mut bytes);
+        assert_eq!(bytes.len(), 8 + 11 * 8);
+        bytes.push(42);
+        let mut slice = &bytes[..];
+        let decoded = Recursive::<Min<i64>>::decode_from(&mut slice).unwrap();
+        decoded.validate();
+        assert_eq!(decoded, segment_tree);
+        assert_eq!(slice, [42]);
+        assert!(Recursive::<Min<i64>>::decode_from(&mut &bytes[..50]).is_none());
+    }
+
+    #[test]
+    fn lower_bound_in_works() {
+        let values: Vec<usize> = small_values(13);
+        let nodes: Vec<Sum<usize>> = values.iter().map(Sum::initialize).collect();
+        let segment_tree = Recursive::build(&nodes);
+        let expected = |left: usize, right: usize, target: usize| {
+            (left..=right).find(|&k| values[left..=k].iter().sum::<usize>() >= target)
+        };
+        let predicate = |sum: &usize, value: &usize| *sum >= *value;
+        let g = |sum: &usize, value: usize| value.saturating_sub(*sum);
+        for left in 0..values.len() {
+            for right in 0..values.len() {
+                for target in [1, 5, 20, 60] {
+                    assert_eq!(
+                        segment_tree.lower_bound_in(left, right, predicate, g, target),
+                        expected(left, right, target)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn next_greater_and_prev_less_work() {
+        let values: Vec<usize> = small_values(13);
+        let max_nodes: Vec<Max<usize>> = values.iter().map(Max::initialize).collect();
+        let min_nodes: Vec<Min<usize>> = values.iter().map(Min::initialize).collect();
+        let maxs = Recursive::build(&max_nodes);
+        let mins = Recursive::build(&min_nodes);
+        for i in 0..=values.len() {
+            for x in 0..6 {
+                let next = (i..values.len()).find(|&j| values[j] > x);
+                assert_eq!(maxs.next_position_greater_than(i, &x), next);
+                let prev = (0..=i.min(values.len() - 1)).rev().find(|&j| values[j] < x);
+                assert_eq!(mins.prev_position_less_than(i, &x), prev);
+            }
+        }
+        let empty = Recursive::<Min<usize>>::build(&[]);
+        assert_eq!(empty.prev_position_less_than(0, &1), None);
+    }
+
+    #[test]
+    fn kth_matching_works() {
+        let values: Vec<usize> = small_values(13)
+            .into_iter()
+            .map(|x: usize| usize::from(x < 2))
+            .collect();
+        let nodes: Vec<Sum<usize>> = values.iter().map(Sum::initialize).collect();
+        let segment_tree = Recursive::build(&nodes);
+        let zeros = |node: &Sum<usize>, len: usize| len - node.value();
+        for left in 0..values.len() {
+            for right in 0..values.len() {
+                for k in 0..8 {
+                    let expected = (left..=right).filter(|&i| values[i] == 0).nth(k);
+                    assert_eq!(segment_tree.kth_matching(left, right, k, zeros), expected);
+                }
+            }
+        }
+        let empty = Recursive::<Sum<usize>>::build(&[]);
+        assert_eq!(empty.kth_matching(0, 0, 0, zeros), None);
+    }
+
+    #[test]
+    fn with_len_works() {
+        let recursive = Recursive::<Sum<usize>>::with_len(7);
+        let iterative = Iterative::<Min<i32>>::filled(7, &-3);
+        let mut lazy = LazyRecursive::<LazySetWrapper<Max<usize>>>::with_len(7);
+        lazy.update(2, 4, &5);
+        assert_eq!(recursive.len(), 7);
+        assert_eq!(recursive.query(0, 6).unwrap().value(), &0);
+        assert_eq!(iterative.query(2, 5).unwrap().value(), &-3);
+        assert_eq!(lazy.query(0, 6).unwrap().value(), &5);
+        assert_eq!(lazy.query(5, 6).unwrap().value(), &0);
+        assert!(Recursive::<Sum<usize>>::with_len(0).is_empty());
+    }
+
+    #[test]
+    fn build_from_fn_works() {
+        let values: Vec<usize> = small_values(13);
+        let recursive = Recursive::<Sum<usize>>::build_from_fn(values.len(), |i| values[i]);
+        let iterative = Iterative::<Sum<usize>>::build_from_fn(values.len(), |i| values[i]);
+        let mut lazy = LazyRecursive::<Sum<usize>>::build_from_fn(values.len(), |i| values[i]);
+        for i in 0..values.len() {
+            for j in i..values.len() {
+                let expected: usize = values[i..=j].iter().sum();
+                assert_eq!(recursive.query(i, j).unwrap().value(), &expected);
+                assert_eq!(iterative.query(i, j).unwrap().value(), &expected);
+                assert_eq!(lazy.query(i, j).unwrap().value(), &expected);
+            }
+        }
+        assert!(Recursive::<Sum<usize>>::build_from_fn(0, |i| i).is_empty());
+    }
+
+    #[test]
+    fn walk_works() {
+        let values: Vec<usize> = small_values(13);
+        let nodes: Vec<Sum<usize>> = values.iter().map(Sum::initialize).collect();
+        let segment_tree = Recursive::build(&nodes);
+        let mut visited = Vec::new();
+        segment_tree.walk(|segment, node| {
+            assert_eq!(node.value(), &values[segment.clone()].iter().sum::<usize>());
+            visited.push(segment);
+            Descend::Both
+        });
+        assert_eq!(visited.len(), 2 * values.len() - 1);
+        assert_eq!(visited[..3], [0..13, 0..7, 0..4]);
+        for (descend, leaf) in [(Descend::Left, 0..1), (Descend::Right, 12..13)] {
+            let mut last = None;
+            segment_tree.walk(|segment, _| {
+                last = Some(segment);
+                descend
+            });
+            assert_eq!(last, Some(leaf));
+        }
+        let mut calls = 0;
+        segment_tree.walk(|_, _| {
+            calls += 1;
+            Descend::Stop
+        });
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn upper_bound_works() {
+        let values: Vec<usize> = small_values(13);
+        let nodes: Vec<Sum<usize>> = values.iter().map(Sum::initialize).collect();
+        let segment_tree = Recursive::build(&nodes);
+        let expected = |target: usize| {
+            (0..values.len())
+                .rev()
+                .find(|&i| values[i..].iter().sum::<usize>() >= target)
+                .unwrap_or(0)
+        };
+        let predicate = |sum: &usize, value: &usize| *sum >= *value;
+        let g = |sum: &usize, value: usize| value.saturating_sub(*sum);
+        for target in 0..70 {
+            assert_eq!(
+                segment_tree.upper_bound(predicate, g, target),
+                expected(target)
+            );
+        }
+    }
+
+    #[test]
+    fn lower_bound_by_node_works() {
+        let values: Vec<usize> = small_values(13);
+        let nodes: Vec<Pair<Sum<usize>, Max<usize>>> =
+            values.iter().map(|&x| Pair::initialize(&(x, x))).collect();
+        let segment_tree = Recursive::build(&nodes);
+        let predicate = |node: &Pair<Sum<usize>, Max<usize>>, value: &(usize, usize)| {
+            *node.first().value() >= value.0 || *node.second().value() >= value.1
+        };
+        let g = |node: &Pair<Sum<usize>, Max<usize>>, value: (usize, usize)| {
+            (value.0.saturating_sub(*node.first().value()), value.1)
+        };
+        for target in 0..70 {
+            for max in 0..8 {
+                let expected = (0..values.len())
+                    .find(|&k| values[..=k].iter().sum::<usize>() >= target || values[k] >= max)
+                    .unwrap_or(values.len() - 1);
+                assert_eq!(
+                    segment_tree.lower_bound_by_node(predicate, g, (target, max)),
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn get_segment_works() {
+        let nodes: Vec<Sum<usize>> = (0..13).map(|x| Sum::initialize(&x)).collect();
+        let segment_tree = Recursive::build(&nodes);
+        let mut canonical = 0;
+        for i in 0..=nodes.len() {
+            for j in 0..=nodes.len() {
+                if let Some(node) = segment_tree.get_segment(i, j) {
+                    canonical += 1;
+                    let expected = segment_tree.query(i, j).unwrap();
+                    assert_eq!(node.value(), expected.value());
+                }
+            }
+        }
+        assert_eq!(canonical, 2 * nodes.len() - 1);
+        assert!(segment_tree.get_segment(0, nodes.len() - 1).is_some());
+        assert!(segment_tree.get_segment(1, 0).is_none());
+    }
+
+    #[test]
+    fn query_until_works() {
+        let values: Vec<usize> = small_values(13).into_iter().map(|x: usize| x + 1).collect();
+        let nodes: Vec<Sum<usize>> = values.iter().map(Sum::initialize).collect();
+        let segment_tree = Recursive::build(&nodes);
+        for left in 0..values.len() {
+            for right in left..values.len() {
+                for target in [1, 5, 20, 60] {
+                    let result = segment_tree
+                        .query_until(left, right, |node| *node.value() >= target)
+                        .unwrap();
+                    let sums: Vec<usize> = (left..=right)
+                        .map(|k| values[left..=k].iter().sum())
+                        .collect();
+                    assert!(sums.contains(result.value()));
+                    assert!(*result.value() >= target || result.value() == sums.last().unwrap());
+                }
+            }
+        }
+        assert!(segment_tree.query_until(1, 0, |_| true).is_none());
+    }
+
+    #[test]
+    fn on_update_works() {
+        use std::sync::{Arc, Mutex};
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = Recursive::build(&nodes);
+        let log = Arc::new(Mutex::new(Vec::new()));
+        for id in [0, 1] {
+            let log_clone = Arc::clone(&log);
+            segment_tree.on_update(move |range, value| {
+                log_clone.lock().unwrap().push((id, range, *value));
+            });
+        }
+        segment_tree.update(3, &1);
+        segment_tree.update(10, &2);
+        segment_tree.clear_observers();
+        segment_tree.update(0, &3);
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                (0, 3..=3, 1),
+                (1, 3..=3, 1),
+                (0, 10..=10, 2),
+                (1, 10..=10, 2)
+            ]
+        );
+        assert_eq!(segment_tree.query(0, 10).unwrap().value(), &48);
+    }
 }
@@ -0,0 +1,274 @@
+use std::{
+    ops::Range,
+    sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use crate::{
+    internal_utils::{
+        empty_utils::assert_not_empty, instrument_utils::combine, range_utils::clamp,
+        validate_utils::assert_matches,
+    },
+    nodes::Node,
+    segment_tree::Iterative,
+};
+
+/// Segment tree split into independent shards, so point updates to different shards can happen concurrently, it has range queries and point updates.
+///
+/// The elements are split into contiguous shards of the same length, each of them an [`Iterative`] segment tree behind its own lock, and a small top tree stores the result of each shard.
+/// Updates take `&self`, lock only the shard of the element and then briefly the top tree, so the tree can be shared between threads, e.g. with [`Arc`](std::sync::Arc) or [`std::thread::scope`].
+/// Queries don't block each other, but they aren't atomic with respect to concurrent updates, an update to a shard may or may not be observed by a query which covers that shard.
+/// ```
+/// # use seg_tree::{Sharded,utils::Sum,nodes::Node};
+/// # let nodes: Vec<Sum<usize>> = (0..100).map(|x| Sum::initialize(&x)).collect();
+/// let seg_tree = Sharded::build(&nodes, 4); // [0,1,...,99] with Sum<usize> nodes, in shards of 25 elements
+/// std::thread::scope(|s| {
+///     s.spawn(|| seg_tree.update(10, &0));
+///     s.spawn(|| seg_tree.update(90, &0));
+/// });
+/// assert_eq!(seg_tree.query(0, 99).unwrap().value(), &(4950 - 10 - 90));
+/// ```
+pub struct Sharded<T> {
+    shards: Vec<RwLock<Iterative<T>>>,
+    top: RwLock<Iterative<T>>,
+    shard_len: usize,
+    n: usize,
+}
+
+impl<T> Sharded<T>
+where
+    T: Node + Clone,
+{
+    /// Builds sharded segment tree from slice, each element of the slice will correspond to a leaf of the segment tree.
+    /// The elements are split into at most `shards` shards, if `shards` is `0` a single shard is used.
+    /// It has time complexity of `O(n*log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn build(values: &[T], shards: usize) -> Self {
+        let n = values.len();
+        let shard_len = n.div_ceil(shards.max(1)).max(1);
+        let (shards, totals): (Vec<_>, Vec<_>) = values
+            .chunks(shard_len)
+            .map(|chunk| {
+                let shard = Iterative::build(chunk);
+                let total = shard.query(0, chunk.len() - 1);
+                (RwLock::new(shard), total)
+            })
+            .unzip();
+        let totals: Vec<T> = totals.into_iter().flatten().collect();
+        Self {
+            shards,
+            top: RwLock::new(Iterative::build(&totals)),
+            shard_len,
+            n,
+        }
+    }
+
+    /// Sets the p-th element of the segment tree to value T and update the segment tree correspondingly.
+    /// It only blocks concurrent updates and queries which touch the same shard.
+    /// It will **panic** if p is not in `[0,n)`.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn update(&self, p: usize, value: &<T as Node>::Value) {
+        assert_not_empty(self.n, "update");
+        let shard_index = p / self.shard_len;
+        let mut shard = write(&self.shards[shard_index]);
+        shard.update(p - shard_index * self.shard_len, value);
+        // The shard stays locked until the top tree is updated,
+        // so updates to the same shard reach the top tree in order.
+        if let Some(total) = shard.query(0, self.last_in_shard(shard_index)) {
+            write(&self.top).set_node(shard_index, total);
+        }
+        drop(shard);
+    }
+
+    /// Returns the result from the range `[left,right]`.
+    /// It returns None if and only if range is empty or the segment tree has no elements.
+    /// It will **panic** if left or right are not in `[0,n)`.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn query(&self, left: usize, right: usize) -> Option<T> {
+        if left > right || self.n == 0 {
+            return None;
+        }
+        let (first, last) = (left / self.shard_len, right / self.shard_len);
+        let (left, right) = (left - first * self.shard_len, right - last * self.shard_len);
+        if first == last {
+            return read(&self.shards[first]).query(left, right);
+        }
+        let ans_left = read(&self.shards[first]).query(left, self.last_in_shard(first));
+        let ans_middle = read(&self.top).query(first + 1, last - 1);
+        let ans_right = read(&self.shards[last]).query(0, right);
+        [ans_middle, ans_right]
+            .into_iter()
+            .flatten()
+            .fold(ans_left, |ans, node| {
+                Some(ans.map_or_else(|| node.clone(), |ans| combine(&ans, &node)))
+            })
+    }
+
+    /// Same as [`query`](Self::query), but the range is clamped to `[0,n)` instead of **panicking** when it's out of bounds, and the range that was actually aggregated is returned with the result, as a half-open range.
+    /// It returns None if and only if the clamped range is empty.
+    /// It has time complexity of `O(log(n))`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn query_with_range(&self, left: usize, right: usize) -> Option<(Range<usize>, T)> {
+        let range = clamp(left, right, self.n)?;
+        self.query(range.start, range.end - 1)
+            .map(|node| (range, node))
+    }
+
+    /// Same as [`query_with_range`](Self::query_with_range), but only the result is returned.
+    /// It returns None if and only if the range clamped to `[0,n)` is empty.
+    #[must_use]
+    pub fn query_clamped(&self, left: usize, right: usize) -> Option<T> {
+        self.query_with_range(left, right).map(|(_, node)| node)
+    }
+
+    /// Returns the index of the last element of the shard, relative to the start of the shard.
+    fn last_in_shard(&self, shard_index: usize) -> usize {
+        self.shard_len.min(self.n - shard_index * self.shard_len) - 1
+    }
+
+    /// Returns the amount of elements of the segment tree.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.n
+    }
+
+    /// Returns `true` if the segment tree has no elements.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Returns the amount of shards the elements are split into.
+    #[must_use]
+    pub const fn shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Checks every shard and the top tree with [`Iterative::validate`], and that each leaf of the top tree is the combination of its shard.
+    /// It's meant for tests and debug assertions.
+    /// It will **panic** at the first node which doesn't match.
+    /// It has time complexity of `O(n)`, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn validate(&self)
+    where
+        <T as Node>::Value: PartialEq,
+    {
+        read(&self.top).validate();
+        for (shard_index, shard) in self.shards.iter().enumerate() {
+            // The shard stays locked while its leaf of the top tree is read,
+            // so a concurrent update to it can't be observed halfway.
+            let shard = read(shard);
+            shard.validate();
+            let total = shard.query(0, self.last_in_shard(shard_index));
+            let leaf = read(&self.top).query(shard_index, shard_index);
+            drop(shard);
+            assert_matches(
+                total.map(|node| node.value().clone()) == leaf.map(|node| node.value().clone()),
+                format_args!("leaf {shard_index} of the top tree"),
+                "its shard",
+            );
+        }
+    }
+}
+
+// A panic can't leave a shard half updated, as the nodes are only written after `combine`
+// returns, so poisoning is ignored.
+fn read<T>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn write<T>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl<T> core::fmt::Debug for Sharded<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Sharded")
+            .field("n", &self.n)
+            .field("shards", &self.shards.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{distributions::Uniform, prelude::Distribution, thread_rng};
+
+    use crate::{
+        nodes::Node,
+        segment_tree::Iterative,
+        utils::{Max, Sum},
+    };
+
+    use super::Sharded;
+
+    #[test]
+    fn empty_query_returns_none() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let segment_tree = Sharded::build(&nodes, 3);
+        assert!(segment_tree.query(10, 0).is_none());
+    }
+
+    #[test]
+    fn query_matches_iterative() {
+        let n = 37;
+        let nodes: Vec<Max<usize>> = (0..n).map(|x| Max::initialize(&(x * 7 % n))).collect();
+        let mut expected = Iterative::build(&nodes);
+        for shards in [0, 1, 4, 5, n, 2 * n] {
+            let segment_tree = Sharded::build(&nodes, shards);
+            assert!(segment_tree.shards() <= shards.clamp(1, n));
+            segment_tree.update(12, &100);
+            expected.update(12, &100);
+            for l in 0..n {
+                for r in l..n {
+                    assert_eq!(segment_tree.query(l, r), expected.query(l, r));
+                }
+            }
+            segment_tree.update(12, &0);
+            expected.update(12, &0);
+        }
+    }
+
+    #[test]
+    fn concurrent_updates_work() {
+        let n = 1000;
+        let nodes: Vec<Sum<usize>> = (0..n).map(|_| Sum::initialize(&0)).collect();
+        let segment_tree = Sharded::build(&nodes, 8);
+        std::thread::scope(|s| {
+            for t in 0..4 {
+                let segment_tree = &segment_tree;
+                s.spawn(move || {
+                    let distribution = Uniform::new(0, n);
+                    let mut rng = thread_rng();
+                    for _ in 0..1000 {
+                        let p = distribution.sample(&mut rng);
+                        if p % 4 == t {
+                            segment_tree.update(p, &1);
+                        }
+                    }
+                });
+            }
+        });
+        let total = (0..n)
+            .filter(|&p| segment_tree.query(p, p).unwrap().value() == &1)
+            .count();
+        assert_eq!(segment_tree.query(0, n - 1).unwrap().value(), &total);
+    }
+
+    #[test]
+    fn validate_works() {
+        let nodes: Vec<Sum<usize>> = (0..100).map(|x| Sum::initialize(&x)).collect();
+        let segment_tree = Sharded::build(&nodes, 7);
+        std::thread::scope(|s| {
+            s.spawn(|| segment_tree.update(10, &0));
+            s.spawn(|| segment_tree.update(95, &0));
+        });
+        segment_tree.validate();
+    }
+
+    #[test]
+    fn empty_tree_works() {
+        let segment_tree = Sharded::<Sum<usize>>::build(&[], 4);
+        assert!(segment_tree.is_empty());
+        assert!(segment_tree.query(0, 0).is_none());
+        segment_tree.validate();
+    }
+}
@@ -0,0 +1,927 @@
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use core::{
+    mem::MaybeUninit,
+    ops::{Deref, DerefMut, Index, IndexMut},
+    slice::SliceIndex,
+};
+
+use crate::internal_utils::append_utils::AppendList;
+
+/// Backing storage for the nodes of the fixed size segment trees ([`Iterative`](crate::Iterative), [`Recursive`](crate::Recursive) and [`LazyRecursive`](crate::LazyRecursive)).
+///
+/// The trees allocate all of their nodes at once when they are built, so a storage is created from an uninitialized buffer of the exact amount of nodes needed, which is then initialized by the tree.
+/// It's implemented for [`Vec<T>`] (the default), [`Box<[T]>`](Box), `&'a mut [T]` and [`Inline`], where `&'a mut [T]` takes the buffer from the user, so the nodes can be placed in an arena, a static buffer or any other custom allocation, and [`Inline`] keeps the nodes inside the tree itself.
+pub trait Storage<T>: Deref<Target = [T]> + DerefMut {
+    /// Uninitialized buffer which becomes `Self` after every node in it is initialized.
+    type Uninit: DerefMut<Target = [MaybeUninit<T>]>;
+    /// Information needed to allocate the buffer, it's `()` for heap allocated storages.
+    type Allocator;
+
+    /// Allocates an uninitialized buffer of exactly `len` nodes.
+    fn allocate(len: usize, allocator: Self::Allocator) -> Self::Uninit;
+
+    /// Converts the buffer into an initialized storage.
+    ///
+    /// # Safety
+    /// Every node of the buffer must have been initialized, as the returned storage may read or drop any of them.
+    /// The trees which don't use some of the nodes, e.g. the padding of [`Recursive`](crate::Recursive), initialize them anyway.
+    unsafe fn assume_init(uninit: Self::Uninit) -> Self;
+}
+
+/// Backing arena for the nodes of the persistent segment trees ([`Persistent`](crate::Persistent) and [`LazyPersistent`](crate::LazyPersistent)).
+///
+/// Unlike the fixed size trees, the persistent trees keep adding nodes as they are updated, so an arena is a growable buffer of nodes, which is indexed through [`Index`].
+/// It's implemented for [`Vec<T>`] (the default), [`GrowableArena`], whose growth policy can be chosen, and [`ChunkedArena`], whose nodes are never moved, and it can be implemented for buffers which live outside of the heap, such as a memory mapped file of [`PersistentWrapper`](crate::PersistentWrapper) nodes, so the versions of a tree can exceed the available memory and be reopened later with [`reopen_in`](crate::Persistent::reopen_in).
+/// The nodes don't need to be contiguous.
+pub trait Arena<T>: Index<usize, Output = T> + IndexMut<usize> {
+    /// Appends a node to the end of the arena.
+    fn push(&mut self, node: T);
+
+    /// Returns the amount of nodes in the arena.
+    fn len(&self) -> usize;
+
+    /// Returns true if and only if the arena has no nodes.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Keeps only the first `len` nodes, dropping the rest.
+    fn truncate(&mut self, len: usize);
+
+    /// Reserves room for at least `additional` more nodes.
+    fn reserve(&mut self, additional: usize);
+
+    /// Returns the amount of nodes the arena can hold without growing.
+    fn capacity(&self) -> usize;
+
+    /// Shrinks the capacity of the arena as much as possible.
+    fn shrink_to_fit(&mut self) {}
+
+    /// Called by [`Persistent`](crate::Persistent) with the roots of its versions every time they change, so an arena shared with readers can publish them, see [`SharedArena`].
+    /// It does nothing by default.
+    fn publish_roots(&mut self, _roots: &[usize]) {}
+}
+
+impl<T> Arena<T> for Vec<T> {
+    #[inline]
+    fn push(&mut self, node: T) {
+        self.push(node);
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    #[inline]
+    fn truncate(&mut self, len: usize) {
+        self.truncate(len);
+    }
+
+    #[inline]
+    fn reserve(&mut self, additional: usize) {
+        self.reserve(additional);
+    }
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        self.capacity()
+    }
+
+    #[inline]
+    fn shrink_to_fit(&mut self) {
+        self.shrink_to_fit();
+    }
+}
+
+/// Policy used by [`GrowableArena`] to make room for more nodes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Growth {
+    /// Doubles the capacity when the arena is full, as [`Vec`] does, which is the default.
+    #[default]
+    Doubling,
+    /// Reserves exactly the amount of nodes requested, e.g. by [`reserve_updates`](crate::Persistent::reserve_updates), and doubles the capacity only when a push finds the arena full.
+    /// It avoids the unused memory left by doubling when the amount of updates is known in advance.
+    Exact,
+    /// Grows the capacity by the given amount of nodes at a time, so the unused memory is bounded by it instead of being as large as the arena.
+    /// Pushing has amortized time complexity of `O(len/step)`, as each growth copies every node.
+    Step(usize),
+}
+
+/// Arena backed by a [`Vec`] which grows following a [`Growth`] policy.
+///
+/// Growing still moves the nodes to a new allocation, so the latency of the push which triggers it is proportional to the size of the arena, reserving in advance avoids it altogether.
+/// ```
+/// # use seg_tree::{Persistent,storage::{GrowableArena,Growth},utils::Sum,nodes::Node};
+/// # let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+/// let arena = GrowableArena::new(Growth::Exact);
+/// let mut seg_tree: Persistent<_, u32, _> = Persistent::build_in(&nodes, arena);
+/// seg_tree.reserve_updates(100);
+/// seg_tree.update(0, 3, &10);
+/// assert_eq!(seg_tree.query(1, 0, 9).unwrap().value(), &52);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct GrowableArena<T> {
+    nodes: Vec<T>,
+    growth: Growth,
+}
+
+impl<T> GrowableArena<T> {
+    /// Creates an empty arena which grows following `growth`.
+    #[must_use]
+    pub const fn new(growth: Growth) -> Self {
+        Self {
+            nodes: Vec::new(),
+            growth,
+        }
+    }
+
+    /// Creates an empty arena with room for `capacity` nodes, which grows following `growth`.
+    #[must_use]
+    pub fn with_capacity(growth: Growth, capacity: usize) -> Self {
+        Self {
+            nodes: Vec::with_capacity(capacity),
+            growth,
+        }
+    }
+
+    /// Returns the policy used to grow the arena.
+    #[must_use]
+    pub const fn growth(&self) -> Growth {
+        self.growth
+    }
+
+    /// Changes the policy used to grow the arena from now on.
+    pub const fn set_growth(&mut self, growth: Growth) {
+        self.growth = growth;
+    }
+}
+
+impl<T> Deref for GrowableArena<T> {
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.nodes
+    }
+}
+
+impl<T> DerefMut for GrowableArena<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.nodes
+    }
+}
+
+impl<T, J> Index<J> for GrowableArena<T>
+where
+    J: SliceIndex<[T]>,
+{
+    type Output = J::Output;
+
+    #[inline]
+    fn index(&self, index: J) -> &Self::Output {
+        &self.nodes[index]
+    }
+}
+
+impl<T, J> IndexMut<J> for GrowableArena<T>
+where
+    J: SliceIndex<[T]>,
+{
+    #[inline]
+    fn index_mut(&mut self, index: J) -> &mut Self::Output {
+        &mut self.nodes[index]
+    }
+}
+
+impl<T> Arena<T> for GrowableArena<T> {
+    #[inline]
+    fn push(&mut self, node: T) {
+        if let Growth::Step(step) = self.growth {
+            if self.nodes.len() == self.nodes.capacity() {
+                self.nodes.reserve_exact(step.max(1));
+            }
+        }
+        self.nodes.push(node);
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    #[inline]
+    fn truncate(&mut self, len: usize) {
+        self.nodes.truncate(len);
+    }
+
+    #[inline]
+    fn reserve(&mut self, additional: usize) {
+        match self.growth {
+            Growth::Doubling => self.nodes.reserve(additional),
+            Growth::Exact | Growth::Step(_) => self.nodes.reserve_exact(additional),
+        }
+    }
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        self.nodes.capacity()
+    }
+
+    #[inline]
+    fn shrink_to_fit(&mut self) {
+        self.nodes.shrink_to_fit();
+    }
+}
+
+/// Arena which stores the nodes in separate chunks of `CHUNK` nodes, so pushing a node never moves the nodes already in the arena.
+///
+/// A full [`Vec`] copies every node to a new allocation when it grows, which makes the push that triggers it as slow as the arena is large, while a full chunked arena only allocates a new chunk.
+/// Indexing has to find the chunk of the node first, so queries are slightly slower than with a contiguous arena.
+/// ```
+/// # use seg_tree::{Persistent,storage::ChunkedArena,utils::Sum,nodes::Node};
+/// # let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+/// let arena = ChunkedArena::<_, 64>::new();
+/// let mut seg_tree: Persistent<_, u32, _> = Persistent::build_in(&nodes, arena);
+/// seg_tree.update(0, 3, &10);
+/// assert_eq!(seg_tree.query(1, 0, 9).unwrap().value(), &52);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ChunkedArena<T, const CHUNK: usize = 4096> {
+    // Every chunk has room for exactly `CHUNK` nodes, the chunks after the first `len` nodes are empty.
+    chunks: Vec<Vec<T>>,
+    len: usize,
+}
+
+impl<T, const CHUNK: usize> ChunkedArena<T, CHUNK> {
+    /// Creates an empty arena, no chunk is allocated until a node is pushed.
+    #[must_use]
+    pub const fn new() -> Self {
+        const { assert!(CHUNK > 0, "chunks must hold at least one node") };
+        Self {
+            chunks: Vec::new(),
+            len: 0,
+        }
+    }
+}
+
+impl<T, const CHUNK: usize> Default for ChunkedArena<T, CHUNK> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const CHUNK: usize> Index<usize> for ChunkedArena<T, CHUNK> {
+    type Output = T;
+
+    #[inline]
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.chunks[index / CHUNK][index % CHUNK]
+    }
+}
+
+impl<T, const CHUNK: usize> IndexMut<usize> for ChunkedArena<T, CHUNK> {
+    #[inline]
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.chunks[index / CHUNK][index % CHUNK]
+    }
+}
+
+impl<T, const CHUNK: usize> Arena<T> for ChunkedArena<T, CHUNK> {
+    #[inline]
+    fn push(&mut self, node: T) {
+        let chunk = self.len / CHUNK;
+        if chunk == self.chunks.len() {
+            self.chunks.push(Vec::with_capacity(CHUNK));
+        }
+        self.chunks[chunk].push(node);
+        self.len += 1;
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn truncate(&mut self, len: usize) {
+        for (k, chunk) in self.chunks.iter_mut().enumerate() {
+            chunk.truncate(len.saturating_sub(k * CHUNK));
+        }
+        self.len = self.len.min(len);
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        let chunks = self.len.saturating_add(additional).div_ceil(CHUNK);
+        while self.chunks.len() < chunks {
+            self.chunks.push(Vec::with_capacity(CHUNK));
+        }
+    }
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        self.chunks.len() * CHUNK
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.chunks.truncate(self.len.div_ceil(CHUNK));
+        self.chunks.shrink_to_fit();
+    }
+}
+
+/// [`Arena`] whose nodes can be read from other threads while new nodes are pushed.
+///
+/// The versions of a [`Persistent`](crate::Persistent) tree built in it can be queried through a [`PersistentReader`](crate::PersistentReader) without any locking, see [`reader`](crate::Persistent::reader).
+/// The nodes are stored in chunks which are never moved, and each node and the root of each version are published atomically once they're pushed, so a reader sees a version once the update which created it returns.
+/// The nodes and versions which are already published are only modified or discarded while no reader is alive, so [`gc`](crate::Persistent::gc), [`retain_versions`](crate::Persistent::retain_versions) and [`rollback_to`](crate::Persistent::rollback_to) will **panic** if there is a reader.
+/// ```
+/// # use seg_tree::{Persistent,storage::SharedArena,utils::Sum,nodes::Node};
+/// # let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+/// let mut seg_tree: Persistent<_, u32, _> = Persistent::build_in(&nodes, SharedArena::new());
+/// let reader = seg_tree.reader();
+/// let handle = std::thread::spawn(move || {
+///     while reader.versions() < 2 {
+///         std::hint::spin_loop();
+///     }
+///     *reader.query(1, 0, 9).unwrap().value()
+/// });
+/// seg_tree.update(0, 0, &10);
+/// assert_eq!(handle.join().unwrap(), 55);
+/// ```
+pub struct SharedArena<T> {
+    nodes: Arc<AppendList<T>>,
+    roots: Arc<AppendList<usize>>,
+}
+
+impl<T> SharedArena<T> {
+    /// Creates an empty arena, no chunk is allocated until a node is pushed.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            nodes: Arc::new(AppendList::new()),
+            roots: Arc::new(AppendList::new()),
+        }
+    }
+
+    /// Returns the published nodes and roots, which readers share with the arena.
+    pub(crate) fn share(&self) -> (Arc<AppendList<T>>, Arc<AppendList<usize>>) {
+        (Arc::clone(&self.nodes), Arc::clone(&self.roots))
+    }
+
+    /// Returns the nodes, it will **panic** if there is a reader, as they may be reading them.
+    fn nodes_mut(&mut self) -> &mut AppendList<T> {
+        Arc::get_mut(&mut self.nodes).expect("published nodes can't be modified while there are readers")
+    }
+}
+
+impl<T> Default for SharedArena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> core::fmt::Debug for SharedArena<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SharedArena")
+            .field("len", &self.nodes.len())
+            .field("versions", &self.roots.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> Index<usize> for SharedArena<T> {
+    type Output = T;
+
+    #[inline]
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.nodes[index]
+    }
+}
+
+/// It will **panic** if there is a reader, see [`SharedArena`].
+impl<T> IndexMut<usize> for SharedArena<T> {
+    #[inline]
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        let nodes = self.nodes_mut();
+        let len = nodes.len();
+        nodes
+            .get_mut(index)
+            .unwrap_or_else(|| panic!("index {index} is out of bounds, there are {len} values"))
+    }
+}
+
+impl<T> Arena<T> for SharedArena<T> {
+    #[inline]
+    fn push(&mut self, node: T) {
+        // SAFETY: the arena is the only writer, readers only share the list to read it.
+        unsafe { self.nodes.push(node) };
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn truncate(&mut self, len: usize) {
+        if len < self.nodes.len() {
+            self.nodes_mut().truncate(len);
+        }
+    }
+
+    /// The chunks are allocated as the nodes are pushed, so it does nothing.
+    fn reserve(&mut self, _additional: usize) {}
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        self.nodes.capacity()
+    }
+
+    /// The versions are only appended, unless the tree discards or rewrites them, e.g. in a [`gc`](crate::Persistent::gc), which needs no reader to be alive.
+    fn publish_roots(&mut self, roots: &[usize]) {
+        let published = self.roots.len();
+        let rewritten = roots.len() < published
+            || (roots.len() == published
+                && (0..published).any(|version| self.roots[version] != roots[version]));
+        if rewritten {
+            Arc::get_mut(&mut self.roots)
+                .expect("published versions can't be modified while there are readers")
+                .truncate(0);
+        }
+        for &root in &roots[self.roots.len()..] {
+            // SAFETY: the arena is the only writer, readers only share the list to read it.
+            unsafe { self.roots.push(root) };
+        }
+    }
+}
+
+impl<T> Storage<T> for Vec<T> {
+    type Uninit = Vec<MaybeUninit<T>>;
+    type Allocator = ();
+
+    #[inline]
+    fn allocate(len: usize, (): Self::Allocator) -> Self::Uninit {
+        let mut nodes = Vec::with_capacity(len);
+        // SAFETY: `MaybeUninit` doesn't need to be initialized.
+        unsafe { nodes.set_len(len) };
+        nodes
+    }
+
+    #[inline]
+    unsafe fn assume_init(uninit: Self::Uninit) -> Self {
+        let mut uninit = core::mem::ManuallyDrop::new(uninit);
+        let (ptr, len, capacity) = (uninit.as_mut_ptr(), uninit.len(), uninit.capacity());
+        // SAFETY: `MaybeUninit<T>` has the same layout as `T`, the caller guarantees the nodes are initialized.
+        unsafe { Self::from_raw_parts(ptr.cast(), len, capacity) }
+    }
+}
+
+impl<T> Storage<T> for Box<[T]> {
+    type Uninit = Box<[MaybeUninit<T>]>;
+    type Allocator = ();
+
+    #[inline]
+    fn allocate(len: usize, (): Self::Allocator) -> Self::Uninit {
+        Self::new_uninit_slice(len)
+    }
+
+    #[inline]
+    unsafe fn assume_init(uninit: Self::Uninit) -> Self {
+        // SAFETY: the caller guarantees the nodes are initialized.
+        unsafe { uninit.assume_init() }
+    }
+}
+
+/// Storage in a buffer given by the user, the nodes are never dropped, which makes it suitable for arenas which are freed at once.
+impl<'a, T> Storage<T> for &'a mut [T] {
+    type Uninit = &'a mut [MaybeUninit<T>];
+    type Allocator = &'a mut [MaybeUninit<T>];
+
+    /// Takes the first `len` nodes of the given buffer.
+    /// It will **panic** if the buffer is smaller than `len`.
+    #[inline]
+    fn allocate(len: usize, allocator: Self::Allocator) -> Self::Uninit {
+        assert!(
+            allocator.len() >= len,
+            "buffer of {} nodes is too small, {len} nodes are needed",
+            allocator.len()
+        );
+        &mut allocator[..len]
+    }
+
+    #[inline]
+    unsafe fn assume_init(uninit: Self::Uninit) -> Self {
+        // SAFETY: `MaybeUninit<T>` has the same layout as `T`, the caller guarantees the nodes are initialized.
+        unsafe { core::slice::from_raw_parts_mut(uninit.as_mut_ptr().cast(), uninit.len()) }
+    }
+}
+
+/// Storage inside the tree itself with room for up to `2*N` nodes, so it never allocates.
+///
+/// It's the storage of [`StaticTree`](crate::StaticTree), which holds up to `N` elements.
+pub struct Inline<T, const N: usize> {
+    // Both halves are contiguous, so they form a single buffer of `2*N` nodes.
+    halves: [[MaybeUninit<T>; N]; 2],
+    len: usize,
+}
+
+impl<T, const N: usize> Deref for Inline<T, N> {
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: the first `len` nodes are initialized.
+        unsafe { core::slice::from_raw_parts(self.halves.as_ptr().cast(), self.len) }
+    }
+}
+
+impl<T, const N: usize> DerefMut for Inline<T, N> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // SAFETY: the first `len` nodes are initialized.
+        unsafe { core::slice::from_raw_parts_mut(self.halves.as_mut_ptr().cast(), self.len) }
+    }
+}
+
+impl<T, const N: usize> Drop for Inline<T, N> {
+    fn drop(&mut self) {
+        // SAFETY: the first `len` nodes are initialized and they aren't used after this.
+        unsafe { core::ptr::drop_in_place(&raw mut **self) }
+    }
+}
+
+impl<T, const N: usize> Storage<T> for Inline<T, N> {
+    type Uninit = Inline<MaybeUninit<T>, N>;
+    type Allocator = ();
+
+    /// It will **panic** if `len` is greater than `2*N`.
+    #[inline]
+    fn allocate(len: usize, (): Self::Allocator) -> Self::Uninit {
+        assert!(
+            len <= 2 * N,
+            "inline storage of {} nodes is too small, {len} nodes are needed",
+            2 * N
+        );
+        Inline {
+            halves: [const { [const { MaybeUninit::uninit() }; N] }; 2],
+            len,
+        }
+    }
+
+    #[inline]
+    unsafe fn assume_init(uninit: Self::Uninit) -> Self {
+        Self {
+            // SAFETY: `MaybeUninit<MaybeUninit<T>>` has the same layout as `MaybeUninit<T>`.
+            halves: unsafe { core::mem::transmute_copy(&uninit.halves) },
+            len: uninit.len,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{
+        mem::MaybeUninit,
+        ops::{Index, IndexMut},
+    };
+    use std::rc::Rc;
+
+    use crate::{
+        nodes::Node,
+        utils::{LazySetWrapper, Min, Sum},
+        Iterative, LazyPersistent, LazyRecursive, Persistent, Recursive, StaticTree,
+    };
+
+    use super::{Arena, ChunkedArena, GrowableArena, Growth};
+
+    /// Arena which only implements the required methods.
+    struct Minimal<T>(Vec<T>);
+
+    impl<T> Index<usize> for Minimal<T> {
+        type Output = T;
+        fn index(&self, index: usize) -> &Self::Output {
+            &self.0[index]
+        }
+    }
+
+    impl<T> IndexMut<usize> for Minimal<T> {
+        fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+            &mut self.0[index]
+        }
+    }
+
+    impl<T> Arena<T> for Minimal<T> {
+        fn push(&mut self, node: T) {
+            self.0.push(node);
+        }
+        fn len(&self) -> usize {
+            self.0.len()
+        }
+        fn truncate(&mut self, len: usize) {
+            self.0.truncate(len);
+        }
+        fn reserve(&mut self, additional: usize) {
+            self.0.reserve(additional);
+        }
+        fn capacity(&self) -> usize {
+            self.0.capacity()
+        }
+    }
+
+    #[test]
+    fn boxed_slice_works() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree: Iterative<_, Box<[_]>> = Iterative::build_in(&nodes, ());
+        segment_tree.update(0, &10);
+        assert_eq!(segment_tree.query(0, 10).unwrap().value(), &65);
+    }
+
+    #[test]
+    fn buffer_works() {
+        let nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let mut buffer: Vec<MaybeUninit<Min<usize>>> =
+            (0..100).map(|_| MaybeUninit::uninit()).collect();
+        let mut segment_tree: Recursive<_, &mut [_]> = Recursive::build_in(&nodes, &mut buffer[..]);
+        segment_tree.update(0, &10);
+        assert_eq!(segment_tree.query(0, 10).unwrap().value(), &1);
+        assert_eq!(segment_tree.lower_bound(|v, x| v <= x, |_, x| x, 5), 1);
+    }
+
+    #[test]
+    fn lazy_buffer_works() {
+        let nodes: Vec<LazySetWrapper<Min<usize>>> =
+            (0..=10).map(|x| LazySetWrapper::initialize(&x)).collect();
+        let mut buffer: Vec<MaybeUninit<_>> = (0..100).map(|_| MaybeUninit::uninit()).collect();
+        let mut segment_tree: LazyRecursive<_, &mut [_]> =
+            LazyRecursive::build_in(&nodes, &mut buffer[..]);
+        segment_tree.update(0, 5, &7);
+        assert_eq!(segment_tree.query(0, 10).unwrap().value(), &6);
+    }
+
+    #[test]
+    #[should_panic(expected = "too small")]
+    fn small_buffer_panics() {
+        let nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let mut buffer: Vec<MaybeUninit<Min<usize>>> =
+            (0..10).map(|_| MaybeUninit::uninit()).collect();
+        let _segment_tree: Iterative<_, &mut [_]> = Iterative::build_in(&nodes, &mut buffer[..]);
+    }
+
+    #[test]
+    fn inline_works() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree: StaticTree<_, 16> = StaticTree::build_in(&nodes, ());
+        let mut expected = Iterative::build(&nodes);
+        segment_tree.update(3, &10);
+        expected.update(3, &10);
+        for (l, r) in [(0, 10), (2, 5), (3, 3), (7, 10)] {
+            assert_eq!(segment_tree.query(l, r), expected.query(l, r));
+        }
+    }
+
+    /// Node which holds a reference to a shared counter, to check that every node is dropped exactly once.
+    #[derive(Clone)]
+    struct Counted(Rc<()>);
+
+    impl Node for Counted {
+        type Value = Rc<()>;
+        fn initialize(value: &Self::Value) -> Self {
+            Self(Rc::clone(value))
+        }
+        fn combine(a: &Self, _: &Self) -> Self {
+            a.clone()
+        }
+        fn value(&self) -> &Self::Value {
+            &self.0
+        }
+    }
+
+    #[test]
+    fn inline_drops_nodes() {
+        let counter = Rc::new(());
+        let nodes = vec![Counted::initialize(&counter); 3];
+        let segment_tree: StaticTree<_, 4> = StaticTree::build_in(&nodes, ());
+        drop(nodes);
+        // 3 leaves, 2 inner nodes and the unused node at 0.
+        assert_eq!(Rc::strong_count(&counter), 7);
+        drop(segment_tree);
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+
+    #[test]
+    fn recursive_drops_nodes() {
+        let counter = Rc::new(());
+        let nodes = vec![Counted::initialize(&counter); 1000];
+        let segment_tree = Recursive::build(&nodes);
+        let boxed: Recursive<_, Box<[_]>> = Recursive::build_in(&nodes, ());
+        drop(nodes);
+        // Every node of both buffers is initialized, including the padding.
+        assert_eq!(Rc::strong_count(&counter), 1 + 2 * 4000);
+        drop(boxed);
+        assert_eq!(segment_tree.into_values().len(), 1000);
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "too small")]
+    fn small_inline_panics() {
+        let nodes: Vec<Min<usize>> = (0..=10).map(|x| Min::initialize(&x)).collect();
+        let _segment_tree: StaticTree<_, 8> = StaticTree::build_in(&nodes, ());
+    }
+
+    #[test]
+    fn arena_works() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree: Persistent<_, u32, _> =
+            Persistent::build_in(&nodes, Minimal(Vec::new()));
+        segment_tree.update(0, 3, &10);
+        segment_tree.update(1, 5, &10);
+        segment_tree.retain_versions(&[0, 2]);
+        assert_eq!(segment_tree.query(0, 0, 10).unwrap().value(), &55);
+        assert_eq!(segment_tree.query(1, 0, 10).unwrap().value(), &67);
+        assert_eq!(segment_tree.query(1, 3, 5).unwrap().value(), &24);
+    }
+
+    #[test]
+    fn lazy_arena_works() {
+        let nodes: Vec<LazySetWrapper<Min<usize>>> =
+            (0..=10).map(|x| LazySetWrapper::initialize(&x)).collect();
+        let mut segment_tree: LazyPersistent<_, u32, _> =
+            LazyPersistent::build_in(&nodes, Minimal(Vec::new()));
+        segment_tree.update(0, 0, 4, &7);
+        segment_tree.gc();
+        assert_eq!(segment_tree.query(0, 0, 10).unwrap().value(), &0);
+        assert_eq!(segment_tree.query(1, 0, 10).unwrap().value(), &5);
+        assert_eq!(segment_tree.query(1, 2, 4).unwrap().value(), &7);
+    }
+
+    #[test]
+    #[should_panic(expected = "already has")]
+    fn non_empty_arena_panics() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let _segment_tree: Persistent<_, u32, _> =
+            Persistent::build_in(&nodes, vec![Sum::initialize(&0).into()]);
+    }
+
+    #[test]
+    fn gc_compacts_in_place() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree: Persistent<_, u32, _> =
+            Persistent::build_in(&nodes, ChunkedArena::<_, 8>::new());
+        let mut expected = Persistent::build(&nodes);
+        for version in 0..20 {
+            let p = (version * 7) % 11;
+            segment_tree.update(version, p, &version);
+            expected.update(version, p, &version);
+        }
+        let capacity = segment_tree.memory_stats().capacity;
+        segment_tree.retain_versions(&[5, 20]);
+        expected.retain_versions(&[5, 20]);
+        // The nodes are moved within the arena, so no chunk is allocated.
+        assert_eq!(segment_tree.memory_stats().capacity, capacity);
+        let roots = segment_tree.roots().to_vec();
+        let segment_tree: Persistent<_, u32, _> =
+            Persistent::reopen_in(segment_tree.into_arena(), 11, roots);
+        segment_tree.validate();
+        for version in 0..2 {
+            assert_eq!(segment_tree.version_parent(version), None);
+            for (l, r) in [(0, 10), (2, 5), (3, 3), (7, 10)] {
+                assert_eq!(
+                    segment_tree.query(version, l, r),
+                    expected.query(version, l, r)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn lazy_gc_compacts_in_place() {
+        let nodes: Vec<LazySetWrapper<Min<usize>>> =
+            (0..=10).map(|x| LazySetWrapper::initialize(&x)).collect();
+        let mut segment_tree: LazyPersistent<_, u32, _> =
+            LazyPersistent::build_in(&nodes, ChunkedArena::<_, 4>::new());
+        segment_tree.update(0, 0, 4, &7);
+        segment_tree.update(1, 3, 8, &2);
+        // Updating below a pending lazy value stores the children after their parent.
+        assert_eq!(segment_tree.query(2, 3, 3).unwrap().value(), &2);
+        let capacity = segment_tree.memory_stats().capacity;
+        segment_tree.retain_versions(&[2]);
+        assert_eq!(segment_tree.memory_stats().capacity, capacity);
+        let roots = segment_tree.roots().to_vec();
+        let segment_tree: LazyPersistent<_, u32, _> =
+            LazyPersistent::reopen_in(segment_tree.into_arena(), 11, roots);
+        segment_tree.validate();
+        assert_eq!(segment_tree.query(0, 0, 10).unwrap().value(), &2);
+        assert_eq!(segment_tree.query(0, 0, 2).unwrap().value(), &7);
+        assert_eq!(segment_tree.query(0, 9, 10).unwrap().value(), &9);
+    }
+
+    #[test]
+    #[should_panic(expected = "isn't in the arena")]
+    fn reopen_missing_root_panics() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let segment_tree = Persistent::build(&nodes);
+        let _segment_tree: Persistent<_> =
+            Persistent::reopen_in(segment_tree.into_arena(), 11, vec![100]);
+    }
+
+    #[test]
+    fn growable_arena_follows_growth() {
+        let mut arena = GrowableArena::new(Growth::Step(4));
+        for x in 0..10 {
+            arena.push(x);
+        }
+        assert!(arena.capacity() >= 12);
+        assert_eq!(&arena[..], &(0..10).collect::<Vec<_>>()[..]);
+        arena.set_growth(Growth::Exact);
+        arena.reserve(7);
+        assert!(arena.capacity() >= 17);
+        assert_eq!(arena.growth(), Growth::Exact);
+        let mut arena = GrowableArena::<usize>::with_capacity(Growth::Doubling, 3);
+        arena.reserve(5);
+        assert!(arena.capacity() >= 5);
+    }
+
+    #[test]
+    fn growable_arena_works() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree: Persistent<_, u32, _> =
+            Persistent::build_in(&nodes, GrowableArena::new(Growth::Step(8)));
+        let mut expected = Persistent::build(&nodes);
+        for version in 0..20 {
+            let p = (version * 7) % 11;
+            segment_tree.update(version, p, &version);
+            expected.update(version, p, &version);
+        }
+        segment_tree.retain_versions(&[0, 5, 20]);
+        expected.retain_versions(&[0, 5, 20]);
+        for version in 0..3 {
+            for (l, r) in [(0, 10), (2, 5), (3, 3), (7, 10)] {
+                assert_eq!(
+                    segment_tree.query(version, l, r),
+                    expected.query(version, l, r)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn chunked_arena_never_moves_nodes() {
+        let mut arena = ChunkedArena::<usize, 4>::new();
+        arena.push(0);
+        let first = &raw const arena[0];
+        for x in 1..10 {
+            arena.push(x);
+        }
+        assert!(core::ptr::eq(first, &raw const arena[0]));
+        assert_eq!((arena.len(), arena.capacity()), (10, 12));
+        assert!((0..10).all(|x| arena[x] == x));
+        arena.truncate(3);
+        assert_eq!((arena.len(), arena.capacity()), (3, 12));
+        arena.push(7);
+        assert_eq!(arena[3], 7);
+        arena.shrink_to_fit();
+        assert_eq!(arena.capacity(), 4);
+        arena.reserve(5);
+        assert_eq!(arena.capacity(), 12);
+        assert!(core::ptr::eq(first, &raw const arena[0]));
+    }
+
+    #[test]
+    fn chunked_arena_works() {
+        let nodes: Vec<Sum<usize>> = (0..=10).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree: Persistent<_, u32, _> =
+            Persistent::build_in(&nodes, ChunkedArena::<_, 8>::new());
+        let mut expected = Persistent::build(&nodes);
+        for version in 0..20 {
+            let p = (version * 7) % 11;
+            segment_tree.update(version, p, &version);
+            expected.update(version, p, &version);
+        }
+        segment_tree.retain_versions(&[0, 5, 20]);
+        expected.retain_versions(&[0, 5, 20]);
+        segment_tree.validate();
+        for version in 0..3 {
+            for (l, r) in [(0, 10), (2, 5), (3, 3), (7, 10)] {
+                assert_eq!(
+                    segment_tree.query(version, l, r),
+                    expected.query(version, l, r)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn lazy_chunked_arena_works() {
+        let nodes: Vec<LazySetWrapper<Min<usize>>> =
+            (0..=10).map(|x| LazySetWrapper::initialize(&x)).collect();
+        let mut segment_tree: LazyPersistent<_, u32, _> =
+            LazyPersistent::build_in(&nodes, ChunkedArena::<_, 4>::new());
+        segment_tree.update(0, 0, 4, &7);
+        segment_tree.update(1, 3, 8, &2);
+        segment_tree.gc();
+        segment_tree.validate();
+        assert_eq!(segment_tree.query(1, 0, 10).unwrap().value(), &5);
+        assert_eq!(segment_tree.query(2, 0, 4).unwrap().value(), &2);
+        assert_eq!(segment_tree.query(2, 9, 10).unwrap().value(), &9);
+    }
+}
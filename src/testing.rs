@@ -0,0 +1,832 @@
+use alloc::vec::Vec;
+
+use crate::{
+    nodes::{FlatNode, InvertibleNode, LazyNode, Node},
+    segment_tree::{
+        Adaptive, ArcPersistent, Flat, Iterative, LazyPersistent, LazyRecursive, NodeIndex,
+        Persistent, Recursive,
+    },
+    storage::{Arena, Storage},
+};
+
+/// Operation on a segment tree, see [`NaiveRangeQuery::check`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Op<V> {
+    /// Sets the p-th element to the value, for lazy trees it's a range update of `[p,p]`.
+    Update(usize, V),
+    /// Applies the value to every element in the range `[left,right]`, only lazy trees support it.
+    RangeUpdate(usize, usize, V),
+    /// Queries the range `[left,right]`.
+    Query(usize, usize),
+}
+
+impl<V> Op<V>
+where
+    V: From<u8>,
+{
+    /// Decodes a sequence of operations over `n` elements from arbitrary bytes, so fuzzers can generate them.
+    /// Every decoded position is in `[0,n)`, and every range is non-empty, range updates are only decoded if `lazy` is `true`.
+    /// It returns no operations if `n` is `0`.
+    #[must_use]
+    pub fn decode(bytes: &[u8], n: usize, lazy: bool) -> Vec<Self> {
+        if n == 0 {
+            return Vec::new();
+        }
+        let kinds = if lazy { 3 } else { 2 };
+        bytes
+            .chunks_exact(4)
+            .map(|chunk| {
+                let (a, b) = (usize::from(chunk[1]) % n, usize::from(chunk[2]) % n);
+                let (left, right) = (a.min(b), a.max(b));
+                match chunk[0] % kinds {
+                    0 => Self::Query(left, right),
+                    1 => Self::Update(a, chunk[3].into()),
+                    _ => Self::RangeUpdate(left, right, chunk[3].into()),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Returns random operations over `n` elements, decoded with [`Op::decode`] from random bytes, so the unit tests of the nodes and trees share a randomized differential test.
+#[cfg(test)]
+pub(crate) fn random_ops<V>(n: usize, lazy: bool) -> Vec<Op<V>>
+where
+    V: From<u8>,
+{
+    use rand::{thread_rng, Rng};
+    let bytes: Vec<u8> = (0..4000).map(|_| thread_rng().gen()).collect();
+    Op::decode(&bytes, n, lazy)
+}
+
+/// Returns `n` small values with repeats, `(x*7)%5` for each `x` in `[0,n)`, which is the shared fixture of the unit tests of the trees.
+#[cfg(test)]
+pub(crate) fn small_values<T>(n: usize) -> Vec<T>
+where
+    T: From<u8>,
+{
+    (0..n).map(|x| T::from([0, 2, 4, 1, 3][x % 5])).collect()
+}
+
+/// Segment trees which can run [`Op`]s, so they can be checked against a [`NaiveRangeQuery`].
+///
+/// Persistent trees run every operation on their latest version, and their updates create a new version.
+pub trait OpTarget {
+    /// Node of the segment tree.
+    type Node: Node;
+
+    /// Runs [`Op::Update`].
+    fn update(&mut self, p: usize, value: &<Self::Node as Node>::Value);
+
+    /// Runs [`Op::RangeUpdate`].
+    /// It will **panic** if the tree doesn't support range updates, which is the default.
+    fn update_range(&mut self, left: usize, right: usize, value: &<Self::Node as Node>::Value) {
+        let _ = (left, right, value);
+        panic!(
+            "{} doesn't support range updates",
+            core::any::type_name::<Self>()
+        );
+    }
+
+    /// Runs [`Op::Query`].
+    fn query(&mut self, left: usize, right: usize) -> Option<Self::Node>;
+}
+
+/// Brute force implementation of the operations of the segment trees over a [`Vec`], to be used as a trusted baseline.
+///
+/// Every query combines the elements of the range one by one, so it has time complexity of `O(n)`, it's meant for differential testing of custom nodes against the segment trees, and as a baseline for benchmarks.
+/// ```
+/// # use seg_tree::{Recursive,testing::NaiveRangeQuery,utils::Sum,nodes::Node};
+/// # let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+/// let mut seg_tree = Recursive::build(&nodes);
+/// let mut naive = NaiveRangeQuery::build(&nodes);
+/// seg_tree.update(3, &10);
+/// naive.update(3, &10);
+/// for (l, r) in [(0, 9), (2, 5), (4, 4)] {
+///     assert_eq!(seg_tree.query(l, r), naive.query(l, r));
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NaiveRangeQuery<T> {
+    values: Vec<T>,
+}
+
+impl<T> NaiveRangeQuery<T>
+where
+    T: Node + Clone,
+{
+    /// Builds the baseline from slice, each element of the slice will correspond to an element of the baseline.
+    /// It has time complexity of `O(n)`.
+    pub fn build(values: &[T]) -> Self {
+        Self {
+            values: values.to_vec(),
+        }
+    }
+
+    /// Returns the result from the range `[left,right]`, combining the elements from left to right.
+    /// It returns None if and only if range is empty or the baseline has no elements.
+    /// It will **panic** if left or right are not in `[0,n)`.
+    /// It has time complexity of `O(n)`, assuming that [`combine`](Node::combine) has constant time complexity.
+    #[must_use]
+    pub fn query(&self, left: usize, right: usize) -> Option<T> {
+        if left > right || self.values.is_empty() {
+            return None;
+        }
+        let (first, rest) = self.values[left..=right].split_first()?;
+        Some(
+            rest.iter()
+                .fold(first.clone(), |ans, node| T::combine(&ans, node)),
+        )
+    }
+
+    /// Sets the p-th element to value.
+    /// It will **panic** if p is not in `[0,n)`.
+    /// It has time complexity of `O(1)`.
+    pub fn update(&mut self, p: usize, value: &<T as Node>::Value) {
+        self.values[p] = T::initialize(value);
+    }
+
+    /// Same as [`Recursive::lower_bound`](crate::Recursive::lower_bound), but it checks every prefix in order, so it doesn't need `g`.
+    /// It returns `n-1` if no prefix satisfies `predicate`, as the segment trees do.
+    /// It has time complexity of `O(n)`, assuming that [`combine`](Node::combine) has constant time complexity.
+    pub fn lower_bound<F>(&self, predicate: F, value: &<T as Node>::Value) -> usize
+    where
+        F: Fn(&<T as Node>::Value, &<T as Node>::Value) -> bool,
+    {
+        let mut prefix: Option<T> = None;
+        for (i, node) in self.values.iter().enumerate() {
+            let curr = prefix.map_or_else(|| node.clone(), |prefix| T::combine(&prefix, node));
+            if predicate(curr.value(), value) {
+                return i;
+            }
+            prefix = Some(curr);
+        }
+        self.values.len().saturating_sub(1)
+    }
+
+    /// Returns the elements of the baseline.
+    #[must_use]
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    /// Returns the amount of elements of the baseline.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns `true` if the baseline has no elements.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl<T> NaiveRangeQuery<T>
+where
+    T: LazyNode + Clone,
+{
+    /// Applies value to every element in the range `[left,right]`, as [`LazyRecursive::update`](crate::LazyRecursive::update) does, by setting it as the lazy value of each element and applying it right away.
+    /// It will **panic** if left or right are not in `[0,n)`.
+    /// It has time complexity of `O(n)`, assuming that [`update_lazy_value`](LazyNode::update_lazy_value) and [`lazy_update`](LazyNode::lazy_update) have constant time complexity.
+    pub fn update_range(&mut self, left: usize, right: usize, value: &<T as Node>::Value) {
+        for (p, node) in self.values[left..=right].iter_mut().enumerate() {
+            node.update_lazy_value(value);
+            node.lazy_update(left + p, left + p);
+        }
+    }
+}
+
+impl<T> NaiveRangeQuery<T>
+where
+    T: Node + Clone,
+    T::Value: PartialEq + core::fmt::Debug,
+{
+    /// Runs `ops` on both the baseline and `tree`, asserting that every query returns the same value on both.
+    /// Range updates aren't supported, see [`check_lazy`](NaiveRangeQuery::check_lazy) for lazy trees.
+    /// It will **panic** if a query differs, with the index of the operation which failed.
+    pub fn check<S>(&mut self, tree: &mut S, ops: &[Op<T::Value>])
+    where
+        S: OpTarget<Node = T>,
+    {
+        for (i, op) in ops.iter().enumerate() {
+            match op {
+                Op::Update(p, value) => {
+                    self.update(*p, value);
+                    tree.update(*p, value);
+                }
+                Op::RangeUpdate(..) => unsupported_range_update(i),
+                Op::Query(left, right) => self.check_query(tree, i, *left, *right),
+            }
+        }
+    }
+
+    fn check_query<S>(&self, tree: &mut S, i: usize, left: usize, right: usize)
+    where
+        S: OpTarget<Node = T>,
+    {
+        let expected = self.query(left, right);
+        let got = tree.query(left, right);
+        assert_eq!(
+            got.as_ref().map(Node::value),
+            expected.as_ref().map(Node::value),
+            "query [{left}, {right}] differs at operation {i}"
+        );
+    }
+}
+
+fn unsupported_range_update(i: usize) -> ! {
+    panic!("range updates need check_lazy, at operation {i}")
+}
+
+impl<T> NaiveRangeQuery<T>
+where
+    T: LazyNode + Clone,
+    T::Value: PartialEq + core::fmt::Debug,
+{
+    /// Same as [`check`](NaiveRangeQuery::check), but for lazy trees, where point updates are range updates of a single element.
+    /// It will **panic** if a query differs, with the index of the operation which failed.
+    pub fn check_lazy<S>(&mut self, tree: &mut S, ops: &[Op<T::Value>])
+    where
+        S: OpTarget<Node = T>,
+    {
+        for (i, op) in ops.iter().enumerate() {
+            match op {
+                Op::Update(p, value) => {
+                    self.update_range(*p, *p, value);
+                    tree.update(*p, value);
+                }
+                Op::RangeUpdate(left, right, value) => {
+                    self.update_range(*left, *right, value);
+                    tree.update_range(*left, *right, value);
+                }
+                Op::Query(left, right) => self.check_query(tree, i, *left, *right),
+            }
+        }
+    }
+}
+
+impl<T> OpTarget for NaiveRangeQuery<T>
+where
+    T: Node + Clone,
+{
+    type Node = T;
+
+    fn update(&mut self, p: usize, value: &T::Value) {
+        Self::update(self, p, value);
+    }
+
+    fn query(&mut self, left: usize, right: usize) -> Option<T> {
+        Self::query(self, left, right)
+    }
+}
+
+impl<T, S> OpTarget for Iterative<T, S>
+where
+    T: Node + Clone,
+    S: Storage<T>,
+{
+    type Node = T;
+
+    fn update(&mut self, p: usize, value: &T::Value) {
+        Self::update(self, p, value);
+    }
+
+    fn query(&mut self, left: usize, right: usize) -> Option<T> {
+        Self::query(self, left, right)
+    }
+}
+
+impl<T, S> OpTarget for Recursive<T, S>
+where
+    T: Node + Clone,
+    S: Storage<T>,
+{
+    type Node = T;
+
+    fn update(&mut self, p: usize, value: &T::Value) {
+        Self::update(self, p, value);
+    }
+
+    fn query(&mut self, left: usize, right: usize) -> Option<T> {
+        Self::query(self, left, right)
+    }
+}
+
+impl<T> OpTarget for Adaptive<T>
+where
+    T: InvertibleNode + Clone,
+{
+    type Node = T;
+
+    fn update(&mut self, p: usize, value: &T::Value) {
+        Self::update(self, p, value);
+    }
+
+    fn query(&mut self, left: usize, right: usize) -> Option<T> {
+        Self::query(self, left, right)
+    }
+}
+
+impl<T> OpTarget for Flat<T>
+where
+    T: FlatNode,
+{
+    type Node = T;
+
+    fn update(&mut self, p: usize, value: &T::Value) {
+        Self::update(self, p, value);
+    }
+
+    fn query(&mut self, left: usize, right: usize) -> Option<T> {
+        Self::query(self, left, right)
+    }
+}
+
+impl<T, S> OpTarget for LazyRecursive<T, S>
+where
+    T: LazyNode + Clone,
+    S: Storage<T>,
+{
+    type Node = T;
+
+    fn update(&mut self, p: usize, value: &T::Value) {
+        Self::update(self, p, p, value);
+    }
+
+    fn update_range(&mut self, left: usize, right: usize, value: &T::Value) {
+        Self::update(self, left, right, value);
+    }
+
+    fn query(&mut self, left: usize, right: usize) -> Option<T> {
+        Self::query(self, left, right)
+    }
+}
+
+impl<T, I, A> OpTarget for Persistent<T, I, A>
+where
+    T: Node + Clone,
+    I: NodeIndex,
+    A: Arena<crate::internal_utils::persistent_utils::PersistentWrapper<T, I>>,
+{
+    type Node = T;
+
+    fn update(&mut self, p: usize, value: &T::Value) {
+        Self::update(self, self.versions() - 1, p, value);
+    }
+
+    fn query(&mut self, left: usize, right: usize) -> Option<T> {
+        Self::query(self, self.versions() - 1, left, right)
+    }
+}
+
+impl<T, I, A> OpTarget for LazyPersistent<T, I, A>
+where
+    T: LazyNode + Clone,
+    I: NodeIndex,
+    A: Arena<crate::internal_utils::persistent_utils::PersistentWrapper<T, I>>,
+{
+    type Node = T;
+
+    fn update(&mut self, p: usize, value: &T::Value) {
+        Self::update(self, self.versions() - 1, p, p, value);
+    }
+
+    fn update_range(&mut self, left: usize, right: usize, value: &T::Value) {
+        Self::update(self, self.versions() - 1, left, right, value);
+    }
+
+    fn query(&mut self, left: usize, right: usize) -> Option<T> {
+        Self::query(self, self.versions() - 1, left, right)
+    }
+}
+
+impl<T> OpTarget for ArcPersistent<T>
+where
+    T: Node + Clone,
+{
+    type Node = T;
+
+    fn update(&mut self, p: usize, value: &T::Value) {
+        Self::update(self, self.versions() - 1, p, value);
+    }
+
+    fn query(&mut self, left: usize, right: usize) -> Option<T> {
+        Self::query(self, self.versions() - 1, left, right)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> OpTarget for crate::segment_tree::Sharded<T>
+where
+    T: Node + Clone,
+{
+    type Node = T;
+
+    fn update(&mut self, p: usize, value: &T::Value) {
+        Self::update(self, p, value);
+    }
+
+    fn query(&mut self, left: usize, right: usize) -> Option<T> {
+        Self::query(self, left, right)
+    }
+}
+
+/// Segment trees which can be split and appended, so they share [`check_split_off_and_append`].
+#[cfg(test)]
+pub(crate) trait SplitTarget: OpTarget + Sized {
+    fn split_off(&mut self, at: usize) -> Self;
+    fn append(&mut self, other: Self);
+    fn len(&self) -> usize;
+    fn validate(&self);
+}
+
+#[cfg(test)]
+impl<T> SplitTarget for Iterative<T>
+where
+    T: Node + Clone,
+    T::Value: PartialEq,
+{
+    fn split_off(&mut self, at: usize) -> Self {
+        Self::split_off(self, at)
+    }
+    fn append(&mut self, other: Self) {
+        Self::append(self, other);
+    }
+    fn len(&self) -> usize {
+        Self::len(self)
+    }
+    fn validate(&self) {
+        Self::validate(self);
+    }
+}
+
+#[cfg(test)]
+impl<T> SplitTarget for Recursive<T>
+where
+    T: Node + Clone,
+    T::Value: PartialEq,
+{
+    fn split_off(&mut self, at: usize) -> Self {
+        Self::split_off(self, at)
+    }
+    fn append(&mut self, other: Self) {
+        Self::append(self, other);
+    }
+    fn len(&self) -> usize {
+        Self::len(self)
+    }
+    fn validate(&self) {
+        Self::validate(self);
+    }
+}
+
+/// Splits and appends the tree built by `build` from `[0,10]` with [`Min`](crate::utils::Min) nodes, checking the trees and their queries after each step.
+#[cfg(test)]
+pub(crate) fn check_split_off_and_append<S>(build: fn(&[crate::utils::Min<usize>]) -> S)
+where
+    S: SplitTarget<Node = crate::utils::Min<usize>>,
+{
+    let nodes: Vec<crate::utils::Min<usize>> = (0..=10).map(|x| Node::initialize(&x)).collect();
+    let mut segment_tree = build(&nodes);
+    let mut right = segment_tree.split_off(4);
+    segment_tree.validate();
+    right.validate();
+    assert_eq!(segment_tree.len(), 4);
+    assert_eq!(right.len(), 7);
+    assert_eq!(right.query(0, 6).unwrap().value(), &4);
+    right.update(0, &20);
+    segment_tree.append(right);
+    segment_tree.validate();
+    assert_eq!(segment_tree.query(3, 5).unwrap().value(), &3);
+    assert_eq!(segment_tree.query(4, 5).unwrap().value(), &5);
+    assert_eq!(segment_tree.split_off(11).len(), 0);
+    assert_eq!(segment_tree.split_off(0).len(), 11);
+    assert_eq!(segment_tree.len(), 0);
+}
+
+/// Asserts the laws [`Node`] requires over every combination of `samples`, giving node implementations an executable specification:
+/// - [`initialize`](Node::initialize) keeps the value, `N::initialize(&a).value() == &a`.
+/// - [`combine`](Node::combine) is associative, `combine(combine(a, b), c) == combine(a, combine(b, c))`, comparing their values.
+///
+/// It will **panic** with the samples which break a law.
+/// It has time complexity of `O(s^3)`, where `s` is the amount of samples, assuming that [`combine`](Node::combine) has constant time complexity.
+/// ```
+/// # use seg_tree::{testing::assert_node_laws,utils::{Max,Sum}};
+/// assert_node_laws::<Sum<i64>>(&[-3, 0, 1, 7, 1 << 40]);
+/// assert_node_laws::<Max<u8>>(&[0, 1, 255]);
+/// ```
+pub fn assert_node_laws<N>(samples: &[N::Value])
+where
+    N: Node,
+    N::Value: PartialEq + core::fmt::Debug,
+{
+    let nodes: Vec<N> = samples.iter().map(N::initialize).collect();
+    for (a, node) in samples.iter().zip(&nodes) {
+        assert_law(
+            node.value() == a,
+            format_args!("initialize doesn't keep the value {a:?}, it's {:?}", node.value()),
+        );
+    }
+    for (a, node_a) in samples.iter().zip(&nodes) {
+        for (b, node_b) in samples.iter().zip(&nodes) {
+            let ab = N::combine(node_a, node_b);
+            for (c, node_c) in samples.iter().zip(&nodes) {
+                let left = N::combine(&ab, node_c);
+                let right = N::combine(node_a, &N::combine(node_b, node_c));
+                assert_law(
+                    left.value() == right.value(),
+                    format_args!(
+                        "combine isn't associative for {a:?}, {b:?} and {c:?}, {:?} != {:?}",
+                        left.value(),
+                        right.value()
+                    ),
+                );
+            }
+        }
+    }
+}
+
+/// Asserts the laws [`LazyNode`] requires over every segment of `samples` and every one or two of `tags`.
+///
+/// Each segment is replayed against the naive model, which applies every tag to each element on its own:
+/// - Applying a tag is compatible with [`combine`](Node::combine), `update_lazy_value(t)` then `lazy_update(i, j)` on the node of `[i,j]` gives the combination of applying `t` to each element.
+/// - Tags compose in order, `update_lazy_value(a)`, `update_lazy_value(b)` then `lazy_update(i, j)` gives the combination of applying `a` and then `b` to each element, which catches tags which don't commute but are composed as if they did.
+///
+/// Applying a tag to the element `k` of a segment calls `lazy_update(k, k)`, so the positions given to [`lazy_update`](LazyNode::lazy_update) are the indices in `samples`.
+/// It will **panic** with the segment, the tags and the order in which they were composed and applied when a law is broken, the laws of [`assert_node_laws`] are assumed to hold.
+/// It has time complexity of `O(s^3*t^2)`, where `s` is the amount of samples and `t` the amount of tags, assuming that [`combine`](Node::combine), [`update_lazy_value`](LazyNode::update_lazy_value) and [`lazy_update`](LazyNode::lazy_update) have constant time complexity.
+/// ```
+/// # use seg_tree::{testing::assert_lazy_laws,utils::{LazySetWrapper,Max}};
+/// assert_lazy_laws::<LazySetWrapper<Max<i32>>>(&[-3, 0, 7, 2], &[1, 5]);
+/// ```
+pub fn assert_lazy_laws<N>(samples: &[N::Value], tags: &[N::Value])
+where
+    N: LazyNode + Clone,
+    N::Value: PartialEq + core::fmt::Debug,
+{
+    let nodes: Vec<N> = samples.iter().map(N::initialize).collect();
+    for i in 0..nodes.len() {
+        for j in i..nodes.len() {
+            let segment = &nodes[i..=j];
+            for t in tags {
+                let found = apply_tags(&combine_all(segment), &[t], i, j);
+                let expected = combine_all(&apply_tags_to_each(segment, &[t], i));
+                assert_law(
+                    found.value() == expected.value(),
+                    format_args!(
+                        "lazy_update isn't compatible with combine for tag {t:?} on {:?}, \
+                         update_lazy_value({t:?}) then lazy_update({i}, {j}) gives {:?} != {:?}",
+                        &samples[i..=j],
+                        found.value(),
+                        expected.value()
+                    ),
+                );
+            }
+            for a in tags {
+                for b in tags {
+                    let found = apply_tags(&combine_all(segment), &[a, b], i, j);
+                    let expected = combine_all(&apply_tags_to_each(segment, &[a, b], i));
+                    assert_law(
+                        found.value() == expected.value(),
+                        format_args!(
+                            "tags {a:?} and {b:?} don't compose on {:?}, update_lazy_value({a:?}), \
+                             update_lazy_value({b:?}) then lazy_update({i}, {j}) gives {:?} != {:?}",
+                            &samples[i..=j],
+                            found.value(),
+                            expected.value()
+                        ),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Composes tags into the lazy value of node, in order, and then applies them to the segment `[i,j]`.
+fn apply_tags<N>(node: &N, tags: &[&N::Value], i: usize, j: usize) -> N
+where
+    N: LazyNode + Clone,
+{
+    let mut node = node.clone();
+    for tag in tags {
+        node.update_lazy_value(tag);
+    }
+    node.lazy_update(i, j);
+    node
+}
+
+/// Applies each tag in order to each node on its own, as the naive model does, where the first node is at position `first`.
+fn apply_tags_to_each<N>(nodes: &[N], tags: &[&N::Value], first: usize) -> Vec<N>
+where
+    N: LazyNode + Clone,
+{
+    let mut nodes = nodes.to_vec();
+    for (k, node) in nodes.iter_mut().enumerate() {
+        for tag in tags {
+            *node = apply_tags(node, &[tag], first + k, first + k);
+        }
+    }
+    nodes
+}
+
+/// Combines the nodes from left to right.
+/// It will **panic** if there are no nodes.
+fn combine_all<N>(nodes: &[N]) -> N
+where
+    N: Node + Clone,
+{
+    let (first, rest) = nodes.split_first().expect("the segment isn't empty");
+    rest.iter()
+        .fold(first.clone(), |ans, node| N::combine(&ans, node))
+}
+
+fn assert_law(holds: bool, message: core::fmt::Arguments<'_>) {
+    assert!(holds, "{message}");
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{distributions::Uniform, prelude::Distribution, thread_rng};
+
+    use crate::{
+        nodes::{LazyNode, Node},
+        utils::{LazySetWrapper, Max, Min, Sum},
+        ArcPersistent, Flat, Iterative, LazyPersistent, LazyRecursive, Persistent, Recursive,
+    };
+
+    use super::{assert_lazy_laws, assert_node_laws, random_ops, NaiveRangeQuery, Op, OpTarget};
+
+    #[test]
+    fn matches_recursive() {
+        let n = 50;
+        let mut rng = thread_rng();
+        let distribution = Uniform::new(0, n);
+        let nodes: Vec<Sum<usize>> = (0..n).map(|x| Sum::initialize(&x)).collect();
+        let mut segment_tree = Recursive::build(&nodes);
+        let mut naive = NaiveRangeQuery::build(&nodes);
+        for _ in 0..1000 {
+            let (p, value) = (distribution.sample(&mut rng), distribution.sample(&mut rng));
+            segment_tree.update(p, &value);
+            naive.update(p, &value);
+            let (l, r) = (distribution.sample(&mut rng), distribution.sample(&mut rng));
+            assert_eq!(segment_tree.query(l, r), naive.query(l, r));
+        }
+    }
+
+    #[test]
+    fn lower_bound_matches_recursive() {
+        let nodes: Vec<Max<usize>> = [3, 1, 4, 1, 5, 9, 2, 6].map(|x| Max::initialize(&x)).into();
+        let segment_tree = Recursive::build(&nodes);
+        let naive = NaiveRangeQuery::build(&nodes);
+        let predicate = |left_value: &usize, value: &usize| left_value >= value;
+        for value in 0..=10 {
+            assert_eq!(
+                segment_tree.lower_bound(predicate, |_, value| value, value),
+                naive.lower_bound(predicate, &value)
+            );
+        }
+    }
+
+    #[test]
+    fn update_range_matches_lazy_recursive() {
+        let n = 50;
+        let mut rng = thread_rng();
+        let distribution = Uniform::new(0, n);
+        let nodes: Vec<LazySetWrapper<Min<usize>>> =
+            (0..n).map(|x| LazySetWrapper::initialize(&x)).collect();
+        let mut segment_tree = LazyRecursive::build(&nodes);
+        let mut naive = NaiveRangeQuery::build(&nodes);
+        for _ in 0..1000 {
+            let (l, r) = (distribution.sample(&mut rng), distribution.sample(&mut rng));
+            let (l, r) = (l.min(r), l.max(r));
+            let value = distribution.sample(&mut rng);
+            segment_tree.update(l, r, &value);
+            naive.update_range(l, r, &value);
+            let (l, r) = (distribution.sample(&mut rng), distribution.sample(&mut rng));
+            assert_eq!(
+                segment_tree.query(l, r).map(|node| *node.value()),
+                naive.query(l, r).map(|node| *node.value())
+            );
+        }
+    }
+
+    #[test]
+    fn decode_works() {
+        let ops: Vec<Op<usize>> = Op::decode(&[0, 7, 2, 1, 1, 3, 0, 9, 2, 4, 1, 5, 5], 5, true);
+        assert_eq!(
+            ops,
+            [Op::Query(2, 2), Op::Update(3, 9), Op::RangeUpdate(1, 4, 5)]
+        );
+        assert!(Op::<usize>::decode(&[1, 2, 3, 4], 0, false).is_empty());
+    }
+
+    #[test]
+    fn check_works() {
+        let n = 37;
+        let nodes: Vec<Sum<usize>> = (0..n).map(|x| Sum::initialize(&x)).collect();
+        let ops = random_ops(n, false);
+        NaiveRangeQuery::build(&nodes).check(&mut Iterative::build(&nodes), &ops);
+        NaiveRangeQuery::build(&nodes).check(&mut Recursive::build(&nodes), &ops);
+        NaiveRangeQuery::build(&nodes).check(&mut Flat::build(&nodes), &ops);
+        NaiveRangeQuery::build(&nodes).check(&mut crate::Adaptive::build(&nodes), &ops);
+        NaiveRangeQuery::build(&nodes).check(&mut Persistent::build(&nodes), &ops);
+        NaiveRangeQuery::build(&nodes).check(&mut ArcPersistent::build(&nodes), &ops);
+        #[cfg(feature = "std")]
+        NaiveRangeQuery::build(&nodes).check(&mut crate::Sharded::build(&nodes, 4), &ops);
+    }
+
+    #[test]
+    fn check_lazy_works() {
+        let n = 37;
+        let nodes: Vec<LazySetWrapper<Max<usize>>> =
+            (0..n).map(|x| LazySetWrapper::initialize(&x)).collect();
+        let ops = random_ops(n, true);
+        NaiveRangeQuery::build(&nodes).check_lazy(&mut LazyRecursive::build(&nodes), &ops);
+        NaiveRangeQuery::build(&nodes).check_lazy(&mut LazyPersistent::build(&nodes), &ops);
+    }
+
+    #[test]
+    #[should_panic(expected = "differs at operation 1")]
+    fn check_catches_wrong_results() {
+        struct IgnoresUpdates(Iterative<Sum<usize>>);
+        impl OpTarget for IgnoresUpdates {
+            type Node = Sum<usize>;
+            fn update(&mut self, _: usize, _: &usize) {}
+            fn query(&mut self, left: usize, right: usize) -> Option<Sum<usize>> {
+                self.0.query(left, right)
+            }
+        }
+        let nodes: Vec<Sum<usize>> = (0..10).map(|x| Sum::initialize(&x)).collect();
+        let ops = [Op::Update(3, 10), Op::Query(0, 9)];
+        NaiveRangeQuery::build(&nodes).check(&mut IgnoresUpdates(Iterative::build(&nodes)), &ops);
+    }
+
+    #[test]
+    fn assert_node_laws_works() {
+        assert_node_laws::<Sum<usize>>(&[0, 1, 2, 10]);
+        assert_node_laws::<Min<i32>>(&[-5, 0, 3]);
+        assert_node_laws::<LazySetWrapper<Max<usize>>>(&[0, 7]);
+    }
+
+    #[test]
+    #[should_panic = "combine isn't associative for 1, 1 and 1, -1 != 1"]
+    fn assert_node_laws_catches_non_associative_nodes() {
+        #[derive(Clone)]
+        struct Difference(i32);
+        impl Node for Difference {
+            type Value = i32;
+            fn initialize(value: &i32) -> Self {
+                Self(*value)
+            }
+            fn combine(a: &Self, b: &Self) -> Self {
+                Self(a.0 - b.0)
+            }
+            fn value(&self) -> &i32 {
+                &self.0
+            }
+        }
+        assert_node_laws::<Difference>(&[1, 2]);
+    }
+
+    #[test]
+    fn assert_lazy_laws_works() {
+        assert_lazy_laws::<LazySetWrapper<Min<i32>>>(&[-5, 0, 3, 3], &[-1, 4]);
+        assert_lazy_laws::<LazySetWrapper<Max<usize>>>(&[0, 7, 2], &[0, 1, 9]);
+    }
+
+    #[test]
+    #[should_panic = "tags 1 and 2 don't compose on [0]"]
+    fn assert_lazy_laws_catches_tags_composed_out_of_order() {
+        // Assigns the maximum, but keeps the first pending assignment instead of the last one.
+        #[derive(Clone)]
+        struct KeepsFirst(i32, Option<i32>);
+        impl Node for KeepsFirst {
+            type Value = i32;
+            fn initialize(value: &i32) -> Self {
+                Self(*value, None)
+            }
+            fn combine(a: &Self, b: &Self) -> Self {
+                Self(a.0.max(b.0), None)
+            }
+            fn value(&self) -> &i32 {
+                &self.0
+            }
+        }
+        impl LazyNode for KeepsFirst {
+            fn lazy_update(&mut self, _i: usize, _j: usize) {
+                if let Some(value) = self.1.take() {
+                    self.0 = value;
+                }
+            }
+            fn update_lazy_value(&mut self, new_value: &i32) {
+                self.1 = self.1.or(Some(*new_value));
+            }
+            fn lazy_value(&self) -> Option<&i32> {
+                self.1.as_ref()
+            }
+        }
+        assert_lazy_laws::<KeepsFirst>(&[0, 3], &[1, 2]);
+    }
+}
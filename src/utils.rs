@@ -1,10 +1,26 @@
+mod add_extremum;
+mod add_sum;
+mod assign_sum;
+mod checked_lazy;
+mod compressor;
 mod lazy_set_wrapper;
 mod max;
 mod max_subarray_sum;
 mod min;
+mod pair;
 mod sum;
 
+pub(crate) use self::compressor::compress_pairs;
 pub use self::{
-    lazy_set_wrapper::LazySetWrapper, max::Max, max_subarray_sum::MaxSubArraySum, min::Min,
+    add_extremum::{AddMax, AddMin},
+    add_sum::AddSum,
+    assign_sum::AssignSum,
+    checked_lazy::CheckedLazy,
+    compressor::Compressor,
+    lazy_set_wrapper::{LazyResetWrapper, LazySetWrapper},
+    max::Max,
+    max_subarray_sum::MaxSubArraySum,
+    min::Min,
+    pair::Pair,
     sum::Sum,
 };
@@ -0,0 +1,135 @@
+use core::ops::Add;
+
+use crate::nodes::{IdempotentNode, LazyNode, MonoidNode, Node};
+
+macro_rules! add_extremum {
+    ($(#[$meta:meta])* $name:ident, $combine:ident, $identity:ident) => {
+        $(#[$meta])*
+        #[derive(Clone, Debug, Eq, Hash, PartialEq)]
+        pub struct $name<T> {
+            value: T,
+            lazy_value: Option<T>,
+        }
+
+        impl<T> Node for $name<T>
+        where
+            T: Ord + Add<Output = T> + Clone,
+        {
+            type Value = T;
+            #[inline]
+            fn initialize(v: &Self::Value) -> Self {
+                Self {
+                    value: v.clone(),
+                    lazy_value: None,
+                }
+            }
+            #[inline]
+            fn combine(a: &Self, b: &Self) -> Self {
+                Self {
+                    value: a.value.clone().$combine(b.value.clone()),
+                    lazy_value: None,
+                }
+            }
+            #[inline]
+            fn value(&self) -> &Self::Value {
+                &self.value
+            }
+            #[inline]
+            fn into_value(self) -> Self::Value {
+                self.value
+            }
+        }
+
+        impl<T> IdempotentNode for $name<T> where T: Ord + Add<Output = T> + Clone {}
+
+        /// The update adds the value to each element of the range, it assumes that `+` is commutative and associative, so the additions can be accumulated in any order.
+        impl<T> LazyNode for $name<T>
+        where
+            T: Ord + Add<Output = T> + Clone,
+        {
+            #[inline]
+            fn lazy_update(&mut self, _i: usize, _j: usize) {
+                if let Some(value) = self.lazy_value.take() {
+                    self.value = self.value.clone() + value;
+                }
+            }
+            #[inline]
+            fn update_lazy_value(&mut self, new_value: &<Self as Node>::Value) {
+                if let Some(value) = self.lazy_value.take() {
+                    self.lazy_value = Some(value + new_value.clone());
+                } else {
+                    self.lazy_value = Some(new_value.clone());
+                }
+            }
+            #[inline]
+            fn lazy_value(&self) -> Option<&<Self as Node>::Value> {
+                self.lazy_value.as_ref()
+            }
+        }
+
+        impl_monoid!($name, $identity, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+    };
+}
+
+macro_rules! impl_monoid {
+    ($name:ident, $identity:ident, $($t:ty),*) => {
+        $(
+            #[doc = concat!("The identity of the primitive integers is their `", stringify!($identity), "`.")]
+            impl MonoidNode for $name<$t> {
+                #[inline]
+                fn identity() -> Self {
+                    Self {
+                        value: <$t>::$identity,
+                        lazy_value: None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+add_extremum!(
+    /// Implementation of range min with an update which adds a value to each element of the range, it implements [`Node`], [`LazyNode`] and [`IdempotentNode`], and [`MonoidNode`] for the primitive integers.
+    ///
+    /// Adding a value to each element adds it to their minimum, so unlike [`Sum`](crate::utils::Sum) the update doesn't depend on the length of the segment, and pending additions are accumulated by adding them.
+    AddMin,
+    min,
+    MAX
+);
+
+add_extremum!(
+    /// Implementation of range max with an update which adds a value to each element of the range, it implements [`Node`], [`LazyNode`] and [`IdempotentNode`], and [`MonoidNode`] for the primitive integers.
+    ///
+    /// Adding a value to each element adds it to their maximum, so unlike [`Sum`](crate::utils::Sum) the update doesn't depend on the length of the segment, and pending additions are accumulated by adding them.
+    AddMax,
+    max,
+    MIN
+);
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        nodes::Node,
+        segment_tree::{LazyPersistent, LazyRecursive},
+        testing::{assert_lazy_laws, random_ops, NaiveRangeQuery},
+        utils::{AddMax, AddMin},
+    };
+
+    #[test]
+    fn add_min_works() {
+        let nodes: Vec<AddMin<i64>> = (0..37).map(|x| AddMin::initialize(&(x % 11 - 5))).collect();
+        let ops = random_ops(nodes.len(), true);
+        NaiveRangeQuery::build(&nodes).check_lazy(&mut LazyRecursive::build(&nodes), &ops);
+        NaiveRangeQuery::build(&nodes).check_lazy(&mut LazyPersistent::build(&nodes), &ops);
+        assert_lazy_laws::<AddMin<i64>>(&[-3, 0, 4, 4], &[-2, 0, 5]);
+    }
+
+    #[test]
+    fn add_max_works() {
+        let nodes: Vec<AddMax<i64>> = (0..37).map(|x| AddMax::initialize(&(x % 11 - 5))).collect();
+        let ops = random_ops(nodes.len(), true);
+        NaiveRangeQuery::build(&nodes).check_lazy(&mut LazyRecursive::build(&nodes), &ops);
+        NaiveRangeQuery::build(&nodes).check_lazy(&mut LazyPersistent::build(&nodes), &ops);
+        assert_lazy_laws::<AddMax<i64>>(&[-3, 0, 4, 4], &[-2, 0, 5]);
+    }
+}
@@ -0,0 +1,35 @@
+use crate::utils::Sum;
+
+/// Range sum with an update which adds a value to each element of the range, it's an alias of [`Sum`], as that's already its [`LazyNode`](crate::nodes::LazyNode) update.
+///
+/// The alias only names the update, so it reads apart from other updates of range sums like [`AssignSum`](crate::utils::AssignSum).
+/// Adding `a` to each element of a segment of `n` elements adds `a*n` to its sum, so it assumes that `a*n`, where a: T and n: usize, is well defined and `a*n = a+...+a` with 'n' a.
+/// ```
+/// # use seg_tree::{LazyRecursive,utils::AddSum,nodes::Node};
+/// let nodes: Vec<AddSum<usize>> = (0..10).map(|x| AddSum::initialize(&x)).collect();
+/// let mut seg_tree = LazyRecursive::build(&nodes); // [0,1,2,3,4,5,6,7,8,9] with AddSum<usize> nodes
+/// seg_tree.update(2, 5, &1); // [0,1,3,4,5,6,6,7,8,9]
+/// seg_tree.update(4, 9, &2); // [0,1,3,4,7,8,8,9,10,11]
+/// assert_eq!(seg_tree.query(0, 3).unwrap().value(), &8);
+/// assert_eq!(seg_tree.query(3, 6).unwrap().value(), &27);
+/// ```
+pub type AddSum<T> = Sum<T>;
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        nodes::Node,
+        segment_tree::{LazyPersistent, LazyRecursive},
+        testing::{assert_lazy_laws, random_ops, NaiveRangeQuery},
+        utils::AddSum,
+    };
+
+    #[test]
+    fn add_sum_works() {
+        let nodes: Vec<AddSum<usize>> = (0..37).map(|x| AddSum::initialize(&(x % 11))).collect();
+        let ops = random_ops(nodes.len(), true);
+        NaiveRangeQuery::build(&nodes).check_lazy(&mut LazyRecursive::build(&nodes), &ops);
+        NaiveRangeQuery::build(&nodes).check_lazy(&mut LazyPersistent::build(&nodes), &ops);
+        assert_lazy_laws::<AddSum<usize>>(&[0, 3, 4, 4], &[0, 2, 5]);
+    }
+}
@@ -0,0 +1,36 @@
+use crate::utils::{LazySetWrapper, Sum};
+
+/// Range sum with an update which sets each element of the range to a value, it's an alias of [`LazySetWrapper`] over [`Sum`], as [`Sum`] rebuilds a segment set to a value from its length.
+///
+/// Setting each element of a segment of `n` elements to `a` sets its sum to `a*n`, so it assumes that `a*n`, where a: T and n: usize, is well defined and `a*n = a+...+a` with 'n' a.
+/// A pending update is overwritten by the next one, as only the last value set remains.
+/// ```
+/// # use seg_tree::{LazyRecursive,utils::AssignSum,nodes::Node};
+/// let nodes: Vec<AssignSum<usize>> = (0..10).map(|x| AssignSum::initialize(&x)).collect();
+/// let mut seg_tree = LazyRecursive::build(&nodes); // [0,1,2,3,4,5,6,7,8,9] with AssignSum<usize> nodes
+/// seg_tree.update(2, 5, &1); // [0,1,1,1,1,1,6,7,8,9]
+/// seg_tree.update(4, 9, &2); // [0,1,1,1,2,2,2,2,2,2]
+/// assert_eq!(seg_tree.query(0, 3).unwrap().value(), &3);
+/// assert_eq!(seg_tree.query(3, 6).unwrap().value(), &7);
+/// ```
+pub type AssignSum<T> = LazySetWrapper<Sum<T>>;
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        nodes::Node,
+        segment_tree::{LazyPersistent, LazyRecursive},
+        testing::{assert_lazy_laws, random_ops, NaiveRangeQuery},
+        utils::AssignSum,
+    };
+
+    #[test]
+    fn assign_sum_works() {
+        let nodes: Vec<AssignSum<usize>> =
+            (0..37).map(|x| AssignSum::initialize(&(x % 11))).collect();
+        let ops = random_ops(nodes.len(), true);
+        NaiveRangeQuery::build(&nodes).check_lazy(&mut LazyRecursive::build(&nodes), &ops);
+        NaiveRangeQuery::build(&nodes).check_lazy(&mut LazyPersistent::build(&nodes), &ops);
+        assert_lazy_laws::<AssignSum<usize>>(&[0, 3, 4, 4], &[0, 2, 5]);
+    }
+}
@@ -0,0 +1,164 @@
+use crate::nodes::{LazyNode, Node};
+
+/// A wrapper for lazy nodes which checks the invariants of [`LazyNode`] on every call, and **panics** with the name of the wrapped type as soon as one is violated.
+///
+/// A node breaking them usually shows up as silently wrong answers far away from the bug, so it's meant to be used while writing and testing a custom [`LazyNode`], and removed afterwards.
+/// ```should_panic
+/// # use seg_tree::{LazyRecursive,utils::CheckedLazy,nodes::{LazyNode,Node}};
+/// #[derive(Clone)]
+/// struct Forgetful(usize, Option<usize>);
+/// # impl Node for Forgetful {
+/// #     type Value = usize;
+/// #     fn initialize(value: &usize) -> Self { Self(*value, None) }
+/// #     fn combine(a: &Self, b: &Self) -> Self { Self(a.0 + b.0, None) }
+/// #     fn value(&self) -> &usize { &self.0 }
+/// # }
+/// impl LazyNode for Forgetful {
+///     fn lazy_update(&mut self, i: usize, j: usize) {
+///         if let Some(value) = self.1 { // Should have been `self.1.take()`.
+///             self.0 += value * (j - i + 1);
+///         }
+///     }
+///     // ...
+/// #   fn update_lazy_value(&mut self, new_value: &usize) { self.1 = Some(self.1.unwrap_or(0) + new_value); }
+/// #   fn lazy_value(&self) -> Option<&usize> { self.1.as_ref() }
+/// }
+/// # let nodes: Vec<CheckedLazy<Forgetful>> = (0..10).map(|x| CheckedLazy::initialize(&x)).collect();
+/// let mut seg_tree = LazyRecursive::build(&nodes);
+/// seg_tree.update(0, 4, &1); // Panics, as the lazy value is still there after lazy_update.
+/// ```
+#[derive(Clone, Debug)]
+pub struct CheckedLazy<T>
+where
+    T: LazyNode,
+{
+    node: T,
+}
+
+impl<T> CheckedLazy<T>
+where
+    T: LazyNode,
+{
+    /// Returns the wrapped node.
+    pub fn into_inner(self) -> T {
+        self.node
+    }
+}
+
+impl<T> Node for CheckedLazy<T>
+where
+    T: LazyNode,
+{
+    type Value = <T as Node>::Value;
+
+    #[inline]
+    fn initialize(value: &Self::Value) -> Self {
+        Self {
+            node: Node::initialize(value),
+        }
+    }
+
+    #[inline]
+    fn combine(a: &Self, b: &Self) -> Self {
+        Self {
+            node: Node::combine(&a.node, &b.node),
+        }
+    }
+
+    #[inline]
+    fn value(&self) -> &Self::Value {
+        self.node.value()
+    }
+
+    #[inline]
+    fn into_value(self) -> Self::Value {
+        self.node.into_value()
+    }
+}
+
+impl<T> LazyNode for CheckedLazy<T>
+where
+    T: LazyNode,
+{
+    fn lazy_update(&mut self, i: usize, j: usize) {
+        self.node.lazy_update(i, j);
+        assert!(
+            self.node.lazy_value().is_none(),
+            "{}::lazy_update didn't clear the lazy value of [{i}, {j}]",
+            core::any::type_name::<T>()
+        );
+    }
+
+    fn update_lazy_value(&mut self, new_value: &<Self as Node>::Value) {
+        self.node.update_lazy_value(new_value);
+        assert!(
+            self.node.lazy_value().is_some(),
+            "{}::update_lazy_value didn't set the lazy value",
+            core::any::type_name::<T>()
+        );
+    }
+
+    #[inline]
+    fn lazy_value(&self) -> Option<&<Self as Node>::Value> {
+        self.node.lazy_value()
+    }
+}
+
+impl<T> From<T> for CheckedLazy<T>
+where
+    T: LazyNode,
+{
+    #[inline]
+    fn from(node: T) -> Self {
+        Self { node }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        nodes::{LazyNode, Node},
+        segment_tree::LazyRecursive,
+        utils::Sum,
+    };
+
+    use super::CheckedLazy;
+
+    #[test]
+    fn valid_nodes_work() {
+        let nodes: Vec<CheckedLazy<Sum<usize>>> =
+            (0..=10).map(|x| CheckedLazy::initialize(&x)).collect();
+        let mut segment_tree = LazyRecursive::build(&nodes);
+        segment_tree.update(0, 5, &1);
+        segment_tree.update(3, 10, &2);
+        assert_eq!(segment_tree.query(0, 10).unwrap().value(), &(55 + 6 + 16));
+    }
+
+    #[test]
+    #[should_panic = "update_lazy_value didn't set the lazy value"]
+    fn update_lazy_value_is_checked() {
+        #[derive(Clone)]
+        struct Ignores(Sum<usize>);
+        impl Node for Ignores {
+            type Value = usize;
+            fn initialize(value: &usize) -> Self {
+                Self(Sum::initialize(value))
+            }
+            fn combine(a: &Self, b: &Self) -> Self {
+                Self(Sum::combine(&a.0, &b.0))
+            }
+            fn value(&self) -> &usize {
+                self.0.value()
+            }
+        }
+        impl LazyNode for Ignores {
+            fn lazy_update(&mut self, _i: usize, _j: usize) {}
+            fn update_lazy_value(&mut self, _new_value: &usize) {}
+            fn lazy_value(&self) -> Option<&usize> {
+                None
+            }
+        }
+        let mut node = CheckedLazy::from(Ignores::initialize(&1));
+        node.update_lazy_value(&2);
+    }
+}
@@ -0,0 +1,139 @@
+use alloc::vec::Vec;
+
+use crate::nodes::Node;
+
+/// Coordinate compression, it maps each distinct key to its rank among them, so the keys can index the leaves of a segment tree over the value domain.
+///
+/// See `build_compressed` (e.g. [`Recursive::build_compressed`](crate::Recursive::build_compressed)) to build a segment tree over the compressed keys directly.
+/// ```
+/// # use seg_tree::utils::Compressor;
+/// let compressor: Compressor<i64> = [40, -5, 1_000_000, 40].into_iter().collect();
+/// assert_eq!(compressor.len(), 3);
+/// assert_eq!(compressor.index(&40), Some(1));
+/// assert_eq!(compressor.index(&7), None);
+/// assert_eq!(compressor.lower_bound(&7), 1);
+/// assert_eq!(compressor.key(2), &1_000_000);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Compressor<K> {
+    keys: Vec<K>,
+}
+
+impl<K> Compressor<K>
+where
+    K: Ord,
+{
+    /// Returns the index of key, or None if it wasn't one of the keys.
+    /// It has time complexity of `O(log(n))`.
+    #[must_use]
+    pub fn index(&self, key: &K) -> Option<usize> {
+        self.keys.binary_search(key).ok()
+    }
+
+    /// Returns the index of the smallest key which is greater or equal to key, or `n` if there's none.
+    /// It has time complexity of `O(log(n))`.
+    #[must_use]
+    pub fn lower_bound(&self, key: &K) -> usize {
+        self.keys.partition_point(|x| x < key)
+    }
+
+    /// Returns the index of the smallest key which is greater than key, or `n` if there's none.
+    /// It has time complexity of `O(log(n))`.
+    #[must_use]
+    pub fn upper_bound(&self, key: &K) -> usize {
+        self.keys.partition_point(|x| x <= key)
+    }
+
+    /// Returns the key of index.
+    /// It will **panic** if index is not in `[0,n)`.
+    #[must_use]
+    pub fn key(&self, index: usize) -> &K {
+        &self.keys[index]
+    }
+
+    /// Returns the distinct keys in increasing order.
+    #[must_use]
+    pub fn keys(&self) -> &[K] {
+        &self.keys
+    }
+
+    /// Returns the amount of distinct keys.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns `true` if there are no keys.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+impl<K> FromIterator<K> for Compressor<K>
+where
+    K: Ord,
+{
+    /// Compresses the keys, the duplicated keys are mapped to the same index.
+    /// It has time complexity of `O(n*log(n))`.
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+    {
+        let mut keys: Vec<K> = iter.into_iter().collect();
+        keys.sort_unstable();
+        keys.dedup();
+        Self { keys }
+    }
+}
+
+/// Compresses the keys of pairs and returns the leaf of each index, which is the combination of the nodes with its key in the order they appear in pairs.
+pub fn compress_pairs<K, T>(pairs: &[(K, T)]) -> (Compressor<K>, Vec<T>)
+where
+    K: Ord + Clone,
+    T: Node + Clone,
+{
+    let compressor: Compressor<K> = pairs.iter().map(|(key, _)| key.clone()).collect();
+    let mut leaves: Vec<Option<T>> = (0..compressor.len()).map(|_| None).collect();
+    for (key, node) in pairs {
+        let leaf = &mut leaves[compressor.lower_bound(key)];
+        *leaf = Some(
+            leaf.take()
+                .map_or_else(|| node.clone(), |leaf| T::combine(&leaf, node)),
+        );
+    }
+    (compressor, leaves.into_iter().flatten().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{nodes::Node, utils::Sum};
+
+    use super::{compress_pairs, Compressor};
+
+    #[test]
+    fn compressor_works() {
+        let keys = [5, -3, 5, 10, 0];
+        let compressor: Compressor<i32> = keys.iter().copied().collect();
+        assert_eq!(compressor.keys(), [-3, 0, 5, 10]);
+        for (i, key) in compressor.keys().iter().enumerate() {
+            assert_eq!(compressor.index(key), Some(i));
+        }
+        assert_eq!(compressor.lower_bound(&5), 2);
+        assert_eq!(compressor.upper_bound(&5), 3);
+        assert_eq!(compressor.lower_bound(&11), 4);
+        assert!(Compressor::<i32>::from_iter([]).is_empty());
+    }
+
+    #[test]
+    fn compress_pairs_works() {
+        let pairs: Vec<(&str, Sum<usize>)> = [("b", 1), ("a", 2), ("b", 3)]
+            .into_iter()
+            .map(|(key, value)| (key, Sum::initialize(&value)))
+            .collect();
+        let (compressor, leaves) = compress_pairs(&pairs);
+        assert_eq!(compressor.keys(), ["a", "b"]);
+        let values: Vec<usize> = leaves.iter().map(|leaf| *leaf.value()).collect();
+        assert_eq!(values, [2, 4]);
+    }
+}
@@ -1,95 +1,140 @@
-use crate::nodes::{LazyNode, Node};
-
-/// A wrapper for nodes to easily implement [`LazyNode`] with an update which sets the range to a value.
-#[derive(Clone)]
-pub struct LazySetWrapper<T>
-where
-    T: Node,
-{
-    node: T,
-    lazy_value: Option<<T as Node>::Value>,
-}
+use crate::nodes::{LazyNode, MonoidNode, Node, SetApply};
 
-impl<T> std::fmt::Debug for LazySetWrapper<T>
-where
-    T: Node + std::fmt::Debug,
-    <T as Node>::Value: std::fmt::Debug,
-{
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("LazySetWrapper")
-            .field("node", &self.node)
-            .field("lazy_value", &self.lazy_value)
-            .finish()
-    }
-}
-
-impl<T> Node for LazySetWrapper<T>
-where
-    T: Node,
-{
-    type Value = <T as Node>::Value;
-
-    #[inline]
-    fn initialize(value: &Self::Value) -> Self {
-        Self {
-            node: Node::initialize(value),
-            lazy_value: None,
+macro_rules! lazy_set_wrapper {
+    ($(#[$meta:meta])* $name:ident, $bound:ident, |$value:ident, $len:ident| $apply:expr) => {
+        $(#[$meta])*
+        #[derive(Clone)]
+        pub struct $name<T>
+        where
+            T: Node,
+        {
+            node: T,
+            lazy_value: Option<<T as Node>::Value>,
         }
-    }
 
-    #[inline]
-    fn combine(a: &Self, b: &Self) -> Self {
-        Self {
-            node: Node::combine(&a.node, &b.node),
-            lazy_value: None,
+        impl<T> core::fmt::Debug for $name<T>
+        where
+            T: Node + core::fmt::Debug,
+            <T as Node>::Value: core::fmt::Debug,
+        {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                f.debug_struct(stringify!($name))
+                    .field("node", &self.node)
+                    .field("lazy_value", &self.lazy_value)
+                    .finish()
+            }
         }
-    }
 
-    #[inline]
-    fn value(&self) -> &Self::Value {
-        self.node.value()
-    }
-}
-impl<T> LazyNode for LazySetWrapper<T>
-where
-    T: Node,
-{
-    #[inline]
-    fn lazy_update(&mut self, _i: usize, _j: usize) {
-        if let Some(value) = self.lazy_value.take() {
-            self.node = Node::initialize(&value);
+        impl<T> Node for $name<T>
+        where
+            T: Node,
+        {
+            type Value = <T as Node>::Value;
+
+            #[inline]
+            fn initialize(value: &Self::Value) -> Self {
+                Self {
+                    node: Node::initialize(value),
+                    lazy_value: None,
+                }
+            }
+
+            #[inline]
+            fn combine(a: &Self, b: &Self) -> Self {
+                Self {
+                    node: Node::combine(&a.node, &b.node),
+                    lazy_value: None,
+                }
+            }
+
+            #[inline]
+            fn value(&self) -> &Self::Value {
+                self.node.value()
+            }
+
+            #[inline]
+            fn into_value(self) -> Self::Value {
+                self.node.into_value()
+            }
         }
-    }
-    #[inline]
-    fn update_lazy_value(&mut self, new_value: &<Self as Node>::Value) {
-        self.lazy_value = Some(new_value.clone());
-    }
-    #[inline]
-    fn lazy_value(&self) -> Option<&<Self as Node>::Value> {
-        self.lazy_value.as_ref()
-    }
-}
-impl<T> From<T> for LazySetWrapper<T>
-where
-    T: Node,
-{
-    #[inline]
-    fn from(node: T) -> Self {
-        Self {
-            node,
-            lazy_value: None,
+        impl<T> LazyNode for $name<T>
+        where
+            T: $bound,
+        {
+            #[inline]
+            fn lazy_update(&mut self, i: usize, j: usize) {
+                if let Some($value) = self.lazy_value.take() {
+                    let $len = j - i + 1;
+                    self.node = $apply;
+                }
+            }
+            #[inline]
+            fn update_lazy_value(&mut self, new_value: &<Self as Node>::Value) {
+                self.lazy_value = Some(new_value.clone());
+            }
+            #[inline]
+            fn lazy_value(&self) -> Option<&<Self as Node>::Value> {
+                self.lazy_value.as_ref()
+            }
         }
-    }
+        impl<T> From<T> for $name<T>
+        where
+            T: Node,
+        {
+            #[inline]
+            fn from(node: T) -> Self {
+                Self {
+                    node,
+                    lazy_value: None,
+                }
+            }
+        }
+
+        /// The identity is the one of the wrapped node, without a pending value.
+        impl<T> MonoidNode for $name<T>
+        where
+            T: MonoidNode,
+        {
+            #[inline]
+            fn identity() -> Self {
+                Self {
+                    node: T::identity(),
+                    lazy_value: None,
+                }
+            }
+        }
+    };
 }
 
+lazy_set_wrapper!(
+    /// A wrapper for nodes to easily implement [`LazyNode`] with an update which sets the range to a value.
+    ///
+    /// The wrapped node implements [`SetApply`], so a segment set to a value is rebuilt according to its length, e.g. a segment of [`Sum`](crate::utils::Sum) nodes sums the value times its length.
+    /// Nodes which used to be wrapped before [`SetApply`] was required either implement it, which is only [`IdempotentNode`](crate::nodes::IdempotentNode) for nodes like min or max, or are wrapped in [`LazyResetWrapper`] to keep the previous behavior.
+    LazySetWrapper,
+    SetApply,
+    |value, len| T::repeated(&value, len)
+);
+
+lazy_set_wrapper!(
+    /// Same as [`LazySetWrapper`], but a segment set to a value is initialized from it ignoring its length, so it wraps any [`Node`].
+    ///
+    /// It's only correct for nodes where a segment of equal elements is the same as a single one, like min or max, e.g. a segment of [`Sum`](crate::utils::Sum) nodes would sum the value once, so prefer [`LazySetWrapper`] for nodes which implement [`SetApply`].
+    LazyResetWrapper,
+    Node,
+    |value, _len| Node::initialize(&value)
+);
+
 #[cfg(test)]
 mod tests {
     use crate::{
         nodes::{LazyNode, Node},
-        utils::Min,
+        segment_tree::LazyRecursive,
+        testing::small_values,
+        utils::{Min, Pair, Sum},
     };
 
-    use super::LazySetWrapper;
+    use super::{LazyResetWrapper, LazySetWrapper};
 
     type LSMin<T> = LazySetWrapper<Min<T>>;
     #[test]
@@ -107,4 +152,61 @@ mod tests {
         node.lazy_update(0, 10);
         assert_eq!(node.value(), &2);
     }
+
+    #[test]
+    fn reset_wrapper_wraps_any_node() {
+        // Node which keeps the last element, so it's neither idempotent nor additive.
+        #[derive(Clone)]
+        struct Last(usize);
+        impl Node for Last {
+            type Value = usize;
+            fn initialize(value: &usize) -> Self {
+                Self(*value)
+            }
+            fn combine(_a: &Self, b: &Self) -> Self {
+                b.clone()
+            }
+            fn value(&self) -> &usize {
+                &self.0
+            }
+        }
+        let nodes: Vec<LazyResetWrapper<Last>> = (0..10).map(|x| Node::initialize(&x)).collect();
+        let mut segment_tree = LazyRecursive::build(&nodes);
+        segment_tree.update(2, 6, &20);
+        assert_eq!(segment_tree.query(0, 4).unwrap().value(), &20);
+        assert_eq!(segment_tree.query(0, 8).unwrap().value(), &8);
+        assert_eq!(segment_tree.query(3, 6).unwrap().value(), &20);
+    }
+
+    #[test]
+    fn lazy_update_uses_segment_length() {
+        // Node represents the range [0,10] with sum 1.
+        let mut node = LazySetWrapper::<Sum<usize>>::initialize(&1);
+        node.update_lazy_value(&2);
+        node.lazy_update(0, 10);
+        assert_eq!(node.value(), &22);
+    }
+
+    #[test]
+    fn set_wrapper_works_for_sums() {
+        let mut values: Vec<usize> = small_values(13);
+        let nodes: Vec<LazySetWrapper<Pair<Min<usize>, Sum<usize>>>> = values
+            .iter()
+            .map(|&x| LazySetWrapper::initialize(&(x, x)))
+            .collect();
+        let mut segment_tree = LazyRecursive::build(&nodes);
+        for step in 0..20 {
+            let left = (step * 5) % 13;
+            let right = (left + step % 6).min(12);
+            segment_tree.update(left, right, &(step, step));
+            values[left..=right].fill(step);
+            for i in 0..values.len() {
+                for j in i..values.len() {
+                    let min = *values[i..=j].iter().min().unwrap();
+                    let sum: usize = values[i..=j].iter().sum();
+                    assert_eq!(segment_tree.query(i, j).unwrap().value(), &(min, sum));
+                }
+            }
+        }
+    }
 }
@@ -1,7 +1,7 @@
-use crate::nodes::Node;
+use crate::nodes::{FlatNode, IdempotentNode, MonoidNode, Node, SelectionNode};
 
-/// Implementation of range max for generic type T, it only implements [`Node`].
-#[derive(Clone, Debug, Eq, PartialEq)]
+/// Implementation of range max for generic type T, it implements [`Node`], [`IdempotentNode`] and [`SelectionNode`], [`FlatNode`] when `T` is [`Copy`], and [`MonoidNode`] for the primitive integers.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct Max<T> {
     value: T,
 }
@@ -22,6 +22,48 @@ where
     fn value(&self) -> &Self::Value {
         &self.value
     }
+    fn into_value(self) -> Self::Value {
+        self.value
+    }
+}
+
+impl<T> IdempotentNode for Max<T> where T: Ord + Clone {}
+
+/// Ties select the left node.
+impl<T> SelectionNode for Max<T>
+where
+    T: Ord + Clone,
+{
+    #[inline]
+    fn selects_left(left: &Self, right: &Self) -> bool {
+        left.value >= right.value
+    }
+}
+
+/// The identity of the primitive integers is their `MIN`.
+macro_rules! impl_monoid {
+    ($($t:ty),*) => {
+        $(
+            impl MonoidNode for Max<$t> {
+                #[inline]
+                fn identity() -> Self {
+                    Self { value: <$t>::MIN }
+                }
+            }
+        )*
+    };
+}
+
+impl_monoid!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+impl<T> FlatNode for Max<T>
+where
+    T: Ord + Copy,
+{
+    #[inline]
+    fn combine_values(a: Self::Value, b: Self::Value) -> Self::Value {
+        a.max(b)
+    }
 }
 
 #[cfg(test)]
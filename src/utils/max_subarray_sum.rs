@@ -1,7 +1,7 @@
 use crate::nodes::Node;
 
 /// Implementation of the solution to the maximum subarray problem. It just implements [`Node`].
-#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
 pub struct MaxSubArraySum {
     max_sum: i64,
     max_prefix_sum: i64,
@@ -12,7 +12,7 @@ pub struct MaxSubArraySum {
 impl Node for MaxSubArraySum {
     type Value = i64;
     fn initialize(value: &Self::Value) -> Self {
-        let v = value.to_owned();
+        let v = *value;
         Self {
             max_sum: v,
             max_prefix_sum: v,
@@ -45,6 +45,7 @@ mod tests {
     const N: usize = 1_000;
 
     #[test]
+    #[allow(clippy::cast_possible_wrap)]
     fn max_sub_array_sum_works() {
         let random = Uniform::from((i64::MIN / (N as i64))..(i64::MAX / (N as i64)));
         let mut rng = thread_rng();
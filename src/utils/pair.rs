@@ -0,0 +1,113 @@
+use crate::nodes::{Node, SetApply};
+
+/// Node which combines two nodes of the same elements independently, so a single query answers both.
+///
+/// E.g. `Pair<Min<i64>, Sum<i64>>` gives the minimum and the sum of a range. Its [`Value`](Node::Value) is the pair of the values of both nodes, which is kept in the node so it can be returned by reference.
+/// See [`Recursive::zip`](crate::Recursive::zip) to pair two already built segment trees.
+#[derive(Clone, Debug)]
+pub struct Pair<A, B>
+where
+    A: Node,
+    B: Node,
+{
+    first: A,
+    second: B,
+    value: (A::Value, B::Value),
+}
+
+impl<A, B> Pair<A, B>
+where
+    A: Node,
+    B: Node,
+{
+    /// Pairs both nodes, which must correspond to the same segment.
+    pub fn new(first: A, second: B) -> Self {
+        let value = (first.value().clone(), second.value().clone());
+        Self {
+            first,
+            second,
+            value,
+        }
+    }
+
+    /// Returns the first node.
+    pub const fn first(&self) -> &A {
+        &self.first
+    }
+
+    /// Returns the second node.
+    pub const fn second(&self) -> &B {
+        &self.second
+    }
+
+    /// Returns both nodes.
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+}
+
+impl<A, B> Node for Pair<A, B>
+where
+    A: Node,
+    B: Node,
+{
+    type Value = (A::Value, B::Value);
+
+    #[inline]
+    fn initialize(value: &Self::Value) -> Self {
+        Self::new(A::initialize(&value.0), B::initialize(&value.1))
+    }
+
+    #[inline]
+    fn combine(a: &Self, b: &Self) -> Self {
+        Self::new(
+            A::combine(&a.first, &b.first),
+            B::combine(&a.second, &b.second),
+        )
+    }
+
+    #[inline]
+    fn value(&self) -> &Self::Value {
+        &self.value
+    }
+
+    #[inline]
+    fn into_value(self) -> Self::Value {
+        self.value
+    }
+}
+
+/// Both nodes are built for the segment independently.
+impl<A, B> SetApply for Pair<A, B>
+where
+    A: SetApply,
+    B: SetApply,
+{
+    #[inline]
+    fn repeated(value: &Self::Value, len: usize) -> Self {
+        Self::new(A::repeated(&value.0, len), B::repeated(&value.1, len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        nodes::Node,
+        segment_tree::Iterative,
+        utils::{Max, Min, Sum},
+    };
+
+    use super::Pair;
+
+    #[test]
+    fn pair_works() {
+        type MinMax = Pair<Min<usize>, Max<usize>>;
+        let nodes: Vec<MinMax> = (0..=10).map(|x| MinMax::initialize(&(x, x))).collect();
+        let mut segment_tree = Iterative::build(&nodes);
+        segment_tree.update(5, &(20, 20));
+        assert_eq!(segment_tree.query(3, 7).unwrap().value(), &(3, 20));
+        let node = Pair::new(Sum::initialize(&1), Min::initialize(&2));
+        assert_eq!(node.first().value(), &1);
+        assert_eq!(node.second().value(), &2);
+    }
+}
@@ -1,9 +1,9 @@
-use std::ops::{Add, Mul};
+use core::ops::{Add, Mul, Sub};
 
-use crate::nodes::{LazyNode, Node};
+use crate::nodes::{FlatNode, InvertibleNode, LazyNode, MonoidNode, Node, SetApply};
 
-/// Implementation of range sum for generic type T, it implements [`Node`] and [`LazyNode`], as such it can be used as a node in every segment tree type.
-#[derive(Clone, Debug)]
+/// Implementation of range sum for generic type T, it implements [`Node`] and [`LazyNode`], as such it can be used as a node in every segment tree type. It also implements [`FlatNode`] when `T` is [`Copy`], [`InvertibleNode`] when `T` has [`Sub`], and [`MonoidNode`] when `T` has [`Default`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct Sum<T>
 where
     T: Add<Output = T>,
@@ -37,6 +37,62 @@ where
     fn value(&self) -> &Self::Value {
         &self.value
     }
+    #[inline]
+    fn into_value(self) -> Self::Value {
+        self.value
+    }
+}
+
+impl<T> FlatNode for Sum<T>
+where
+    T: Add<Output = T> + Copy,
+{
+    #[inline]
+    fn combine_values(a: Self::Value, b: Self::Value) -> Self::Value {
+        a + b
+    }
+}
+
+/// The identity is the default value of `T`, which is `0` for numbers.
+impl<T> MonoidNode for Sum<T>
+where
+    T: Add<Output = T> + Default + Clone,
+{
+    #[inline]
+    fn identity() -> Self {
+        Self {
+            value: T::default(),
+            lazy_value: None,
+        }
+    }
+}
+
+/// The difference of the sums is the sum of the rest of the segment, it assumes that `(a+b)-a = b`.
+impl<T> InvertibleNode for Sum<T>
+where
+    T: Add<Output = T> + Sub<Output = T> + Clone,
+{
+    #[inline]
+    fn uncombine(whole: &Self, prefix: &Self) -> Self {
+        Self {
+            value: whole.value.clone() - prefix.value.clone(),
+            lazy_value: None,
+        }
+    }
+}
+
+/// A segment of `len` elements equal to `a` sums `a*len`, with the same assumption on `a*n` as the [`LazyNode`] implementation.
+impl<T> SetApply for Sum<T>
+where
+    T: Add<Output = T> + Mul<usize, Output = T> + Clone,
+{
+    #[inline]
+    fn repeated(value: &Self::Value, len: usize) -> Self {
+        Self {
+            value: value.clone() * len,
+            lazy_value: None,
+        }
+    }
 }
 
 /// Implementation for sum range query node, the update adds the value to each item in the range.
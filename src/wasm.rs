@@ -0,0 +1,91 @@
+use alloc::vec::Vec;
+
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::{
+    nodes::Node,
+    segment_tree::Iterative,
+    utils::{Max, Min, Sum},
+};
+
+macro_rules! wasm_tree {
+    ($(#[$doc:meta])* $name:ident, $node:ident, $value:ty) => {
+        $(#[$doc])*
+        #[wasm_bindgen]
+        pub struct $name {
+            tree: Iterative<$node<$value>>,
+        }
+
+        #[wasm_bindgen]
+        impl $name {
+            /// Builds the segment tree from the values, each of them will correspond to a leaf of the segment tree.
+            /// It has time complexity of `O(n)`.
+            #[wasm_bindgen(constructor)]
+            #[must_use]
+            pub fn new(values: &[$value]) -> Self {
+                let nodes: Vec<$node<$value>> = values.iter().map($node::initialize).collect();
+                Self {
+                    tree: Iterative::build(&nodes),
+                }
+            }
+
+            /// Sets the i-th element to value.
+            /// It will **panic** if i is not in `[0,n)`.
+            /// It has time complexity of `O(log(n))`.
+            pub fn update(&mut self, i: usize, value: $value) {
+                self.tree.update(i, &value);
+            }
+
+            /// Returns the result from the range `[left,right]`, or `undefined` if the range is empty.
+            /// It will **panic** if left or right are not in `[0,n)`.
+            /// It has time complexity of `O(log(n))`.
+            #[must_use]
+            pub fn query(&self, left: usize, right: usize) -> Option<$value> {
+                self.tree.query(left, right).map($node::into_value)
+            }
+
+            /// Returns the amount of elements.
+            #[wasm_bindgen(getter)]
+            #[must_use]
+            #[allow(clippy::missing_const_for_fn)]
+            pub fn length(&self) -> usize {
+                self.tree.len()
+            }
+        }
+    };
+}
+
+wasm_tree!(
+    /// Range sum of numbers with point updates, an [`Iterative`] segment tree with [`Sum<f64>`] nodes.
+    SumTree,
+    Sum,
+    f64
+);
+wasm_tree!(
+    /// Range min of 32-bit integers with point updates, an [`Iterative`] segment tree with [`Min<i32>`] nodes.
+    MinTree,
+    Min,
+    i32
+);
+wasm_tree!(
+    /// Range max of 32-bit integers with point updates, an [`Iterative`] segment tree with [`Max<i32>`] nodes.
+    MaxTree,
+    Max,
+    i32
+);
+
+#[cfg(test)]
+mod tests {
+    use super::{MinTree, SumTree};
+
+    #[test]
+    fn wrappers_work() {
+        let mut sums = SumTree::new(&[1.5, 2.0, 3.25]);
+        sums.update(1, 4.0);
+        assert_eq!(sums.query(0, 2), Some(8.75));
+        assert_eq!(sums.query(2, 1), None);
+        assert_eq!(sums.length(), 3);
+        let mins = MinTree::new(&[3, -1, 2]);
+        assert_eq!(mins.query(0, 2), Some(-1));
+    }
+}